@@ -0,0 +1,237 @@
+// Taking over AppImages installed by hand. Each one's repository comes from the update
+// information embedded in it (or from the user), its version from its file name; the
+// file stays where it is and is recorded in the state file like any install, so
+// `update` and the other commands manage it from then on.
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::cli::{AdoptArgs, InstallArgs, OutputFormat};
+use crate::config::validate_app_name;
+use crate::history::{self, Entry};
+use crate::install::{
+    extract_for, integrate, integration_paths, record_install, sandbox_for, version_in,
+};
+use crate::state::{Provenance, State};
+use crate::verify::{sha256_file, verify_appimage};
+use crate::{arch, progress, style, tr, update_info, Asset, Context, Error, Release};
+
+/// Adopt the AppImage at `args.path`, or every AppImage in the `args.scan` directory.
+/// A scan skips the files it can't adopt, with a warning; a single file is an error.
+pub fn adopt(ctx: &Context, args: &AdoptArgs) -> Result<(), Error> {
+    let (path, scan) = (args.path.as_deref(), args.scan.as_deref());
+    let files = match (path, scan) {
+        (Some(path), _) => vec![std::path::absolute(path)?],
+        (None, Some(dir)) => appimages_in(&std::path::absolute(dir)?)?,
+        (None, None) => return Err("Give the AppImage to adopt, or a directory to --scan".into()),
+    };
+    if let Some(name) = &args.name {
+        validate_app_name(name)?;
+    }
+
+    let mut adopted = Vec::new();
+    for file in &files {
+        match adopt_file(ctx, file, args) {
+            Ok(report) => adopted.push(report),
+            Err(e) if scan.is_some() => warn!(
+                "{}",
+                tr!(
+                    "adopt-skipping",
+                    file = file.display().to_string(),
+                    error = e.to_string()
+                )
+            ),
+            Err(e) => return Err(format!("Cannot adopt {}: {}", file.display(), e).into()),
+        }
+    }
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&adopted)?;
+    } else if scan.is_some() {
+        let (count, total) = (adopted.len(), files.len());
+        if args.dry_run {
+            info!(
+                "{}",
+                tr!("would-adopt-count", adopted = count, total = total)
+            );
+        } else {
+            info!("{}", tr!("adopted-count", adopted = count, total = total));
+        }
+    }
+    Ok(())
+}
+
+/// Adopt one AppImage, returning what was recorded.
+pub fn adopt_file(
+    ctx: &Context,
+    file: &Path,
+    args: &AdoptArgs,
+) -> Result<serde_json::Value, Error> {
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", file.display()))?
+        .to_string_lossy()
+        .into_owned();
+    verify_appimage(file, &file_name, arch::host())?;
+
+    let state = State::load(&ctx.state_path)?;
+    if let Some((name, _)) = state
+        .apps
+        .iter()
+        .find(|(_, installed)| installed.install_path == file)
+    {
+        return Err(format!("already managed as {}", name).into());
+    }
+
+    let embedded = update_info::read(file)?.and_then(|info| info.repo());
+    let spec = match args.repo.clone().or(embedded) {
+        Some(spec) => spec,
+        None => ask(ctx, &tr!("adopt-repo-question", file = file_name.as_str()))?.ok_or(
+            "it has no update information saying where it's from; give its repository with --repo",
+        )?,
+    };
+    let mut app = ctx.config.resolve(&spec, None)?;
+    if let Some(name) = &args.name {
+        app.name = name.clone();
+    }
+    if let Some(installed) = state.apps.get(&app.name) {
+        return Err(format!(
+            "{} is already installed ({}, {})",
+            app.name,
+            installed.tag,
+            installed.install_path.display()
+        )
+        .into());
+    }
+    let version = args
+        .version
+        .as_deref()
+        .or_else(|| version_in(&file_name))
+        .ok_or("cannot tell its version from its name; give it with --version")?
+        .to_string();
+
+    let report = serde_json::json!({
+        "app": app.name,
+        "repo": app.repo.to_string(),
+        "action": if args.dry_run { "would-adopt" } else { "adopted" },
+        "version": version,
+        "path": file,
+    });
+    if args.dry_run {
+        if ctx.output != OutputFormat::Json {
+            info!(
+                "{}",
+                tr!(
+                    "would-adopt",
+                    file = file.display().to_string(),
+                    app = app.name.as_str(),
+                    version = version.as_str(),
+                    repo = app.repo.to_string()
+                )
+            );
+        }
+        return Ok(report);
+    }
+
+    let sha256 = sha256_file(file)?;
+    let install_args = InstallArgs::default();
+    let (symlink, desktop_entry) = integration_paths(ctx, &install_args, &app);
+    let sandbox = sandbox_for(&install_args, &app, None)?;
+    let extract = extract_for(&install_args, &app, sandbox)?;
+    let integration = integrate(
+        ctx,
+        &app,
+        file,
+        &version,
+        symlink.is_some(),
+        desktop_entry.is_some(),
+        sandbox,
+        extract,
+    )?;
+    let release = Release {
+        tag_name: version.clone(),
+        prerelease: false,
+        draft: false,
+        body: None,
+        assets: Vec::new(),
+    };
+    let asset = Asset {
+        name: file_name,
+        browser_download_url: String::new(),
+        size: fs::metadata(file)?.len(),
+        state: "uploaded".to_string(),
+        digest: None,
+    };
+    let repo = app.repo.to_string();
+    history::record(
+        ctx,
+        &Entry {
+            repo: Some(&repo),
+            new_version: Some(&version),
+            sha256: Some(&sha256),
+            path: Some(file),
+            ..Entry::new("adopted", &app.name)
+        },
+    );
+    ctx.update_state(|state| {
+        record_install(
+            state,
+            &app,
+            &release,
+            &asset,
+            sha256,
+            file.to_path_buf(),
+            integration,
+            Provenance::default(),
+        )
+    })?;
+
+    if ctx.output != OutputFormat::Json {
+        info!(
+            "{}",
+            style::success(tr!(
+                "adopted",
+                file = file.display().to_string(),
+                app = app.name.as_str(),
+                version = version.as_str(),
+                repo = app.repo.to_string()
+            ))
+        );
+    }
+    Ok(report)
+}
+
+/// The AppImages in `dir`, going by their names.
+pub fn appimages_in(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        let is_appimage = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("appimage"));
+        if is_appimage && path.is_file() {
+            files.push(path);
+        } else {
+            debug!("Not an AppImage: {}", path.display());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Ask for an answer to `question`; `None` when it is left empty or can't be asked
+/// (stdin and stderr aren't terminals, or output is quieted).
+fn ask(ctx: &Context, question: &str) -> Result<Option<String>, Error> {
+    if !ctx.level.shows_info() || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(None);
+    }
+    progress::bars().suspend(|| {
+        eprint!("{} ", question);
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        Ok((!answer.is_empty()).then(|| answer.to_string()))
+    })
+}
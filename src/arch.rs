@@ -0,0 +1,78 @@
+// CPU architecture detection and matching of architecture names in asset file names.
+use std::env::consts::ARCH;
+
+/// Architectures we know how to recognise, with the spellings used in release asset names.
+const KNOWN: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x86-64", "amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("arm", &["armhf", "armv7l", "armv7", "arm"]),
+    ("x86", &["i386", "i686", "x86"]),
+];
+
+/// The architecture this binary was built for, in Rust's naming (`x86_64`, `aarch64`, ...).
+pub fn host() -> &'static str {
+    ARCH
+}
+
+/// The architecture an ELF `e_machine` value stands for, if it is one we know.
+pub fn from_elf_machine(machine: u16) -> Option<&'static str> {
+    match machine {
+        0x3e => Some("x86_64"),
+        0xb7 => Some("aarch64"),
+        0x28 => Some("arm"),
+        0x03 => Some("x86"),
+        _ => None,
+    }
+}
+
+/// Map a user-supplied architecture name (e.g. `amd64`, `arm64`) to its canonical name.
+pub fn parse(value: &str) -> Result<String, String> {
+    let value = value.to_lowercase();
+    KNOWN
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&value.as_str()))
+        .map(|(arch, _)| arch.to_string())
+        .ok_or_else(|| {
+            format!(
+                "Unknown architecture '{}': expected x86_64, aarch64, arm or x86",
+                value
+            )
+        })
+}
+
+/// How well `asset_name` suits `arch`: `Some(2)` when it names `arch`, `Some(1)` when it
+/// names no known architecture (likely a single-arch release), `None` when it is for
+/// a different architecture.
+pub fn score(asset_name: &str, arch: &str) -> Option<u8> {
+    let name = asset_name.to_lowercase();
+    let mut mentions_other = false;
+
+    for (known, aliases) in KNOWN {
+        if aliases.iter().any(|alias| contains_word(&name, alias)) {
+            if *known == arch {
+                return Some(2);
+            }
+            mentions_other = true;
+        }
+    }
+
+    if mentions_other {
+        None
+    } else {
+        Some(1)
+    }
+}
+
+/// Whether `word` occurs in `text` without being part of a longer alphanumeric run
+/// (so `arm` does not match `arm64`, and `x86` does not match `x86_64`).
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(start, _)| {
+        let rest = &text[start + word.len()..];
+        let before = text[..start].chars().next_back();
+        let after = rest.chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !rest.starts_with("_64")
+            && !rest.starts_with("-64")
+    })
+}
@@ -0,0 +1,203 @@
+// Archive release assets (`asset_type = "archive"`): tarballs and zip files holding a
+// single program, as many Rust and Go tools publish instead of AppImages. The program
+// is unpacked from the downloaded archive and then installed like an AppImage.
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::install::create_dirs;
+use crate::Error;
+
+/// File name endings of the archives that can be unpacked.
+const EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".zip"];
+
+/// Whether the asset `name` is an archive that can be unpacked.
+pub fn is_archive(name: &str) -> bool {
+    EXTENSIONS
+        .iter()
+        .any(|extension| name.to_ascii_lowercase().ends_with(extension))
+}
+
+/// The asset `name` without its archive extension, which the program unpacked from it
+/// is installed as.
+pub fn program_name(name: &str) -> &str {
+    let lowercase = name.to_ascii_lowercase();
+    match EXTENSIONS
+        .iter()
+        .find(|extension| lowercase.ends_with(*extension))
+    {
+        Some(extension) => &name[..name.len() - extension.len()],
+        None => name,
+    }
+}
+
+/// A program found in an archive, unpacked to a scratch file.
+struct Program {
+    /// Its path inside the archive
+    member: String,
+    unpacked: PathBuf,
+}
+
+/// Unpack the program in `archive`, downloaded as the asset `name`, to `path`,
+/// returning its path inside the archive. The program is the archive's only ELF
+/// executable or, when there are several, the one named `app_name`; READMEs, licences
+/// and completion scripts are left behind. The scratch directory is created with
+/// `dir_mode`.
+pub fn unpack_program(
+    archive: &Path,
+    name: &str,
+    app_name: &str,
+    path: &Path,
+    dir_mode: Option<u32>,
+) -> Result<String, Error> {
+    let work_dir = path.with_file_name(format!(".{}-extract", app_name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    create_dirs(&work_dir, dir_mode)?;
+
+    let result = unpack_programs(archive, name, &work_dir).and_then(|programs| {
+        let program = choose(programs, name, app_name)?;
+        debug!("Unpacked {} from {}", program.member, name);
+        fs::rename(&program.unpacked, path)?;
+        Ok(program.member)
+    });
+    fs::remove_dir_all(&work_dir)?;
+    result
+}
+
+/// Unpack every ELF executable in `archive` into `work_dir`, under numbered names so
+/// no member can be written outside it.
+fn unpack_programs(archive: &Path, name: &str, work_dir: &Path) -> Result<Vec<Program>, Error> {
+    let file = File::open(archive)?;
+    let result = if name.to_ascii_lowercase().ends_with(".zip") {
+        unpack_zip(file, work_dir)
+    } else {
+        unpack_tar(file, work_dir)
+    };
+    result.map_err(|e| format!("Cannot unpack {}: {}", name, e).into())
+}
+
+fn unpack_tar(file: File, work_dir: &Path) -> io::Result<Vec<Program>> {
+    let mut programs = Vec::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // Links would point at files outside the archive or at other members
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let member = entry.path()?.display().to_string();
+        if let Some(program) = unpack_if_program(&mut entry, member, work_dir, programs.len())? {
+            programs.push(program);
+        }
+    }
+    Ok(programs)
+}
+
+fn unpack_zip(file: File, work_dir: &Path) -> io::Result<Vec<Program>> {
+    let mut programs = Vec::new();
+    let mut archive = zip::ZipArchive::new(file)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if !entry.is_file() || entry.is_symlink() {
+            continue;
+        }
+        let member = entry.name().to_string();
+        if let Some(program) = unpack_if_program(&mut entry, member, work_dir, programs.len())? {
+            programs.push(program);
+        }
+    }
+    Ok(programs)
+}
+
+/// Unpack the archive member `member`, read from `entry`, as the `number`-th program
+/// when it is an ELF executable.
+fn unpack_if_program(
+    entry: &mut impl Read,
+    member: String,
+    work_dir: &Path,
+    number: usize,
+) -> io::Result<Option<Program>> {
+    let mut magic = Vec::with_capacity(4);
+    entry.take(4).read_to_end(&mut magic)?;
+    if magic != b"\x7fELF" {
+        return Ok(None);
+    }
+    let unpacked = work_dir.join(number.to_string());
+    let mut file = File::create(&unpacked)?;
+    file.write_all(&magic)?;
+    io::copy(entry, &mut file)?;
+    Ok(Some(Program { member, unpacked }))
+}
+
+/// The program to install among `programs`, unpacked from the asset `name`.
+fn choose(mut programs: Vec<Program>, name: &str, app_name: &str) -> Result<Program, Error> {
+    if programs.len() > 1 {
+        let file_name = |program: &Program| {
+            program
+                .member
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        };
+        match programs
+            .iter()
+            .position(|program| file_name(program).eq_ignore_ascii_case(app_name))
+        {
+            Some(index) => return Ok(programs.swap_remove(index)),
+            None => {
+                let members: Vec<&str> = programs
+                    .iter()
+                    .map(|program| program.member.as_str())
+                    .collect();
+                return Err(format!(
+                    "{} holds several programs ({}) and none is named {}; name the app after the one to install",
+                    name,
+                    members.join(", "),
+                    app_name
+                )
+                .into());
+            }
+        }
+    }
+    programs
+        .pop()
+        .ok_or_else(|| format!("{} holds no Linux program", name).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(member: &str) -> Program {
+        Program {
+            member: member.to_string(),
+            unpacked: PathBuf::from(member),
+        }
+    }
+
+    #[test]
+    fn names_programs_after_their_archive() {
+        assert_eq!(program_name("tool-1.0-x86_64.tar.gz"), "tool-1.0-x86_64");
+        assert_eq!(program_name("tool.TGZ"), "tool");
+        assert_eq!(program_name("tool.zip"), "tool");
+        assert_eq!(program_name("tool.AppImage"), "tool.AppImage");
+        assert!(is_archive("Tool.Tar.Gz") && !is_archive("tool.tar.xz"));
+    }
+
+    #[test]
+    fn chooses_the_only_program_or_the_one_named_after_the_app() {
+        let chosen = choose(vec![program("tool-1.0/tool")], "tool.tar.gz", "other").unwrap();
+        assert_eq!(chosen.member, "tool-1.0/tool");
+
+        let programs = || vec![program("bin/helper"), program("bin/Tool")];
+        let chosen = choose(programs(), "tool.tar.gz", "tool").unwrap();
+        assert_eq!(chosen.member, "bin/Tool");
+        assert!(choose(programs(), "tool.tar.gz", "other").is_err());
+        assert!(choose(Vec::new(), "tool.tar.gz", "tool").is_err());
+    }
+}
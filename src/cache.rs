@@ -0,0 +1,367 @@
+// On-disk caches under $XDG_CACHE_HOME/rust-unicorn:
+//
+// - `api`: API responses, revalidated with their ETag so an unchanged release costs a
+//   304 instead of rate-limit quota.
+// - `downloads`: downloaded AppImages, stored once per content as `sha256/<hash>` with
+//   `urls/<hash of url>` naming the content of each URL, so installing a version
+//   again (a reinstall, a downgrade) copies it instead of downloading it. URL entries
+//   keep the server's ETag/Last-Modified, and after `REVALIDATE_AFTER` the server is
+//   asked whether the file changed before the copy is used.
+use indicatif::HumanBytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+use crate::checksum::{self, Algorithm};
+use crate::cli::OutputFormat;
+use crate::download::Validators;
+use crate::install::create_dirs;
+use crate::verify::sha256_file;
+use crate::{paths, tr, usage, Asset, Context, Error};
+
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+/// Cache directory: `$XDG_CACHE_HOME/rust-unicorn/api`, falling back to
+/// `~/.cache/rust-unicorn/api`.
+pub fn cache_dir() -> Option<PathBuf> {
+    Some(paths::cache_home()?.join("rust-unicorn").join("api"))
+}
+
+/// Directory of cached downloads: `$XDG_CACHE_HOME/rust-unicorn/downloads`.
+pub fn downloads_dir() -> Option<PathBuf> {
+    Some(paths::cache_home()?.join("rust-unicorn").join("downloads"))
+}
+
+/// Cache file for `url`, named by its hash.
+fn entry_path(url: &str) -> Option<PathBuf> {
+    Some(cache_dir()?.join(format!("{:x}.json", Sha256::digest(url.as_bytes()))))
+}
+
+/// The cached response for `url`, if any. Unreadable entries count as missing.
+pub fn load(url: &str) -> Option<CachedResponse> {
+    let text = fs::read_to_string(entry_path(url)?).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// Cache `body` as the response for `url` with its `etag`, creating the cache
+/// directory with `dir_mode`.
+pub fn store(url: &str, etag: &str, body: &str, dir_mode: Option<u32>) -> io::Result<()> {
+    let Some(path) = entry_path(url) else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        create_dirs(parent, dir_mode)?;
+    }
+
+    let entry = CachedResponse {
+        etag: etag.to_string(),
+        body: body.to_string(),
+    };
+    // Write to a temporary file first so concurrent updates never read a partial entry
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string(&entry)?)?;
+    fs::rename(&tmp_path, &path)
+}
+
+/// How long a download cached under its URL is used without asking the server
+/// whether the file changed.
+const REVALIDATE_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+/// What the download cache knows about a URL.
+#[derive(Serialize, Deserialize)]
+pub struct UrlEntry {
+    /// SHA-256 of the file last downloaded from it
+    pub sha256: String,
+    #[serde(flatten)]
+    pub validators: Validators,
+    /// When the server last sent or confirmed the file, in seconds since the Unix epoch
+    #[serde(default)]
+    pub checked_at: u64,
+}
+
+/// A download found in the cache.
+pub enum CachedDownload {
+    /// Copied into place; its SHA-256
+    Restored(String),
+    /// Cached under its URL, but to be used only once the server confirms the file is
+    /// unchanged
+    Revalidate(UrlEntry),
+}
+
+/// Whether downloads are cached, as the config's `cache_downloads` says (by default
+/// they are).
+pub fn caching_downloads(ctx: &Context) -> bool {
+    ctx.config.cache_downloads.unwrap_or(true)
+}
+
+/// The cached download of `asset`, when one is cached under the SHA-256 the release
+/// gives for it or under its URL. It is copied to `path` right away unless it was
+/// found by URL with an ETag or Last-Modified, and `revalidate` is set or it was
+/// last checked over `REVALIDATE_AFTER` ago. With `revalidate`, a file cached by
+/// URL without either isn't used at all, as there is no way to check it.
+pub fn find_download(
+    ctx: &Context,
+    asset: &Asset,
+    path: &Path,
+    revalidate: bool,
+) -> Option<CachedDownload> {
+    if !caching_downloads(ctx) {
+        return None;
+    }
+    let published = asset
+        .digest
+        .as_deref()
+        .and_then(checksum::parse_digest)
+        .filter(|checksum| checksum.algorithm == Algorithm::Sha256)
+        .map(|checksum| checksum.digest);
+    let sha256 = match published {
+        Some(sha256) => sha256,
+        None => {
+            let entry = url_entry(&asset.browser_download_url)?;
+            match must_revalidate(&entry, revalidate, now())? {
+                true => return Some(CachedDownload::Revalidate(entry)),
+                false => entry.sha256,
+            }
+        }
+    };
+    let sha256 = restore_download(&sha256, path)?;
+    info!(
+        "{}",
+        tr!("using-cached-download", asset = asset.name.as_str())
+    );
+    Some(CachedDownload::Restored(sha256))
+}
+
+/// Whether the file cached under a URL's `entry` may be used only once the server
+/// confirms it is unchanged, at time `now`; `None` when it can't be used at all.
+fn must_revalidate(entry: &UrlEntry, revalidate: bool, now: u64) -> Option<bool> {
+    let fresh = now.saturating_sub(entry.checked_at) < REVALIDATE_AFTER.as_secs();
+    match (entry.validators.is_empty(), revalidate) {
+        (true, true) => None,
+        // Without validators there is nothing to ask, as before they were kept
+        (true, false) => Some(false),
+        (false, _) => Some(revalidate || !fresh),
+    }
+}
+
+/// The entry for `url`.
+fn url_entry(url: &str) -> Option<UrlEntry> {
+    let text = fs::read_to_string(downloads_dir()?.join("urls").join(url_key(url))).ok()?;
+    Some(parse_url_entry(&text))
+}
+
+/// A URL entry, also when written as a bare SHA-256 by earlier versions.
+fn parse_url_entry(text: &str) -> UrlEntry {
+    serde_json::from_str(text).unwrap_or_else(|_| UrlEntry {
+        sha256: text.trim().to_string(),
+        validators: Validators::default(),
+        checked_at: 0,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Copy the cached file with SHA-256 `sha256` to `path`, returning the SHA-256. A
+/// cached file that doesn't hash to its name is dropped rather than used.
+pub fn restore_download(sha256: &str, path: &Path) -> Option<String> {
+    let cached = downloads_dir()?.join("sha256").join(sha256);
+    if !cached.is_file() {
+        return None;
+    }
+
+    debug!(
+        "Copying the cached {} to {}",
+        cached.display(),
+        path.display()
+    );
+    let copied = fs::copy(&cached, path).and_then(|_| sha256_file(path));
+    match copied {
+        Ok(actual) if actual == sha256 => Some(actual),
+        Ok(_) | Err(_) => {
+            debug!("Dropping the damaged cache entry {}", cached.display());
+            let _ = fs::remove_file(&cached);
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+/// Keep a copy of the verified download of `url` at `path`, whose SHA-256 is
+/// `sha256`, for installing it again later. Content already cached under another URL
+/// is stored only once. `validators` are what the server just sent or confirmed for
+/// it; without them (for files that came from the cache) the URL's entry is only
+/// written when it doesn't name this file yet.
+pub fn store_download(
+    ctx: &Context,
+    url: &str,
+    sha256: &str,
+    path: &Path,
+    validators: Option<&Validators>,
+) -> io::Result<()> {
+    let Some(dir) = downloads_dir().filter(|_| caching_downloads(ctx)) else {
+        return Ok(());
+    };
+    let blob_dir = dir.join("sha256");
+    let url_dir = dir.join("urls");
+    create_dirs(&blob_dir, ctx.dir_mode)?;
+    create_dirs(&url_dir, ctx.dir_mode)?;
+
+    let blob = blob_dir.join(sha256);
+    if !blob.exists() {
+        // Copied under a temporary name first so a lookup never finds a partial file
+        let tmp_path = blob.with_extension("tmp");
+        fs::copy(path, &tmp_path)?;
+        fs::rename(&tmp_path, &blob)?;
+        debug!("Cached {} as {}", url, blob.display());
+    }
+    let known = url_entry(url).is_some_and(|entry| entry.sha256 == sha256);
+    if validators.is_none() && known {
+        return Ok(());
+    }
+    let entry = UrlEntry {
+        sha256: sha256.to_string(),
+        validators: validators.cloned().unwrap_or_default(),
+        checked_at: now(),
+    };
+    let entry_path = url_dir.join(url_key(url));
+    let tmp_path = entry_path.with_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_string(&entry)?)?;
+    fs::rename(&tmp_path, entry_path)
+}
+
+/// File name of the entry for `url`: the hash of the URL.
+fn url_key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+/// Number of files in `dir` and their total size.
+fn count(dir: &Path) -> (usize, u64) {
+    let files = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).count())
+        .unwrap_or(0);
+    (files, usage::size(dir))
+}
+
+/// `cache stats`: how many downloads and API responses are cached, and their size.
+pub fn stats(ctx: &Context) -> Result<(), Error> {
+    let (downloads, download_bytes) =
+        downloads_dir().map_or((0, 0), |dir| count(&dir.join("sha256")));
+    let (responses, response_bytes) = cache_dir().map_or((0, 0), |dir| count(&dir));
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "downloads": { "files": downloads, "bytes": download_bytes },
+            "api_responses": { "files": responses, "bytes": response_bytes },
+        }))?;
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "cache-downloads",
+                count = downloads,
+                size = HumanBytes(download_bytes).to_string()
+            )
+        );
+        info!(
+            "{}",
+            tr!(
+                "cache-api-responses",
+                count = responses,
+                size = HumanBytes(response_bytes).to_string()
+            )
+        );
+        if let Some(dir) = downloads_dir() {
+            info!(
+                "{}",
+                tr!(
+                    "cache-location",
+                    path = dir.parent().unwrap_or(&dir).display().to_string()
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `cache clean`: delete the cached downloads and API responses.
+pub fn clean(ctx: &Context) -> Result<(), Error> {
+    let mut reclaimed = 0;
+    for dir in [downloads_dir(), cache_dir()].into_iter().flatten() {
+        if dir.exists() {
+            reclaimed += usage::size(&dir);
+            debug!("Removing {}", dir.display());
+            fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Cannot remove {}: {}", dir.display(), e))?;
+        }
+    }
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({ "reclaimed_bytes": reclaimed }))?;
+    } else {
+        info!(
+            "{}",
+            tr!("cache-cleaned", size = HumanBytes(reclaimed).to_string())
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 3600;
+
+    fn entry(etag: Option<&str>, checked_at: u64) -> UrlEntry {
+        UrlEntry {
+            sha256: "a".repeat(64),
+            validators: Validators {
+                etag: etag.map(str::to_string),
+                last_modified: None,
+            },
+            checked_at,
+        }
+    }
+
+    #[test]
+    fn cached_downloads_are_revalidated_when_stale_or_asked_to() {
+        let now = 100 * DAY;
+        let checked = entry(Some("\"v1\""), now - 60);
+        assert_eq!(must_revalidate(&checked, false, now), Some(false));
+        assert_eq!(must_revalidate(&checked, true, now), Some(true));
+        let stale = entry(Some("\"v1\""), now - DAY - 1);
+        assert_eq!(must_revalidate(&stale, false, now), Some(true));
+
+        // Nothing to revalidate with: used as is, unless revalidation is demanded
+        let unvalidated = entry(None, 0);
+        assert_eq!(must_revalidate(&unvalidated, false, now), Some(false));
+        assert_eq!(must_revalidate(&unvalidated, true, now), None);
+    }
+
+    #[test]
+    fn url_entries_keep_their_validators_and_read_old_entries() {
+        let written = serde_json::to_string(&entry(Some("\"v1\""), 5)).unwrap();
+        let read = parse_url_entry(&written);
+        assert_eq!(read.sha256, "a".repeat(64));
+        assert_eq!(read.validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(read.checked_at, 5);
+
+        let old = parse_url_entry(&format!("{}\n", "b".repeat(64)));
+        assert_eq!(old.sha256, "b".repeat(64));
+        assert!(old.validators.is_empty());
+        assert_eq!(old.checked_at, 0);
+    }
+}
@@ -0,0 +1,60 @@
+// Built-in catalog of popular AppImage apps (catalog.toml), letting them be
+// installed by short name.
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::cli::Provider;
+use crate::config::{AppConfig, Channel};
+
+/// An app in the catalog.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogEntry {
+    /// One line saying what the app is
+    pub description: String,
+    pub repo: String,
+    pub provider: Option<Provider>,
+    #[serde(default)]
+    pub channel: Channel,
+    pub asset_pattern: Option<String>,
+    pub checksum_asset: Option<String>,
+}
+
+impl CatalogEntry {
+    /// The entry as the settings of a configured app.
+    pub fn settings(&self) -> AppConfig {
+        AppConfig {
+            repo: self.repo.clone(),
+            provider: self.provider,
+            channel: self.channel,
+            asset_pattern: self.asset_pattern.clone(),
+            checksum_asset: self.checksum_asset.clone(),
+            ..AppConfig::default()
+        }
+    }
+}
+
+/// Every catalog entry, keyed by short name.
+pub fn entries() -> &'static BTreeMap<String, CatalogEntry> {
+    static CATALOG: OnceLock<BTreeMap<String, CatalogEntry>> = OnceLock::new();
+    // The catalog ships inside the binary, so a parse error is a build mistake
+    CATALOG.get_or_init(|| {
+        toml::from_str(include_str!("catalog.toml")).expect("invalid built-in catalog")
+    })
+}
+
+/// The catalog entry called `name`.
+pub fn get(name: &str) -> Option<&'static CatalogEntry> {
+    entries().get(name)
+}
+
+/// Catalog entries whose name, description or repository mention `term`, ignoring case.
+pub fn search(term: &str) -> impl Iterator<Item = (&'static String, &'static CatalogEntry)> {
+    let term = term.to_lowercase();
+    entries().iter().filter(move |(name, entry)| {
+        [name.as_str(), &entry.description, &entry.repo]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&term))
+    })
+}
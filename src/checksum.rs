@@ -0,0 +1,313 @@
+// Locating and parsing the checksum files published alongside release assets: coreutils
+// `sha256sum`/`sha512sum` output, BSD-style `.DIGEST` files, bare per-asset digests and
+// electron-builder's `latest-linux.yml`.
+use base64::Engine;
+
+use crate::Asset;
+
+/// Hash function a published checksum was made with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// The algorithm producing hex digests of `len` characters.
+    fn from_hex_len(len: usize) -> Option<Algorithm> {
+        match len {
+            64 => Some(Algorithm::Sha256),
+            128 => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+        }
+    }
+}
+
+/// A digest published for an asset.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// Lowercase hex
+    pub digest: String,
+}
+
+/// Checksum assets that may cover `asset_name`, most specific first: the `configured`
+/// one (with `{asset}` standing for `asset_name`) alone if given and present, else
+/// per-asset files (`<name>.sha256`, `.sha512`, `.DIGEST`, ...), combined
+/// SHA256SUMS-style files and electron-builder's `latest-linux*.yml`.
+pub fn find_checksum_assets<'a>(
+    assets: &'a [Asset],
+    asset_name: &str,
+    configured: Option<&str>,
+) -> Vec<&'a Asset> {
+    if let Some(configured) = configured {
+        let name = configured.replace("{asset}", asset_name);
+        if let Some(asset) = assets.iter().find(|a| a.name == name) {
+            return vec![asset];
+        }
+    }
+
+    let per_asset = |name: &str| {
+        let name = name.to_lowercase();
+        name.strip_prefix(&asset_name.to_lowercase())
+            .is_some_and(|suffix| {
+                [".sha256", ".sha256sum", ".sha512", ".sha512sum", ".digest"].contains(&suffix)
+            })
+    };
+    let combined = |name: &str| {
+        let name = name.to_lowercase();
+        matches!(
+            name.as_str(),
+            "sha256sums"
+                | "sha256sums.txt"
+                | "sha256sum.txt"
+                | "sha512sums"
+                | "sha512sums.txt"
+                | "sha512sum.txt"
+                | "checksums.txt"
+                | "checksums.sha256"
+                | "checksums.sha512"
+        ) || [
+            "_sha256sums.txt",
+            "-sha256sums.txt",
+            "_sha512sums.txt",
+            "-sha512sums.txt",
+            "_checksums.txt",
+            "-checksums.txt",
+        ]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+    };
+    let electron_builder = |name: &str| name.starts_with("latest-linux") && name.ends_with(".yml");
+
+    let mut found: Vec<&Asset> = assets.iter().filter(|a| per_asset(&a.name)).collect();
+    found.extend(assets.iter().filter(|a| combined(&a.name)));
+    found.extend(assets.iter().filter(|a| electron_builder(&a.name)));
+    found
+}
+
+/// Extract the checksum of `asset_name` from checksum file contents.
+///
+/// Understands coreutils output (`<hex>  <name>` or `<hex> *<name>`), BSD-style tagged
+/// lines (`SHA256 (<name>) = <hex>`), electron-builder's `latest-linux.yml` and
+/// single-hash files that contain only the digest.
+pub fn parse_checksum(text: &str, asset_name: &str) -> Option<Checksum> {
+    if text
+        .lines()
+        .any(|line| line.trim_start().starts_with("sha512:"))
+    {
+        return parse_electron_builder(text, asset_name);
+    }
+
+    let mut lone_hash = None;
+    let mut lines = 0;
+
+    for line in text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        lines += 1;
+        if let Some((name, hash)) = parse_tagged(line) {
+            if names_asset(name, asset_name) {
+                return checksum(hash);
+            }
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let hash = match fields.next().and_then(checksum) {
+            Some(hash) => hash,
+            None => continue,
+        };
+        match fields.next() {
+            Some(name) => {
+                if names_asset(name.trim_start_matches('*'), asset_name) {
+                    return Some(hash);
+                }
+            }
+            None => lone_hash = Some(hash),
+        }
+    }
+
+    // A bare digest only counts when it is the whole file
+    if lines == 1 {
+        lone_hash
+    } else {
+        None
+    }
+}
+
+/// The checksum of `asset_name` pasted into release notes: a line naming the asset
+/// with a digest beside it, whatever markdown surrounds them (`<hex>  <name>` in a
+/// code block, a table row, `- <name>: <hex>`, ...).
+pub fn parse_release_notes(body: &str, asset_name: &str) -> Option<Checksum> {
+    body.lines().find_map(|line| {
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || "|`*:,()=".contains(c))
+            .filter(|word| !word.is_empty())
+            .collect();
+        if !words.iter().any(|word| names_asset(word, asset_name)) {
+            return None;
+        }
+        words.iter().find_map(|word| checksum(word))
+    })
+}
+
+/// The checksum in an API's `<algorithm>:<hex>` asset digest, e.g. GitHub's `sha256:…`.
+pub fn parse_digest(digest: &str) -> Option<Checksum> {
+    let (algorithm, hex) = digest.split_once(':')?;
+    let checksum = checksum(hex)?;
+    let named = match checksum.algorithm {
+        Algorithm::Sha256 => "sha256",
+        Algorithm::Sha512 => "sha512",
+    };
+    algorithm.eq_ignore_ascii_case(named).then_some(checksum)
+}
+
+/// Name and digest of a BSD-style `SHA256 (<name>) = <hex>` line.
+fn parse_tagged(line: &str) -> Option<(&str, &str)> {
+    let (tag, rest) = line.split_once(" (")?;
+    if !tag.to_uppercase().starts_with("SHA") {
+        return None;
+    }
+    let (name, hash) = rest.rsplit_once(") = ")?;
+    Some((name, hash.trim()))
+}
+
+/// The SHA-512 electron-builder's update file lists for `asset_name`: each entry's
+/// `url` (or the top-level `path`) comes before its `sha512`, a base64 digest.
+fn parse_electron_builder(text: &str, asset_name: &str) -> Option<Checksum> {
+    let mut current = None;
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches("- ");
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+        match key.trim() {
+            "url" | "path" => current = Some(value),
+            "sha512" if current.is_some_and(|name| names_asset(name, asset_name)) => {
+                let digest = base64::engine::general_purpose::STANDARD
+                    .decode(value)
+                    .ok()?;
+                let digest: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+                return (digest.len() == 128).then_some(Checksum {
+                    algorithm: Algorithm::Sha512,
+                    digest,
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether `name` in a checksum file is `asset_name`. Some tools prefix the file with
+/// a directory, e.g. `dist/app.AppImage`.
+fn names_asset(name: &str, asset_name: &str) -> bool {
+    name == asset_name || name.rsplit('/').next() == Some(asset_name)
+}
+
+/// `value` as a hex digest of a known length.
+fn checksum(value: &str) -> Option<Checksum> {
+    let algorithm = Algorithm::from_hex_len(value.len())?;
+    value
+        .bytes()
+        .all(|b| b.is_ascii_hexdigit())
+        .then(|| Checksum {
+            algorithm,
+            digest: value.to_lowercase(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256(digest: &str) -> Option<Checksum> {
+        Some(Checksum {
+            algorithm: Algorithm::Sha256,
+            digest: digest.to_string(),
+        })
+    }
+
+    #[test]
+    fn parses_coreutils_bsd_and_lone_digests() {
+        let (a, b) = ("a".repeat(64), "B".repeat(64));
+        let sums = format!(
+            "# SHA-256\n{}  other.AppImage\n{} *dist/Tool.AppImage\n",
+            a, b
+        );
+        assert_eq!(
+            parse_checksum(&sums, "Tool.AppImage"),
+            sha256(&"b".repeat(64))
+        );
+        assert_eq!(parse_checksum(&sums, "Missing.AppImage"), None);
+
+        let tagged = format!("SHA256 (Tool.AppImage) = {}\n", a);
+        assert_eq!(parse_checksum(&tagged, "Tool.AppImage"), sha256(&a));
+
+        // A bare digest names no file, so it only counts when it is all there is
+        assert_eq!(
+            parse_checksum(&format!("{}\n", a), "Tool.AppImage"),
+            sha256(&a)
+        );
+        assert_eq!(
+            parse_checksum(&format!("{}\n{}  other\n", a, b), "Tool.AppImage"),
+            None
+        );
+        let sha512 = parse_checksum(&"c".repeat(128), "Tool.AppImage").unwrap();
+        assert_eq!(sha512.algorithm, Algorithm::Sha512);
+        assert_eq!(parse_checksum(&"d".repeat(63), "Tool.AppImage"), None);
+    }
+
+    #[test]
+    fn parses_electron_builder_update_files() {
+        let digest = [7u8; 64];
+        let encoded = base64::engine::general_purpose::STANDARD.encode(digest);
+        let yml = format!(
+            "version: 2.0\nfiles:\n  - url: Tool-2.0-arm64.AppImage\n    sha512: {}\n    size: 1\n  - url: Tool-2.0.AppImage\n    sha512: '{}'\n    size: 2\npath: Tool-2.0.AppImage\n",
+            base64::engine::general_purpose::STANDARD.encode([1u8; 64]),
+            encoded
+        );
+        let checksum = parse_checksum(&yml, "Tool-2.0.AppImage").unwrap();
+        assert_eq!(checksum.algorithm, Algorithm::Sha512);
+        assert_eq!(checksum.digest, "07".repeat(64));
+        assert_eq!(parse_checksum(&yml, "Tool-2.0.deb"), None);
+    }
+
+    #[test]
+    fn finds_digests_in_release_notes_and_api_fields() {
+        let a = "a".repeat(64);
+        let notes = format!(
+            "## Checksums\n\n| File | SHA-256 |\n|---|---|\n| `Other.AppImage` | `{}` |\n| `Tool.AppImage` | `{}` |\n",
+            "0".repeat(64),
+            a
+        );
+        assert_eq!(parse_release_notes(&notes, "Tool.AppImage"), sha256(&a));
+        assert_eq!(
+            parse_release_notes(&format!("- Tool.AppImage: {}", a), "Tool.AppImage"),
+            sha256(&a)
+        );
+        assert_eq!(
+            parse_release_notes("Tool.AppImage is new", "Tool.AppImage"),
+            None
+        );
+
+        assert_eq!(parse_digest(&format!("sha256:{}", a)), sha256(&a));
+        assert_eq!(
+            parse_digest(&format!("SHA256:{}", a.to_uppercase())),
+            sha256(&a)
+        );
+        // The algorithm must match the digest's length
+        assert_eq!(parse_digest(&format!("sha512:{}", a)), None);
+        assert_eq!(parse_digest(&a), None);
+    }
+}
@@ -0,0 +1,1089 @@
+// Command-line interface definition.
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::catalog;
+use crate::config::{AssetType, Channel, Config};
+use crate::http::TlsBackend;
+use crate::pattern::AssetPattern;
+use crate::sandbox::Sandbox;
+use crate::state::{self, State};
+use crate::OutputLevel;
+
+/// Repository installed when none is given on the command line.
+pub const DEFAULT_REPO: &str = "laurent22/joplin";
+
+/// AppImage installer for GitHub, GitLab and Gitea/Forgejo releases
+#[derive(Parser)]
+#[command(name = "rust-unicorn", version, about)]
+pub struct Cli {
+    /// Config file (default: $XDG_CONFIG_HOME/rust-unicorn/config.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Installation directory (default: ~/Applications if it exists, else $XDG_DATA_HOME/rust-unicorn/apps)
+    #[arg(short = 'd', long, global = true, value_name = "PATH")]
+    pub install_dir: Option<PathBuf>,
+
+    /// Directory for app symlinks, ideally on PATH (default: ~/.local/bin)
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        conflicts_with = "symlink_in_install_dir"
+    )]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Put app symlinks next to the AppImages in the install directory (the old behavior)
+    #[arg(long, global = true)]
+    pub symlink_in_install_dir: bool,
+
+    /// Octal mode for directories created by the installer (default: from umask)
+    #[arg(long, global = true, value_name = "MODE", value_parser = parse_dir_mode)]
+    pub dir_mode: Option<u32>,
+
+    /// GitHub token used to authenticate API requests (default: $GITHUB_TOKEN, then the config file)
+    #[arg(long, global = true, value_name = "TOKEN")]
+    pub token: Option<String>,
+
+    /// Send all requests through this proxy: http://, https://, socks5:// or socks5h://
+    /// (default: from HTTPS_PROXY/HTTP_PROXY/ALL_PROXY, except hosts in NO_PROXY)
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Also trust the root certificates in this PEM file (e.g. of a TLS-intercepting proxy)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cacert: Option<PathBuf>,
+
+    /// TLS implementation for HTTPS (default: native)
+    #[arg(long, global = true, value_enum, value_name = "BACKEND")]
+    pub tls: Option<TlsBackend>,
+
+    /// Retry downloads and API requests up to N times after transient network failures (default: 3)
+    #[arg(long, global = true, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Give up connecting to a server after DURATION (default: 30s; 0 waits forever)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = duration_text)]
+    pub connect_timeout: Option<String>,
+
+    /// Fail a request when the server sends nothing for DURATION (default: 60s; 0 waits
+    /// forever). Such failures are retried
+    #[arg(long, global = true, value_name = "DURATION", value_parser = duration_text)]
+    pub read_timeout: Option<String>,
+
+    /// Abort a download that takes longer than DURATION in all (default: 2h; 0 for no limit)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = duration_text)]
+    pub download_timeout: Option<String>,
+
+    /// Wait up to DURATION (e.g. 15m) for an exhausted API rate limit to reset, instead of failing
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    pub wait_for_rate_limit: Option<Duration>,
+
+    /// Cap the download rate at RATE bytes per second, shared by parallel downloads
+    /// (e.g. 500K or 2M)
+    #[arg(long, global = true, value_name = "RATE", value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// When another rust-unicorn process is changing the install directory, wait for it
+    /// instead of failing
+    #[arg(long, global = true)]
+    pub wait_for_lock: bool,
+
+    /// Take over the Joplin AppImages and `joplin` symlink older versions left in
+    /// ~/Documents/repository/rust-unicorn without asking, leaving the files in place or
+    /// moving them to the default install directory (--migrate-legacy=move)
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "HOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "in-place"
+    )]
+    pub migrate_legacy: Option<LegacyMigration>,
+
+    /// Show more detail: -v for debug messages (HTTP requests, asset selection, file
+    /// operations), -vv for trace messages. RUST_LOG overrides this
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Suppress output messages, but still report errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Suppress all output, including errors (exit code only)
+    #[arg(short, long, global = true)]
+    pub silent: bool,
+
+    /// Output format: human-readable text, or JSON describing what was done
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text"
+    )]
+    pub output: OutputFormat,
+
+    /// When to color messages: auto colors them on terminals unless NO_COLOR is set
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto"
+    )]
+    pub color: ColorChoice,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// The output level selected by `--quiet`/`--silent`; `--silent` wins when both are given.
+    pub fn output_level(&self) -> OutputLevel {
+        if self.silent {
+            OutputLevel::Silent
+        } else if self.quiet {
+            OutputLevel::Quiet
+        } else {
+            OutputLevel::Normal
+        }
+    }
+}
+
+/// Write the completion script for `shell`. It calls back into `rust-unicorn` (with
+/// `COMPLETE` set) for every completion, so app names are always current.
+pub fn write_completions(shell: Shell, out: &mut dyn io::Write) -> io::Result<()> {
+    let shells = clap_complete::env::Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| io::Error::other(format!("Completions for {} are not supported", shell)))?;
+    completer.write_registration(
+        "COMPLETE",
+        "rust-unicorn",
+        "rust-unicorn",
+        "rust-unicorn",
+        out,
+    )
+}
+
+/// Names of installed, configured and catalog apps, for completing apps to install.
+fn install_candidates() -> Vec<CompletionCandidate> {
+    let mut candidates = app_candidates();
+    let known: Vec<String> = candidates
+        .iter()
+        .map(|c| c.get_value().to_string_lossy().into_owned())
+        .collect();
+    candidates.extend(
+        catalog::entries()
+            .iter()
+            .filter(|(name, _)| !known.contains(name))
+            .map(|(name, entry)| {
+                CompletionCandidate::new(name).help(Some(entry.description.clone().into()))
+            }),
+    );
+    candidates
+}
+
+/// Names of installed and configured apps, for completing APP arguments.
+fn app_candidates() -> Vec<CompletionCandidate> {
+    let mut names: Vec<String> = Config::load(None)
+        .map(|config| config.apps.into_keys().collect())
+        .unwrap_or_default();
+    if let Some(state) = state::default_path().and_then(|path| State::load(&path).ok()) {
+        names.extend(state.apps.into_keys());
+    }
+    names.sort();
+    names.dedup();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Install the latest AppImage release of a repository
+    Install(InstallCommandArgs),
+    /// Update an installed app (or all of them) to the latest release
+    Update(UpdateArgs),
+    /// Take over AppImages installed by hand, so they're managed from now on
+    Adopt(AdoptArgs),
+    /// Remove an installed app with its symlink, desktop entry, icon and metainfo
+    #[command(alias = "uninstall")]
+    Remove {
+        /// App to remove, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+
+        /// Also delete older versions of the app left in the install directory
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Go back to the version an app's last update replaced (run again to undo)
+    Rollback {
+        /// App to roll back, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+    },
+    /// Delete replaced versions beyond `keep_versions` and other leftovers, reporting
+    /// the space reclaimed
+    Cleanup {
+        /// Only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Hold an app at its installed version; `update --all` skips it
+    Pin {
+        /// App to pin, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+    },
+    /// Let a pinned app be updated again
+    Unpin {
+        /// App to unpin, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+    },
+    /// List installed apps
+    List,
+    /// Show what is recorded about an installed app
+    Info {
+        /// App to show, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+    },
+    /// Show how much space each app takes, with its old versions, extracted files and icon
+    #[command(alias = "du")]
+    DiskUsage {
+        /// Order of the apps
+        #[arg(long, value_enum, default_value_t = UsageSort::Name)]
+        sort: UsageSort,
+    },
+    /// Update rust-unicorn itself to its latest release
+    SelfUpdate {
+        /// Reinstall even if this already is the latest version
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Print a completion script for SHELL, completing app names dynamically.
+    /// Load it with e.g. `source <(rust-unicorn completions bash)`.
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Report apps with newer releases available, without downloading.
+    /// Exits with status 100 when updates are available.
+    Check {
+        /// Apps to check, as OWNER/REPO or configured app names (default: all installed and configured apps)
+        #[arg(value_name = "APP", add = ArgValueCandidates::new(app_candidates))]
+        apps: Vec<String>,
+
+        /// Print the result as JSON (same as --output json)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the installed apps, with their settings, versions and pins, as a manifest
+    /// for `import` (e.g. `rust-unicorn export > apps.toml`)
+    Export,
+    /// Install every app of a manifest written by `export`: pinned apps at their
+    /// recorded version and pinned again, the others at their latest release
+    Import {
+        /// Manifest to import
+        file: PathBuf,
+
+        /// Install every app at its recorded version instead of the latest release
+        #[arg(long)]
+        exact: bool,
+
+        /// Show what would be installed without downloading anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the release notes of an app's latest release (the one `update` would install)
+    Changelog {
+        /// App, as OWNER/REPO or the app name
+        #[arg(add = ArgValueCandidates::new(app_candidates))]
+        app: String,
+
+        /// Show the notes of this release tag instead
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+
+        /// Print the notes as published (markdown) instead of rendering them, e.g. for piping
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Find projects shipping AppImages, in the built-in catalog and on GitHub
+    Search {
+        /// Words to look for in project names and descriptions
+        term: String,
+
+        /// Show at most this many GitHub projects
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Run `update --all` on a schedule with a systemd user timer
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCommand,
+    },
+    /// Stay running and update installed apps periodically, notifying about the result;
+    /// an alternative to `schedule` without systemd
+    Daemon(DaemonArgs),
+    /// Recompute the SHA-256 of installed apps and compare it with the one recorded at
+    /// install and the checksums their releases publish, reporting tampered or corrupted
+    /// files
+    Verify {
+        /// Apps to verify (default: all installed apps)
+        #[arg(value_name = "APP", add = ArgValueCandidates::new(app_candidates))]
+        apps: Vec<String>,
+
+        /// Only compare with the recorded SHA-256, without asking the releases
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Check that installed apps are intact and FUSE is available, suggesting fixes
+    Doctor {
+        /// Apply the suggested fixes
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Inspect or empty the cache of downloads and API responses
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Store command-line flags as defaults in the config file, or show the defaults
+    /// in effect
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Browse the installed apps in a full-screen interface, updating, removing and
+    /// pinning them with single keys
+    Ui,
+}
+
+impl Command {
+    /// Whether the command changes installed apps or the state file, and so must not
+    /// run alongside another process doing the same.
+    pub fn modifies_installs(&self) -> bool {
+        match self {
+            Command::Install(args) => !args.install.dry_run,
+            Command::Update(args) => !args.install.dry_run,
+            Command::Import { dry_run, .. } | Command::Cleanup { dry_run } => !dry_run,
+            Command::Adopt(args) => !args.dry_run,
+            Command::Doctor { fix } => *fix,
+            Command::Remove { .. }
+            | Command::Rollback { .. }
+            | Command::Pin { .. }
+            | Command::Unpin { .. } => true,
+            Command::List
+            | Command::Info { .. }
+            | Command::DiskUsage { .. }
+            | Command::SelfUpdate { .. }
+            | Command::Completions { .. }
+            | Command::Check { .. }
+            | Command::Verify { .. }
+            | Command::Export
+            | Command::Changelog { .. }
+            | Command::Search { .. }
+            | Command::Schedule { .. }
+            | Command::Cache { .. }
+            | Command::Config { .. } => false,
+            // Take the lock for each run (or change) instead of holding it for good
+            Command::Daemon(_) | Command::Ui => false,
+        }
+    }
+}
+
+/// Subcommands of `schedule`.
+#[derive(Subcommand)]
+pub enum ScheduleCommand {
+    /// Install and start the timer (daily unless told otherwise)
+    Enable {
+        /// Update once a day
+        #[arg(long, conflicts_with_all = ["weekly", "on_calendar"])]
+        daily: bool,
+
+        /// Update once a week
+        #[arg(long, conflicts_with = "on_calendar")]
+        weekly: bool,
+
+        /// Update at the times given by a systemd OnCalendar expression (e.g. "Mon,Thu 08:00")
+        #[arg(long, value_name = "EXPR")]
+        on_calendar: Option<String>,
+    },
+    /// Stop the timer and remove its units
+    Disable,
+    /// Show whether updates are scheduled, and when they last ran and run next
+    Status,
+}
+
+/// Subcommands of `config`.
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Store the given flags in the config file, so later commands default to them,
+    /// e.g. `config set-default --install-dir ~/Apps --notify` or
+    /// `config set-default --app joplin --channel beta`
+    SetDefault(SetDefaultArgs),
+    /// Show the defaults in effect and whether each is built in, from the config file
+    /// or from the environment
+    ShowDefaults {
+        /// Show this app's settings instead
+        #[arg(long, add = ArgValueCandidates::new(app_candidates))]
+        app: Option<String>,
+    },
+}
+
+/// Options for `config set-default`, besides the global ones (`--install-dir`,
+/// `--proxy`, `--retries`, ...), which it stores as well.
+#[derive(Args)]
+pub struct SetDefaultArgs {
+    /// Store the per-app flags as settings of this app instead of globally
+    #[arg(long, add = ArgValueCandidates::new(app_candidates))]
+    pub app: Option<String>,
+
+    /// Notify about updates by default, even when run from a terminal
+    #[arg(long, overrides_with = "no_notify")]
+    pub notify: bool,
+
+    /// Never notify about updates
+    #[arg(long, overrides_with = "notify")]
+    pub no_notify: bool,
+
+    /// How many apps to update at once
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    /// Create app symlinks
+    #[arg(long, overrides_with = "no_symlink")]
+    pub symlink: bool,
+
+    /// Don't create app symlinks
+    #[arg(long, overrides_with = "symlink")]
+    pub no_symlink: bool,
+
+    /// Create desktop entries
+    #[arg(long, overrides_with = "no_desktop_entry")]
+    pub desktop_entry: bool,
+
+    /// Don't create desktop entries
+    #[arg(long, overrides_with = "desktop_entry")]
+    pub no_desktop_entry: bool,
+
+    /// Forge hosting the app's repository (with --app)
+    #[arg(long, value_enum, value_name = "PROVIDER")]
+    pub provider: Option<Provider>,
+
+    /// Release channel the app follows (with --app)
+    #[arg(long, value_enum, value_name = "CHANNEL")]
+    pub channel: Option<Channel>,
+
+    /// Only consider the app's assets matching this glob or 're:<regex>' (with --app)
+    #[arg(long, value_name = "PATTERN", value_parser = AssetPattern::parse)]
+    pub asset_filter: Option<AssetPattern>,
+
+    /// Launch the app inside this sandbox (with --app)
+    #[arg(long, value_enum, value_name = "TOOL", overrides_with = "no_sandbox")]
+    pub sandbox: Option<Sandbox>,
+
+    /// Launch the app directly, dropping its sandbox setting (with --app)
+    #[arg(long, overrides_with = "sandbox")]
+    pub no_sandbox: bool,
+
+    /// Install the app with this method (with --app)
+    #[arg(long, value_enum, value_name = "METHOD")]
+    pub method: Option<AssetType>,
+}
+
+/// Options for `daemon`.
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Time between runs, e.g. 30m or 6h; each wait varies by up to a tenth so many
+    /// machines don't hit the API together
+    #[arg(long, value_name = "DURATION", default_value = "6h", value_parser = parse_interval)]
+    pub interval: Duration,
+
+    /// Only check for updates and notify about them, without installing anything
+    #[arg(long)]
+    pub check_only: bool,
+
+    /// How many apps to update at once (default: 4)
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    /// Don't offer the org.rustunicorn.Updater interface on the D-Bus session bus
+    #[arg(long)]
+    pub no_dbus: bool,
+}
+
+/// Subcommands of `cache`.
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Delete every cached download and API response
+    Clean,
+    /// Show how many downloads and API responses are cached, and their size
+    Stats,
+}
+
+/// Options for `install`.
+#[derive(Args)]
+pub struct InstallCommandArgs {
+    #[command(flatten)]
+    pub install: InstallArgs,
+
+    /// Install every app listed in FILE, a TOML file with `[apps.<name>]` tables like
+    /// the config file's
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["app", "tag", "run"])]
+    pub from: Option<PathBuf>,
+
+    /// Install this AppImage from disk instead of downloading, e.g. on an offline
+    /// machine. APP (or --name) says which app it is, so it can be updated later
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["from", "tag", "channel", "asset_filter", "download_only"])]
+    pub file: Option<PathBuf>,
+
+    /// Name to install the --file AppImage under (default: APP's name)
+    #[arg(long, requires = "file")]
+    pub name: Option<String>,
+
+    /// Version of the --file AppImage, ideally its release tag (default: from the file name)
+    #[arg(long = "version", value_name = "VERSION", requires = "file")]
+    pub file_version: Option<String>,
+}
+
+/// Options for `update`.
+#[derive(Args)]
+pub struct UpdateArgs {
+    #[command(flatten)]
+    pub install: InstallArgs,
+
+    /// Update every installed app
+    #[arg(long, conflicts_with_all = ["app", "tag", "run"])]
+    pub all: bool,
+
+    /// How many apps to update at once with --all (default: 4)
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    /// Send a desktop notification about updated and failed apps (default: only when not run from a terminal)
+    #[arg(long)]
+    pub notify: bool,
+
+    /// When the forge's API fails, install the newer release last seen by `check` or an
+    /// earlier update, if its SHA-256 is known to verify the download against
+    #[arg(long)]
+    pub use_last_known: bool,
+}
+
+/// When messages are colored.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// On terminals, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Options for `adopt`.
+#[derive(Args)]
+pub struct AdoptArgs {
+    /// AppImage to adopt; it stays where it is
+    #[arg(required_unless_present = "scan", conflicts_with = "scan")]
+    pub path: Option<PathBuf>,
+
+    /// Adopt every AppImage in this directory, skipping those that can't be
+    #[arg(long, value_name = "DIR")]
+    pub scan: Option<PathBuf>,
+
+    /// Repository (OWNER/REPO or project URL) or configured app name, for AppImages
+    /// without update information saying where they're from
+    #[arg(long, value_name = "REPO", requires = "path")]
+    pub repo: Option<String>,
+
+    /// Name for the app (default: the repository's name)
+    #[arg(long, requires = "path")]
+    pub name: Option<String>,
+
+    /// Version of the AppImage, when its file name doesn't show it
+    #[arg(long = "version", value_name = "VERSION", requires = "path")]
+    pub version: Option<String>,
+
+    /// Only show what would be adopted
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// How command results are printed.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Messages for people
+    #[default]
+    Text,
+    /// One JSON document on stdout per command; messages are suppressed
+    Json,
+}
+
+/// What `--migrate-legacy` does with the files of the legacy install directory.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LegacyMigration {
+    /// Manage them where they are
+    InPlace,
+    /// Move them to the default install directory
+    Move,
+}
+
+/// Order of the apps `disk-usage` lists.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum UsageSort {
+    /// By app name
+    #[default]
+    Name,
+    /// Largest first
+    Size,
+}
+
+/// Options shared by `install` and `update`.
+#[derive(Args, Clone, Default)]
+pub struct InstallArgs {
+    /// Repository (OWNER/REPO or project URL) or configured app name [install default: laurent22/joplin]
+    #[arg(value_name = "APP", add = ArgValueCandidates::new(install_candidates))]
+    pub app: Option<String>,
+
+    /// Show which release and asset would be installed and which files written, without downloading or changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Forge hosting the repository, when it can't be inferred from the spec
+    #[arg(long, value_enum, value_name = "PROVIDER")]
+    pub provider: Option<Provider>,
+
+    /// Install this release tag (e.g. v2.14.20) instead of the latest release
+    #[arg(long, value_name = "TAG")]
+    pub tag: Option<String>,
+
+    /// Install the app with this method instead of the one its asset_type setting picks
+    #[arg(long, value_enum, value_name = "METHOD")]
+    pub method: Option<AssetType>,
+
+    /// Release channel to follow, overriding the app's configured channel
+    #[arg(long, value_enum, value_name = "CHANNEL")]
+    pub channel: Option<Channel>,
+
+    /// Only consider assets matching this glob (e.g. '*-qt6-*.AppImage') or 're:<regex>'
+    #[arg(long, value_name = "PATTERN", value_parser = AssetPattern::parse)]
+    pub asset_filter: Option<AssetPattern>,
+
+    /// Never prompt; take the first asset when several match equally well
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// Pick the AppImage for this CPU architecture instead of the host's (e.g. aarch64)
+    #[arg(long, value_name = "ARCH", value_parser = crate::arch::parse)]
+    pub arch: Option<String>,
+
+    /// Force download even if already installed
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Don't create a symlink named after the app
+    #[arg(long, overrides_with = "symlink")]
+    pub no_symlink: bool,
+
+    /// Create a symlink named after the app, even if the config disables it
+    #[arg(long, overrides_with = "no_symlink")]
+    pub symlink: bool,
+
+    /// Don't create a desktop entry for application launchers
+    #[arg(long)]
+    pub no_desktop_entry: bool,
+
+    /// Fail when the asset name's version differs from the release tag
+    #[arg(long)]
+    pub strict_version_match: bool,
+
+    /// Only accept downloads served by HOST after redirects (repeatable)
+    #[arg(long = "pin-host", value_name = "HOST")]
+    pub pinned_hosts: Vec<String>,
+
+    /// Wait up to DURATION (e.g. 10m) for a freshly published release's AppImage
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub wait_for_assets: Option<Duration>,
+
+    /// Always download the whole AppImage, even when a .zsync file allows a delta update
+    #[arg(long)]
+    pub no_delta: bool,
+
+    /// Ask the server whether a download cached under its URL changed before using it,
+    /// even when it was checked within the last day
+    #[arg(long, conflicts_with = "no_cache")]
+    pub revalidate: bool,
+
+    /// Neither use nor fill the download cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Only download and verify the release into the download cache (or check the
+    /// cached copy is current), without installing it
+    #[arg(long, conflicts_with_all = ["no_cache", "dry_run", "run"])]
+    pub download_only: bool,
+
+    /// Print the release notes of the version being installed (with --dry-run, before anything changes)
+    #[arg(long)]
+    pub show_notes: bool,
+
+    /// Stop running instances of the app (with SIGTERM) before replacing its AppImage
+    #[arg(long)]
+    pub stop_running: bool,
+
+    /// Launch the app inside this sandbox, through a wrapper the symlink and desktop
+    /// entry point at
+    #[arg(long, value_enum, value_name = "TOOL", overrides_with = "no_sandbox")]
+    pub sandbox: Option<Sandbox>,
+
+    /// Launch the AppImage directly, even if the config or an earlier install sandboxed it
+    #[arg(long, overrides_with = "sandbox")]
+    pub no_sandbox: bool,
+
+    /// Extract the AppImage and launch its AppRun, as happens anyway when FUSE is missing
+    #[arg(long)]
+    pub extract: bool,
+
+    /// Start the app, detached from the terminal, once it is installed
+    #[arg(long)]
+    pub run: bool,
+
+    /// Add the symlink directory to PATH in the shell's startup file when it isn't there
+    #[arg(long)]
+    pub fix_path: bool,
+
+    /// When the release metadata can't be fetched, install the newer release last seen
+    /// for the app instead (set by `update --use-last-known`)
+    #[arg(skip)]
+    pub use_last_known: bool,
+}
+
+impl InstallArgs {
+    /// Whether to create the app symlink, given the config's choice (if any).
+    pub fn symlink(&self, configured: Option<bool>) -> bool {
+        if self.no_symlink {
+            false
+        } else {
+            self.symlink || configured.unwrap_or(true)
+        }
+    }
+
+    /// The sandbox to launch the app in, given the configured one (if any).
+    pub fn sandbox(&self, configured: Option<Sandbox>) -> Option<Sandbox> {
+        if self.no_sandbox {
+            None
+        } else {
+            self.sandbox.or(configured)
+        }
+    }
+
+    /// Whether to create a desktop entry, given the config's choice (if any).
+    pub fn desktop_entry(&self, configured: Option<bool>) -> bool {
+        !self.no_desktop_entry && configured.unwrap_or(true)
+    }
+}
+
+/// Forge hosting an app's releases.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    #[value(name = "github")]
+    GitHub,
+    #[value(name = "gitlab")]
+    GitLab,
+    /// Gitea and its forks: Forgejo, Codeberg
+    #[serde(alias = "forgejo")]
+    #[value(name = "gitea", alias = "forgejo")]
+    Gitea,
+    /// A download URL on the project's own site, configured per app
+    #[value(name = "url")]
+    Url,
+}
+
+impl Provider {
+    /// Prefix used in repository specs, e.g. `gitlab:group/project`.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Provider::GitHub => "github",
+            Provider::GitLab => "gitlab",
+            Provider::Gitea => "gitea",
+            Provider::Url => "url",
+        }
+    }
+
+    /// Host used when a spec doesn't name one.
+    pub fn default_host(self) -> &'static str {
+        match self {
+            Provider::GitHub => "github.com",
+            Provider::GitLab => "gitlab.com",
+            Provider::Gitea => "codeberg.org",
+            // Each direct-URL app names its own host
+            Provider::Url => "",
+        }
+    }
+}
+
+/// A repository (or GitLab project) whose releases provide an app.
+#[derive(Clone)]
+pub struct RepoSpec {
+    pub provider: Provider,
+    /// Host of a self-hosted instance; `None` for the provider's public host
+    pub host: Option<String>,
+    /// Owner, or for GitLab the (possibly nested) group path
+    pub owner: String,
+    pub name: String,
+}
+
+impl RepoSpec {
+    /// Name used for the symlink and to identify the installed app.
+    pub fn app_name(&self) -> String {
+        self.name.to_lowercase()
+    }
+
+    /// Host serving the repository.
+    pub fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or(self.provider.default_host())
+    }
+}
+
+/// Formats back into a spec `parse_repo` understands: `owner/name` for GitHub,
+/// `gitlab:group/project` or `gitea:https://host/owner/name` for other providers.
+impl fmt::Display for RepoSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.provider, &self.host) {
+            // Direct downloads only exist in the config, so they are referred to by app name
+            (Provider::Url, _) => write!(f, "{}", self.name),
+            (Provider::GitHub, None) => write!(f, "{}/{}", self.owner, self.name),
+            (provider, None) => write!(f, "{}:{}/{}", provider.prefix(), self.owner, self.name),
+            (provider, Some(host)) => write!(
+                f,
+                "{}:https://{}/{}/{}",
+                provider.prefix(),
+                host,
+                self.owner,
+                self.name
+            ),
+        }
+    }
+}
+
+/// Parse a repository spec: `owner/name`, a project URL such as
+/// `https://gitlab.com/group/project`, or either prefixed with the provider
+/// (`gitlab:group/project`). `provider` (from `--provider` or the config) applies
+/// when the spec itself doesn't say.
+pub fn parse_repo(spec: &str, provider: Option<Provider>) -> Result<RepoSpec, String> {
+    let invalid = || {
+        format!(
+            "Invalid repository '{}': expected owner/name or a project URL",
+            spec
+        )
+    };
+
+    let (prefixed, rest) = match spec.split_once(':') {
+        Some(("github", rest)) => (Some(Provider::GitHub), rest),
+        Some(("gitlab", rest)) => (Some(Provider::GitLab), rest),
+        Some(("gitea" | "forgejo", rest)) => (Some(Provider::Gitea), rest),
+        _ => (None, spec),
+    };
+
+    let url = rest
+        .strip_prefix("https://")
+        .or_else(|| rest.strip_prefix("http://"))
+        .or_else(|| rest.strip_prefix("github.com/").map(|_| rest));
+    let (host, path) = match url {
+        Some(url) => {
+            let (host, path) = url.split_once('/').ok_or_else(invalid)?;
+            (Some(host.to_lowercase()), path)
+        }
+        None => (None, rest),
+    };
+
+    let provider = match (prefixed.or(provider), host.as_deref()) {
+        (Some(Provider::Url), _) => {
+            return Err("Direct-URL apps must be set up in the config file with a url".to_string())
+        }
+        (Some(provider), _) => provider,
+        (None, None | Some("github.com")) => Provider::GitHub,
+        (None, Some(host)) if host == "gitlab.com" || host.starts_with("gitlab.") => {
+            Provider::GitLab
+        }
+        (None, Some("codeberg.org")) => Provider::Gitea,
+        (None, Some(host)) => {
+            return Err(format!(
+                "Cannot tell which forge {} runs; use --provider",
+                host
+            ))
+        }
+    };
+    let host = host.filter(|host| host != provider.default_host());
+
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    let (owner, name) = path.rsplit_once('/').ok_or_else(invalid)?;
+    // Only GitLab has nested groups
+    if owner.is_empty() || name.is_empty() || (provider != Provider::GitLab && owner.contains('/'))
+    {
+        return Err(invalid());
+    }
+
+    Ok(RepoSpec {
+        provider,
+        host,
+        owner: owner.to_string(),
+        name: name.to_string(),
+    })
+}
+
+/// Parse an octal directory mode such as `755`, `0755` or `0o755`.
+pub fn parse_dir_mode(value: &str) -> Result<u32, String> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!(
+            "Invalid directory mode '{}': expected an octal mode like 0755",
+            value
+        )),
+    }
+}
+
+/// Parse a rate in bytes per second such as `800`, `500K`, `1.5M` or `2MB`; the
+/// suffixes are binary, as in curl's --limit-rate.
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "Invalid rate '{}': expected bytes per second, e.g. 500K or 2M",
+            value
+        )
+    };
+    let digits = value.trim_end_matches(['B', 'b']);
+    let (number, unit) = match digits.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => digits.split_at(index),
+        None => (digits, ""),
+    };
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "k" | "K" => 1024,
+        "m" | "M" => 1024 * 1024,
+        "g" | "G" => 1024 * 1024 * 1024,
+        _ => return Err(invalid()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let rate = (number * multiplier as f64) as u64;
+    if rate == 0 {
+        return Err(invalid());
+    }
+    Ok(rate)
+}
+
+/// Parse a duration such as `90`, `90s`, `15m` or `2h` (plain numbers are seconds).
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let seconds_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => {
+            return Err(format!(
+                "Invalid duration '{}': expected e.g. 90s, 15m or 2h",
+                value
+            ))
+        }
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}': expected e.g. 90s, 15m or 2h", value))?;
+
+    let seconds = number
+        .checked_mul(seconds_per_unit)
+        .ok_or_else(|| format!("Invalid duration '{}': too long", value))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse the interval of `daemon`, which can't be zero.
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    match parse_duration(value)? {
+        interval if interval.is_zero() => Err("The interval must be longer than 0s".to_string()),
+        interval => Ok(interval),
+    }
+}
+
+/// Check that `value` is a duration, keeping it as written for the config.
+fn duration_text(value: &str) -> Result<String, String> {
+    parse_duration(value).map(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repository_specs() {
+        let repo = parse_repo("laurent22/joplin", None).unwrap();
+        assert!(repo.provider == Provider::GitHub && repo.host.is_none());
+        assert_eq!(
+            (repo.owner.as_str(), repo.name.as_str()),
+            ("laurent22", "joplin")
+        );
+
+        let repo = parse_repo("https://github.com/laurent22/joplin.git/", None).unwrap();
+        assert_eq!(repo.to_string(), "laurent22/joplin");
+        let repo = parse_repo("https://gitlab.com/group/sub/project", None).unwrap();
+        assert!(repo.provider == Provider::GitLab && repo.host.is_none());
+        assert_eq!(repo.owner, "group/sub");
+        let repo = parse_repo("https://codeberg.org/owner/app", None).unwrap();
+        assert!(repo.provider == Provider::Gitea && repo.host.is_none());
+        let repo = parse_repo("gitea:https://git.example.com/owner/app", None).unwrap();
+        assert_eq!(repo.to_string(), "gitea:https://git.example.com/owner/app");
+        let repo = parse_repo("owner/app", Some(Provider::GitLab)).unwrap();
+        assert!(repo.provider == Provider::GitLab);
+
+        for invalid in ["joplin", "/joplin", "owner/", "a/b/c", "https://github.com"] {
+            assert!(parse_repo(invalid, None).is_err(), "{}", invalid);
+        }
+        assert!(parse_repo("https://git.example.com/owner/app", None).is_err());
+        assert!(parse_repo("owner/app", Some(Provider::Url)).is_err());
+    }
+
+    #[test]
+    fn parses_rates_durations_and_modes() {
+        assert_eq!(parse_rate("800"), Ok(800));
+        assert_eq!(parse_rate("500K"), Ok(500 * 1024));
+        assert_eq!(parse_rate("1.5M"), Ok(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_rate("2MB"), Ok(2 * 1024 * 1024));
+        for invalid in ["", "0", "0.0001", "5T", "fast"] {
+            assert!(parse_rate(invalid).is_err(), "{}", invalid);
+        }
+
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        for invalid in ["", "h", "1.5h", "2d", "-1s", "99999999999999999999h"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+
+        assert_eq!(parse_dir_mode("755"), Ok(0o755));
+        assert_eq!(parse_dir_mode("0755"), Ok(0o755));
+        assert_eq!(parse_dir_mode("0o700"), Ok(0o700));
+        for invalid in ["", "789", "17777", "rwx"] {
+            assert!(parse_dir_mode(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,490 @@
+// Configuration file (~/.config/rust-unicorn/config.toml).
+//
+// Example:
+//
+//     install_dir = "~/Applications"
+//     bin_dir = "~/.local/bin"
+//     symlink = true
+//     desktop_entry = true
+//     stable_filename = false
+//     quiet = false
+//     parallel_updates = 4
+//     max_parallel_downloads = 2
+//     max_parallel_api_requests = 4
+//     retries = 3
+//     connect_timeout = "30s"
+//     read_timeout = "60s"
+//     download_timeout = "2h"
+//     keep_versions = 2
+//     cache_downloads = true
+//     limit_rate = "2M"
+//     proxy = "socks5h://127.0.0.1:1080"
+//     cacert = "~/.config/rust-unicorn/corporate-ca.pem"
+//     tls = "rustls"
+//     github_token = "ghp_..."
+//
+//     [apps.joplin]
+//     repo = "laurent22/joplin"
+//     signing_keys = ["0123456789ABCDEF0123456789ABCDEF01234567"]
+//     post_install = 'xdg-mime default joplin.desktop x-scheme-handler/joplin'
+//
+//     [apps.freecad]
+//     repo = "FreeCAD/FreeCAD"
+//     channel = "prerelease"
+//     checksum_asset = "{asset}-SHA256.txt"
+//
+//     [apps.inkscape]
+//     repo = "inkscape/inkscape"
+//     provider = "gitlab"
+//
+//     [apps.example]
+//     provider = "url"
+//     url = "https://example.org/download/Example-{version}-x86_64.AppImage"
+//     version_url = "https://example.org/latest.json"
+//     version_pattern = '"version":\s*"([^"]+)"'
+//
+//     [apps.obsidian]
+//     repo = "obsidianmd/obsidian-releases"
+//     asset_pattern = "Obsidian-*.AppImage"
+//     symlink = false
+//     sandbox = "firejail"
+//
+//     [apps.ripgrep]
+//     repo = "BurntSushi/ripgrep"
+//     asset_type = "archive"
+//     asset_pattern = "ripgrep-*-unknown-linux-musl.tar.gz"
+//
+// Every key is optional; command-line flags take precedence over these values. Apps
+// in the built-in catalog (catalog.toml) need no entry unless their settings change.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{self, Provider, RepoSpec};
+use crate::http::TlsBackend;
+use crate::sandbox::Sandbox;
+use crate::Error;
+use crate::{catalog, paths};
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default installation directory
+    pub install_dir: Option<PathBuf>,
+    /// Directory for app symlinks
+    pub bin_dir: Option<PathBuf>,
+    /// Whether to create a symlink named after each app
+    pub symlink: Option<bool>,
+    /// Whether to create a desktop entry for each app
+    pub desktop_entry: Option<bool>,
+    /// Whether to install each app as `<app>.AppImage` rather than under its asset's
+    /// versioned name, so its path stays the same across updates
+    pub stable_filename: Option<bool>,
+    /// Suppress informational output unless overridden on the command line
+    pub quiet: Option<bool>,
+    /// How many apps `update --all` refreshes at once
+    pub parallel_updates: Option<u16>,
+    /// How many AppImages are downloaded at once, across all updates; unlimited by default
+    pub max_parallel_downloads: Option<u16>,
+    /// How many release API requests are in flight at once; unlimited by default
+    pub max_parallel_api_requests: Option<u16>,
+    /// How often a download or API request is retried after a transient network failure
+    pub retries: Option<u32>,
+    /// Longest wait for a connection, as for --connect-timeout (e.g. "10s"); "0" waits forever
+    pub connect_timeout: Option<String>,
+    /// Longest wait for more data from a server, as for --read-timeout
+    pub read_timeout: Option<String>,
+    /// Longest time a single download may take, as for --download-timeout
+    pub download_timeout: Option<String>,
+    /// How many replaced versions of each app are kept for `rollback`; 0 keeps none
+    pub keep_versions: Option<u32>,
+    /// Whether downloaded AppImages are kept in the cache, so installing the same
+    /// version again needs no download; on by default
+    pub cache_downloads: Option<bool>,
+    /// Cap on the combined download rate, as for --limit-rate (e.g. "2M")
+    pub limit_rate: Option<String>,
+    /// Proxy for all requests, as for --proxy; otherwise the proxy environment variables apply
+    pub proxy: Option<String>,
+    /// PEM file of extra root certificates to trust, as for --cacert
+    pub cacert: Option<PathBuf>,
+    /// TLS implementation, as for --tls
+    pub tls: Option<TlsBackend>,
+    /// Whether `update` sends a desktop notification about updated and failed apps;
+    /// by default it does when not run from a terminal
+    pub notify: Option<bool>,
+    /// GitHub token for API requests, used when neither --token nor GITHUB_TOKEN is set
+    pub github_token: Option<String>,
+    /// Apps to manage, keyed by the name used on the command line and for the symlink
+    pub apps: BTreeMap<String, AppConfig>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfig {
+    /// Repository as `owner/name` or a project URL; not used by direct-URL apps
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub repo: String,
+    /// Forge hosting `repo`, when it can't be inferred from it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// Per-app override of the global `symlink` setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symlink: Option<bool>,
+    /// Per-app override of the global `stable_filename` setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stable_filename: Option<bool>,
+    /// Release channel to follow
+    #[serde(default, skip_serializing_if = "Channel::is_stable")]
+    pub channel: Channel,
+    /// Kind of release asset to install from
+    #[serde(default, skip_serializing_if = "AssetType::is_appimage")]
+    pub asset_type: AssetType,
+    /// Glob (or `re:<regex>`) the asset name must match, for releases with several AppImages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_pattern: Option<String>,
+    /// Asset name globs (or `re:<regex>`) in order of preference, for releases
+    /// offering the app several ways (e.g. an AppImage and a tarball): the first one
+    /// matching an installable asset picks it, and the install method with it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefer: Vec<String>,
+    /// Download URL of a direct-URL app, with `{version}` standing for the version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// URL of a page or JSON document announcing the latest version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_url: Option<String>,
+    /// Regex finding the version in the `version_url` response (its first group, if
+    /// any); without it the whole response is the version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_pattern: Option<String>,
+    /// OpenPGP fingerprints allowed to sign this app's releases; when set, a
+    /// detached `.asc`/`.sig` signature is required and verified
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signing_keys: Vec<String>,
+    /// Release asset holding the AppImage's SHA-256, for releases where it isn't found
+    /// automatically; `{asset}` stands for the AppImage's name (e.g. `{asset}-SHA256.txt`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum_asset: Option<String>,
+    /// Shell command run after the app is installed or updated, with `$UNICORN_APP`,
+    /// `$UNICORN_VERSION`, `$UNICORN_PATH` and `$UNICORN_OLD_VERSION` set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+    /// Launch the app inside firejail or bubblewrap, through a wrapper with a profile
+    /// of its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<Sandbox>,
+    /// Reminder printed after every successful update of the app, e.g. a manual step
+    /// the new version needs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Also show `notes` as a desktop notification after an update, even when
+    /// notifications are otherwise off
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remind_after_update: bool,
+}
+
+impl AppConfig {
+    /// The app's `notes`, without control characters (other than line breaks and
+    /// tabs) that could move the cursor or recolor the terminal. `None` when blank.
+    pub fn notes(&self) -> Option<String> {
+        let notes: String = self
+            .notes
+            .as_deref()?
+            .chars()
+            .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+            .collect();
+        let notes = notes.trim();
+        (!notes.is_empty()).then(|| notes.to_string())
+    }
+}
+
+/// Which releases an app follows.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Only stable releases (GitHub's "latest release")
+    #[default]
+    Stable,
+    /// The newest release, including prereleases and betas
+    Prerelease,
+}
+
+impl Channel {
+    fn is_stable(&self) -> bool {
+        *self == Channel::Stable
+    }
+}
+
+/// Kind of release asset an app is installed from, which decides its install method.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetType {
+    /// An AppImage, installed as it is
+    #[default]
+    #[value(name = "appimage")]
+    AppImage,
+    /// A `.tar.gz`, `.tgz` or `.zip` archive holding a single program, which is
+    /// unpacked from it and installed without desktop integration
+    Archive,
+}
+
+impl AssetType {
+    pub fn is_appimage(&self) -> bool {
+        *self == AssetType::AppImage
+    }
+
+    /// The name it has in the config file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetType::AppImage => "appimage",
+            AssetType::Archive => "archive",
+        }
+    }
+}
+
+/// Apps to install in one go with `install --from` or `import`, in the config file's
+/// `[apps.<name>]` format. Files written by `export` also record what was installed:
+///
+/// ```toml
+/// [apps.joplin]
+/// repo = "laurent22/joplin"
+///
+/// [installed.joplin]
+/// version = "v2.14.22"
+/// pinned = true
+/// ```
+#[derive(Deserialize, Serialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub apps: BTreeMap<String, AppConfig>,
+    /// The exported version of each app, keyed like `apps`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub installed: BTreeMap<String, RecordedVersion>,
+}
+
+/// The version of an app recorded by `export`.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedVersion {
+    /// Release tag
+    pub version: String,
+    /// Whether the app was pinned at this version
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+impl Manifest {
+    /// Load the manifest at `path`, checking that every entry has a valid app name and
+    /// names a repository or URL.
+    pub fn load(path: &Path) -> Result<Manifest, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read manifest {}: {}", path.display(), e))?;
+        let manifest: Manifest = toml::from_str(&text)
+            .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+        for (name, app) in &manifest.apps {
+            validate_app_name(name)
+                .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+            configured_repo(name, app)
+                .map_err(|e| format!("Manifest entry for app '{}': {}", name, e))?;
+        }
+        if let Some(name) = manifest
+            .installed
+            .keys()
+            .find(|name| !manifest.apps.contains_key(*name))
+        {
+            return Err(format!(
+                "Manifest {} records a version of '{}' but has no [apps.{}] entry",
+                path.display(),
+                name,
+                name
+            )
+            .into());
+        }
+        Ok(manifest)
+    }
+}
+
+/// An app resolved from the command line and/or the config file.
+pub struct App {
+    pub name: String,
+    pub repo: RepoSpec,
+    /// The app's `[apps.<name>]` settings, or defaults for unconfigured apps
+    pub settings: AppConfig,
+}
+
+/// Location of the config file: `$XDG_CONFIG_HOME/rust-unicorn/config.toml`,
+/// falling back to `~/.config/rust-unicorn/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    Some(
+        paths::config_home()?
+            .join("rust-unicorn")
+            .join("config.toml"),
+    )
+}
+
+impl Config {
+    /// Load the config from `path`, or from the default location when `None`.
+    ///
+    /// A missing file at the default location yields an empty config; an explicitly
+    /// given path must exist. Every `[apps]` key must be a valid app name.
+    pub fn load(path: Option<&Path>) -> Result<Config, Error> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        if !required && !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&text)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        config.install_dir = config.install_dir.map(|dir| expand_tilde(&dir));
+        config.bin_dir = config.bin_dir.map(|dir| expand_tilde(&dir));
+        config.cacert = config.cacert.map(|path| expand_tilde(&path));
+        for name in config.apps.keys() {
+            validate_app_name(name)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Resolve an app given on the command line, either as a repository spec (see
+    /// [`cli::parse_repo`], with `provider` applying to it), as the name of an app
+    /// configured in the `[apps]` table, or as the name of an app in the built-in
+    /// catalog.
+    pub fn resolve(&self, spec: &str, provider: Option<Provider>) -> Result<App, String> {
+        if let Some(app) = self.apps.get(spec) {
+            return Ok(App {
+                name: spec.to_string(),
+                repo: configured_repo(spec, app)
+                    .map_err(|e| format!("Config entry for app '{}': {}", spec, e))?,
+                settings: app.clone(),
+            });
+        }
+        if let Some(entry) = catalog::get(spec) {
+            let settings = entry.settings();
+            return Ok(App {
+                name: spec.to_string(),
+                repo: configured_repo(spec, &settings)?,
+                settings,
+            });
+        }
+
+        if !spec.contains('/') {
+            return Err(format!(
+                "Unknown app '{}': give it as owner/name, add it to the config file, or find it with `rust-unicorn search`",
+                spec
+            ));
+        }
+        let repo = cli::parse_repo(spec, provider)?;
+
+        // A configured app referenced by its repository keeps its configured name and settings
+        let configured = self.apps.iter().find(|(_, app)| {
+            cli::parse_repo(&app.repo, app.provider)
+                .is_ok_and(|r| r.to_string().eq_ignore_ascii_case(&repo.to_string()))
+        });
+        // So does a catalog app
+        let cataloged = catalog::entries().iter().find(|(_, entry)| {
+            cli::parse_repo(&entry.repo, entry.provider)
+                .is_ok_and(|r| r.to_string().eq_ignore_ascii_case(&repo.to_string()))
+        });
+        match (configured, cataloged) {
+            (Some((name, app)), _) => Ok(App {
+                name: name.clone(),
+                repo,
+                settings: app.clone(),
+            }),
+            (None, Some((name, entry))) => Ok(App {
+                name: name.clone(),
+                repo,
+                settings: entry.settings(),
+            }),
+            (None, None) => Ok(App {
+                name: repo.app_name(),
+                settings: AppConfig {
+                    repo: repo.to_string(),
+                    provider: Some(repo.provider),
+                    ..AppConfig::default()
+                },
+                repo,
+            }),
+        }
+    }
+}
+
+/// Check that `name` can name an app. It becomes part of file names (the symlink,
+/// `<name>.desktop`, the sandbox directory), so it can't be empty, `.` or `..`, or
+/// hold a `/` or NUL.
+pub fn validate_app_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        return Err(format!(
+            "Invalid app name '{}': it can't be empty, '.' or '..', or contain '/' or NUL",
+            name.escape_debug()
+        ));
+    }
+    Ok(())
+}
+
+/// The repository of a configured app; direct-URL apps get one named after the app.
+fn configured_repo(name: &str, app: &AppConfig) -> Result<RepoSpec, String> {
+    match app.provider {
+        Some(Provider::Url) if app.url.is_none() => Err("provider \"url\" needs a url".to_string()),
+        Some(Provider::Url) => Ok(RepoSpec {
+            provider: Provider::Url,
+            host: None,
+            owner: String::new(),
+            name: name.to_string(),
+        }),
+        _ if app.repo.is_empty() => Err("missing repo".to_string()),
+        _ => cli::parse_repo(&app.repo, app.provider),
+    }
+}
+
+/// Expand a leading `~` to the user's home directory.
+fn expand_tilde(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), paths::home()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_names_must_be_file_names() {
+        for name in ["", ".", "..", "a/b", "/", "nul\0"] {
+            assert!(validate_app_name(name).is_err(), "{:?} was accepted", name);
+        }
+        for name in ["joplin", "my-app.beta", "..app", "Ünïcode"] {
+            assert!(validate_app_name(name).is_ok(), "{:?} was rejected", name);
+        }
+    }
+
+    #[test]
+    fn notes_lose_control_characters_but_keep_their_text() {
+        let app = AppConfig {
+            notes: Some("  Re-enable {plugin}\u{1b}[31m\u{7}\nthen\trestart\r\n".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            app.notes().as_deref(),
+            Some("Re-enable {plugin}[31m\nthen\trestart")
+        );
+
+        let blank = AppConfig {
+            notes: Some(" \u{1b}\n".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(blank.notes(), None);
+    }
+}
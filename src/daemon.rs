@@ -0,0 +1,145 @@
+// `daemon`: stays running and updates the installed apps every `--interval` (or,
+// with `--check-only`, just looks for updates), reporting through desktop
+// notifications, the log and the D-Bus interface in `dbus`. The install directory is
+// locked for each run only, so manual commands work in between.
+use indicatif::HumanDuration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+
+use crate::cli::{DaemonArgs, InstallArgs, UpdateArgs};
+use crate::dbus::{Bus, Status};
+use crate::install::{check, create_dirs, update_all_observed, UpdateProgress};
+use crate::{lock, notify, tr, Context, Error};
+
+/// Run until interrupted (SIGINT or SIGTERM), starting with a run right away. A
+/// failed run is logged and the next one happens as planned; an update asked for
+/// over D-Bus happens at once.
+pub async fn run(ctx: &Arc<Context>, args: &DaemonArgs) -> Result<(), Error> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let status = Arc::new(Status::default());
+    let bus = if args.no_dbus {
+        None
+    } else {
+        match Bus::serve(Arc::clone(&status)).await {
+            Ok(bus) => Some(bus),
+            Err(e) => {
+                warn!("{}", tr!("dbus-unavailable", error = e.to_string()));
+                None
+            }
+        }
+    };
+
+    let interval = HumanDuration(args.interval).to_string();
+    if args.check_only {
+        info!("{}", tr!("daemon-started-checking", interval = interval));
+    } else {
+        info!("{}", tr!("daemon-started", interval = interval));
+    }
+    let mut update = !args.check_only;
+    loop {
+        if let Err(e) = run_once(ctx, args, update, &status, bus.as_ref()).await {
+            error!("{}", tr!("daemon-run-failed", error = e.to_string()));
+        }
+
+        let wait = jitter(args.interval);
+        let next = chrono::Local::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+        info!(
+            "{}",
+            tr!(
+                "daemon-next-run",
+                time = next.format("%Y-%m-%d %H:%M").to_string()
+            )
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => update = !args.check_only,
+            _ = status.update_requested.notified() => {
+                info!("{}", tr!("daemon-update-requested"));
+                update = true;
+            }
+            _ = tokio::signal::ctrl_c() => break,
+            _ = terminate.recv() => break,
+        }
+    }
+    info!("{}", tr!("daemon-stopped"));
+    Ok(())
+}
+
+/// One run: check for updates and, with `update`, install them.
+async fn run_once(
+    ctx: &Arc<Context>,
+    args: &DaemonArgs,
+    update: bool,
+    status: &Status,
+    bus: Option<&Bus>,
+) -> Result<(), Error> {
+    let checked = check(ctx, &[]).await;
+    if let Ok(pending) = &checked {
+        *status.pending.lock().unwrap() = pending.clone();
+        if let Some(bus) = bus {
+            bus.updates_available(pending);
+        }
+    }
+    if !update {
+        let available: Vec<String> = checked?.into_iter().map(|pending| pending.app).collect();
+        notify::send_available(&available).await;
+        return Ok(());
+    }
+    // Apps that could be checked are updated even when others couldn't
+    if checked.as_ref().is_ok_and(|pending| pending.is_empty()) {
+        return Ok(());
+    }
+
+    create_dirs(&ctx.install_dir, ctx.dir_mode)?;
+    let _lock = lock::acquire(&ctx.install_dir, true).await?;
+    let update = UpdateArgs {
+        // Nobody is there to answer
+        install: InstallArgs {
+            non_interactive: true,
+            ..InstallArgs::default()
+        },
+        all: true,
+        jobs: args.jobs,
+        notify: true,
+        use_last_known: false,
+    };
+    let outcome = Mutex::new((Vec::new(), Vec::new()));
+    let observe = |progress: UpdateProgress| {
+        if let UpdateProgress::Finished { app, result, .. } = &progress {
+            let (updated, failed) = &mut *outcome.lock().unwrap();
+            match result {
+                Ok(report) if report.action == "updated" => updated.push(app.to_string()),
+                Ok(_) => {}
+                Err(_) => failed.push(app.to_string()),
+            }
+            if result.is_ok() {
+                status
+                    .pending
+                    .lock()
+                    .unwrap()
+                    .retain(|pending| pending.app != *app);
+            }
+        }
+        if let Some(bus) = bus {
+            bus.progress(&progress);
+        }
+    };
+    let result = update_all_observed(ctx, &update, &observe).await;
+    if let Some(bus) = bus {
+        let (updated, failed) = outcome.into_inner().unwrap();
+        bus.update_finished(updated, failed);
+    }
+    result.and(checked.map(|_| ()))
+}
+
+/// `interval`, shortened or lengthened by up to a tenth.
+fn jitter(interval: Duration) -> Duration {
+    // The clock's sub-second noise is random enough for spreading runs
+    let noise = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos()
+        % 1000;
+    interval.mul_f64(0.9 + f64::from(noise) / 5000.0)
+}
@@ -0,0 +1,153 @@
+// D-Bus interface of `daemon`: `org.rustunicorn.Updater` on the session bus, at
+// /org/rustunicorn/Updater, so desktop applets can show pending AppImage updates the
+// way they show package updates, and start them.
+//
+// Methods:
+//   PendingUpdates() -> a(sss)   installed apps with a newer release, as
+//                                (app, installed version, latest version)
+//   UpdateAll()                  update every app now instead of at the next run
+// Signals:
+//   UpdatesAvailable(a(sss))     after each check, with what PendingUpdates returns
+//   Progress(s app, u done, u total)
+//                                an app of an update run is finished
+//   UpdateFinished(as updated, as failed)
+//                                an update run is over
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::debug;
+use zbus::fdo::RequestNameFlags;
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+
+use crate::install::{PendingUpdate, UpdateProgress};
+use crate::Error;
+
+/// Bus name the daemon owns.
+pub const NAME: &str = "org.rustunicorn.Updater";
+
+/// Object path of the interface.
+pub const PATH: &str = "/org/rustunicorn/Updater";
+
+/// What the daemon shares with its D-Bus interface.
+#[derive(Default)]
+pub struct Status {
+    /// Result of the last check, less the apps updated since
+    pub pending: Mutex<Vec<PendingUpdate>>,
+    /// Woken when an applet asks for an update run
+    pub update_requested: Notify,
+}
+
+impl Status {
+    fn pending_tuples(&self) -> Vec<(String, String, String)> {
+        tuples(&self.pending.lock().unwrap())
+    }
+}
+
+fn tuples(pending: &[PendingUpdate]) -> Vec<(String, String, String)> {
+    pending
+        .iter()
+        .map(|update| {
+            (
+                update.app.clone(),
+                update.installed.clone(),
+                update.latest.clone(),
+            )
+        })
+        .collect()
+}
+
+struct Updater {
+    status: Arc<Status>,
+}
+
+#[interface(name = "org.rustunicorn.Updater")]
+impl Updater {
+    async fn pending_updates(&self) -> Vec<(String, String, String)> {
+        self.status.pending_tuples()
+    }
+
+    async fn update_all(&self) {
+        debug!("Update requested over D-Bus");
+        self.status.update_requested.notify_one();
+    }
+
+    #[zbus(signal)]
+    async fn updates_available(
+        emitter: &SignalEmitter<'_>,
+        updates: Vec<(String, String, String)>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn progress(
+        emitter: &SignalEmitter<'_>,
+        app: &str,
+        done: u32,
+        total: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_finished(
+        emitter: &SignalEmitter<'_>,
+        updated: Vec<String>,
+        failed: Vec<String>,
+    ) -> zbus::Result<()>;
+}
+
+/// The daemon's session bus connection, serving the interface until dropped.
+pub struct Bus {
+    emitter: SignalEmitter<'static>,
+}
+
+impl Bus {
+    /// Serve the interface for `status` under the name `NAME`, which fails when
+    /// another daemon has it or there is no session bus.
+    pub async fn serve(status: Arc<Status>) -> Result<Bus, Error> {
+        let connection = zbus::connection::Builder::session()
+            .and_then(|builder| builder.serve_at(PATH, Updater { status }))
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())?;
+        // Neither taking the name from another daemon nor giving it up to one
+        connection
+            .request_name_with_flags(NAME, RequestNameFlags::DoNotQueue.into())
+            .await
+            .map_err(|e| format!("{} ({})", e, NAME))?;
+        debug!("Serving {} on the session bus", NAME);
+        let emitter = SignalEmitter::new(&connection, PATH).map_err(|e| e.to_string())?;
+        Ok(Bus { emitter })
+    }
+
+    /// Announce the result of a check.
+    pub fn updates_available(&self, pending: &[PendingUpdate]) {
+        let (emitter, updates) = (self.emitter.clone(), tuples(pending));
+        emit(async move { Updater::updates_available(&emitter, updates).await });
+    }
+
+    /// Announce that an app of an update run is finished.
+    pub fn progress(&self, progress: &UpdateProgress) {
+        if let UpdateProgress::Finished {
+            app, done, total, ..
+        } = *progress
+        {
+            let (emitter, app) = (self.emitter.clone(), app.to_string());
+            emit(async move { Updater::progress(&emitter, &app, done as u32, total as u32).await });
+        }
+    }
+
+    /// Announce that an update run is over.
+    pub fn update_finished(&self, updated: Vec<String>, failed: Vec<String>) {
+        let emitter = self.emitter.clone();
+        emit(async move { Updater::update_finished(&emitter, updated, failed).await });
+    }
+}
+
+/// Send a signal in the background; applets missing one is no reason to fail.
+fn emit(signal: impl Future<Output = zbus::Result<()>> + Send + 'static) {
+    tokio::spawn(async move {
+        if let Err(e) = signal.await {
+            debug!("Cannot emit a D-Bus signal: {}", e);
+        }
+    });
+}
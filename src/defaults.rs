@@ -0,0 +1,706 @@
+// `config set-default` and `config show-defaults`: command-line flags stored as
+// defaults in the config file, globally or for one app, and the effective defaults
+// with where each comes from.
+//
+// Flags are parsed by the same definitions as everywhere else, so a value the
+// command line would reject is never stored; flags without a config key are refused.
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+use crate::cli::OutputFormat;
+use crate::config::{self, validate_app_name, AppConfig, Config};
+use crate::http::TlsBackend;
+use crate::install::{create_dirs, DEFAULT_PARALLEL_UPDATES};
+use crate::state::State;
+use crate::{catalog, http, paths, retry, tr, versions, Context, Error};
+
+/// How a flag's value is written to the config file.
+#[derive(Clone, Copy)]
+enum Value {
+    /// As given on the command line
+    Text,
+    /// A path, made absolute so it means the same wherever the config is read
+    Path,
+    Integer,
+    /// The flag sets the key to true or false
+    Bool(bool),
+    /// The flag removes the key, leaving the built-in default
+    Unset,
+}
+
+/// Whether a flag sets a global or a per-app key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Global,
+    App,
+    Both,
+}
+
+/// The flags `set-default` stores: the argument's id, the config key it sets, how,
+/// and where.
+const FLAGS: &[(&str, &str, Value, Scope)] = &[
+    ("install_dir", "install_dir", Value::Path, Scope::Global),
+    ("bin_dir", "bin_dir", Value::Path, Scope::Global),
+    ("token", "github_token", Value::Text, Scope::Global),
+    ("proxy", "proxy", Value::Text, Scope::Global),
+    ("cacert", "cacert", Value::Text, Scope::Global),
+    ("tls", "tls", Value::Text, Scope::Global),
+    ("retries", "retries", Value::Integer, Scope::Global),
+    (
+        "connect_timeout",
+        "connect_timeout",
+        Value::Text,
+        Scope::Global,
+    ),
+    ("read_timeout", "read_timeout", Value::Text, Scope::Global),
+    (
+        "download_timeout",
+        "download_timeout",
+        Value::Text,
+        Scope::Global,
+    ),
+    ("limit_rate", "limit_rate", Value::Text, Scope::Global),
+    ("quiet", "quiet", Value::Bool(true), Scope::Global),
+    ("notify", "notify", Value::Bool(true), Scope::Global),
+    ("no_notify", "notify", Value::Bool(false), Scope::Global),
+    ("jobs", "parallel_updates", Value::Integer, Scope::Global),
+    (
+        "desktop_entry",
+        "desktop_entry",
+        Value::Bool(true),
+        Scope::Global,
+    ),
+    (
+        "no_desktop_entry",
+        "desktop_entry",
+        Value::Bool(false),
+        Scope::Global,
+    ),
+    ("symlink", "symlink", Value::Bool(true), Scope::Both),
+    ("no_symlink", "symlink", Value::Bool(false), Scope::Both),
+    ("provider", "provider", Value::Text, Scope::App),
+    ("channel", "channel", Value::Text, Scope::App),
+    ("asset_filter", "asset_pattern", Value::Text, Scope::App),
+    ("sandbox", "sandbox", Value::Text, Scope::App),
+    ("no_sandbox", "sandbox", Value::Unset, Scope::App),
+    ("method", "asset_type", Value::Text, Scope::App),
+];
+
+/// Arguments that govern `set-default` itself rather than being stored.
+const OWN_ARGS: &[&str] = &["app", "config", "verbose", "output", "color"];
+
+/// Store the flags given to `config set-default`, as parsed into `matches`, in the
+/// config file at `config_path` (or the default one): for the app `app` when given,
+/// otherwise globally. The rest of the file, comments included, is kept as it is.
+pub fn set_default(
+    ctx: &Context,
+    config_path: Option<&Path>,
+    app: Option<&str>,
+    matches: &ArgMatches,
+) -> Result<(), Error> {
+    let mut changes = Vec::new();
+    // Argument groups are among the ids too, with the ids of their arguments as values
+    let arguments = matches
+        .ids()
+        .map(|id| id.as_str())
+        .filter(|id| matches.try_get_one::<clap::Id>(id).is_err());
+    for id in arguments {
+        if matches.value_source(id) != Some(ValueSource::CommandLine) || OWN_ARGS.contains(&id) {
+            continue;
+        }
+        let flag = format!("--{}", id.replace('_', "-"));
+        let &(_, key, value, scope) = FLAGS
+            .iter()
+            .find(|(flag_id, ..)| *flag_id == id)
+            .ok_or_else(|| format!("{} has no config setting, so it can't be a default", flag))?;
+        match (scope, app) {
+            (Scope::Global, Some(app)) => {
+                return Err(format!("{} applies to every app; drop --app {}", flag, app).into())
+            }
+            (Scope::App, None) => {
+                return Err(format!("{} is set per app; add --app <APP>", flag).into())
+            }
+            _ => {}
+        }
+        let item = match value {
+            Value::Text => {
+                let raw = matches.get_raw(id).into_iter().flatten().next();
+                let text = raw.map(|raw| raw.to_string_lossy().into_owned());
+                Some(toml_edit::value(text.unwrap_or_default()))
+            }
+            Value::Path => {
+                let raw = matches.get_raw(id).into_iter().flatten().next();
+                let raw = Path::new(raw.unwrap_or_default());
+                // A leading `~` is expanded when the config is read
+                let path = if raw.starts_with("~") {
+                    raw.to_path_buf()
+                } else {
+                    std::path::absolute(raw)?
+                };
+                Some(toml_edit::value(path.to_string_lossy().into_owned()))
+            }
+            Value::Integer => {
+                let raw = matches.get_raw(id).into_iter().flatten().next();
+                let number = raw
+                    .and_then(|raw| raw.to_str())
+                    .and_then(|raw| raw.parse::<i64>().ok())
+                    .ok_or_else(|| format!("{} needs a number", flag))?;
+                Some(toml_edit::value(number))
+            }
+            Value::Bool(value) => Some(toml_edit::value(value)),
+            Value::Unset => None,
+        };
+        changes.push((key, item));
+    }
+    if changes.is_empty() {
+        return Err(
+            "Give the flags to store as defaults, e.g. --install-dir ~/Apps --notify".into(),
+        );
+    }
+
+    let path = edit_config(ctx, config_path, |document| {
+        let table = match app {
+            Some(app) => app_table(ctx, document, app)?,
+            None => document.as_table_mut(),
+        };
+        for (key, item) in &changes {
+            match item {
+                Some(item) => table[*key] = item.clone(),
+                None => {
+                    table.remove(key);
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    let set: Vec<(&str, String)> = changes
+        .iter()
+        .map(|(key, item)| {
+            let value = item.as_ref().and_then(|item| item.as_value());
+            let text = match value {
+                Some(toml_edit::Value::String(text)) => text.value().clone(),
+                Some(value) => value.to_string().trim().to_string(),
+                None => String::new(),
+            };
+            (*key, text)
+        })
+        .collect();
+    if ctx.output == OutputFormat::Json {
+        let values: serde_json::Map<String, serde_json::Value> = set
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.as_str().into()))
+            .collect();
+        return ctx.print_json(&serde_json::json!({
+            "path": path,
+            "app": app,
+            "set": values,
+        }));
+    }
+    let file = path.display().to_string();
+    for (key, value) in &set {
+        let (key, file) = (*key, file.as_str());
+        match (app, value.is_empty()) {
+            (Some(app), true) => info!(
+                "{}",
+                tr!("default-unset-app", key = key, app = app, file = file)
+            ),
+            (Some(app), false) => info!(
+                "{}",
+                tr!(
+                    "default-set-app",
+                    key = key,
+                    value = value.as_str(),
+                    app = app,
+                    file = file
+                )
+            ),
+            (None, _) => info!(
+                "{}",
+                tr!(
+                    "default-set",
+                    key = key,
+                    value = value.as_str(),
+                    file = file
+                )
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Apply `change` to the config file at `config_path` (or the default one), keeping
+/// its comments and layout, and return its path. The file is only replaced when the
+/// result still loads.
+pub fn edit_config(
+    ctx: &Context,
+    config_path: Option<&Path>,
+    change: impl FnOnce(&mut toml_edit::DocumentMut) -> Result<(), Error>,
+) -> Result<PathBuf, Error> {
+    let path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => config::default_path().ok_or("Cannot find the config file: HOME is not set")?,
+    };
+    let text = if path.exists() {
+        fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?
+    } else {
+        String::new()
+    };
+    let mut document: toml_edit::DocumentMut = text
+        .parse()
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+    change(&mut document)?;
+
+    // Never leave a config file behind that the next run can't load
+    let text = document.to_string();
+    toml::from_str::<Config>(&text)
+        .map_err(|e| format!("Cannot store this in {}: {}", path.display(), e))?;
+    if let Some(dir) = path.parent() {
+        create_dirs(dir, ctx.dir_mode)?;
+    }
+    let tmp_path = path.with_extension("toml.tmp");
+    fs::write(&tmp_path, text)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(path)
+}
+
+/// The `[apps.<app>]` table of `document`, created for an app that isn't configured
+/// yet with the settings it has now (from the catalog or its install), so storing a
+/// default doesn't lose them.
+pub fn app_table<'a>(
+    ctx: &Context,
+    document: &'a mut toml_edit::DocumentMut,
+    app: &str,
+) -> Result<&'a mut toml_edit::Table, Error> {
+    validate_app_name(app)?;
+    let apps = document
+        .entry("apps")
+        .or_insert_with(|| {
+            let mut apps = toml_edit::Table::new();
+            apps.set_implicit(true);
+            toml_edit::Item::Table(apps)
+        })
+        .as_table_mut()
+        .ok_or("The config file's `apps` is not a table")?;
+    if !apps.contains_key(app) {
+        let (settings, _) = app_settings(ctx, &ctx.config, app)?;
+        let text = toml::to_string(&settings)
+            .map_err(|e| format!("Cannot write the settings of {}: {}", app, e))?;
+        let table: toml_edit::DocumentMut = text
+            .parse()
+            .map_err(|e| format!("Cannot write the settings of {}: {}", app, e))?;
+        apps.insert(app, toml_edit::Item::Table(table.as_table().clone()));
+    }
+    apps[app]
+        .as_table_mut()
+        .ok_or_else(|| format!("The config file's entry for {} is not a table", app).into())
+}
+
+/// A default and where it comes from.
+#[derive(Serialize)]
+struct Default {
+    key: &'static str,
+    value: String,
+    /// `built-in`, `config` or `env`; for apps also `installed`, for settings
+    /// recorded when the app was installed
+    source: String,
+}
+
+impl Default {
+    fn new(key: &'static str, value: impl ToString, source: impl ToString) -> Default {
+        Default {
+            key,
+            value: value.to_string(),
+            source: source.to_string(),
+        }
+    }
+
+    /// `configured` when the config file sets it, else the built-in `default`.
+    fn of<T: ToString>(
+        key: &'static str,
+        configured: Option<T>,
+        default: impl ToString,
+    ) -> Default {
+        match configured {
+            Some(value) => Default::new(key, value, "config"),
+            None => Default::new(key, default, "built-in"),
+        }
+    }
+}
+
+/// Print the defaults in effect, or with `app` that app's settings, each with its
+/// source. The config file is read afresh from `config_path` (or the default one), so
+/// flags given to this command don't show up as configured.
+pub fn show_defaults(
+    ctx: &Context,
+    config_path: Option<&Path>,
+    app: Option<&str>,
+) -> Result<(), Error> {
+    let config = Config::load(config_path)?;
+    let defaults = match app {
+        Some(app) => app_defaults(ctx, &config, app)?,
+        None => global_defaults(&config),
+    };
+
+    if ctx.output == OutputFormat::Json {
+        return ctx.print_json(&defaults);
+    }
+    for default in &defaults {
+        info!(
+            "{:<26} {:<40} ({})",
+            default.key,
+            default.value,
+            tr!("default-source", source = default.source.as_str())
+        );
+    }
+    Ok(())
+}
+
+fn global_defaults(config: &Config) -> Vec<Default> {
+    let path = |path: Option<PathBuf>| path.map(|path| path.display().to_string());
+    let unlimited = tr!("default-unlimited");
+    let none = tr!("default-none");
+    let env_var = |names: &[&'static str]| {
+        names
+            .iter()
+            .find(|name| env::var(name).is_ok_and(|value| !value.is_empty()))
+            .copied()
+    };
+
+    let proxy = match (
+        &config.proxy,
+        env_var(&[
+            "HTTPS_PROXY",
+            "https_proxy",
+            "HTTP_PROXY",
+            "http_proxy",
+            "ALL_PROXY",
+            "all_proxy",
+        ]),
+    ) {
+        (Some(proxy), _) => Default::new("proxy", proxy, "config"),
+        (None, Some(name)) => Default::new(
+            "proxy",
+            env::var(name).unwrap_or_default(),
+            format!("env {}", name),
+        ),
+        (None, None) => Default::new("proxy", &none, "built-in"),
+    };
+    // Tokens are secret; only say whether there is one
+    let token = match (env_var(&["GITHUB_TOKEN"]), &config.github_token) {
+        (Some(name), _) => Default::new(
+            "github_token",
+            tr!("default-set-secret"),
+            format!("env {}", name),
+        ),
+        (None, Some(_)) => Default::new("github_token", tr!("default-set-secret"), "config"),
+        (None, None) => Default::new("github_token", &none, "built-in"),
+    };
+    vec![
+        Default::of(
+            "install_dir",
+            path(config.install_dir.clone()),
+            path(paths::default_install_dir()).unwrap_or_default(),
+        ),
+        Default::of(
+            "bin_dir",
+            path(config.bin_dir.clone()),
+            path(paths::default_bin_dir()).unwrap_or_default(),
+        ),
+        Default::of("symlink", config.symlink, true),
+        Default::of("desktop_entry", config.desktop_entry, true),
+        Default::of("stable_filename", config.stable_filename, false),
+        Default::of("quiet", config.quiet, false),
+        Default::of("notify", config.notify, tr!("default-notify")),
+        Default::of(
+            "parallel_updates",
+            config.parallel_updates,
+            DEFAULT_PARALLEL_UPDATES,
+        ),
+        Default::of(
+            "max_parallel_downloads",
+            config.max_parallel_downloads,
+            &unlimited,
+        ),
+        Default::of(
+            "max_parallel_api_requests",
+            config.max_parallel_api_requests,
+            &unlimited,
+        ),
+        Default::of("retries", config.retries, retry::DEFAULT_RETRIES),
+        Default::of(
+            "connect_timeout",
+            config.connect_timeout.as_ref(),
+            duration(http::DEFAULT_CONNECT_TIMEOUT),
+        ),
+        Default::of(
+            "read_timeout",
+            config.read_timeout.as_ref(),
+            duration(http::DEFAULT_READ_TIMEOUT),
+        ),
+        Default::of(
+            "download_timeout",
+            config.download_timeout.as_ref(),
+            duration(http::DEFAULT_DOWNLOAD_TIMEOUT),
+        ),
+        Default::of(
+            "keep_versions",
+            config.keep_versions,
+            versions::DEFAULT_KEEP_VERSIONS,
+        ),
+        Default::of("cache_downloads", config.cache_downloads, true),
+        Default::of("limit_rate", config.limit_rate.as_ref(), &unlimited),
+        proxy,
+        Default::of("cacert", path(config.cacert.clone()), &none),
+        Default::of(
+            "tls",
+            config.tls.map(tls_name),
+            tls_name(TlsBackend::Native),
+        ),
+        token,
+    ]
+}
+
+/// The settings of `app` and their source: its entry in `config`, else the catalog
+/// (`built-in`), else those recorded when it was installed.
+fn app_settings(
+    ctx: &Context,
+    config: &Config,
+    app: &str,
+) -> Result<(AppConfig, &'static str), Error> {
+    if let Some(settings) = config.apps.get(app) {
+        return Ok((settings.clone(), "config"));
+    }
+    if let Some(entry) = catalog::get(app) {
+        return Ok((entry.settings(), "built-in"));
+    }
+    match State::load(&ctx.state_path)?.apps.remove(app) {
+        Some(installed) => match installed.settings {
+            Some(settings) => Ok((settings, "installed")),
+            None => Ok((config.resolve(&installed.repo, None)?.settings, "installed")),
+        },
+        None => Err(format!(
+            "Unknown app '{}': it is neither configured, in the catalog nor installed",
+            app
+        )
+        .into()),
+    }
+}
+
+/// The settings of `app`, with the global and built-in defaults it falls back to.
+fn app_defaults(ctx: &Context, config: &Config, app: &str) -> Result<Vec<Default>, Error> {
+    let (settings, source) = app_settings(ctx, config, app)?;
+
+    // The keys set, as the config file would have them
+    let table: toml::Table = toml::Value::try_from(&settings)
+        .ok()
+        .and_then(|value| value.as_table().cloned())
+        .unwrap_or_default();
+    let mut defaults: Vec<Default> = table
+        .iter()
+        .filter_map(|(key, value)| {
+            let key = APP_KEYS.iter().find(|known| *known == key)?;
+            let value = match value {
+                toml::Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+            Some(Default::new(key, value, source))
+        })
+        .collect();
+    // Settings left out fall back to the global or built-in defaults
+    let fallbacks = [
+        ("channel", Default::new("channel", "stable", "built-in")),
+        (
+            "asset_type",
+            Default::new("asset_type", settings.asset_type.name(), "built-in"),
+        ),
+        ("symlink", Default::of("symlink", config.symlink, true)),
+        (
+            "stable_filename",
+            Default::of("stable_filename", config.stable_filename, false),
+        ),
+    ];
+    for (key, fallback) in fallbacks {
+        if !table.contains_key(key) {
+            defaults.push(fallback);
+        }
+    }
+    Ok(defaults)
+}
+
+/// The keys of `[apps.<name>]` tables, as `show-defaults` names them.
+const APP_KEYS: &[&str] = &[
+    "repo",
+    "provider",
+    "symlink",
+    "stable_filename",
+    "channel",
+    "asset_type",
+    "asset_pattern",
+    "prefer",
+    "url",
+    "version_url",
+    "version_pattern",
+    "signing_keys",
+    "checksum_asset",
+    "post_install",
+    "sandbox",
+    "notes",
+    "remind_after_update",
+];
+
+/// `duration` as the config file would write it, e.g. `30s` or `2h`.
+fn duration(duration: Duration) -> String {
+    match duration.as_secs() {
+        secs if secs > 0 && secs % 3600 == 0 => format!("{}h", secs / 3600),
+        secs if secs > 0 && secs % 60 == 0 => format!("{}m", secs / 60),
+        secs => format!("{}s", secs),
+    }
+}
+
+fn tls_name(tls: TlsBackend) -> &'static str {
+    match tls {
+        TlsBackend::Native => "native",
+        TlsBackend::Rustls => "rustls",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::config::Channel;
+    use crate::OutputLevel;
+    use clap::CommandFactory;
+
+    /// The matches of `config set-default` given `args`.
+    fn set_default_matches(args: &[&str]) -> Result<ArgMatches, clap::Error> {
+        let command = ["rust-unicorn", "config", "set-default"];
+        let matches = Cli::command().try_get_matches_from(command.iter().chain(args))?;
+        Ok(matches
+            .subcommand_matches("config")
+            .and_then(|matches| matches.subcommand_matches("set-default"))
+            .unwrap()
+            .clone())
+    }
+
+    /// A scratch config file holding `text`, with a context using it.
+    fn scratch(name: &str, text: &str) -> (Context, PathBuf) {
+        let dir = env::temp_dir().join(format!(
+            "rust-unicorn-defaults-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, text).unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        let ctx = Context::new(config, OutputLevel::Silent, None).unwrap();
+        (ctx, path)
+    }
+
+    fn set(ctx: &Context, path: &Path, args: &[&str]) -> Result<(), Error> {
+        let app = args
+            .iter()
+            .position(|arg| *arg == "--app")
+            .map(|index| args[index + 1]);
+        set_default(ctx, Some(path), app, &set_default_matches(args).unwrap())
+    }
+
+    #[test]
+    fn global_flags_are_stored_keeping_the_rest_of_the_file() {
+        let (ctx, path) = scratch("global", "# kept\nkeep_versions = 2\n");
+        set(
+            &ctx,
+            &path,
+            &[
+                "--notify",
+                "--retries",
+                "5",
+                "--limit-rate",
+                "2M",
+                "--no-desktop-entry",
+                "-j",
+                "3",
+            ],
+        )
+        .unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# kept\nkeep_versions = 2\n"), "{}", text);
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.notify, Some(true));
+        assert_eq!(config.retries, Some(5));
+        assert_eq!(config.limit_rate.as_deref(), Some("2M"));
+        assert_eq!(config.desktop_entry, Some(false));
+        assert_eq!(config.parallel_updates, Some(3));
+        assert_eq!(config.keep_versions, Some(2));
+    }
+
+    #[test]
+    fn directories_are_stored_as_absolute_paths() {
+        let (ctx, path) = scratch("paths", "");
+        set(
+            &ctx,
+            &path,
+            &["--install-dir", "apps", "--bin-dir", "~/bin"],
+        )
+        .unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(config.install_dir, Some(cwd.join("apps")));
+        assert_eq!(config.bin_dir, paths::home().map(|home| home.join("bin")));
+    }
+
+    #[test]
+    fn app_flags_are_stored_in_the_apps_table() {
+        let (ctx, path) = scratch("app", "");
+        set(
+            &ctx,
+            &path,
+            &["--app", "joplin", "--channel", "prerelease", "--no-symlink"],
+        )
+        .unwrap();
+        set(&ctx, &path, &["--app", "joplin", "--sandbox", "firejail"]).unwrap();
+        set(&ctx, &path, &["--app", "joplin", "--no-sandbox"]).unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+        let joplin = &config.apps["joplin"];
+        // The catalog's settings are kept
+        assert_eq!(joplin.repo, "laurent22/joplin");
+        assert!(joplin.channel == Channel::Prerelease);
+        assert_eq!(joplin.symlink, Some(false));
+        assert!(joplin.sandbox.is_none());
+    }
+
+    #[test]
+    fn flags_without_a_setting_or_in_the_wrong_scope_are_rejected() {
+        let text = "retries = 1\n";
+        let (ctx, path) = scratch("rejected", text);
+        for args in [
+            &["--wait-for-lock"][..],
+            &["--dir-mode", "700"],
+            &["--channel", "prerelease"],
+            &["--app", "joplin", "--retries", "3"],
+            &["--app", "no-such-app", "--no-symlink"],
+            &[],
+        ] {
+            assert!(set(&ctx, &path, args).is_err(), "{:?} was accepted", args);
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+
+        // Values are checked as the command line checks them
+        assert!(set_default_matches(&["--jobs", "0"]).is_err());
+        assert!(set_default_matches(&["--retries", "many"]).is_err());
+        assert!(set_default_matches(&["--limit-rate", "fast"]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+// Desktop entries (~/.local/share/applications/<app>.desktop) so installed
+// AppImages show up in application launchers. The MIME types and URL schemes the
+// AppImage's own desktop file handles are carried over, so files and links (such as
+// `joplin://`) open the app.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+use crate::install::create_dirs;
+use crate::{paths, Error};
+
+/// Directory holding user desktop entries: `$XDG_DATA_HOME/applications`.
+pub fn applications_dir() -> Option<PathBuf> {
+    Some(paths::data_home()?.join("applications"))
+}
+
+/// Write (or overwrite) the desktop entry for `app_name` launching `exec`, as the
+/// handler of `mime_types`. A missing `dir` is created with `dir_mode`.
+pub fn write_entry(
+    dir: &Path,
+    app_name: &str,
+    display_name: &str,
+    exec: &Path,
+    icon: &str,
+    mime_types: &[String],
+    dir_mode: Option<u32>,
+) -> io::Result<PathBuf> {
+    create_dirs(dir, dir_mode)?;
+
+    let path = dir.join(format!("{}.desktop", app_name));
+    let contents = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={}\n\
+         Exec={} %U\n\
+         Icon={}\n\
+         Terminal=false\n\
+         X-RustUnicorn-App={}\n",
+        display_name,
+        quote_exec_arg(&exec.to_string_lossy()),
+        icon,
+        app_name,
+    );
+    let contents = if mime_types.is_empty() {
+        contents
+    } else {
+        format!("{}MimeType={};\n", contents, mime_types.join(";"))
+    };
+    fs::write(&path, contents)?;
+
+    Ok(path)
+}
+
+/// The MIME types (`x-scheme-handler/<scheme>` for URL schemes) the desktop file
+/// inside `appimage` declares, extracted into a scratch directory under `dir` created
+/// with `dir_mode`.
+pub fn embedded_mime_types(
+    appimage: &Path,
+    dir: &Path,
+    app_name: &str,
+    dir_mode: Option<u32>,
+) -> Result<Vec<String>, Error> {
+    let work_dir = dir.join(format!(".{}-extract", app_name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    create_dirs(&work_dir, dir_mode)?;
+
+    let result = extract_mime_types(appimage, &work_dir);
+    fs::remove_dir_all(&work_dir)?;
+
+    result
+}
+
+/// Extract the desktop file at the root of `appimage`, where the AppImage
+/// specification puts it, and read its `MimeType` key.
+fn extract_mime_types(appimage: &Path, work_dir: &Path) -> Result<Vec<String>, Error> {
+    extract_member(appimage, work_dir, Path::new("*.desktop"))?;
+    let root = work_dir.join("squashfs-root");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&root)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    paths.sort();
+    let path = paths
+        .into_iter()
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "desktop")
+        })
+        .ok_or("AppImage has no desktop file")?;
+    // The desktop file is commonly a link into usr/share/applications, which isn't extracted
+    if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+        let target = fs::read_link(&path)?;
+        let member = target.strip_prefix("/").unwrap_or(&target);
+        extract_member(appimage, work_dir, member)?;
+        return Ok(mime_types(&fs::read_to_string(root.join(member))?));
+    }
+    Ok(mime_types(&fs::read_to_string(&path)?))
+}
+
+/// Extract the files of `appimage` matching `pattern` into `work_dir/squashfs-root`.
+fn extract_member(appimage: &Path, work_dir: &Path, pattern: &Path) -> Result<(), Error> {
+    let status = Command::new(appimage)
+        .arg("--appimage-extract")
+        .arg(pattern)
+        .current_dir(work_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| {
+            format!(
+                "Cannot run {} to extract its desktop file: {}",
+                appimage.display(),
+                e
+            )
+        })?;
+    if !status.success() {
+        return Err(format!(
+            "{} --appimage-extract failed ({})",
+            appimage.display(),
+            status
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The `MimeType` entries of the `[Desktop Entry]` group of a desktop file.
+fn mime_types(contents: &str) -> Vec<String> {
+    let mut in_entry = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+        } else if let (true, Some(value)) = (in_entry, line.strip_prefix("MimeType")) {
+            if let Some(value) = value.trim_start().strip_prefix('=') {
+                return value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|mime_type| {
+                        mime_type.contains('/') && !mime_type.contains(char::is_whitespace)
+                    })
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Refresh the MIME cache of the applications in `dir` with `update-desktop-database`,
+/// so a changed entry's handlers take effect. Systems without it read the entries
+/// directly, so its absence is fine.
+pub fn update_database(dir: &Path) {
+    match Command::new("update-desktop-database")
+        .arg(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(status) if !status.success() => debug!("update-desktop-database failed ({})", status),
+        Ok(_) => debug!("Updated the desktop database of {}", dir.display()),
+        Err(e) => debug!("Cannot run update-desktop-database: {}", e),
+    }
+}
+
+/// Quote an Exec argument as required by the Desktop Entry specification when it
+/// contains characters the launcher would otherwise interpret.
+fn quote_exec_arg(arg: &str) -> String {
+    let needs_quoting = arg
+        .chars()
+        .any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c));
+    if !needs_quoting {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    // The whole value is additionally subject to string escaping, which doubles backslashes
+    quoted.replace('\\', "\\\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mime_types_of_the_desktop_entry_group_only() {
+        let entry = "[Desktop Entry]\nName=Tool\nMimeType = text/markdown;x-scheme-handler/tool;bogus;;\n\n[Desktop Action new]\nMimeType=text/plain;\n";
+        assert_eq!(
+            mime_types(entry),
+            ["text/markdown", "x-scheme-handler/tool"]
+        );
+        assert!(mime_types("[Desktop Action new]\nMimeType=text/plain;\n").is_empty());
+        assert!(mime_types("[Desktop Entry]\nName=Tool\n").is_empty());
+    }
+
+    #[test]
+    fn quotes_exec_arguments_the_launcher_would_interpret() {
+        assert_eq!(quote_exec_arg("/apps/Tool.AppImage"), "/apps/Tool.AppImage");
+        assert_eq!(
+            quote_exec_arg("/my apps/Tool.AppImage"),
+            "\"/my apps/Tool.AppImage\""
+        );
+        // Escaped for the Exec key, then each backslash again for the string value
+        assert_eq!(quote_exec_arg("$HOME"), "\"\\\\$HOME\"");
+        assert_eq!(quote_exec_arg("a\"b"), "\"a\\\\\"b\"");
+    }
+}
@@ -0,0 +1,285 @@
+// Direct downloads from a project's own site (`provider = "url"`), for apps that
+// publish AppImages outside any forge. The configured URL is turned into a release
+// with a single asset so the usual install pipeline applies.
+use regex::Regex;
+use reqwest::header::{self, HeaderMap};
+use tracing::debug;
+
+use crate::config::App;
+use crate::http::{self, HttpFetcher};
+use crate::method;
+use crate::provider::ReleaseProvider;
+use crate::release::sanitize_file_name;
+use crate::Error;
+use crate::{Asset, Release};
+
+pub struct Direct<'a> {
+    http: &'a dyn HttpFetcher,
+    app: &'a App,
+}
+
+impl<'a> Direct<'a> {
+    pub fn new(http: &'a dyn HttpFetcher, app: &'a App) -> Self {
+        Direct { http, app }
+    }
+}
+
+impl ReleaseProvider for Direct<'_> {
+    async fn latest_release(&self) -> Result<Release, Error> {
+        build_release(self.http, self.app, None).await
+    }
+
+    async fn release_by_tag(&self, tag: &str) -> Result<Release, Error> {
+        build_release(self.http, self.app, Some(tag)).await
+    }
+
+    /// Only the current download is known.
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        Ok(vec![self.latest_release().await?])
+    }
+}
+
+/// Build the release for `app`: its version is `tag`, else the one announced at the
+/// configured `version_url`, else (for URLs without `{version}`) the server's
+/// ETag/Last-Modified. The asset is named after the URL's file name, else the one the
+/// server suggests in a Content-Disposition header, else the app, with the extension
+/// of its asset type.
+async fn build_release(
+    http: &dyn HttpFetcher,
+    app: &App,
+    tag: Option<&str>,
+) -> Result<Release, Error> {
+    let settings = &app.settings;
+    let template = settings.url.as_deref().ok_or_else(|| {
+        format!(
+            "Config entry for app '{}': provider \"url\" needs a url",
+            app.name
+        )
+    })?;
+
+    let version = match (tag, &settings.version_url) {
+        (Some(tag), _) => Some(tag.to_string()),
+        (_, Some(version_url)) => Some(discover_version(http, app, version_url).await?),
+        (_, None) => None,
+    };
+    let url = match &version {
+        Some(version) => template.replace("{version}", version),
+        None if template.contains("{version}") => {
+            return Err(format!(
+                "Config entry for app '{}': url contains {{version}}, so a version_url is needed",
+                app.name
+            )
+            .into());
+        }
+        None => template.to_string(),
+    };
+
+    // Download endpoints like ".../download?os=linux" don't name the file
+    let method = method::for_type(settings.asset_type);
+    let path = url.split(['?', '#']).next().unwrap_or(&url);
+    let url_name = path
+        .rsplit('/')
+        .next()
+        .filter(|&file_name| method.matches(file_name))
+        .and_then(sanitize_file_name);
+    let headers = match (&version, &url_name) {
+        (Some(_), Some(_)) => HeaderMap::new(),
+        (None, _) => response_headers(http, &url).await?,
+        // Only the name is missing, which the app's can stand in for
+        (Some(_), None) => response_headers(http, &url).await.unwrap_or_else(|e| {
+            debug!("Cannot ask {} for the file name: {}", url, e);
+            HeaderMap::new()
+        }),
+    };
+    let tag_name = match version {
+        Some(version) => version,
+        None => revision(&headers, &url)?,
+    };
+    let disposition_name = || {
+        http::disposition_file_name(&headers)
+            .and_then(|name| sanitize_file_name(&name))
+            .filter(|name| method.matches(name))
+    };
+    let (name, source) = match url_name {
+        Some(name) => (name, "the URL"),
+        None => match disposition_name() {
+            Some(name) => (name, "the server's Content-Disposition header"),
+            None => (method.fallback_asset_name(&app.name), "the app"),
+        },
+    };
+    debug!("Named the download {} after {}", name, source);
+
+    Ok(Release {
+        tag_name,
+        prerelease: false,
+        draft: false,
+        body: None,
+        assets: vec![Asset {
+            name,
+            browser_download_url: url,
+            size: 0,
+            state: "uploaded".to_string(),
+            digest: None,
+        }],
+    })
+}
+
+/// Fetch `version_url` and extract the version with the app's `version_pattern`.
+async fn discover_version(
+    http: &dyn HttpFetcher,
+    app: &App,
+    version_url: &str,
+) -> Result<String, Error> {
+    let text = http
+        .get(version_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    let version = match &app.settings.version_pattern {
+        Some(pattern) => {
+            let regex = Regex::new(pattern).map_err(|e| {
+                format!(
+                    "Config entry for app '{}': invalid version_pattern: {}",
+                    app.name, e
+                )
+            })?;
+            let captures = regex.captures(&text).ok_or_else(|| {
+                format!(
+                    "No version matching version_pattern found at {}",
+                    version_url
+                )
+            })?;
+            captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map_or("", |m| m.as_str())
+                .trim()
+                .to_string()
+        }
+        None => text.trim().to_string(),
+    };
+    if version.is_empty() || version.contains(char::is_whitespace) {
+        return Err(format!(
+            "Cannot read a version from {}; set version_pattern to pick it out",
+            version_url
+        )
+        .into());
+    }
+
+    Ok(version)
+}
+
+/// The headers of the final response to a GET of `url`, after any redirects, leaving
+/// its body unread. Servers answer a HEAD differently often enough (without the
+/// Content-Disposition, or not at all) that only the download's own request will do.
+async fn response_headers(http: &dyn HttpFetcher, url: &str) -> Result<HeaderMap, Error> {
+    let response = http.get(url).send().await?.error_for_status()?;
+    Ok(response.headers().clone())
+}
+
+/// Identify the current file behind the unversioned `url`, which answered with
+/// `headers`, by its ETag or Last-Modified header, so a changed file reads as a new
+/// release.
+fn revision(headers: &HeaderMap, url: &str) -> Result<String, Error> {
+    let revision = headers
+        .get(header::ETAG)
+        .or_else(|| headers.get(header::LAST_MODIFIED))
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_start_matches("W/").trim_matches('"').to_string())
+        .filter(|value| !value.is_empty());
+
+    Ok(revision.ok_or_else(|| format!("{} has no ETag or Last-Modified header to tell versions apart; configure a version_url", url))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Provider;
+    use crate::config::{AppConfig, AssetType, Config};
+    use crate::Context;
+    use crate::OutputLevel;
+    use reqwest::{Method, Request, Response, StatusCode};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    /// Answers downloads with `disposition` as their Content-Disposition, if any, and
+    /// refuses HEAD requests, as some download servers do.
+    struct Server {
+        disposition: Option<&'static str>,
+    }
+
+    impl HttpFetcher for Server {
+        fn execute(
+            &self,
+            request: Request,
+        ) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send + '_>> {
+            let mut response = ::http::Response::builder().header(header::ETAG, "\"r1\"");
+            if request.method() != Method::GET {
+                response = response.status(StatusCode::METHOD_NOT_ALLOWED);
+            } else if let Some(disposition) = self.disposition {
+                response = response.header(header::CONTENT_DISPOSITION, disposition);
+            }
+            let response = Response::from(response.body(String::new()).unwrap());
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    /// The asset name of the app of `asset_type` downloaded from `url`, from a server
+    /// suggesting `disposition`.
+    async fn asset_name(
+        url: &str,
+        asset_type: AssetType,
+        disposition: Option<&'static str>,
+    ) -> String {
+        let mut config = Config::default();
+        config.apps.insert(
+            "tool".to_string(),
+            AppConfig {
+                provider: Some(Provider::Url),
+                url: Some(url.to_string()),
+                asset_type,
+                ..AppConfig::default()
+            },
+        );
+        let ctx = Context::new(config, OutputLevel::Silent, None).unwrap();
+        let app = ctx.config.resolve("tool", None).unwrap();
+        let release = build_release(&Server { disposition }, &app, None)
+            .await
+            .unwrap();
+        assert_eq!(release.tag_name, "r1");
+        release.assets[0].name.clone()
+    }
+
+    #[tokio::test]
+    async fn names_the_asset_after_the_url_then_the_server_then_the_app() {
+        let disposition = Some("attachment; filename*=UTF-8''..%2FTool%20%CE%B2.AppImage");
+        let (appimage, archive) = (AssetType::AppImage, AssetType::Archive);
+        let url = "https://example.com/download?id=42";
+        assert_eq!(
+            asset_name(
+                "https://example.com/Tool-x86_64.AppImage",
+                appimage,
+                disposition
+            )
+            .await,
+            "Tool-x86_64.AppImage"
+        );
+        assert_eq!(
+            asset_name(url, appimage, disposition).await,
+            "Tool β.AppImage"
+        );
+        assert_eq!(
+            asset_name(url, appimage, Some("attachment; filename=\"Tool.deb\"")).await,
+            "tool.AppImage"
+        );
+        assert_eq!(asset_name(url, appimage, None).await, "tool.AppImage");
+        assert_eq!(
+            asset_name(url, archive, Some("attachment; filename=tool-linux.zip")).await,
+            "tool-linux.zip"
+        );
+        assert_eq!(asset_name(url, archive, None).await, "tool.tar.gz");
+    }
+}
@@ -0,0 +1,365 @@
+// `doctor`: checks that every installed app is intact (its AppImage present,
+// executable and unchanged since install; its symlink, desktop entry, icon, metainfo,
+// sandbox wrapper and extracted files in place) and that FUSE is available. Problems come
+// with a suggested fix, which `--fix` applies.
+use serde::Serialize;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::cli::{InstallArgs, OutputFormat};
+use crate::install::install;
+use crate::method::{self, Finalize};
+use crate::state::{InstalledApp, State};
+use crate::verify::sha256_file;
+use crate::{extract, sandbox, style, tr, Context, Error};
+
+/// What repairs a problem, in the order repairs are applied.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fix {
+    /// Set the AppImage's executable bits
+    MakeExecutable,
+    /// Download the installed version again, which also recreates everything else
+    Reinstall,
+    /// Recreate the symlink, desktop entry, icon, sandbox wrapper and extracted files
+    Reintegrate,
+    /// Nothing `--fix` can do; the suggestion says what to do instead
+    Manual,
+}
+
+/// A problem with an installed app.
+#[derive(Serialize)]
+pub struct Problem {
+    pub app: String,
+    pub problem: String,
+    pub fix: Fix,
+    /// What the fix does, or what the user should do
+    pub suggestion: String,
+    pub fixed: bool,
+}
+
+/// Diagnose every installed app and, with `fix`, repair what can be repaired. Fails
+/// when problems remain.
+pub async fn doctor(ctx: &Context, fix: bool) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+    let fuse = extract::fuse_available();
+    let mut problems = Vec::new();
+    for (name, installed) in &state.apps {
+        let mut found = diagnose(name, installed, fuse);
+        if fix && !found.is_empty() {
+            repair(ctx, name, installed, fuse, &mut found).await;
+        }
+        problems.extend(found);
+    }
+    let remaining = problems.iter().filter(|problem| !problem.fixed).count();
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "fuse_available": fuse,
+            "problems": problems,
+        }))?;
+    } else {
+        if !fuse {
+            warn!("{}", tr!("fuse-missing"));
+        }
+        for problem in &problems {
+            warn!("{}: {}", problem.app, problem.problem);
+            if problem.fixed {
+                info!("{}", tr!("doctor-fixed", fix = problem.suggestion.as_str()));
+            } else {
+                info!(
+                    "{}",
+                    tr!("doctor-suggestion", fix = problem.suggestion.as_str())
+                );
+            }
+        }
+        if problems.is_empty() {
+            info!("{}", style::success(tr!("doctor-healthy")));
+        } else if fix {
+            info!(
+                "{}",
+                tr!(
+                    "doctor-fixed-count",
+                    fixed = problems.len() - remaining,
+                    total = problems.len()
+                )
+            );
+        }
+    }
+
+    if remaining > 0 {
+        let fixable = problems
+            .iter()
+            .any(|problem| !problem.fixed && problem.fix != Fix::Manual);
+        let hint = if fixable && !fix {
+            "; run `rust-unicorn doctor --fix` to repair them"
+        } else {
+            ""
+        };
+        return Err(format!("{} problem(s) found{}", remaining, hint).into());
+    }
+    Ok(())
+}
+
+/// The problems of the installed app `name`. A missing or changed AppImage needs
+/// reinstalling, which makes everything else moot.
+fn diagnose(name: &str, installed: &InstalledApp, fuse: bool) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut report = |problem: String, fix: Fix, suggestion: String| {
+        problems.push(Problem {
+            app: name.to_string(),
+            problem,
+            fix,
+            suggestion,
+            fixed: false,
+        });
+    };
+    let reinstall = || {
+        tr!(
+            "fix-reinstall",
+            app = name,
+            version = installed.tag.as_str()
+        )
+    };
+    let path = &installed.install_path;
+
+    if !path.exists() {
+        report(
+            tr!("appimage-missing", path = path.display().to_string()),
+            Fix::Reinstall,
+            reinstall(),
+        );
+        return problems;
+    }
+    match sha256_file(path) {
+        Ok(sha256) if installed.sha256.is_empty() || sha256 == installed.sha256 => {}
+        Ok(_) => {
+            report(
+                tr!("appimage-changed", path = path.display().to_string()),
+                Fix::Reinstall,
+                reinstall(),
+            );
+            return problems;
+        }
+        Err(e) => {
+            report(
+                tr!(
+                    "appimage-unreadable",
+                    path = path.display().to_string(),
+                    error = e.to_string()
+                ),
+                Fix::Reinstall,
+                reinstall(),
+            );
+            return problems;
+        }
+    }
+    let executable =
+        fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0);
+    if !executable {
+        report(
+            tr!("not-executable", path = path.display().to_string()),
+            Fix::MakeExecutable,
+            tr!("fix-make-executable"),
+        );
+    }
+
+    if let Some(symlink) = &installed.symlink {
+        let expected = launcher(name, installed);
+        if symlink.is_symlink() {
+            let points_there = match (fs::canonicalize(symlink), fs::canonicalize(&expected)) {
+                (Ok(target), Ok(expected)) => target == expected,
+                _ => false,
+            };
+            if !points_there {
+                let target = fs::read_link(symlink).unwrap_or_default();
+                let problem = tr!(
+                    "symlink-wrong",
+                    path = symlink.display().to_string(),
+                    target = target.display().to_string(),
+                    expected = expected.display().to_string()
+                );
+                report(problem, Fix::Reintegrate, tr!("fix-reintegrate"));
+            }
+        } else if symlink.exists() {
+            let path = symlink.display().to_string();
+            report(
+                tr!("symlink-not-link", path = path.as_str()),
+                Fix::Manual,
+                tr!("fix-move-away", path = path.as_str()),
+            );
+        } else {
+            report(
+                tr!("symlink-missing", path = symlink.display().to_string()),
+                Fix::Reintegrate,
+                tr!("fix-reintegrate"),
+            );
+        }
+    }
+    if let Some(entry) = installed
+        .desktop_entry
+        .as_ref()
+        .filter(|entry| !entry.exists())
+    {
+        report(
+            tr!("desktop-entry-missing", path = entry.display().to_string()),
+            Fix::Reintegrate,
+            tr!("fix-reintegrate"),
+        );
+    }
+    if let Some(icon) = installed.icon.as_ref().filter(|icon| !icon.exists()) {
+        report(
+            tr!("icon-missing", path = icon.display().to_string()),
+            Fix::Reintegrate,
+            tr!("fix-reintegrate"),
+        );
+    }
+    if let Some(metainfo) = installed
+        .metainfo
+        .as_ref()
+        .filter(|metainfo| !metainfo.exists())
+    {
+        report(
+            tr!("metainfo-missing", path = metainfo.display().to_string()),
+            Fix::Reintegrate,
+            tr!("fix-reintegrate"),
+        );
+    }
+    if installed.sandbox.is_some() {
+        match sandbox::wrapper_path(name) {
+            Ok(wrapper) if !wrapper.exists() => report(
+                tr!("wrapper-missing", path = wrapper.display().to_string()),
+                Fix::Reintegrate,
+                tr!("fix-reintegrate"),
+            ),
+            _ => {}
+        }
+    }
+    match &installed.app_dir {
+        Some(app_dir) if !extract::app_run(app_dir).exists() => report(
+            tr!("app-dir-missing", path = app_dir.display().to_string()),
+            Fix::Reintegrate,
+            tr!("fix-reintegrate"),
+        ),
+        None if installed.sandbox.is_none() && !installed.from_archive() && !fuse => {
+            report(tr!("needs-fuse"), Fix::Reintegrate, tr!("fix-extract"))
+        }
+        _ => {}
+    }
+    problems
+}
+
+/// What the app's symlink should point at: its sandbox wrapper, its extracted
+/// `AppRun` or the AppImage itself.
+fn launcher(name: &str, installed: &InstalledApp) -> PathBuf {
+    if installed.sandbox.is_some() {
+        if let Ok(wrapper) = sandbox::wrapper_path(name) {
+            return wrapper;
+        }
+    }
+    match &installed.app_dir {
+        Some(app_dir) => extract::app_run(app_dir),
+        None => installed.install_path.clone(),
+    }
+}
+
+/// Apply the fixes `problems` call for, marking the problems they fix.
+async fn repair(
+    ctx: &Context,
+    name: &str,
+    installed: &InstalledApp,
+    fuse: bool,
+    problems: &mut [Problem],
+) {
+    let mut fixes: Vec<Fix> = problems
+        .iter()
+        .map(|problem| problem.fix)
+        .filter(|fix| *fix != Fix::Manual)
+        .collect();
+    fixes.sort();
+    fixes.dedup();
+    for fix in fixes {
+        let result = match fix {
+            Fix::MakeExecutable => make_executable(&installed.install_path),
+            Fix::Reinstall => reinstall(ctx, name, installed).await,
+            Fix::Reintegrate => reintegrate(ctx, name, installed, fuse),
+            Fix::Manual => continue,
+        };
+        match result {
+            Ok(()) => problems
+                .iter_mut()
+                .filter(|problem| problem.fix == fix)
+                .for_each(|problem| problem.fixed = true),
+            Err(e) => warn!(
+                "{}",
+                tr!("doctor-fix-failed", app = name, error = e.to_string())
+            ),
+        }
+    }
+}
+
+fn make_executable(path: &Path) -> Result<(), Error> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+/// Install the app's recorded version again, over whatever is left of it.
+async fn reinstall(ctx: &Context, name: &str, installed: &InstalledApp) -> Result<(), Error> {
+    let args = InstallArgs {
+        // Configured apps are addressed by name so their per-app settings apply
+        app: Some(if ctx.config.apps.contains_key(name) {
+            name.to_string()
+        } else {
+            installed.repo.clone()
+        }),
+        tag: Some(installed.tag.clone()),
+        force: true,
+        non_interactive: true,
+        ..InstallArgs::default()
+    };
+    install(ctx, &args, false).await?;
+    Ok(())
+}
+
+/// Recreate the app's symlink, desktop entry, icon, wrapper and extracted files as
+/// recorded, extracting it now if it needs FUSE but FUSE is missing.
+fn reintegrate(
+    ctx: &Context,
+    name: &str,
+    installed: &InstalledApp,
+    fuse: bool,
+) -> Result<(), Error> {
+    let spec = if ctx.config.apps.contains_key(name) {
+        name
+    } else {
+        installed.repo.as_str()
+    };
+    let app = ctx.config.resolve(spec, None)?;
+    let extract = installed.app_dir.is_some()
+        || (installed.sandbox.is_none() && !installed.from_archive() && !fuse);
+    let integration = method::for_type(installed.method()).finalize(
+        ctx,
+        &app,
+        &installed.install_path,
+        &installed.tag,
+        &Finalize {
+            symlink: installed.symlink.is_some(),
+            desktop_entry: installed.desktop_entry.is_some(),
+            sandbox: installed.sandbox,
+            extract,
+        },
+    )?;
+    ctx.update_state(|state| {
+        if let Some(entry) = state.apps.get_mut(name) {
+            entry.symlink = integration.symlink;
+            entry.desktop_entry = integration.desktop_entry;
+            entry.icon = integration.icon;
+            entry.metainfo = integration.metainfo;
+            entry.app_dir = integration.app_dir;
+        }
+    })
+}
@@ -0,0 +1,426 @@
+// Download engine: streams a release asset to disk with a progress bar, hashing it
+// on the way and enforcing --pin-host on the host that actually serves it. Large
+// assets are fetched in concurrent ranged segments where the server allows it.
+use futures_util::StreamExt; // For stream handling
+use indicatif::HumanBytes;
+use reqwest::{header, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256}; // For hashing downloaded files
+use std::ffi::CString;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::progress::Transfer;
+use crate::retry;
+use crate::tr;
+use crate::verify::sha256_file;
+use crate::Context;
+use crate::Error;
+
+/// Downloads smaller than this are streamed over a single connection.
+const SEGMENTED_MIN_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Connections a segmented download is split over.
+const SEGMENTS: u64 = 4;
+
+/// A finished download.
+pub struct Downloaded {
+    /// SHA-256 of the file, lowercase hex
+    pub sha256: String,
+    /// URL the bytes were served from, after redirects
+    pub final_url: Url,
+    /// What identifies this version of the file to the server, to ask later whether
+    /// it changed
+    pub validators: Validators,
+}
+
+/// The `ETag` and `Last-Modified` a server sent with a file.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn of(response: &Response) -> Validators {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Validators {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        }
+    }
+
+    /// Whether the server sent anything to revalidate the file with.
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Download `url` to `path`, returning the file's SHA-256 and where it was served
+/// from. When `pinned_hosts` is non-empty, the host serving the bytes after redirects
+/// must be one of them.
+///
+/// Transient failures are retried up to `ctx.retries` times; an interrupted transfer
+/// resumes with a range request where the server supports it, and starts over
+/// otherwise. With `max_parallel_downloads` set, it waits for a free slot first; the
+/// whole transfer must finish within `ctx.download_timeout`.
+pub async fn download(
+    ctx: &Context,
+    url: &str,
+    path: &Path,
+    pinned_hosts: &[String],
+) -> Result<Downloaded, Error> {
+    let _permit = ctx.download_permit().await;
+    within_deadline(ctx, url, transfer(ctx, url, path, pinned_hosts, None))
+        .await?
+        .ok_or_else(|| format!("{} answered 304 Not Modified to a plain request", url).into())
+}
+
+/// `download`, unless the server says the file it identified by `validators` hasn't
+/// changed (304 Not Modified): then nothing is written and `None` is returned.
+pub async fn download_if_changed(
+    ctx: &Context,
+    url: &str,
+    path: &Path,
+    pinned_hosts: &[String],
+    validators: &Validators,
+) -> Result<Option<Downloaded>, Error> {
+    let _permit = ctx.download_permit().await;
+    let transfer = transfer(ctx, url, path, pinned_hosts, Some(validators));
+    within_deadline(ctx, url, transfer).await
+}
+
+/// Run `download` of `url`, failing it if it outlasts `ctx.download_timeout`.
+pub async fn within_deadline<T>(
+    ctx: &Context,
+    url: &str,
+    download: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    match ctx.download_timeout {
+        Some(limit) => tokio::time::timeout(limit, download).await.map_err(|_| {
+            format!("Download of {} did not finish within {}s; raise download_timeout for slow connections", url, limit.as_secs())
+        })?,
+        None => download.await,
+    }
+}
+
+async fn transfer(
+    ctx: &Context,
+    url: &str,
+    path: &Path,
+    pinned_hosts: &[String],
+    unless: Option<&Validators>,
+) -> Result<Option<Downloaded>, Error> {
+    let mut resp = retry::retry_transient(ctx, || request(ctx, url, 0, unless)).await?;
+    let final_url = check_host(resp.url(), pinned_hosts)?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let validators = Validators::of(&resp);
+    info!("{}", tr!("download-source", url = url));
+    if final_url.as_str() != url {
+        info!(
+            "{}",
+            tr!(
+                "download-served-by",
+                host = final_url.host_str().unwrap_or(""),
+                url = final_url.as_str()
+            )
+        );
+    }
+
+    // Get the content length for the progress bar and the free space check
+    let total_size = resp.content_length().unwrap_or(0);
+    check_free_space(path, total_size)?;
+    // Labeled with the asset name, to tell bars apart during parallel updates
+    let label = url.rsplit('/').next().unwrap_or(url);
+
+    // Large files come faster over several connections, where the server allows it
+    let accepts_ranges = resp
+        .headers()
+        .get(header::ACCEPT_RANGES)
+        .is_some_and(|value| value == "bytes");
+    if accepts_ranges && total_size >= SEGMENTED_MIN_SIZE {
+        drop(resp);
+        match download_segments(ctx, &final_url, label, path, total_size, pinned_hosts).await {
+            Ok(sha256) => {
+                return Ok(Some(Downloaded {
+                    sha256,
+                    final_url,
+                    validators,
+                }))
+            }
+            Err(e) => {
+                warn!(
+                    "{}",
+                    tr!("segmented-download-failed", error = e.to_string())
+                );
+                resp = retry::retry_transient(ctx, || request(ctx, url, 0, None)).await?;
+                check_host(resp.url(), pinned_hosts)?;
+            }
+        }
+    }
+
+    let mut progress = Transfer::new(ctx, label, total_size);
+
+    // Stream the download with progress updates
+    let mut file = File::create(path)?;
+    let mut hasher = Sha256::new();
+    let mut downloaded: u64 = 0;
+    let mut stream = resp.bytes_stream();
+    let mut retry = 0;
+
+    loop {
+        let chunk = match stream.next().await {
+            Some(Ok(chunk)) => chunk,
+            None => break,
+            Some(Err(e)) => {
+                let e = Error::from(e);
+                if retry >= ctx.retries || !retry::is_transient(&e) {
+                    return Err(e);
+                }
+                retry += 1;
+                retry::pause(ctx, retry, &e).await;
+
+                // Pick up where the transfer broke off, or start over if the server
+                // can't resume
+                let resp =
+                    retry::retry_transient(ctx, || request(ctx, url, downloaded, None)).await?;
+                check_host(resp.url(), pinned_hosts)?;
+                if resp.status() != StatusCode::PARTIAL_CONTENT {
+                    file.set_len(0)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    hasher = Sha256::new();
+                    downloaded = 0;
+                }
+                stream = resp.bytes_stream();
+                continue;
+            }
+        };
+        file.write_all(&chunk)?;
+        hasher.update(&chunk);
+        if let Some(throttle) = &ctx.throttle {
+            throttle.consume(chunk.len()).await;
+        }
+
+        downloaded += chunk.len() as u64;
+        progress.set_position(downloaded);
+    }
+
+    // Flush to disk before the file is renamed into place
+    file.sync_all()?;
+    progress.finish();
+
+    Ok(Some(Downloaded {
+        sha256: format!("{:x}", hasher.finalize()),
+        final_url,
+        validators,
+    }))
+}
+
+/// Download `url`, which is `total_size` bytes long, to `path` in `SEGMENTS` ranges
+/// fetched at once, and return its SHA-256.
+async fn download_segments(
+    ctx: &Context,
+    url: &Url,
+    label: &str,
+    path: &Path,
+    total_size: u64,
+    pinned_hosts: &[String],
+) -> Result<String, Error> {
+    debug!("Downloading {} in {} segments", url, SEGMENTS);
+    let file = File::create(path)?;
+    file.set_len(total_size)?;
+    let progress = Mutex::new(Transfer::new(ctx, label, total_size));
+
+    let segment_size = total_size.div_ceil(SEGMENTS);
+    let segments = (0..SEGMENTS)
+        .map(|index| {
+            (
+                index * segment_size,
+                ((index + 1) * segment_size).min(total_size),
+            )
+        })
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| download_segment(ctx, url, &file, start, end, pinned_hosts, &progress));
+    let result = futures_util::future::try_join_all(segments).await;
+
+    let progress = progress.into_inner().unwrap();
+    match result {
+        Ok(_) => {
+            file.sync_all()?;
+            progress.finish();
+            // The segments arrive out of order, so hash the assembled file
+            Ok(sha256_file(path)?)
+        }
+        Err(e) => {
+            progress.clear();
+            Err(e)
+        }
+    }
+}
+
+/// Download bytes `start..end` of `url` into the same range of `file`, resuming where
+/// a transient failure broke the transfer off. Failures before the first byte and
+/// in the middle of the body count against the same `ctx.retries`.
+async fn download_segment(
+    ctx: &Context,
+    url: &Url,
+    file: &File,
+    start: u64,
+    end: u64,
+    pinned_hosts: &[String],
+    progress: &Mutex<Transfer>,
+) -> Result<(), Error> {
+    let mut offset = start;
+    let mut retry = 0;
+    while offset < end {
+        let resumed_at = offset;
+        match fetch_range(ctx, url, file, &mut offset, end, pinned_hosts, progress).await {
+            Ok(()) if offset == resumed_at => return Err("range request returned no data".into()),
+            Ok(()) => {}
+            Err(e) if retry < ctx.retries && retry::is_transient(&e) => {
+                retry += 1;
+                retry::pause(ctx, retry, &e).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Request bytes `offset..end` of `url` and write them into `file`, advancing
+/// `offset` past every byte written until the body ends or fails.
+async fn fetch_range(
+    ctx: &Context,
+    url: &Url,
+    file: &File,
+    offset: &mut u64,
+    end: u64,
+    pinned_hosts: &[String],
+    progress: &Mutex<Transfer>,
+) -> Result<(), Error> {
+    let range = format!("bytes={}-{}", offset, end - 1);
+    debug!("GET {} ({})", url, range);
+    let resp = ctx
+        .http
+        .get(url.clone())
+        .header(header::RANGE, range)
+        .send()
+        .await?
+        .error_for_status()?;
+    check_host(resp.url(), pinned_hosts)?;
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return Err("the server ignored the range request".into());
+    }
+
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let chunk = &chunk[..chunk.len().min((end - *offset) as usize)];
+        file.write_all_at(chunk, *offset)?;
+        *offset += chunk.len() as u64;
+        if let Some(throttle) = &ctx.throttle {
+            throttle.consume(chunk.len()).await;
+        }
+        progress.lock().unwrap().inc(chunk.len() as u64);
+    }
+    Ok(())
+}
+
+/// GET `url`, asking for the bytes from `offset` on when it's non-zero, and only if
+/// it changed since the version identified by `unless`, if given.
+async fn request(
+    ctx: &Context,
+    url: &str,
+    offset: u64,
+    unless: Option<&Validators>,
+) -> Result<Response, Error> {
+    let mut request = ctx.http.get(url);
+    if offset > 0 {
+        request = request.header(header::RANGE, format!("bytes={}-", offset));
+    }
+    if let Some(Validators {
+        etag,
+        last_modified,
+    }) = unless
+    {
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    debug!("GET {} from byte {}", url, offset);
+    let response = request.send().await?;
+    debug!("{} {}", response.status(), response.url());
+    Ok(response.error_for_status()?)
+}
+
+/// Enforce --pin-host on the URL the bytes are actually served from after redirects.
+pub(crate) fn check_host(final_url: &Url, pinned_hosts: &[String]) -> Result<Url, Error> {
+    let final_host = final_url.host_str().unwrap_or("").to_ascii_lowercase();
+    if !pinned_hosts.is_empty()
+        && !pinned_hosts
+            .iter()
+            .any(|host| host.eq_ignore_ascii_case(&final_host))
+    {
+        return Err(format!(
+            "Download was served by '{}', which is not an allowed host (--pin-host {})",
+            final_host,
+            pinned_hosts.join(", --pin-host ")
+        )
+        .into());
+    }
+    Ok(final_url.clone())
+}
+
+/// Fail before downloading when the file system `path` is written to has less than
+/// `needed` bytes available, rather than dying mid-write with a truncated file.
+fn check_free_space(path: &Path, needed: u64) -> Result<(), Error> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    // Unknown free space is no reason to refuse the download
+    let Ok(available) = available_space(dir) else {
+        return Ok(());
+    };
+    if needed > available {
+        return Err(format!(
+            "Not enough disk space in {}: the download needs {} but only {} is available",
+            dir.display(),
+            HumanBytes(needed),
+            HumanBytes(available)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Bytes available to unprivileged users on the file system holding `dir`.
+fn available_space(dir: &Path) -> io::Result<u64> {
+    let c_path = CString::new(dir.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` is only read after statvfs succeeds
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    // Both fields are narrower than u64 on some 32-bit targets
+    #[allow(clippy::unnecessary_cast)]
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
@@ -0,0 +1,94 @@
+// Error type shared by the library, with one variant per failure callers may want
+// to tell apart and an exit code for each.
+use std::io;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The release has no AppImage asset fit for this machine and filter
+    #[error("No suitable AppImage in release {tag} of {repo}: {reason}")]
+    NoMatchingAsset {
+        repo: String,
+        tag: String,
+        reason: String,
+    },
+
+    /// The download doesn't match the checksum published with the release
+    #[error(
+        "Checksum mismatch for {asset}: expected {expected} (from {source_name}), got {actual}"
+    )]
+    ChecksumMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+        /// The checksum asset `expected` was read from
+        source_name: String,
+    },
+
+    /// The forge's API refused the request until `reset`
+    #[error("API rate limited by {host} {}. Set GITHUB_TOKEN or pass --token for a higher limit, or use --wait-for-rate-limit", describe_reset(*reset))]
+    RateLimited {
+        host: String,
+        reset: Option<SystemTime>,
+    },
+
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("{0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Anything else, described by its message
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Process exit status for this error, so scripts can react to the kind of failure
+    /// without parsing messages: 2 for a missing asset, 3 for a failed verification,
+    /// 4 when rate limited, 5 for network errors, 1 for everything else.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::NoMatchingAsset { .. } => 2,
+            Error::ChecksumMismatch { .. } => 3,
+            Error::RateLimited { .. } => 4,
+            Error::Network(_) => 5,
+            Error::Io(_) | Error::Json(_) | Error::Other(_) => 1,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_string())
+    }
+}
+
+/// "until 14:05 (in 12 minutes)" in local time, or "for now" when the reset time is
+/// unknown or already past.
+fn describe_reset(reset: Option<SystemTime>) -> String {
+    let Some((reset, wait)) =
+        reset.and_then(|reset| Some((reset, reset.duration_since(SystemTime::now()).ok()?)))
+    else {
+        return "for now".to_string();
+    };
+    let until = chrono::DateTime::<chrono::Local>::from(reset).format("%H:%M");
+    if wait >= Duration::from_secs(60) {
+        format!(
+            "until {} (in {} minutes)",
+            until,
+            wait.as_secs().div_ceil(60)
+        )
+    } else {
+        format!("until {} (in {} seconds)", until, wait.as_secs().max(1))
+    }
+}
@@ -0,0 +1,47 @@
+// `export`: writes the managed app set as a manifest that `import` (or
+// `install --from`) replays on another machine.
+use std::io::Write;
+use tracing::warn;
+
+use crate::cli;
+use crate::config::{AppConfig, Manifest, RecordedVersion};
+use crate::state::State;
+use crate::{tr, Context, Error};
+
+/// Write every installed app to `out` as a manifest: its configured settings (or just
+/// its repository for unconfigured apps), installed version and pin.
+pub fn export(ctx: &Context, out: &mut impl Write) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+    let mut manifest = Manifest::default();
+    for (name, installed) in state.apps {
+        let settings = match ctx.config.apps.get(&name) {
+            Some(settings) => settings.clone(),
+            None if cli::parse_repo(&installed.repo, None).is_ok() => AppConfig {
+                repo: installed.repo,
+                ..AppConfig::default()
+            },
+            // Direct-URL apps installed before settings were recorded
+            None => {
+                warn!("{}", tr!("export-skipping", app = name.as_str()));
+                continue;
+            }
+        };
+        manifest.apps.insert(name.clone(), settings);
+        manifest.installed.insert(
+            name,
+            RecordedVersion {
+                version: installed.tag,
+                pinned: installed.pinned,
+            },
+        );
+    }
+
+    let text =
+        toml::to_string(&manifest).map_err(|e| format!("Cannot write the manifest: {}", e))?;
+    writeln!(
+        out,
+        "# Apps managed by rust-unicorn. Restore them with `rust-unicorn import <file>`.\n"
+    )?;
+    out.write_all(text.as_bytes())?;
+    Ok(())
+}
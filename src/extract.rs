@@ -0,0 +1,144 @@
+// Extracted installs for systems without FUSE (containers, minimal distributions
+// lacking libfuse2), where AppImages can't mount themselves. The AppImage is
+// unpacked next to itself into `<name>.AppDir`, and its `AppRun` is launched instead.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+use crate::install::create_dirs;
+use crate::tr;
+use crate::Error;
+
+/// Libraries the AppImage runtime mounts images with.
+const LIBFUSE: &[&str] = &["libfuse.so.2", "libfuse3.so.3"];
+
+/// Whether AppImages can mount themselves: the FUSE device, `fusermount` and libfuse
+/// are all there.
+pub fn fuse_available() -> bool {
+    let fusermount = [
+        "/usr/bin/fusermount",
+        "/usr/bin/fusermount3",
+        "/bin/fusermount",
+        "/bin/fusermount3",
+    ];
+    Path::new("/dev/fuse").exists()
+        && fusermount.iter().any(|path| Path::new(path).exists())
+        && libfuse_installed()
+}
+
+/// Whether the dynamic linker knows libfuse, going by its cache.
+fn libfuse_installed() -> bool {
+    match fs::read("/etc/ld.so.cache") {
+        Ok(cache) => LIBFUSE.iter().any(|library| {
+            cache
+                .windows(library.len())
+                .any(|name| name == library.as_bytes())
+        }),
+        // Without a cache (musl, say), look where distributions put it
+        Err(_) => [
+            "/usr/lib",
+            "/usr/lib64",
+            "/lib",
+            "/usr/lib/x86_64-linux-gnu",
+            "/usr/lib/aarch64-linux-gnu",
+        ]
+        .iter()
+        .any(|dir| {
+            LIBFUSE
+                .iter()
+                .any(|library| Path::new(dir).join(library).exists())
+        }),
+    }
+}
+
+/// Where the AppImage at `install_path` is extracted: next to it, named after it
+/// (and so after its version), with `.AppDir` in place of `.AppImage`.
+pub fn app_dir(install_path: &Path) -> PathBuf {
+    let name = install_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let stem = name
+        .strip_suffix(".AppImage")
+        .or_else(|| name.strip_suffix(".appimage"))
+        .unwrap_or(&name);
+    install_path.with_file_name(format!("{}.AppDir", stem))
+}
+
+/// The program launching an extracted app.
+pub fn app_run(app_dir: &Path) -> PathBuf {
+    app_dir.join("AppRun")
+}
+
+/// Unpack the (executable) AppImage at `appimage` into `app_dir`, unless an earlier
+/// install already did. The runtime's `--appimage-extract` needs no FUSE; the
+/// scratch directory it unpacks into is created with `dir_mode`.
+pub fn extract(appimage: &Path, app_dir: &Path, dir_mode: Option<u32>) -> Result<(), Error> {
+    if app_run(app_dir).exists() {
+        debug!("{} is already extracted", appimage.display());
+        return Ok(());
+    }
+    let parent = app_dir
+        .parent()
+        .ok_or("Cannot extract to the root directory")?;
+    let work_dir = parent.join(format!(
+        ".{}.extract",
+        app_dir.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    create_dirs(&work_dir, dir_mode)?;
+
+    info!(
+        "{}",
+        tr!(
+            "extracting",
+            appimage = appimage.display().to_string(),
+            path = app_dir.display().to_string()
+        )
+    );
+    let result = unpack(appimage, &work_dir).and_then(|root| {
+        if app_dir.exists() {
+            fs::remove_dir_all(app_dir)?;
+        }
+        fs::rename(root, app_dir)?;
+        Ok(())
+    });
+    fs::remove_dir_all(&work_dir)?;
+    result
+}
+
+/// Run the AppImage's extraction in `work_dir`, returning the directory it made.
+fn unpack(appimage: &Path, work_dir: &Path) -> Result<PathBuf, Error> {
+    let status = Command::new(appimage)
+        .arg("--appimage-extract")
+        .current_dir(work_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Cannot run {} to extract it: {}", appimage.display(), e))?;
+    if !status.success() {
+        return Err(format!(
+            "{} --appimage-extract failed ({})",
+            appimage.display(),
+            status
+        )
+        .into());
+    }
+    let root = work_dir.join("squashfs-root");
+    if !app_run(&root).exists() {
+        return Err(format!("{} has no AppRun", appimage.display()).into());
+    }
+    Ok(root)
+}
+
+/// Delete the extracted app at `app_dir`, if it is there.
+pub fn remove(app_dir: &Path) -> Result<(), Error> {
+    if app_dir.exists() {
+        debug!("Removing {}", app_dir.display());
+        fs::remove_dir_all(app_dir)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// Gitea-compatible forges (Codeberg, Forgejo, self-hosted Gitea). Their release API
+// (https://<host>/api/v1/swagger) returns GitHub-shaped releases, so only the
+// endpoints differ.
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+use crate::cli::RepoSpec;
+use crate::http::HttpFetcher;
+use crate::provider::{fetch_json, ReleaseProvider};
+use crate::Error;
+use crate::Release;
+
+pub struct Gitea<'a> {
+    http: &'a dyn HttpFetcher,
+    repo: &'a RepoSpec,
+    bypass_cache: bool,
+    /// Mode of the cache directories created
+    dir_mode: Option<u32>,
+}
+
+impl<'a> Gitea<'a> {
+    pub fn new(
+        http: &'a dyn HttpFetcher,
+        repo: &'a RepoSpec,
+        bypass_cache: bool,
+        dir_mode: Option<u32>,
+    ) -> Self {
+        Gitea {
+            http,
+            repo,
+            bypass_cache,
+            dir_mode,
+        }
+    }
+
+    /// The API URL of the repository's `segments`, each percent-encoded.
+    fn url(&self, segments: &[&str]) -> Result<Url, Error> {
+        let base = format!("https://{}/api/v1/repos", self.repo.host());
+        let mut url = Url::parse(&base).map_err(|e| format!("Invalid URL '{}': {}", base, e))?;
+        url.path_segments_mut()
+            .map_err(|_| format!("Invalid URL '{}'", base))?
+            .push(&self.repo.owner)
+            .push(&self.repo.name)
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: Url) -> Result<T, Error> {
+        fetch_json(
+            self.http,
+            url.as_str(),
+            None,
+            self.bypass_cache,
+            self.dir_mode,
+        )
+        .await
+    }
+}
+
+impl ReleaseProvider for Gitea<'_> {
+    async fn latest_release(&self) -> Result<Release, Error> {
+        // Skips drafts and prereleases, like GitHub's
+        self.get(self.url(&["releases", "latest"])?).await
+    }
+
+    async fn release_by_tag(&self, tag: &str) -> Result<Release, Error> {
+        self.get(self.url(&["releases", "tags", tag])?).await
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        // Listed newest first; Gitea pages with `limit` rather than `per_page`
+        let mut url = self.url(&["releases"])?;
+        url.set_query(Some("limit=30"));
+        let releases: Vec<Release> = self.get(url).await?;
+        Ok(releases.into_iter().filter(|r| !r.draft).collect())
+    }
+}
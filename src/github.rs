@@ -0,0 +1,133 @@
+// GitHub releases API (https://docs.github.com/en/rest/releases).
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::cli::RepoSpec;
+use crate::http::HttpFetcher;
+use crate::provider::{fetch_json, ReleaseProvider};
+use crate::Error;
+use crate::Release;
+
+pub struct GitHub<'a> {
+    http: &'a dyn HttpFetcher,
+    repo: &'a RepoSpec,
+    /// Raises the anonymous rate limit when given
+    token: Option<&'a str>,
+    bypass_cache: bool,
+    /// Mode of the cache directories created
+    dir_mode: Option<u32>,
+}
+
+impl<'a> GitHub<'a> {
+    pub fn new(
+        http: &'a dyn HttpFetcher,
+        repo: &'a RepoSpec,
+        token: Option<&'a str>,
+        bypass_cache: bool,
+        dir_mode: Option<u32>,
+    ) -> Self {
+        GitHub {
+            http,
+            repo,
+            token,
+            bypass_cache,
+            dir_mode,
+        }
+    }
+
+    /// The API URL of the repository's `segments`, each percent-encoded so tags with
+    /// `/`, `#` or `?` in them stay one segment.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = Url::parse("https://api.github.com/repos").expect("valid API URL");
+        url.path_segments_mut()
+            .expect("API URL has a path")
+            .push(&self.repo.owner)
+            .push(&self.repo.name)
+            .extend(segments);
+        url
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: Url) -> Result<T, Error> {
+        fetch_json(
+            self.http,
+            url.as_str(),
+            self.token,
+            self.bypass_cache,
+            self.dir_mode,
+        )
+        .await
+    }
+}
+
+impl ReleaseProvider for GitHub<'_> {
+    async fn latest_release(&self) -> Result<Release, Error> {
+        self.get(self.url(&["releases", "latest"])).await
+    }
+
+    async fn release_by_tag(&self, tag: &str) -> Result<Release, Error> {
+        self.get(self.url(&["releases", "tags", tag])).await
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        let mut url = self.url(&["releases"]);
+        url.set_query(Some("per_page=30"));
+        let releases: Vec<Release> = self.get(url).await?;
+        // Drafts are only visible to maintainers
+        Ok(releases.into_iter().filter(|r| !r.draft).collect())
+    }
+}
+
+/// A repository found by [`search_repositories`].
+#[derive(Deserialize)]
+pub struct Repository {
+    /// `owner/name`
+    pub full_name: String,
+    pub description: Option<String>,
+    pub stargazers_count: u64,
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    items: Vec<Repository>,
+}
+
+/// Search GitHub repositories for `query`, most starred first
+/// (https://docs.github.com/en/rest/search/search#search-repositories). Cache
+/// directories are created with `dir_mode`.
+pub async fn search_repositories(
+    http: &dyn HttpFetcher,
+    token: Option<&str>,
+    query: &str,
+    per_page: usize,
+    dir_mode: Option<u32>,
+) -> Result<Vec<Repository>, Error> {
+    let url = Url::parse_with_params(
+        "https://api.github.com/search/repositories",
+        &[
+            ("q", query),
+            ("sort", "stars"),
+            ("per_page", &per_page.to_string()),
+        ],
+    )
+    .map_err(|e| format!("Invalid search query: {}", e))?;
+    let results: SearchResults = fetch_json(http, url.as_str(), token, false, dir_mode).await?;
+    Ok(results.items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::parse_repo;
+
+    #[test]
+    fn tags_are_one_path_segment() {
+        let http = reqwest::Client::new();
+        let repo = parse_repo("owner/name", None).unwrap();
+        let github = GitHub::new(&http, &repo, None, false, None);
+        assert_eq!(
+            github.url(&["releases", "tags", "app/v1.0#beta"]).as_str(),
+            "https://api.github.com/repos/owner/name/releases/tags/app%2Fv1.0%23beta"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// GitLab releases API (https://docs.gitlab.com/ee/api/releases/), mapped onto the
+// GitHub-shaped `Release` and `Asset` the install pipeline works with.
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::cli::RepoSpec;
+use crate::http::HttpFetcher;
+use crate::provider::{fetch_json, ReleaseProvider};
+use crate::Error;
+use crate::{Asset, Release};
+
+pub struct GitLab<'a> {
+    http: &'a dyn HttpFetcher,
+    repo: &'a RepoSpec,
+    bypass_cache: bool,
+    /// Mode of the cache directories created
+    dir_mode: Option<u32>,
+}
+
+impl<'a> GitLab<'a> {
+    pub fn new(
+        http: &'a dyn HttpFetcher,
+        repo: &'a RepoSpec,
+        bypass_cache: bool,
+        dir_mode: Option<u32>,
+    ) -> Self {
+        GitLab {
+            http,
+            repo,
+            bypass_cache,
+            dir_mode,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let project = encode(&format!("{}/{}", self.repo.owner, self.repo.name));
+        let api_url = format!(
+            "https://{}/api/v4/projects/{}/{}",
+            self.repo.host(),
+            project,
+            path
+        );
+        fetch_json(self.http, &api_url, None, self.bypass_cache, self.dir_mode).await
+    }
+}
+
+impl ReleaseProvider for GitLab<'_> {
+    async fn latest_release(&self) -> Result<Release, Error> {
+        let release: GitLabRelease = self.get("releases/permalink/latest").await?;
+        Ok(release.into())
+    }
+
+    async fn release_by_tag(&self, tag: &str) -> Result<Release, Error> {
+        let release: GitLabRelease = self.get(&format!("releases/{}", encode(tag))).await?;
+        Ok(release.into())
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        // Sorted by release date, newest first
+        let releases: Vec<GitLabRelease> = self.get("releases?per_page=30").await?;
+        Ok(releases
+            .into_iter()
+            .filter(|r| !r.upcoming_release)
+            .map(Release::from)
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    /// Scheduled releases whose release date is still in the future
+    #[serde(default)]
+    upcoming_release: bool,
+    /// Release notes
+    description: Option<String>,
+    #[serde(default)]
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize, Default)]
+struct GitLabAssets {
+    /// Release links; GitLab has no uploaded assets, AppImages are attached as links
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+    /// Stable `/-/releases/<tag>/downloads/...` URL, when the link has a filepath
+    direct_asset_url: Option<String>,
+}
+
+impl From<GitLabRelease> for Release {
+    fn from(release: GitLabRelease) -> Self {
+        Release {
+            tag_name: release.tag_name,
+            // GitLab doesn't mark prereleases
+            prerelease: false,
+            draft: false,
+            body: release.description,
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| {
+                    let url = link.direct_asset_url.unwrap_or(link.url);
+                    // Link names are free-form ("Linux AppImage"); prefer the file name in the URL
+                    let file_name = url
+                        .rsplit('/')
+                        .next()
+                        .filter(|name| name.ends_with(".AppImage"));
+                    Asset {
+                        name: file_name.map(str::to_string).unwrap_or(link.name),
+                        browser_download_url: url,
+                        size: 0,
+                        state: "uploaded".to_string(),
+                        digest: None,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Percent-encode a path segment; GitLab identifies projects by their URL-encoded
+/// full path (`group%2Fsubgroup%2Fproject`).
+fn encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
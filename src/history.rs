@@ -0,0 +1,91 @@
+// Append-only log of what rust-unicorn changed (~/.local/state/rust-unicorn/history.jsonl):
+// one JSON object per line for every install, update, rollback, removal and failure.
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::install::create_dirs;
+use crate::state::Provenance;
+use crate::tr;
+use crate::Context;
+
+/// One operation, as written to the history file.
+#[derive(Serialize, Default)]
+pub struct Entry<'a> {
+    /// Local time of the operation, RFC 3339
+    pub time: String,
+    /// `installed`, `updated`, `rolled-back`, `removed`, `self-updated`, or `install-failed`/`update-failed`
+    pub action: &'a str,
+    pub app: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<&'a str>,
+    /// Version before the operation, if one was installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<&'a str>,
+    /// Version after the operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_version: Option<&'a str>,
+    /// SHA-256 of the file now installed, lowercase hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<&'a Path>,
+    /// Where the file now installed was downloaded from and how it was verified
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<&'a Provenance>,
+    /// Why the operation failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<'a> Entry<'a> {
+    /// An entry for `action` on `app`, timestamped now.
+    pub fn new(action: &'a str, app: &'a str) -> Entry<'a> {
+        Entry {
+            time: chrono::Local::now()
+                .format("%Y-%m-%dT%H:%M:%S%:z")
+                .to_string(),
+            action,
+            app,
+            ..Entry::default()
+        }
+    }
+}
+
+/// Location of the history file: next to the state file.
+pub fn path(state_path: &Path) -> PathBuf {
+    state_path.with_file_name("history.jsonl")
+}
+
+/// Append `entry` to the history file. The operation it describes already happened,
+/// so failing to log it is only a warning.
+pub fn record(ctx: &Context, entry: &Entry) {
+    let path = path(&ctx.state_path);
+    if let Err(e) = append(&path, entry, ctx.dir_mode) {
+        warn!(
+            "{}",
+            tr!(
+                "history-write-failed",
+                path = path.display().to_string(),
+                error = e.to_string()
+            )
+        );
+    }
+}
+
+fn append(path: &Path, entry: &Entry, dir_mode: Option<u32>) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dirs(dir, dir_mode)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    // A single write to a file opened for appending keeps lines from concurrent
+    // updates whole
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
@@ -0,0 +1,61 @@
+// Per-app `post_install` commands, run through the shell after an app is installed
+// or updated, e.g. to re-register a protocol handler.
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+use crate::cli::OutputFormat;
+use crate::config::App;
+use crate::{progress, tr, Context};
+
+/// Run `app`'s `post_install` command, if it has one, now that `version` is installed
+/// at `path` (replacing `old_version`, if any). The command sees them as `$UNICORN_APP`,
+/// `$UNICORN_VERSION`, `$UNICORN_PATH` and `$UNICORN_OLD_VERSION`. The install has
+/// already succeeded, so a failing command is only reported.
+pub fn post_install(
+    ctx: &Context,
+    app: &App,
+    version: &str,
+    old_version: Option<&str>,
+    path: &Path,
+) {
+    let Some(hook) = &app.settings.post_install else {
+        return;
+    };
+    debug!("Running post_install command of {}: {}", app.name, hook);
+
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(hook)
+        .env("UNICORN_APP", &app.name)
+        .env("UNICORN_VERSION", version)
+        .env("UNICORN_PATH", path)
+        .env("UNICORN_OLD_VERSION", old_version.unwrap_or_default())
+        .stdin(Stdio::null());
+    // Keep stdout for the JSON report
+    if ctx.output == OutputFormat::Json {
+        command.stdout(io::stderr());
+    }
+
+    match progress::bars().suspend(|| command.status()) {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!(
+            "{}",
+            tr!(
+                "hook-failed",
+                app = app.name.as_str(),
+                status = status.to_string()
+            )
+        ),
+        Err(e) => warn!(
+            "{}",
+            tr!(
+                "hook-not-run",
+                app = app.name.as_str(),
+                error = e.to_string()
+            )
+        ),
+    }
+}
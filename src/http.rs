@@ -0,0 +1,332 @@
+// HTTP: the `HttpFetcher` every request goes through, and construction of the default
+// one, a client with the user agent, timeouts, proxy, extra root certificates and TLS
+// implementation taken from the config (which the command line overrides).
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Method, NoProxy, Proxy, Request, Response, Url};
+use serde::Deserialize;
+use std::fs;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::cli;
+use crate::config::Config;
+use crate::Error;
+
+/// Longest wait for a connection to a server, unless `connect_timeout` says otherwise.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest wait for the next bytes of a response, unless `read_timeout` says otherwise.
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest time one download may take in all, unless `download_timeout` says otherwise.
+/// Generous, so large AppImages still arrive over slow or rate-limited links.
+pub const DEFAULT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(2 * 3600);
+
+/// TLS implementation used for HTTPS.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsBackend {
+    /// The system's TLS library (OpenSSL on most distributions)
+    Native,
+    /// rustls, trusting the system's root certificates
+    Rustls,
+}
+
+/// Sends the requests of installs, updates and checks: API calls, downloads, checksums
+/// and signatures. The `reqwest::Client` from `client` is the usual one; programs
+/// embedding rust-unicorn can supply their own, such as a client with middleware or
+/// tracing, or one pointed at a mock server in tests. It should send a User-Agent
+/// header, which GitHub's API requires.
+pub trait HttpFetcher: Send + Sync {
+    /// Send `request` and return the response, whatever its status.
+    fn execute(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send + '_>>;
+}
+
+impl HttpFetcher for Client {
+    fn execute(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send + '_>> {
+        Box::pin(async move { Ok(Client::execute(self, request).await?) })
+    }
+}
+
+impl dyn HttpFetcher + '_ {
+    /// Start a GET request for `url`.
+    pub fn get(&self, url: impl AsRef<str>) -> Fetch<'_> {
+        self.request(Method::GET, url.as_ref())
+    }
+
+    /// Start a HEAD request for `url`.
+    pub fn head(&self, url: impl AsRef<str>) -> Fetch<'_> {
+        self.request(Method::HEAD, url.as_ref())
+    }
+
+    fn request(&self, method: Method, url: &str) -> Fetch<'_> {
+        let request = Url::parse(url)
+            .map(|parsed| Request::new(method, parsed))
+            .map_err(|e| format!("Invalid URL '{}': {}", url, e).into());
+        Fetch {
+            fetcher: self,
+            request,
+        }
+    }
+}
+
+/// A request being put together for an `HttpFetcher`; an invalid URL or header
+/// surfaces as the error of `send`.
+pub struct Fetch<'a> {
+    fetcher: &'a dyn HttpFetcher,
+    request: Result<Request, Error>,
+}
+
+impl Fetch<'_> {
+    /// Set the header `name` to `value`.
+    pub fn header(self, name: HeaderName, value: impl AsRef<str>) -> Self {
+        let value = HeaderValue::from_str(value.as_ref())
+            .map_err(|e| format!("Invalid {} header: {}", name, e));
+        self.with_header(name, value)
+    }
+
+    /// Authenticate with the bearer `token`, which is kept out of debug output.
+    pub fn bearer_auth(self, token: &str) -> Self {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map(|mut value| {
+                value.set_sensitive(true);
+                value
+            })
+            .map_err(|_| "Invalid characters in the API token".to_string());
+        self.with_header(header::AUTHORIZATION, value)
+    }
+
+    fn with_header(mut self, name: HeaderName, value: Result<HeaderValue, String>) -> Self {
+        self.request = match (self.request, value) {
+            (Ok(mut request), Ok(value)) => {
+                request.headers_mut().insert(name, value);
+                Ok(request)
+            }
+            (Ok(_), Err(e)) => Err(e.into()),
+            (Err(e), _) => Err(e),
+        };
+        self
+    }
+
+    /// Send the request.
+    pub async fn send(self) -> Result<Response, Error> {
+        self.fetcher.execute(self.request?).await
+    }
+}
+
+/// Build the client for every request the installer makes.
+pub fn client(config: &Config) -> Result<Client, Error> {
+    // Stalled connections fail (and get retried) instead of hanging forever
+    let mut client = Client::builder().user_agent("rust-unicorn");
+    if let Some(timeout) = timeout(
+        &config.connect_timeout,
+        "connect_timeout",
+        DEFAULT_CONNECT_TIMEOUT,
+    )? {
+        client = client.connect_timeout(timeout);
+    }
+    if let Some(timeout) = timeout(&config.read_timeout, "read_timeout", DEFAULT_READ_TIMEOUT)? {
+        client = client.read_timeout(timeout);
+    }
+
+    // Without an explicit proxy, reqwest follows HTTPS_PROXY, HTTP_PROXY and NO_PROXY
+    if let Some(proxy) = &config.proxy {
+        let proxy = Proxy::all(proxy.as_str())
+            .map_err(|e| format!("Invalid proxy '{}': {}", proxy, e))?
+            .no_proxy(NoProxy::from_env());
+        client = client.proxy(proxy);
+    }
+
+    match config.tls {
+        Some(TlsBackend::Native) => client = client.use_native_tls(),
+        Some(TlsBackend::Rustls) => client = client.use_rustls_tls(),
+        None => {}
+    }
+    // Trusted in addition to the system's roots, e.g. for a TLS-intercepting proxy
+    if let Some(path) = &config.cacert {
+        let pem = fs::read(path)
+            .map_err(|e| format!("Cannot read CA certificates {}: {}", path.display(), e))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificates in {}: {}", path.display(), e))?;
+        if certificates.is_empty() {
+            return Err(format!("No PEM certificates found in {}", path.display()).into());
+        }
+        for certificate in certificates {
+            client = client.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(client.build()?)
+}
+
+/// The deadline for a whole download set by `config`; `None` when there is none.
+pub fn download_timeout(config: &Config) -> Result<Option<Duration>, Error> {
+    timeout(
+        &config.download_timeout,
+        "download_timeout",
+        DEFAULT_DOWNLOAD_TIMEOUT,
+    )
+}
+
+/// The timeout in `value`, the config key `key`, or `default` if it isn't set; 0
+/// turns the timeout off.
+fn timeout(
+    value: &Option<String>,
+    key: &str,
+    default: Duration,
+) -> Result<Option<Duration>, Error> {
+    let timeout = match value {
+        Some(value) => {
+            cli::parse_duration(value).map_err(|e| format!("{} ({} in the config file)", e, key))?
+        }
+        None => default,
+    };
+    Ok(Some(timeout).filter(|timeout| !timeout.is_zero()))
+}
+
+/// The file name a response's Content-Disposition header suggests, as sent: the RFC
+/// 5987 `filename*=` form (UTF-8 or ISO-8859-1, percent-encoded) when present, else
+/// `filename=`, quoted or not. It still needs sanitizing before use.
+pub fn disposition_file_name(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_DISPOSITION)?;
+    // Servers send UTF-8 in plain `filename=` too, though the header is meant to be ASCII
+    let value = String::from_utf8_lossy(value.as_bytes());
+    let mut plain = None;
+    for (name, value) in disposition_params(&value) {
+        match name.to_ascii_lowercase().as_str() {
+            "filename*" => {
+                if let Some(name) = decode_ext_value(&value) {
+                    return Some(name);
+                }
+            }
+            "filename" => plain = plain.or(Some(value)),
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// The `name=value` parameters after the disposition type in `header`, with quoted
+/// values unquoted.
+fn disposition_params(header: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = header.split_once(';').map_or("", |(_, params)| params);
+    loop {
+        rest = rest.trim_start_matches([';', ' ', '\t']);
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        let (name, after) = (name.trim(), after.trim_start());
+        let value = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                rest = "";
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            rest = &quoted[index + 1..];
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                value
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                rest = &after[end..];
+                after[..end].trim_end().to_string()
+            }
+        };
+        params.push((name.to_string(), value));
+    }
+    params
+}
+
+/// Decode an RFC 5987 `charset'language'percent-encoded` value.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_name(value: &str) -> Option<String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_bytes(value.as_bytes()).unwrap(),
+        );
+        disposition_file_name(&headers)
+    }
+
+    #[test]
+    fn reads_the_file_name_from_content_disposition() {
+        let expected = Some("Tool-1.0.AppImage".to_string());
+        assert_eq!(
+            file_name("attachment; filename=Tool-1.0.AppImage"),
+            expected
+        );
+        assert_eq!(
+            file_name("attachment;filename=Tool-1.0.AppImage;size=3"),
+            expected
+        );
+        assert_eq!(
+            file_name("attachment; filename=\"Tool-1.0.AppImage\""),
+            expected
+        );
+        assert_eq!(
+            file_name(r#"attachment; filename="Tool \"1.0\"; x.AppImage"; size=3"#).as_deref(),
+            Some(r#"Tool "1.0"; x.AppImage"#)
+        );
+        assert_eq!(
+            file_name("attachment; filename*=UTF-8''Na%C3%AFve%20Tool.AppImage").as_deref(),
+            Some("Naïve Tool.AppImage")
+        );
+        assert_eq!(
+            file_name("attachment; filename*=iso-8859-1'de'Gr%FC%DFe.AppImage").as_deref(),
+            Some("Grüße.AppImage")
+        );
+        // The extended form wins, wherever it is; a broken one gives way to the plain one
+        assert_eq!(
+            file_name(
+                "attachment; filename=\"fallback.AppImage\"; filename*=UTF-8''Tool-1.0.AppImage"
+            ),
+            expected
+        );
+        assert_eq!(
+            file_name("attachment; filename*=UTF-8''Tool%2.AppImage; filename=Tool-1.0.AppImage"),
+            expected
+        );
+        assert_eq!(file_name("inline"), None);
+        assert_eq!(file_name("attachment; size=3"), None);
+    }
+}
@@ -0,0 +1,121 @@
+// Translations of user-facing messages. The messages live in Fluent files under
+// `locales/`, compiled in; the language comes from LC_ALL, LC_MESSAGES or LANG.
+// English is used for other languages and for messages a translation lacks.
+//
+// Messages are looked up with `tr!`:
+//
+//     info!("{}", tr!("install-success", app = name, version = tag));
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+use std::env;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+
+type Bundle = FluentBundle<FluentResource>;
+
+/// Translations, by language; English comes first as the fallback.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.ftl")),
+    ("de", include_str!("locales/de.ftl")),
+];
+
+/// The message `id` with `args`, in the user's language.
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $crate::i18n::FluentValue::from($value));)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+
+/// Format the message `id`, falling back to English and then to the id itself.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let (bundle, fallback) = bundles();
+    for bundle in [bundle, fallback].into_iter().flatten() {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = Vec::new();
+            return bundle
+                .format_pattern(pattern, args, &mut errors)
+                .into_owned();
+        }
+    }
+    id.to_string()
+}
+
+/// The user's language, as its two-letter code, from the first of LC_ALL,
+/// LC_MESSAGES and LANG that is set (`de_DE.UTF-8` gives `de`).
+pub fn language() -> String {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(locale) if locale != "C" && locale != "POSIX" => locale
+            .split(['_', '.', '@', '-'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase(),
+        _ => "en".to_string(),
+    }
+}
+
+/// The bundle of the user's language, if it is translated, and the English one.
+fn bundles() -> (Option<&'static Bundle>, Option<&'static Bundle>) {
+    static BUNDLES: OnceLock<(Option<Bundle>, Option<Bundle>)> = OnceLock::new();
+    let (bundle, fallback) = BUNDLES.get_or_init(|| {
+        let language = language();
+        let translated = LOCALES.iter().skip(1).find(|(code, _)| *code == language);
+        let (code, source) = LOCALES[0];
+        (
+            translated.and_then(|(code, source)| bundle(code, source)),
+            bundle(code, source),
+        )
+    });
+    (bundle.as_ref(), fallback.as_ref())
+}
+
+/// A bundle of the messages in `source`, written in the language `code`.
+fn bundle(code: &str, source: &str) -> Option<Bundle> {
+    let language: LanguageIdentifier = code.parse().ok()?;
+    let resource = FluentResource::try_new(source.to_string()).ok()?;
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Unicode isolation marks around arguments only show up as noise in terminals
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations_parse_and_have_the_english_messages() {
+        // Messages start their line with their id
+        let ids = |source: &'static str| -> Vec<&'static str> {
+            source
+                .lines()
+                .filter_map(|line| line.split_once(" ="))
+                .map(|(id, _)| id)
+                .filter(|id| id.starts_with(|c: char| c.is_ascii_lowercase()))
+                .collect()
+        };
+        let english = ids(LOCALES[0].1);
+        for (code, source) in LOCALES {
+            let bundle = bundle(code, source).unwrap_or_else(|| panic!("{} doesn't parse", code));
+            for id in ids(source) {
+                assert!(bundle.has_message(id), "{} can't load {}", code, id);
+                assert!(english.contains(&id), "{} has {}, which en lacks", code, id);
+            }
+            for id in &english {
+                assert!(bundle.has_message(id), "{} lacks {}", code, id);
+            }
+        }
+    }
+}
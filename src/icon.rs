@@ -0,0 +1,114 @@
+// Extracting the application icon embedded in an AppImage.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::install::create_dirs;
+use crate::paths;
+use crate::Error;
+
+/// How many symlink hops to follow from `.DirIcon` to the actual image.
+const MAX_ICON_LINKS: usize = 4;
+
+/// Directory for installed icons: `$XDG_DATA_HOME/icons`.
+pub fn icons_dir() -> Option<PathBuf> {
+    Some(paths::data_home()?.join("icons"))
+}
+
+/// Extract the `.DirIcon` of `appimage` and install it as `<icons_dir>/<app_name>.<ext>`.
+///
+/// Only the icon is pulled out, using the AppImage runtime's own
+/// `--appimage-extract <pattern>`, so the AppImage must already be executable.
+/// Missing directories are created with `dir_mode`.
+pub fn install_icon(
+    appimage: &Path,
+    icons_dir: &Path,
+    app_name: &str,
+    dir_mode: Option<u32>,
+) -> Result<PathBuf, Error> {
+    create_dirs(icons_dir, dir_mode)?;
+    let work_dir = icons_dir.join(format!(".{}-extract", app_name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    create_dirs(&work_dir, dir_mode)?;
+
+    let result = extract_dir_icon(appimage, &work_dir).and_then(|bytes| {
+        let extension = image_extension(&bytes).ok_or("AppImage icon is neither PNG nor SVG")?;
+        remove_icon(icons_dir, app_name)?;
+        let path = icons_dir.join(format!("{}.{}", app_name, extension));
+        fs::write(&path, bytes)?;
+        Ok(path)
+    });
+    fs::remove_dir_all(&work_dir)?;
+
+    result
+}
+
+/// Delete any icon previously installed for `app_name`.
+pub fn remove_icon(icons_dir: &Path, app_name: &str) -> std::io::Result<()> {
+    for extension in ["png", "svg"] {
+        let path = icons_dir.join(format!("{}.{}", app_name, extension));
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extract `.DirIcon`, following it when it is a symlink to another file in the image.
+fn extract_dir_icon(appimage: &Path, work_dir: &Path) -> Result<Vec<u8>, Error> {
+    let root = work_dir.join("squashfs-root");
+    let mut member = PathBuf::from(".DirIcon");
+
+    for _ in 0..MAX_ICON_LINKS {
+        let status = Command::new(appimage)
+            .arg("--appimage-extract")
+            .arg(&member)
+            .current_dir(work_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| {
+                format!(
+                    "Cannot run {} to extract its icon: {}",
+                    appimage.display(),
+                    e
+                )
+            })?;
+        if !status.success() {
+            return Err(format!(
+                "{} --appimage-extract failed ({})",
+                appimage.display(),
+                status
+            )
+            .into());
+        }
+
+        let path = root.join(&member);
+        let metadata = fs::symlink_metadata(&path)
+            .map_err(|_| format!("AppImage has no {}", member.display()))?;
+        if !metadata.file_type().is_symlink() {
+            return Ok(fs::read(&path)?);
+        }
+
+        // Link targets are relative to the link's directory inside the image
+        let target = fs::read_link(&path)?;
+        let base = member.parent().map(Path::to_path_buf).unwrap_or_default();
+        member = base.join(target.strip_prefix("/").unwrap_or(&target));
+    }
+
+    Err("Too many symlinks while resolving .DirIcon".into())
+}
+
+/// File extension for PNG or SVG image bytes.
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("png");
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+    if head.contains("<svg") {
+        return Some("svg");
+    }
+    None
+}
@@ -0,0 +1,2452 @@
+// Install steps: the install/update/remove/list/check commands and the filesystem
+// work around them (directories, symlinks, desktop integration, state records).
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt}; // For setting Unix permission bits
+use std::path::{Path, PathBuf};
+use std::sync::Arc; // For sharing state between update tasks
+
+use crate::cache::{CachedDownload, UrlEntry};
+use crate::cli::{self, InstallArgs, OutputFormat, Provider, UpdateArgs};
+use crate::config::{validate_app_name, App, AppConfig, Manifest};
+use crate::download::Validators;
+use crate::history::{self, Entry};
+use crate::hook;
+use crate::method::{self, Artifact, Finalize};
+use crate::notes;
+use crate::notify::{self, UpdateSummary};
+use crate::pattern::AssetPattern;
+use crate::progress::{self, Phase};
+use crate::release::{
+    configured_pattern, configured_preferences, fetch_release_appimage, Asset, AssetFilter,
+    Release, ReleaseSelector,
+};
+use crate::sandbox::{self, Sandbox};
+use crate::stage::Stage;
+use crate::state::{self, InstalledApp, PreviousVersion, Provenance, State};
+use crate::verify::{sha256_file, verification_method, verify_checksum, verify_signature};
+use crate::Error;
+use crate::{
+    arch, cache, checksum, desktop, download, extract, icon, last_known, metainfo, running,
+    shell_path, style, tr, update_info, versions, zsync, Context,
+};
+use tracing::{debug, error, info, warn};
+
+/// Apps refreshed at once by `update --all` unless configured otherwise.
+pub const DEFAULT_PARALLEL_UPDATES: u16 = 4;
+
+/// An installed app with a newer release, as found by `check`.
+#[derive(Clone, Serialize)]
+pub struct PendingUpdate {
+    pub app: String,
+    pub installed: String,
+    pub latest: String,
+}
+
+/// How far `update_all` has got, for callers showing progress their own way.
+pub enum UpdateProgress<'a> {
+    /// `total` apps are about to be updated
+    Started { total: usize },
+    /// `app`, the `done`-th of `total`, is finished: updated (or already up to date)
+    /// with its report, or failed with the error
+    Finished {
+        app: &'a str,
+        done: usize,
+        total: usize,
+        result: Result<&'a InstallReport, &'a str>,
+    },
+}
+
+/// What `install` did (or, with `--dry-run`, would do), as reported by `--output json`.
+#[derive(Serialize)]
+pub struct InstallReport {
+    pub app: String,
+    pub repo: String,
+    /// `installed`, `updated` or `up-to-date`; `would-install` or `would-update` for a dry
+    /// run, and `downloaded` with `--download-only`
+    pub action: &'static str,
+    /// Version installed before, if any
+    pub old_version: Option<String>,
+    pub new_version: String,
+    pub asset: String,
+    pub path: PathBuf,
+    pub symlink: Option<PathBuf>,
+    pub desktop_entry: Option<PathBuf>,
+    /// Sandboxed launch wrapper the symlink and desktop entry start, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrapper: Option<PathBuf>,
+    /// Directory the AppImage was extracted to, to run without FUSE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_dir: Option<PathBuf>,
+    /// Size of the download; 0 when nothing was downloaded
+    pub bytes_downloaded: u64,
+    /// Notes of the new release, when asked for with --show-notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    /// Where the file was downloaded from and how it was verified
+    #[serde(flatten)]
+    pub provenance: Provenance,
+}
+
+/// Everything `install` decides before touching the disk: the release and asset to
+/// install and the files that will be written.
+pub struct InstallPlan {
+    pub app: App,
+    pub release: Release,
+    pub asset: Asset,
+    /// The app's current state entry, if it is installed
+    pub installed: Option<InstalledApp>,
+    pub install_path: PathBuf,
+    /// The installed file is already this release, so nothing needs downloading
+    pub up_to_date: bool,
+    /// Symlink to create for the app, if any
+    pub symlink: Option<PathBuf>,
+    /// Desktop entry to write for the app, if any
+    pub desktop_entry: Option<PathBuf>,
+    /// Sandbox to launch the app in, through a wrapper
+    pub sandbox: Option<Sandbox>,
+    /// Extract the AppImage and launch its `AppRun`, for systems without FUSE
+    pub extract: bool,
+    /// AppImage on disk to install instead of downloading `asset`
+    pub local_file: Option<PathBuf>,
+    /// The app's `prefer` pattern that chose `asset`, if any
+    pub preferred: Option<String>,
+}
+
+/// Work out what installing (or, with `update`, refreshing) an app would do. Only
+/// reads: the state file and the provider's release information.
+pub async fn plan_install(
+    ctx: &Context,
+    args: &InstallArgs,
+    update: bool,
+) -> Result<InstallPlan, Error> {
+    let install_dir = &ctx.install_dir;
+    let spec = match &args.app {
+        Some(spec) => spec.as_str(),
+        None if update => return Err("Give the app to update, or use --all".into()),
+        None => cli::DEFAULT_REPO,
+    };
+    let mut state = State::load(&ctx.state_path)?;
+    let mut app = match ctx.config.resolve(spec, args.provider) {
+        Ok(app) => app,
+        // Installed apps that aren't configured update from their recorded repository
+        Err(_) if update && state.apps.contains_key(spec) => App {
+            name: spec.to_string(),
+            ..ctx.config.resolve(&state.apps[spec].repo, None)?
+        },
+        Err(e) => return Err(e.into()),
+    };
+    app.settings.asset_type = method::select(args.method, &app);
+    let app_name = &app.name;
+    let channel = args.channel.unwrap_or(app.settings.channel);
+
+    let installed = state.apps.remove(app_name);
+    // --method asks for one kind of asset, leaving nothing to prefer between
+    let preferences = match args.method {
+        Some(_) => Vec::new(),
+        None => configured_preferences(&app)?,
+    };
+    let asset_pattern = match &args.asset_filter {
+        Some(pattern) => Some(pattern.clone()),
+        None => match configured_pattern(&app)? {
+            Some(pattern) => Some(pattern),
+            // The AppImage's own update information would rule out other kinds of asset
+            None if !preferences.is_empty() => None,
+            None => installed
+                .as_ref()
+                .and_then(|installed| embedded_pattern(&app, installed)),
+        },
+    };
+    if update && installed.is_none() && !install_dir.join(app_name).is_symlink() {
+        return Err(format!("{} is not installed; use `install` first", app_name).into());
+    }
+    if let Some(pinned) = installed
+        .as_ref()
+        .filter(|installed| update && installed.pinned)
+    {
+        return Err(format!(
+            "{} is pinned at {}; run `unpin {}` to update it",
+            app_name, pinned.tag, app_name
+        )
+        .into());
+    }
+
+    let filter = AssetFilter {
+        asset_type: app.settings.asset_type,
+        arch: args.arch.as_deref().unwrap_or(arch::host()),
+        pattern: asset_pattern.as_ref(),
+        prefer: &preferences,
+        interactive: !args.non_interactive,
+    };
+    let (release, asset) = match fetch_release_appimage(
+        ctx,
+        &app,
+        ReleaseSelector::new(args.tag.as_deref(), channel),
+        &filter,
+        args.wait_for_assets,
+    )
+    .await
+    {
+        Ok((release, asset)) => {
+            if let Some(installed) = installed
+                .as_ref()
+                .filter(|installed| args.tag.is_none() && installed.tag != release.tag_name)
+            {
+                last_known::record(ctx, app_name, installed, &release, &asset);
+            }
+            (release, asset)
+        }
+        // Only failures to reach the API; a missing release or asset is an answer
+        Err(e @ (Error::Network(_) | Error::RateLimited { .. }))
+            if update && args.use_last_known =>
+        {
+            last_known::fallback(
+                ctx,
+                app_name,
+                installed.as_ref(),
+                args.tag.as_deref(),
+                &filter,
+                e,
+            )?
+        }
+        Err(e) => return Err(e),
+    };
+    let preferred = match preferences.iter().position(|p| p.matches(&asset.name)) {
+        Some(index) => {
+            let pattern = &app.settings.prefer[index];
+            debug!(
+                "{} matches prefer pattern {} of {} ({})",
+                asset.name,
+                index + 1,
+                preferences.len(),
+                pattern
+            );
+            if let Some(asset_type) = method::detect(&asset.name) {
+                app.settings.asset_type = asset_type;
+            }
+            Some(pattern.clone())
+        }
+        None => None,
+    };
+    if let Some((installed, old, new)) = installed.as_ref().and_then(|installed| {
+        let old = installed.preferred.as_deref()?;
+        let new = preferred.as_deref().filter(|&new| new != old)?;
+        Some((installed, old, new))
+    }) {
+        let change = style::change(old, new);
+        let (old_method, new_method) = (installed.method(), app.settings.asset_type);
+        if old_method == new_method {
+            warn!(
+                "{}",
+                tr!(
+                    "preference-changed",
+                    app = app_name.as_str(),
+                    change = change
+                )
+            );
+        } else {
+            warn!(
+                "{}",
+                tr!(
+                    "preference-changed-method",
+                    app = app_name.as_str(),
+                    change = change,
+                    method = style::change(old_method.name(), new_method.name())
+                )
+            );
+        }
+    }
+
+    // Guard against releases whose tag was bumped but whose asset is a stale build.
+    if let (Some(tag_version), Some(asset_version)) =
+        (version_in(&release.tag_name), version_in(&asset.name))
+    {
+        if !same_version(tag_version, asset_version) {
+            let (asset, version, tag) = (
+                asset.name.as_str(),
+                asset_version,
+                release.tag_name.as_str(),
+            );
+            if args.strict_version_match {
+                return Err(tr!(
+                    "version-mismatch-error",
+                    asset = asset,
+                    version = version,
+                    tag = tag
+                )
+                .into());
+            }
+            warn!(
+                "{}",
+                tr!(
+                    "version-mismatch",
+                    asset = asset,
+                    version = version,
+                    tag = tag
+                )
+            );
+        }
+    }
+
+    let install_path = install_path_for(ctx, &app, &asset.name);
+
+    // Check if we already have the latest version. Files installed before the state
+    // file existed are recognised by name and recorded now.
+    let same_release = match &installed {
+        Some(installed) => {
+            installed.tag == release.tag_name && installed.install_path == install_path
+        }
+        None => true,
+    };
+    let up_to_date = same_release && install_path.exists() && !args.force && !args.download_only;
+
+    let (symlink, desktop_entry) = integration_paths(ctx, args, &app);
+    let sandbox = sandbox_for(args, &app, installed.as_ref())?;
+    let extract = extract_for(args, &app, sandbox)?;
+
+    Ok(InstallPlan {
+        app,
+        release,
+        asset,
+        installed,
+        install_path,
+        up_to_date,
+        symlink,
+        desktop_entry,
+        sandbox,
+        extract,
+        local_file: None,
+        preferred,
+    })
+}
+
+/// Work out what installing the AppImage (or archive) `file` from disk would do, without any
+/// network access. `args.app`, or else `name`, says which app it is, so later updates
+/// know where to look; `name` also renames the app. The version is `version`, or else
+/// read from the file name.
+pub fn plan_local_install(
+    ctx: &Context,
+    args: &InstallArgs,
+    file: &Path,
+    name: Option<&str>,
+    version: Option<&str>,
+) -> Result<InstallPlan, Error> {
+    if let Some(name) = name {
+        validate_app_name(name)?;
+    }
+    let spec =
+        args.app.as_deref().or(name).ok_or(
+            "Say which app the file is: give its repository or configured name, or --name",
+        )?;
+    let mut app = ctx
+        .config
+        .resolve(spec, args.provider)
+        .map_err(|e| format!("{}. The app must be known so it can be updated later", e))?;
+    if let Some(name) = name {
+        app.name = name.to_string();
+    }
+    app.settings.asset_type = method::select(args.method, &app);
+
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", file.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let size = fs::metadata(file)
+        .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?
+        .len();
+    method::for_type(app.settings.asset_type).check_artifact(
+        file,
+        &file_name,
+        args.arch.as_deref().unwrap_or(arch::host()),
+    )?;
+    let version = version.or_else(|| version_in(&file_name)).ok_or_else(|| {
+        format!(
+            "Cannot tell the version of {} from its name; give it with --version",
+            file_name
+        )
+    })?;
+
+    let installed = State::load(&ctx.state_path)?.apps.remove(&app.name);
+    let install_path = install_path_for(ctx, &app, &file_name);
+    let up_to_date = installed.as_ref().is_some_and(|installed| {
+        installed.tag == version && installed.install_path == install_path
+    }) && install_path.exists()
+        && !args.force;
+    let (symlink, desktop_entry) = integration_paths(ctx, args, &app);
+    let sandbox = sandbox_for(args, &app, installed.as_ref())?;
+    let extract = extract_for(args, &app, sandbox)?;
+
+    Ok(InstallPlan {
+        release: Release {
+            tag_name: version.to_string(),
+            prerelease: false,
+            draft: false,
+            body: None,
+            assets: Vec::new(),
+        },
+        asset: Asset {
+            name: file_name,
+            browser_download_url: file.display().to_string(),
+            size,
+            state: "uploaded".to_string(),
+            digest: None,
+        },
+        app,
+        installed,
+        install_path,
+        up_to_date,
+        symlink,
+        desktop_entry,
+        sandbox,
+        extract,
+        local_file: Some(std::path::absolute(file)?),
+        preferred: None,
+    })
+}
+
+/// Where `app`'s asset `asset_name` is installed: under the asset's name (for a
+/// program unpacked from an archive, without the archive's extension), or with
+/// `stable_filename` under a name that stays the same across updates, like
+/// `<app>.AppImage`.
+fn install_path_for(ctx: &Context, app: &App, asset_name: &str) -> PathBuf {
+    let stable = app
+        .settings
+        .stable_filename
+        .or(ctx.config.stable_filename)
+        .unwrap_or(false);
+    let method = method::for_type(app.settings.asset_type);
+    if stable {
+        ctx.install_dir.join(method.stable_file_name(&app.name))
+    } else {
+        ctx.install_dir.join(method.file_name(asset_name))
+    }
+}
+
+/// The versioned file name `app`'s asset `asset_name` is installed under.
+fn file_name_for<'a>(app: &App, asset_name: &'a str) -> &'a str {
+    method::for_type(app.settings.asset_type).file_name(asset_name)
+}
+
+/// Symlink and desktop entry to create for `app`, as the flags and config ask.
+/// Programs from archives are command-line tools, so they get no desktop entry.
+pub fn integration_paths(
+    ctx: &Context,
+    args: &InstallArgs,
+    app: &App,
+) -> (Option<PathBuf>, Option<PathBuf>) {
+    let create_symlink = args.symlink(app.settings.symlink.or(ctx.config.symlink));
+    let symlink = create_symlink.then(|| ctx.bin_dir.join(&app.name));
+    let desktop_app = method::for_type(app.settings.asset_type).desktop_app();
+    let desktop_entry = if desktop_app && args.desktop_entry(ctx.config.desktop_entry) {
+        desktop::applications_dir().map(|dir| dir.join(format!("{}.desktop", app.name)))
+    } else {
+        None
+    };
+    (symlink, desktop_entry)
+}
+
+/// Sandbox to launch `app` in, as the flags and config ask; an app installed
+/// sandboxed stays so unless told otherwise. Its tool must be installed.
+pub fn sandbox_for(
+    args: &InstallArgs,
+    app: &App,
+    installed: Option<&InstalledApp>,
+) -> Result<Option<Sandbox>, Error> {
+    let sandbox = args.sandbox(
+        app.settings
+            .sandbox
+            .or(installed.and_then(|installed| installed.sandbox)),
+    );
+    // Found out before anything is downloaded or replaced
+    if let Some(sandbox) = sandbox {
+        if !method::for_type(app.settings.asset_type).desktop_app() {
+            return Err(format!(
+                "{} is installed from an archive; only AppImages can be sandboxed",
+                app.name
+            )
+            .into());
+        }
+        sandbox.check(&app.name)?;
+    }
+    Ok(sandbox)
+}
+
+/// Whether to extract the AppImage: when asked to, or when it couldn't mount itself.
+/// Sandboxes don't need FUSE: firejail mounts AppImages itself and the bubblewrap
+/// wrapper has them extract themselves on every start. Programs from archives never
+/// need it.
+pub fn extract_for(args: &InstallArgs, app: &App, sandbox: Option<Sandbox>) -> Result<bool, Error> {
+    if !method::for_type(app.settings.asset_type).desktop_app() {
+        if args.extract {
+            return Err(format!(
+                "{} is installed from an archive; only AppImages can be extracted",
+                app.name
+            )
+            .into());
+        }
+        return Ok(false);
+    }
+    if args.extract && sandbox == Some(Sandbox::Firejail) {
+        return Err("firejail can only run AppImages, not extracted apps; use --sandbox bwrap to combine it with --extract".into());
+    }
+    Ok(args.extract || (sandbox.is_none() && !extract::fuse_available()))
+}
+
+/// Install (or, with `update`, refresh an already installed) app from its latest
+/// release, or from the release given with `--tag`. With `--dry-run`, only describe
+/// what would be done.
+pub async fn install(
+    ctx: &Context,
+    args: &InstallArgs,
+    update: bool,
+) -> Result<InstallReport, Error> {
+    let app = args.app.as_deref().unwrap_or(cli::DEFAULT_REPO);
+    progress::phase(Phase::Resolving);
+    carry_out(
+        ctx,
+        args,
+        app,
+        plan_install(ctx, args, update).await,
+        update,
+    )
+    .await
+}
+
+/// Install the AppImage `file` from disk as `plan_local_install` describes, with the
+/// same integration and records as a downloaded one.
+pub async fn install_file(
+    ctx: &Context,
+    args: &InstallArgs,
+    file: &Path,
+    name: Option<&str>,
+    version: Option<&str>,
+) -> Result<InstallReport, Error> {
+    let file_name = file.display().to_string();
+    let app = name.or(args.app.as_deref()).unwrap_or(&file_name);
+    carry_out(
+        ctx,
+        args,
+        app,
+        plan_local_install(ctx, args, file, name, version),
+        false,
+    )
+    .await
+}
+
+/// Preview or execute `planned`, recording a failure to install `app` in the history.
+async fn carry_out(
+    ctx: &Context,
+    args: &InstallArgs,
+    app: &str,
+    planned: Result<InstallPlan, Error>,
+    update: bool,
+) -> Result<InstallReport, Error> {
+    let result = match planned {
+        Ok(plan) => {
+            let release_notes = if args.show_notes && !plan.up_to_date {
+                notes::show(ctx, &plan.app.name, &plan.release, false)
+            } else {
+                None
+            };
+            if args.dry_run {
+                return Ok(InstallReport {
+                    release_notes,
+                    ..preview(plan, update)
+                });
+            }
+            let reminder = (
+                plan.app.settings.notes(),
+                plan.app.settings.remind_after_update,
+            );
+            let report = execute(ctx, args, plan, update)
+                .await
+                .map(|report| InstallReport {
+                    release_notes,
+                    ..report
+                });
+            if let ((Some(notes), remind), Ok(report)) = (reminder, &report) {
+                if report.action == "updated" {
+                    remind_of_notes(report, &notes, remind).await;
+                }
+            }
+            if let (true, Ok(report)) = (args.run, &report) {
+                // The app is installed either way; only say why it didn't start
+                let launcher = match (&report.wrapper, &report.app_dir) {
+                    (Some(wrapper), _) => wrapper.clone(),
+                    (None, Some(app_dir)) => extract::app_run(app_dir),
+                    (None, None) => report.path.clone(),
+                };
+                match running::launch(&launcher) {
+                    Ok(pid) => info!(
+                        "{}",
+                        tr!("app-started", app = report.app.as_str(), pid = pid)
+                    ),
+                    Err(e) => warn!(
+                        "{}",
+                        tr!(
+                            "app-start-failed",
+                            app = report.app.as_str(),
+                            error = e.to_string()
+                        )
+                    ),
+                }
+            }
+            report
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        let action = if update {
+            "update-failed"
+        } else {
+            "install-failed"
+        };
+        history::record(
+            ctx,
+            &Entry {
+                error: Some(e.to_string()),
+                ..Entry::new(action, app)
+            },
+        );
+    }
+    result
+}
+
+/// Print the notes of the app `report` updated, highlighted, and with `notify` also
+/// show them as a desktop notification, which `--quiet` doesn't suppress.
+async fn remind_of_notes(report: &InstallReport, notes: &str, notify: bool) {
+    info!("{}", tr!("app-notes", app = report.app.as_str()));
+    for line in notes.lines() {
+        info!("  {}", style::reminder(line));
+    }
+    if notify {
+        notify::send_reminder(&report.app, &report.new_version, notes).await;
+    }
+}
+
+/// Install each app of `manifest` in turn, carrying on past failures. Its apps must
+/// already be in the config. Apps the manifest records as pinned are installed at
+/// the recorded version and pinned again; with `exact`, every app with a recorded
+/// version is installed at it instead of the latest release.
+pub async fn install_all(
+    ctx: &Context,
+    args: &InstallArgs,
+    manifest: &Manifest,
+    exact: bool,
+) -> Result<(), Error> {
+    let mut reports = Vec::new();
+    let mut failures = 0;
+    for name in manifest.apps.keys() {
+        let recorded = manifest.installed.get(name);
+        let mut app_args = args.clone();
+        app_args.app = Some(name.clone());
+        if let Some(recorded) = recorded.filter(|recorded| exact || recorded.pinned) {
+            app_args.tag = Some(recorded.version.clone());
+        }
+        let result = install(ctx, &app_args, false).await;
+        if let (Ok(_), Some(recorded)) = (
+            &result,
+            recorded.filter(|recorded| recorded.pinned && !args.dry_run),
+        ) {
+            ctx.update_state(|state| {
+                if let Some(installed) = state.apps.get_mut(name) {
+                    installed.pinned = true;
+                }
+            })?;
+            info!(
+                "{}",
+                tr!(
+                    "pinned",
+                    app = name.as_str(),
+                    version = recorded.version.as_str()
+                )
+            );
+        }
+        match result {
+            Ok(report) => reports.push(serde_json::to_value(report)?),
+            Err(e) => {
+                failures += 1;
+                error!(
+                    "{}",
+                    tr!("install-failed", app = name.as_str(), error = e.to_string())
+                );
+                reports.push(
+                    serde_json::json!({ "app": name, "action": "failed", "error": e.to_string() }),
+                );
+            }
+        }
+    }
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&reports)?;
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} installs failed", failures, manifest.apps.len()).into());
+    }
+    Ok(())
+}
+
+/// Carry out `plan`: download, verify and integrate the new version.
+async fn execute(
+    ctx: &Context,
+    args: &InstallArgs,
+    plan: InstallPlan,
+    update: bool,
+) -> Result<InstallReport, Error> {
+    let InstallPlan {
+        app,
+        release,
+        asset,
+        installed,
+        install_path,
+        up_to_date,
+        symlink,
+        desktop_entry,
+        sandbox,
+        extract,
+        local_file,
+        preferred,
+    } = plan;
+    let repo = &app.repo;
+    let app_name = &app.name;
+    let method = method::for_type(app.settings.asset_type);
+    let finalize = Finalize {
+        symlink: symlink.is_some(),
+        desktop_entry: desktop_entry.is_some(),
+        sandbox,
+        extract,
+    };
+
+    if args.download_only && !cache::caching_downloads(ctx) {
+        return Err(
+            "--download-only fills the download cache, which cache_downloads = false turns off"
+                .into(),
+        );
+    }
+
+    // Create installation directory if it doesn't exist
+    create_dirs(&ctx.install_dir, ctx.dir_mode)?;
+
+    if up_to_date {
+        info!(
+            "{}",
+            tr!(
+                "already-installed",
+                app = repo.name.as_str(),
+                version = release.tag_name.as_str(),
+                path = install_path.display().to_string()
+            )
+        );
+        info!("{}", tr!("use-force"));
+
+        // Make sure it's executable anyway
+        let mut perms = fs::metadata(&install_path)?.permissions();
+        perms.set_mode(perms.mode() | 0o755);
+        fs::set_permissions(&install_path, perms)?;
+
+        // Set up symlink and desktop entry if needed
+        progress::phase(Phase::Integrating);
+        let integration =
+            method.finalize(ctx, &app, &install_path, &release.tag_name, &finalize)?;
+        remove_stale_app_dir(installed.as_ref(), &integration)?;
+
+        let (sha256, provenance) = match &installed {
+            Some(installed) => (installed.sha256.clone(), installed.provenance.clone()),
+            None => (sha256_file(&install_path)?, Provenance::default()),
+        };
+        let kept = installed
+            .as_ref()
+            .map(InstalledApp::kept_versions)
+            .unwrap_or_default();
+        let report = InstallReport {
+            app: app_name.clone(),
+            repo: repo.to_string(),
+            action: "up-to-date",
+            old_version: installed.map(|installed| installed.tag),
+            new_version: release.tag_name.clone(),
+            asset: asset.name.clone(),
+            path: install_path.clone(),
+            symlink: integration.symlink.clone(),
+            desktop_entry: integration.desktop_entry.clone(),
+            wrapper: integration.wrapper.clone(),
+            app_dir: integration.app_dir.clone(),
+            bytes_downloaded: 0,
+            release_notes: None,
+            provenance: provenance.clone(),
+        };
+        ctx.update_state(|state| {
+            record_install(
+                state,
+                &app,
+                &release,
+                &asset,
+                sha256,
+                install_path,
+                integration,
+                provenance,
+            );
+            set_kept_versions(state, app_name, kept);
+            set_preferred(state, app_name, preferred);
+        })?;
+
+        return Ok(report);
+    }
+
+    match &local_file {
+        Some(file) => info!(
+            "{}",
+            tr!(
+                "installing-file",
+                file = file.display().to_string(),
+                app = app_name.as_str(),
+                version = release.tag_name.as_str()
+            )
+        ),
+        None => {
+            let (app, version, asset) = (
+                repo.name.as_str(),
+                release.tag_name.as_str(),
+                asset.name.as_str(),
+            );
+            if release.prerelease {
+                info!(
+                    "{}",
+                    tr!(
+                        "found-prerelease",
+                        app = app,
+                        version = version,
+                        asset = asset
+                    )
+                );
+            } else {
+                info!(
+                    "{}",
+                    tr!("found-release", app = app, version = version, asset = asset)
+                );
+            }
+            if !args.download_only {
+                info!(
+                    "{}",
+                    tr!("downloading-to", path = install_path.display().to_string())
+                );
+            }
+        }
+    }
+
+    // 7.-8. Download and verify the new version into the app's staging area in the
+    //       install directory, so a failed download never replaces a working
+    //       AppImage, and make the program from it there. A local file was checked
+    //       when planning and is copied the same way. Whatever is left in the stage
+    //       is removed when it is dropped.
+    let mut stage = Stage::new(
+        install_path.parent().unwrap_or(&ctx.install_dir),
+        app_name,
+        ctx.dir_mode,
+    )?;
+    let partial_path = stage.path(
+        &install_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+    );
+    let artifact_path = method.artifact_path(&partial_path, &asset.name);
+    let seed = installed
+        .as_ref()
+        .map(|installed| installed.install_path.as_path())
+        .filter(|path| path.exists() && method.delta_updates());
+    let fetched = match &local_file {
+        Some(file) => copy_local(file, &artifact_path),
+        None => {
+            fetch_verified(
+                ctx,
+                args,
+                &app,
+                &release,
+                &asset,
+                seed,
+                &mut stage,
+                &artifact_path,
+            )
+            .await
+        }
+    };
+    // The verified download is in the cache now; its copy in the stage goes with it
+    if args.download_only {
+        let fetched = fetched?;
+        info!(
+            "{}",
+            tr!(
+                "downloaded-only",
+                app = repo.name.as_str(),
+                version = release.tag_name.as_str()
+            )
+        );
+        return Ok(InstallReport {
+            app: app_name.clone(),
+            repo: repo.to_string(),
+            action: "downloaded",
+            old_version: installed.map(|installed| installed.tag),
+            new_version: release.tag_name.clone(),
+            asset: asset.name.clone(),
+            path: install_path,
+            symlink: None,
+            desktop_entry: None,
+            wrapper: None,
+            app_dir: None,
+            bytes_downloaded: fetched.bytes_downloaded,
+            release_notes: None,
+            provenance: fetched.provenance,
+        });
+    }
+    let staged = fetched.and_then(|fetched| {
+        let artifact = Artifact {
+            path: &artifact_path,
+            name: &asset.name,
+            sha256: &fetched.sha256,
+        };
+        let arch = args.arch.as_deref().unwrap_or(arch::host());
+        let staged = method.stage(&artifact, app_name, &partial_path, arch, ctx.dir_mode)?;
+        Ok((staged, fetched))
+    });
+    let (staged, fetched) = staged?;
+    let (sha256, bytes_downloaded, provenance) =
+        (staged.sha256, fetched.bytes_downloaded, fetched.provenance);
+    let partial_path = staged.path;
+
+    // 9. Update file permissions to add the executable bit (chmod +x).
+    let mut perms = fs::metadata(&partial_path)?.permissions();
+    perms.set_mode(perms.mode() | 0o755);
+    fs::set_permissions(&partial_path, perms)?;
+    debug!("Made {} executable", partial_path.display());
+
+    // Running instances of the outgoing version would lose their image under them
+    if let Some(installed) = installed
+        .as_ref()
+        .filter(|installed| installed.install_path.exists())
+    {
+        let instances = running::find(&installed.install_path);
+        running::handle(
+            ctx,
+            app_name,
+            &instances,
+            args.stop_running,
+            !args.non_interactive,
+        )
+        .await?;
+    }
+
+    // Archive the outgoing version instead of overwriting it, so `rollback` can
+    // restore it; it is put back if the new version can't be moved into place.
+    let backup = match &installed {
+        Some(installed) if installed.tag != release.tag_name && installed.install_path.exists() => {
+            Some(versions::archive(app_name, installed, ctx.dir_mode)?)
+        }
+        _ => None,
+    };
+    if let Err(e) = stage.publish(&partial_path, &install_path) {
+        if let (Some(installed), Some(backup)) = (&installed, &backup) {
+            debug!(
+                "Restoring {} from {}",
+                installed.install_path.display(),
+                backup.backup_path.display()
+            );
+            fs::rename(&backup.backup_path, &installed.install_path)?;
+            versions::remove_empty_dirs(&backup.backup_path);
+        }
+        return Err(e.into());
+    }
+    drop(stage);
+    // A reinstall under a new name (after `stable_filename` changed, say) leaves
+    // nothing to keep at the old one
+    if let Some(installed) = installed
+        .as_ref()
+        .filter(|installed| installed.install_path != install_path)
+    {
+        if installed.install_path.exists() {
+            debug!("Removing {}", installed.install_path.display());
+            fs::remove_file(&installed.install_path)?;
+        }
+    }
+
+    // Only the newest `keep_versions` replaced versions are kept
+    let mut replaced: Vec<PreviousVersion> = backup.into_iter().collect();
+    replaced.extend(installed.iter().flat_map(InstalledApp::kept_versions));
+    let (kept, dropped) =
+        versions::retain(replaced, versions::keep_versions(ctx), &release.tag_name);
+    for version in &dropped {
+        debug!(
+            "Removing {} {}, beyond the versions kept",
+            app_name, version.tag
+        );
+        versions::remove_file(&version.backup_path)?;
+    }
+
+    let path = install_path.display().to_string();
+    info!(
+        "{}",
+        if local_file.is_some() || bytes_downloaded == 0 {
+            tr!("copied", path = path)
+        } else {
+            tr!("downloaded", path = path)
+        }
+    );
+
+    // 10. Create a symlink and a desktop entry for easier access
+    progress::phase(Phase::Integrating);
+    if extract && !args.extract {
+        info!(
+            "{}",
+            tr!("extracting-without-fuse", app = app_name.as_str())
+        );
+    }
+    remove_reused_app_dir(installed.as_ref(), &install_path)?;
+    let integration = method.finalize(ctx, &app, &install_path, &release.tag_name, &finalize)?;
+    remove_stale_app_dir(installed.as_ref(), &integration)?;
+
+    // 11. Record the install in the state file.
+    let action = if update { "updated" } else { "installed" };
+    let report = InstallReport {
+        app: app_name.clone(),
+        repo: repo.to_string(),
+        action,
+        old_version: installed.map(|installed| installed.tag),
+        new_version: release.tag_name.clone(),
+        asset: asset.name.clone(),
+        path: install_path.clone(),
+        symlink: integration.symlink.clone(),
+        desktop_entry: integration.desktop_entry.clone(),
+        wrapper: integration.wrapper.clone(),
+        app_dir: integration.app_dir.clone(),
+        bytes_downloaded,
+        release_notes: None,
+        provenance: provenance.clone(),
+    };
+    let repo_spec = repo.to_string();
+    history::record(
+        ctx,
+        &Entry {
+            repo: Some(&repo_spec),
+            old_version: report.old_version.as_deref(),
+            new_version: Some(&release.tag_name),
+            sha256: Some(&sha256),
+            path: Some(&install_path),
+            provenance: Some(&provenance),
+            ..Entry::new(action, app_name)
+        },
+    );
+    ctx.update_state(|state| {
+        record_install(
+            state,
+            &app,
+            &release,
+            &asset,
+            sha256,
+            install_path,
+            integration,
+            provenance,
+        );
+        set_kept_versions(state, app_name, kept);
+        set_preferred(state, app_name, preferred);
+    })?;
+
+    hook::post_install(
+        ctx,
+        &app,
+        &release.tag_name,
+        report.old_version.as_deref(),
+        &report.path,
+    );
+
+    info!(
+        "{}",
+        style::success(tr!(
+            "install-success",
+            app = repo.name.as_str(),
+            version = release.tag_name.as_str(),
+            action = action
+        ))
+    );
+    if finalize.symlink {
+        shell_path::check(&ctx.bin_dir, args.fix_path, ctx.dir_mode);
+        if shell_path::on_path(&ctx.bin_dir) {
+            info!("{}", tr!("run-by-name", app = app_name.as_str()));
+        }
+    }
+
+    Ok(report)
+}
+
+/// A new version downloaded (or copied) and verified, ready to be moved into place.
+struct Fetched {
+    sha256: String,
+    /// Size of the download; 0 when nothing was downloaded
+    bytes_downloaded: u64,
+    provenance: Provenance,
+}
+
+/// Copy the local AppImage (or archive) `file` to `path`. Nothing is downloaded, and
+/// nothing recorded about where the file came from.
+fn copy_local(file: &Path, path: &Path) -> Result<Fetched, Error> {
+    debug!("Copying {} to {}", file.display(), path.display());
+    fs::copy(file, path).map_err(|e| format!("Cannot copy {}: {}", file.display(), e))?;
+    Ok(Fetched {
+        sha256: sha256_file(path)?,
+        bytes_downloaded: 0,
+        provenance: Provenance::default(),
+    })
+}
+
+/// Download `asset` to `path` in `stage` and verify it against the release's checksum
+/// and, when the app has signing keys, its signature. With the installed version as
+/// `seed`, only the changed blocks are downloaded when the release publishes a
+/// `.zsync` file.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_verified(
+    ctx: &Context,
+    args: &InstallArgs,
+    app: &App,
+    release: &Release,
+    asset: &Asset,
+    seed: Option<&Path>,
+    stage: &mut Stage,
+    path: &Path,
+) -> Result<Fetched, Error> {
+    let published = asset.digest.as_deref().and_then(checksum::parse_digest);
+    stage.describe(
+        &asset.browser_download_url,
+        Some(asset.size),
+        published.as_ref().map(|checksum| checksum.digest.as_str()),
+    )?;
+    // 7. Download the binary asset with progress bar, checking where it is served from.
+    progress::phase(Phase::Downloading);
+    let zsync_name = format!("{}.zsync", asset.name);
+    let zsync_asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == zsync_name)
+        .filter(|_| !args.no_delta);
+    let delta = match (seed, zsync_asset) {
+        (Some(seed), Some(zsync_asset)) => {
+            match zsync::download_delta(ctx, asset, zsync_asset, seed, path, &args.pinned_hosts)
+                .await
+            {
+                Ok(downloaded) => Some(downloaded),
+                Err(e) => {
+                    warn!("{}", tr!("delta-failed", error = e.to_string()));
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let cached = match &delta {
+        None if !args.no_cache => {
+            let revalidate = args.revalidate || args.download_only;
+            cache::find_download(ctx, asset, path, revalidate)
+        }
+        _ => None,
+    };
+    // Only what the server sent or confirmed goes into the cache's entry for the URL
+    let (sha256, bytes_downloaded, served_by, validators) = match (delta, cached) {
+        (Some((sha256, bytes_downloaded)), _) => (sha256, bytes_downloaded, None, None),
+        (None, Some(CachedDownload::Restored(sha256))) => (sha256, 0, None, None),
+        (None, Some(CachedDownload::Revalidate(entry))) => {
+            download_asset(ctx, args, asset, path, Some(entry)).await?
+        }
+        (None, None) => download_asset(ctx, args, asset, path, None).await?,
+    };
+    stage.downloaded(&sha256, validators.as_ref())?;
+
+    // 8. Make sure it is an AppImage (or archive) for the right architecture, then
+    //    verify it against the release's published checksum, if any.
+    progress::phase(Phase::Verifying);
+    method::for_type(app.settings.asset_type).check_artifact(
+        path,
+        &asset.name,
+        args.arch.as_deref().unwrap_or(arch::host()),
+    )?;
+    let matched = verify_checksum(
+        ctx,
+        release,
+        asset,
+        path,
+        &sha256,
+        app.settings.checksum_asset.as_deref(),
+    )
+    .await?;
+
+    // Verify the detached signature when the app has signing keys configured.
+    let signed = !app.settings.signing_keys.is_empty();
+    if signed {
+        verify_signature(ctx, release, asset, path, &app.settings.signing_keys).await?;
+    }
+
+    // A failed copy only costs a download next time
+    let url = &asset.browser_download_url;
+    let stored = match args.no_cache {
+        true => Ok(()),
+        false => cache::store_download(ctx, url, &sha256, path, validators.as_ref()),
+    };
+    if let Err(e) = stored {
+        debug!("Cannot cache {}: {}", asset.name, e);
+    }
+    Ok(Fetched {
+        sha256,
+        bytes_downloaded,
+        provenance: Provenance {
+            source_url: Some(asset.browser_download_url.clone()),
+            served_by,
+            verification: Some(verification_method(&matched, signed)),
+        },
+    })
+}
+
+/// Download `asset` to `path` or, given the cache's `entry` for its URL, only when the
+/// server says it changed, using the cached copy otherwise. Returns its SHA-256, the
+/// bytes transferred, the host they came from and what identifies the file to the
+/// server.
+async fn download_asset(
+    ctx: &Context,
+    args: &InstallArgs,
+    asset: &Asset,
+    path: &Path,
+    entry: Option<UrlEntry>,
+) -> Result<(String, u64, Option<String>, Option<Validators>), Error> {
+    let url = &asset.browser_download_url;
+    let downloaded = match entry {
+        Some(entry) => {
+            let validators = &entry.validators;
+            match download::download_if_changed(ctx, url, path, &args.pinned_hosts, validators)
+                .await?
+            {
+                Some(downloaded) => downloaded,
+                // A 304 only vouches for the cached copy while it is intact
+                None => match cache::restore_download(&entry.sha256, path) {
+                    Some(sha256) => {
+                        info!(
+                            "{}",
+                            tr!("served-from-cache-revalidated", asset = asset.name.as_str())
+                        );
+                        return Ok((sha256, 0, None, Some(entry.validators)));
+                    }
+                    None => download::download(ctx, url, path, &args.pinned_hosts).await?,
+                },
+            }
+        }
+        None => download::download(ctx, url, path, &args.pinned_hosts).await?,
+    };
+    let served_by = downloaded.final_url.host_str().map(str::to_string);
+    Ok((
+        downloaded.sha256,
+        fs::metadata(path)?.len(),
+        served_by,
+        Some(downloaded.validators),
+    ))
+}
+
+/// The asset pattern from the update information embedded in the installed AppImage,
+/// when it names the app's repository.
+fn embedded_pattern(app: &App, installed: &InstalledApp) -> Option<AssetPattern> {
+    if !app.settings.asset_type.is_appimage() {
+        return None;
+    }
+    let info = update_info::read(&installed.install_path).ok().flatten()?;
+    let repo = info.repo()?;
+    if app.repo.provider != Provider::GitHub
+        || !repo.eq_ignore_ascii_case(&format!("{}/{}", app.repo.owner, app.repo.name))
+    {
+        return None;
+    }
+    info.asset_pattern()
+}
+
+/// Record which of an app's `prefer` patterns chose its installed asset.
+fn set_preferred(state: &mut State, app_name: &str, preferred: Option<String>) {
+    if let Some(entry) = state.apps.get_mut(app_name) {
+        entry.preferred = preferred;
+    }
+}
+
+/// Set the replaced versions kept for an app's state entry, newest first.
+fn set_kept_versions(state: &mut State, app_name: &str, kept: Vec<PreviousVersion>) {
+    if let Some(entry) = state.apps.get_mut(app_name) {
+        entry.set_kept_versions(kept);
+    }
+}
+
+/// Swap an app back to the version its last update replaced. The current version
+/// becomes the kept one, so rolling back again returns to it.
+pub fn rollback(ctx: &Context, app: &str) -> Result<(), Error> {
+    let app_name = match ctx.config.resolve(app, None) {
+        Ok(app) => app.name,
+        Err(_) => app.to_lowercase(),
+    };
+    let installed = State::load(&ctx.state_path)?
+        .apps
+        .remove(&app_name)
+        .ok_or_else(|| format!("{} is not installed", app_name))?;
+    let previous = installed.previous.clone().ok_or_else(|| {
+        format!(
+            "No previous version of {} is kept; rollback is possible after an update",
+            app_name
+        )
+    })?;
+    if !previous.backup_path.exists() {
+        return Err(format!(
+            "The backup of {} {} is missing: {}",
+            app_name,
+            previous.tag,
+            previous.backup_path.display()
+        )
+        .into());
+    }
+
+    // Configured apps are resolved by name so their per-app settings apply
+    let spec = if ctx.config.apps.contains_key(&app_name) {
+        &app_name
+    } else {
+        &installed.repo
+    };
+    let app = ctx.config.resolve(spec, None)?;
+
+    let current = if installed.install_path.exists() {
+        Some(versions::archive(&app_name, &installed, ctx.dir_mode)?)
+    } else {
+        None
+    };
+    // Versioned files get their own name back; a stable filename stays as it is
+    let restored_path = if installed.install_path.file_name()
+        == Some(file_name_for(&app, &installed.asset_name).as_ref())
+    {
+        installed
+            .install_path
+            .with_file_name(file_name_for(&app, &previous.asset_name))
+    } else {
+        installed.install_path.clone()
+    };
+    debug!(
+        "Restoring {} to {}",
+        previous.backup_path.display(),
+        restored_path.display()
+    );
+    fs::rename(&previous.backup_path, &restored_path)?;
+    versions::remove_empty_dirs(&previous.backup_path);
+
+    remove_reused_app_dir(Some(&installed), &restored_path)?;
+    let integration = method::for_type(installed.method()).finalize(
+        ctx,
+        &app,
+        &restored_path,
+        &previous.tag,
+        &Finalize {
+            symlink: installed.symlink.is_some(),
+            desktop_entry: installed.desktop_entry.is_some(),
+            sandbox: installed.sandbox,
+            extract: installed.app_dir.is_some(),
+        },
+    )?;
+    remove_stale_app_dir(Some(&installed), &integration)?;
+    let report = InstallReport {
+        app: app_name.clone(),
+        repo: installed.repo.clone(),
+        action: "rolled-back",
+        old_version: Some(installed.tag.clone()),
+        new_version: previous.tag.clone(),
+        asset: previous.asset_name.clone(),
+        path: restored_path.clone(),
+        symlink: integration.symlink.clone(),
+        desktop_entry: integration.desktop_entry.clone(),
+        wrapper: integration.wrapper.clone(),
+        app_dir: integration.app_dir.clone(),
+        bytes_downloaded: 0,
+        release_notes: None,
+        provenance: Provenance::default(),
+    };
+    history::record(
+        ctx,
+        &Entry {
+            repo: Some(&installed.repo),
+            old_version: Some(&installed.tag),
+            new_version: Some(&previous.tag),
+            sha256: Some(&previous.sha256),
+            path: Some(&restored_path),
+            ..Entry::new("rolled-back", &app_name)
+        },
+    );
+    ctx.update_state(|state| {
+        state.apps.insert(
+            app_name.clone(),
+            InstalledApp {
+                repo: installed.repo.clone(),
+                tag: previous.tag,
+                asset_version: version_in(&previous.asset_name).map(str::to_string),
+                asset_name: previous.asset_name,
+                sha256: previous.sha256,
+                install_path: restored_path,
+                symlink: integration.symlink,
+                desktop_entry: integration.desktop_entry,
+                icon: integration.icon,
+                metainfo: integration.metainfo,
+                sandbox: integration.sandbox,
+                app_dir: integration.app_dir,
+                installed_at: state::now(),
+                previous: None,
+                archive: Vec::new(),
+                pinned: installed.pinned,
+                settings: installed.settings.clone(),
+                // Not recorded for kept versions
+                provenance: Provenance::default(),
+                method: Some(installed.method()),
+                preferred: None,
+            },
+        );
+        set_kept_versions(
+            state,
+            &app_name,
+            current
+                .into_iter()
+                .chain(installed.archive.clone())
+                .collect(),
+        );
+    })?;
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&report)?;
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "rolled-back",
+                app = app_name.as_str(),
+                change = style::change(
+                    report.old_version.as_deref().unwrap_or(""),
+                    &report.new_version
+                ),
+                path = report.path.display().to_string()
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Describe what executing `plan` would do, without doing any of it.
+fn preview(plan: InstallPlan, update: bool) -> InstallReport {
+    let action = match (plan.up_to_date, update) {
+        (true, _) => "up-to-date",
+        (false, true) => "would-update",
+        (false, false) => "would-install",
+    };
+
+    let release = &plan.release;
+    if plan.up_to_date {
+        info!(
+            "{}",
+            tr!(
+                "already-installed-dry-run",
+                app = plan.app.name.as_str(),
+                version = release.tag_name.as_str(),
+                path = plan.install_path.display().to_string()
+            )
+        );
+    } else {
+        let version = match &plan.installed {
+            Some(installed) => style::change(&installed.tag, &release.tag_name),
+            None => release.tag_name.clone(),
+        };
+        let (app, version) = (plan.app.name.as_str(), version.as_str());
+        info!(
+            "{}",
+            match (update, release.prerelease) {
+                (true, false) => tr!("would-update", app = app, version = version),
+                (true, true) => tr!("would-update-prerelease", app = app, version = version),
+                (false, false) => tr!("would-install", app = app, version = version),
+                (false, true) => tr!("would-install-prerelease", app = app, version = version),
+            }
+        );
+        info!(
+            "{}",
+            tr!(
+                "preview-asset",
+                asset = plan.asset.name.as_str(),
+                size = HumanBytes(plan.asset.size).to_string()
+            )
+        );
+        info!(
+            "{}",
+            tr!(
+                "preview-source",
+                url = plan.asset.browser_download_url.as_str()
+            )
+        );
+    }
+    info!(
+        "{}",
+        tr!(
+            "preview-appimage",
+            path = plan.install_path.display().to_string()
+        )
+    );
+    let app_dir = plan.extract.then(|| extract::app_dir(&plan.install_path));
+    if let Some(app_dir) = &app_dir {
+        info!(
+            "{}",
+            tr!("preview-extracted", path = app_dir.display().to_string())
+        );
+    }
+    let wrapper = plan
+        .sandbox
+        .and_then(|_| sandbox::wrapper_path(&plan.app.name).ok());
+    if let (Some(sandbox), Some(wrapper)) = (plan.sandbox, &wrapper) {
+        info!(
+            "{}",
+            tr!(
+                "preview-sandbox",
+                tool = sandbox.program(),
+                wrapper = wrapper.display().to_string()
+            )
+        );
+    }
+    let launcher = match (&wrapper, &app_dir) {
+        (Some(wrapper), _) => wrapper.clone(),
+        (None, Some(app_dir)) => extract::app_run(app_dir),
+        (None, None) => plan.install_path.clone(),
+    };
+    if let Some(symlink) = &plan.symlink {
+        info!(
+            "{}",
+            tr!(
+                "preview-symlink",
+                symlink = symlink.display().to_string(),
+                target = launcher.display().to_string()
+            )
+        );
+    }
+    if let Some(desktop_entry) = &plan.desktop_entry {
+        info!(
+            "{}",
+            tr!(
+                "preview-desktop-entry",
+                path = desktop_entry.display().to_string()
+            )
+        );
+    }
+
+    // What would be verified is only known once the file is there
+    let source_url = match plan.local_file {
+        Some(_) => None,
+        None => Some(plan.asset.browser_download_url.clone()),
+    };
+    InstallReport {
+        app: plan.app.name,
+        repo: plan.app.repo.to_string(),
+        action,
+        old_version: plan.installed.map(|installed| installed.tag),
+        new_version: plan.release.tag_name,
+        asset: plan.asset.name,
+        path: plan.install_path,
+        symlink: plan.symlink,
+        desktop_entry: plan.desktop_entry,
+        wrapper,
+        app_dir,
+        bytes_downloaded: 0,
+        release_notes: None,
+        provenance: Provenance {
+            source_url,
+            ..Provenance::default()
+        },
+    }
+}
+
+/// Update one app, notifying the desktop of the outcome when wanted.
+pub async fn update(ctx: &Context, args: &UpdateArgs) -> Result<(), Error> {
+    let install_args = InstallArgs {
+        use_last_known: args.use_last_known,
+        ..args.install.clone()
+    };
+    let result = install(ctx, &install_args, true).await;
+    if notify::wanted(args.notify, ctx.config.notify) && !args.install.dry_run {
+        let mut summary = UpdateSummary::default();
+        match &result {
+            Ok(report) => summary.add(report),
+            Err(e) => summary.add_failure(
+                args.install.app.as_deref().unwrap_or_default(),
+                &e.to_string(),
+            ),
+        }
+        notify::send(summary).await;
+    }
+
+    let report = result?;
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&report)?;
+    }
+    Ok(())
+}
+
+/// Update every installed app, running up to the configured number of updates at once.
+pub async fn update_all(ctx: &Arc<Context>, args: &UpdateArgs) -> Result<(), Error> {
+    update_all_observed(ctx, args, &|_| {}).await
+}
+
+/// `update_all`, telling `observe` about its progress.
+pub async fn update_all_observed(
+    ctx: &Arc<Context>,
+    args: &UpdateArgs,
+    observe: &(dyn Fn(UpdateProgress) + Sync),
+) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+    let jobs = args
+        .jobs
+        .or(ctx.config.parallel_updates)
+        .unwrap_or(DEFAULT_PARALLEL_UPDATES)
+        .max(1);
+    let permits = Arc::new(tokio::sync::Semaphore::new(jobs as usize));
+
+    let overall = progress::summary(
+        ctx,
+        state
+            .apps
+            .values()
+            .filter(|installed| !installed.pinned)
+            .count(),
+    );
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut reports = Vec::new();
+    for (name, installed) in &state.apps {
+        if installed.pinned {
+            info!(
+                "{}",
+                tr!(
+                    "skipping-pinned",
+                    app = name.as_str(),
+                    version = installed.tag.as_str()
+                )
+            );
+            reports.push(serde_json::json!({ "app": name, "action": "pinned", "old_version": installed.tag }));
+            continue;
+        }
+        // Configured apps are addressed by name so their per-app settings apply
+        let spec = if ctx.config.apps.contains_key(name) {
+            name.clone()
+        } else {
+            installed.repo.clone()
+        };
+        let mut app_args = args.install.clone();
+        app_args.app = Some(spec);
+        // Concurrent prompts would interleave, so never ask during a batch update
+        app_args.non_interactive = true;
+        app_args.use_last_known = args.use_last_known;
+
+        let ctx = Arc::clone(ctx);
+        let permits = Arc::clone(&permits);
+        let name = name.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result = install(&ctx, &app_args, true)
+                .await
+                .map_err(|e| e.to_string());
+            (name, result)
+        });
+    }
+
+    let total = tasks.len();
+    observe(UpdateProgress::Started { total });
+    let mut done = 0;
+    let mut failures = 0;
+    let mut summary = UpdateSummary::default();
+    while let Some(joined) = tasks.join_next().await {
+        let (name, result) = joined.map_err(|e| format!("Update task failed: {}", e))?;
+        overall.inc(1);
+        done += 1;
+        observe(UpdateProgress::Finished {
+            app: &name,
+            done,
+            total,
+            result: result.as_ref().map_err(String::as_str),
+        });
+        match result {
+            Ok(report) => {
+                summary.add(&report);
+                reports.push(serde_json::to_value(report)?);
+            }
+            Err(e) => {
+                failures += 1;
+                summary.add_failure(&name, &e);
+                error!(
+                    "{}",
+                    tr!("update-failed", app = name.as_str(), error = e.as_str())
+                );
+                reports.push(serde_json::json!({ "app": name, "action": "failed", "error": e }));
+                overall.set_message(format!("({} failed)", failures));
+            }
+        }
+    }
+    overall.finish();
+
+    if notify::wanted(args.notify, ctx.config.notify) && !args.install.dry_run {
+        notify::send(summary).await;
+    }
+    if ctx.output == OutputFormat::Json {
+        reports.sort_by(|a, b| a["app"].as_str().cmp(&b["app"].as_str()));
+        ctx.print_json(&reports)?;
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} updates failed", failures, total).into());
+    }
+    if state.apps.is_empty() {
+        info!("{}", tr!("no-apps"));
+    }
+
+    Ok(())
+}
+
+/// Remove an installed app: the files its install method created (its AppImage,
+/// symlink, desktop entry, icon and metainfo, say) and its state entry. With `purge`, the versions kept for rollback and older versions left in
+/// the install directory go too.
+pub fn remove(ctx: &Context, app: &str, purge: bool) -> Result<(), Error> {
+    let app_name = match ctx.config.resolve(app, None) {
+        Ok(app) => app.name,
+        Err(_) => app.to_lowercase(),
+    };
+    let mut state = State::load(&ctx.state_path)?;
+    let installed = state
+        .apps
+        .remove(&app_name)
+        .ok_or_else(|| format!("{} is not installed", app_name))?;
+
+    method::for_type(installed.method()).uninstall_artifacts(&app_name, &installed)?;
+    let mut purged = if purge {
+        versions::old_versions(&installed)?
+    } else {
+        Vec::new()
+    };
+    if purge {
+        purged.extend(
+            installed
+                .kept_versions()
+                .into_iter()
+                .map(|version| version.backup_path)
+                .filter(|path| path.exists()),
+        );
+    }
+    for file in &purged {
+        versions::remove_file(file)?;
+    }
+    state.save(&ctx.state_path, ctx.dir_mode)?;
+    history::record(
+        ctx,
+        &Entry {
+            repo: Some(&installed.repo),
+            old_version: Some(&installed.tag),
+            sha256: Some(&installed.sha256),
+            path: Some(&installed.install_path),
+            ..Entry::new("removed", &app_name)
+        },
+    );
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "app": app_name,
+            "action": "removed",
+            "old_version": installed.tag,
+            "path": installed.install_path,
+            "purged": purged,
+        }))?;
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "removed",
+                app = app_name.as_str(),
+                version = installed.tag.as_str(),
+                path = installed.install_path.display().to_string()
+            )
+        );
+        for file in &purged {
+            info!(
+                "{}",
+                tr!("removed-old-version", path = file.display().to_string())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Pin an installed app at its current version, or with `pinned` false release it,
+/// so `update --all` leaves it alone and `check` doesn't look for updates.
+pub fn pin(ctx: &Context, app: &str, pinned: bool) -> Result<(), Error> {
+    let app_name = match ctx.config.resolve(app, None) {
+        Ok(app) => app.name,
+        Err(_) => app.to_lowercase(),
+    };
+
+    let mut tag = None;
+    ctx.update_state(|state| {
+        if let Some(installed) = state.apps.get_mut(&app_name) {
+            installed.pinned = pinned;
+            tag = Some(installed.tag.clone());
+        }
+    })?;
+    let tag = tag.ok_or_else(|| format!("{} is not installed", app_name))?;
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "app": app_name,
+            "action": if pinned { "pinned" } else { "unpinned" },
+            "version": tag,
+        }))?;
+    } else if pinned {
+        info!(
+            "{}",
+            tr!("pinned", app = app_name.as_str(), version = tag.as_str())
+        );
+    } else {
+        info!(
+            "{}",
+            tr!("unpinned", app = app_name.as_str(), version = tag.as_str())
+        );
+    }
+
+    Ok(())
+}
+
+/// List installed apps recorded in the state file.
+pub fn list(ctx: &Context) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+
+    if ctx.output == OutputFormat::Json {
+        let apps: Vec<_> = state
+            .apps
+            .iter()
+            .map(|(name, app)| {
+                serde_json::json!({
+                    "app": name,
+                    "repo": app.repo,
+                    "version": app.tag,
+                    "path": app.install_path,
+                    "installed_at": app.installed_at,
+                    "pinned": app.pinned,
+                    "method": app.method(),
+                })
+            })
+            .collect();
+        ctx.print_json(&apps)?;
+    } else {
+        if state.apps.is_empty() {
+            info!("{}", tr!("no-apps"));
+        }
+        for (name, app) in &state.apps {
+            let pinned = if app.pinned {
+                format!(" {}", tr!("list-pinned"))
+            } else {
+                String::new()
+            };
+            info!(
+                "{:<20} {:<15} {}{}",
+                name,
+                app.tag,
+                app.install_path.display(),
+                pinned
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Show what is recorded about the installed app `app`.
+pub fn info(ctx: &Context, app: &str) -> Result<(), Error> {
+    let (app_name, settings) = match ctx.config.resolve(app, None) {
+        Ok(app) => (app.name, Some(app.settings)),
+        Err(_) => (app.to_lowercase(), None),
+    };
+    let state = State::load(&ctx.state_path)?;
+    let installed = state
+        .apps
+        .get(&app_name)
+        .ok_or_else(|| format!("{} is not installed", app_name))?;
+    let notes = settings
+        .as_ref()
+        .or(installed.settings.as_ref())
+        .and_then(AppConfig::notes);
+
+    if ctx.output == OutputFormat::Json {
+        let mut json = serde_json::to_value(installed)?;
+        json["app"] = app_name.into();
+        json["method"] = serde_json::to_value(installed.method())?;
+        json["notes"] = notes.into();
+        return ctx.print_json(&json);
+    }
+
+    info!("{} ({})", app_name, installed.repo);
+    info!(
+        "  {}",
+        tr!("info-version", version = installed.tag.as_str())
+    );
+    match &installed.asset_version {
+        Some(version)
+            if version_in(&installed.tag)
+                .is_some_and(|tag_version| !same_version(version, tag_version)) =>
+        {
+            info!(
+                "  {}",
+                tr!(
+                    "info-asset-version",
+                    asset = installed.asset_name.as_str(),
+                    version = version.as_str()
+                )
+            )
+        }
+        _ => info!(
+            "  {}",
+            tr!("info-asset", asset = installed.asset_name.as_str())
+        ),
+    }
+    info!(
+        "  {}",
+        tr!(
+            "info-path",
+            path = installed.install_path.display().to_string()
+        )
+    );
+    info!(
+        "  {}",
+        tr!("info-method", method = installed.method().name())
+    );
+    let provenance = &installed.provenance;
+    if let Some(url) = &provenance.source_url {
+        info!("  {}", tr!("info-source", url = url.as_str()));
+    }
+    if let Some(host) = &provenance.served_by {
+        info!("  {}", tr!("info-served-by", host = host.as_str()));
+    }
+    if let Some(method) = &provenance.verification {
+        info!("  {}", tr!("info-verification", method = method.as_str()));
+    }
+    if let Some(time) = chrono::DateTime::from_timestamp(installed.installed_at as i64, 0) {
+        let time = time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+        info!("  {}", tr!("info-installed-at", time = time.to_string()));
+    }
+    if installed.pinned {
+        info!("  {}", tr!("info-pinned"));
+    }
+    if let Some(notes) = notes {
+        info!("  {}", tr!("info-notes"));
+        for line in notes.lines() {
+            info!("    {}", style::reminder(line));
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare each app's installed tag with its latest release and report the
+/// result. Without arguments every installed or configured app is checked.
+/// Returns the installed apps that have an update available.
+pub async fn check(ctx: &Context, specs: &[String]) -> Result<Vec<PendingUpdate>, Error> {
+    let json = ctx.output == OutputFormat::Json;
+    let state = State::load(&ctx.state_path)?;
+
+    let mut apps: Vec<App> = Vec::new();
+    if specs.is_empty() {
+        let mut names: Vec<&String> = ctx.config.apps.keys().collect();
+        names.extend(
+            state
+                .apps
+                .keys()
+                .filter(|name| !ctx.config.apps.contains_key(*name)),
+        );
+        for name in names {
+            let spec = match state.apps.get(name) {
+                Some(installed) if !ctx.config.apps.contains_key(name) => &installed.repo,
+                _ => name,
+            };
+            apps.push(ctx.config.resolve(spec, None)?);
+        }
+    } else {
+        for spec in specs {
+            apps.push(ctx.config.resolve(spec, None)?);
+        }
+    }
+
+    let mut updates_available = Vec::new();
+    let mut failures = 0;
+    let mut report = Vec::new();
+    for app in &apps {
+        let installed = state.apps.get(&app.name);
+        if let Some(pinned) = installed.filter(|installed| installed.pinned) {
+            if json {
+                report.push(serde_json::json!({
+                    "app": app.name,
+                    "repo": app.repo.to_string(),
+                    "installed": pinned.tag,
+                    "latest": null,
+                    "status": "pinned",
+                    "error": null,
+                }));
+            } else {
+                info!(
+                    "{}",
+                    tr!(
+                        "check-pinned",
+                        app = app.name.as_str(),
+                        repo = app.repo.to_string(),
+                        version = pinned.tag.as_str()
+                    )
+                );
+            }
+            continue;
+        }
+        let latest = latest_release(ctx, app).await;
+        let (status, latest_tag, detail) = match (&latest, installed) {
+            (Err(e), _) => {
+                failures += 1;
+                ("error", None, e.to_string())
+            }
+            (Ok((release, _)), Some(installed)) if installed.tag == release.tag_name => (
+                "up-to-date",
+                Some(release.tag_name.clone()),
+                style::success(tr!("check-up-to-date", version = release.tag_name.as_str()))
+                    .to_string(),
+            ),
+            (Ok((release, asset)), Some(installed)) => {
+                last_known::record(ctx, &app.name, installed, release, asset);
+                updates_available.push(PendingUpdate {
+                    app: app.name.clone(),
+                    installed: installed.tag.clone(),
+                    latest: release.tag_name.clone(),
+                });
+                (
+                    "update-available",
+                    Some(release.tag_name.clone()),
+                    tr!(
+                        "check-update-available",
+                        change = style::change(&installed.tag, &release.tag_name),
+                        asset = asset.name.as_str()
+                    ),
+                )
+            }
+            (Ok((release, _)), None) => (
+                "not-installed",
+                Some(release.tag_name.clone()),
+                tr!("check-not-installed", version = release.tag_name.as_str()),
+            ),
+        };
+
+        if json {
+            report.push(serde_json::json!({
+                "app": app.name,
+                "repo": app.repo.to_string(),
+                "installed": installed.map(|i| &i.tag),
+                "latest": latest_tag,
+                "status": status,
+                "error": if status == "error" { Some(&detail) } else { None },
+            }));
+        } else if status == "error" {
+            error!("{} ({}): {}", app.name, app.repo, detail);
+        } else {
+            info!("{} ({}): {}", app.name, app.repo, detail);
+        }
+    }
+
+    if json {
+        ctx.print_json(&report)?;
+    }
+    if failures > 0 {
+        return Err(format!("Could not check {} of {} apps", failures, apps.len()).into());
+    }
+
+    Ok(updates_available)
+}
+
+/// The release an update of `app` would install, with its AppImage for this machine.
+pub async fn latest_release(ctx: &Context, app: &App) -> Result<(Release, Asset), Error> {
+    let pattern = configured_pattern(app)?;
+    let preferences = configured_preferences(app)?;
+    let selector = ReleaseSelector::new(None, app.settings.channel);
+    let filter = AssetFilter {
+        asset_type: app.settings.asset_type,
+        arch: arch::host(),
+        pattern: pattern.as_ref(),
+        prefer: &preferences,
+        interactive: false,
+    };
+    fetch_release_appimage(ctx, app, selector, &filter, None).await
+}
+
+/// Files created around an installed AppImage to make it convenient to launch.
+pub struct Integration {
+    pub symlink: Option<PathBuf>,
+    pub desktop_entry: Option<PathBuf>,
+    pub icon: Option<PathBuf>,
+    pub metainfo: Option<PathBuf>,
+    pub sandbox: Option<Sandbox>,
+    pub wrapper: Option<PathBuf>,
+    pub app_dir: Option<PathBuf>,
+}
+
+/// Create the app's symlink and desktop entry (with the AppImage's own icon, when
+/// it can be extracted, and AppStream metainfo for software centres) as requested
+/// for `install_path`, installed at `version`. With `extract`, they launch
+/// the AppImage's extracted `AppRun` instead; with a `sandbox`, a wrapper running the
+/// app inside it.
+#[allow(clippy::too_many_arguments)]
+pub fn integrate(
+    ctx: &Context,
+    app: &App,
+    install_path: &Path,
+    version: &str,
+    create_symlink: bool,
+    create_desktop_entry: bool,
+    sandbox: Option<Sandbox>,
+    extract: bool,
+) -> Result<Integration, Error> {
+    let app_dir = if extract {
+        let app_dir = extract::app_dir(install_path);
+        extract::extract(install_path, &app_dir, ctx.dir_mode)?;
+        Some(app_dir)
+    } else {
+        None
+    };
+    let wrapper = match sandbox {
+        Some(sandbox) => Some(sandbox::write_wrapper(
+            sandbox,
+            &app.name,
+            install_path,
+            app_dir.as_deref(),
+            ctx.dir_mode,
+        )?),
+        None => {
+            sandbox::remove_wrapper(&app.name)?;
+            None
+        }
+    };
+    let app_run = app_dir.as_deref().map(extract::app_run);
+    let launcher = wrapper
+        .as_deref()
+        .or(app_run.as_deref())
+        .unwrap_or(install_path);
+
+    let symlink = if create_symlink {
+        create_dirs(&ctx.bin_dir, ctx.dir_mode)?;
+        Some(create_app_symlink(&ctx.bin_dir, &app.name, launcher)?)
+    } else {
+        None
+    };
+
+    let mut icon = None;
+    let mut metainfo = None;
+    let mut desktop_entry = None;
+    if let (true, Some(dir)) = (create_desktop_entry, desktop::applications_dir()) {
+        // A missing icon shouldn't fail the install; fall back to a themed icon name
+        icon = match icon::icons_dir()
+            .map(|icons| icon::install_icon(install_path, &icons, &app.name, ctx.dir_mode))
+        {
+            Some(Ok(path)) => Some(path),
+            Some(Err(e)) => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "icon-failed",
+                        app = app.name.as_str(),
+                        error = e.to_string()
+                    )
+                );
+                None
+            }
+            None => None,
+        };
+        let icon_name = match &icon {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => app.name.clone(),
+        };
+        // Without the AppImage's desktop file the entry just handles no MIME types
+        let mime_types = desktop::embedded_mime_types(install_path, &dir, &app.name, ctx.dir_mode)
+            .unwrap_or_else(|e| {
+                debug!("No MIME types for {}: {}", app.name, e);
+                Vec::new()
+            });
+        desktop_entry = Some(desktop::write_entry(
+            &dir,
+            &app.name,
+            &app.repo.name,
+            launcher,
+            &icon_name,
+            &mime_types,
+            ctx.dir_mode,
+        )?);
+        desktop::update_database(&dir);
+        // Software centres list the app through its metainfo, which is nice to have
+        metainfo = match metainfo::metainfo_dir()
+            .map(|dir| metainfo::write_metainfo(&dir, install_path, app, version, ctx.dir_mode))
+        {
+            Some(Ok(path)) => Some(path),
+            Some(Err(e)) => {
+                warn!(
+                    "{}",
+                    tr!(
+                        "metainfo-failed",
+                        app = app.name.as_str(),
+                        error = e.to_string()
+                    )
+                );
+                None
+            }
+            None => None,
+        };
+    }
+
+    Ok(Integration {
+        symlink,
+        desktop_entry,
+        icon,
+        metainfo,
+        sandbox,
+        wrapper,
+        app_dir,
+    })
+}
+
+/// Delete the directory an earlier install of the app was extracted to, once the app
+/// runs from a different one (or none).
+fn remove_stale_app_dir(
+    installed: Option<&InstalledApp>,
+    integration: &Integration,
+) -> Result<(), Error> {
+    match installed.and_then(|installed| installed.app_dir.as_ref()) {
+        Some(app_dir) if integration.app_dir.as_ref() != Some(app_dir) => extract::remove(app_dir),
+        _ => Ok(()),
+    }
+}
+
+/// Delete the extracted AppDir of the replaced version when the new one at
+/// `install_path` would reuse its path, as with a stable filename, so the new
+/// version is extracted instead of the old one being kept.
+fn remove_reused_app_dir(
+    installed: Option<&InstalledApp>,
+    install_path: &Path,
+) -> Result<(), Error> {
+    match installed.and_then(|installed| installed.app_dir.as_ref()) {
+        Some(app_dir) if *app_dir == extract::app_dir(install_path) => extract::remove(app_dir),
+        _ => Ok(()),
+    }
+}
+
+/// Store (or replace) the state entry for a freshly installed app.
+#[allow(clippy::too_many_arguments)]
+pub fn record_install(
+    state: &mut State,
+    app: &App,
+    release: &Release,
+    asset: &Asset,
+    sha256: String,
+    install_path: PathBuf,
+    integration: Integration,
+    provenance: Provenance,
+) {
+    state.apps.insert(
+        app.name.clone(),
+        InstalledApp {
+            repo: app.repo.to_string(),
+            tag: release.tag_name.clone(),
+            asset_name: asset.name.clone(),
+            asset_version: version_in(&asset.name).map(str::to_string),
+            sha256,
+            install_path,
+            symlink: integration.symlink,
+            desktop_entry: integration.desktop_entry,
+            icon: integration.icon,
+            metainfo: integration.metainfo,
+            sandbox: integration.sandbox,
+            app_dir: integration.app_dir,
+            installed_at: state::now(),
+            previous: None,
+            archive: Vec::new(),
+            pinned: state
+                .apps
+                .get(&app.name)
+                .is_some_and(|installed| installed.pinned),
+            settings: Some(app.settings.clone()),
+            provenance,
+            method: Some(app.settings.asset_type),
+            preferred: None,
+        },
+    );
+}
+
+/// Point `<bin_dir>/<app_name>` at `app_image`. The link is relative when it sits
+/// next to the AppImage, absolute otherwise.
+pub fn create_app_symlink(
+    bin_dir: &Path,
+    app_name: &str,
+    app_image: &Path,
+) -> Result<PathBuf, Error> {
+    let symlink_path = bin_dir.join(app_name);
+
+    // Remove existing symlink if it exists (even if it dangles), but never clobber
+    // a real program that happens to share the app's name.
+    if symlink_path.is_symlink() {
+        debug!("Replacing symlink {}", symlink_path.display());
+        fs::remove_file(&symlink_path)?;
+    } else if symlink_path.exists() {
+        return Err(format!(
+            "{} already exists and is not a symlink; refusing to replace it",
+            symlink_path.display()
+        )
+        .into());
+    }
+
+    // Create the symlink
+    let target = match app_image.parent() {
+        Some(dir) if dir == bin_dir => PathBuf::from(
+            app_image
+                .file_name()
+                .ok_or("Install path has no file name")?,
+        ),
+        _ => app_image.to_path_buf(),
+    };
+    debug!("Linking {} -> {}", symlink_path.display(), target.display());
+    std::os::unix::fs::symlink(target, &symlink_path)?;
+
+    Ok(symlink_path)
+}
+
+/// Create `dir` and any missing parents.
+///
+/// Only directories that did not exist before are touched: with an explicit
+/// `mode` they are set to exactly that mode (bypassing the umask), otherwise
+/// they get whatever the umask yields. Existing directories keep their permissions.
+pub fn create_dirs(dir: &Path, mode: Option<u32>) -> std::io::Result<()> {
+    let missing: Vec<&Path> = dir.ancestors().take_while(|p| !p.exists()).collect();
+
+    for path in missing.into_iter().rev() {
+        let mut builder = fs::DirBuilder::new();
+        if let Some(mode) = mode {
+            builder.mode(mode);
+        }
+        match builder.create(path) {
+            Ok(()) => {}
+            // Created meanwhile by a concurrent update (the shared applications or
+            // icons directory, say), which also set its mode
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && path.is_dir() => continue,
+            Err(e) => return Err(e),
+        }
+        if let Some(mode) = mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+
+        let actual = fs::metadata(path)?.permissions().mode() & 0o7777;
+        debug!(
+            "{}",
+            tr!(
+                "created-directory",
+                path = path.display().to_string(),
+                mode = format!("{:04o}", actual)
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether the versions `a` and `b` are the same, ignoring a leading `v` and
+/// comparing numeric components as numbers, with missing ones counting as 0
+/// (`v2.14` equals `2.14.0`).
+pub fn same_version(a: &str, b: &str) -> bool {
+    let components = |version: &str| -> Vec<String> {
+        let version = version
+            .strip_prefix(['v', 'V'])
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(version);
+        version
+            .split('.')
+            .map(|component| match component.parse::<u64>() {
+                Ok(number) => number.to_string(),
+                Err(_) => component.to_string(),
+            })
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    let zero = "0".to_string();
+    (0..a.len().max(b.len())).all(|i| a.get(i).unwrap_or(&zero) == b.get(i).unwrap_or(&zero))
+}
+
+/// Extract the first version-looking substring (e.g. `2.14.22` from
+/// `Joplin-2.14.22.AppImage` or `v2.14.22`), requiring at least one dot.
+pub fn version_in(text: &str) -> Option<&str> {
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        if bytes[start].is_ascii_digit() && (start == 0 || !bytes[start - 1].is_ascii_digit()) {
+            let mut end = start;
+            let mut dots = 0;
+            while end < bytes.len() {
+                if bytes[end].is_ascii_digit() {
+                    end += 1;
+                } else if bytes[end] == b'.'
+                    && end + 1 < bytes.len()
+                    && bytes[end + 1].is_ascii_digit()
+                {
+                    dots += 1;
+                    end += 1;
+                } else {
+                    break;
+                }
+            }
+            if dots > 0 {
+                return Some(&text[start..end]);
+            }
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_versions_in_tags_and_file_names() {
+        assert_eq!(version_in("Joplin-2.14.22.AppImage"), Some("2.14.22"));
+        assert_eq!(version_in("v2.14.22"), Some("2.14.22"));
+        assert_eq!(version_in("app/v1.0-beta.2"), Some("1.0"));
+        // Numbers without a dot, like the architecture's, are skipped
+        assert_eq!(version_in("tool-x86_64-3.1.AppImage"), Some("3.1"));
+        assert_eq!(version_in("release-20240101"), None);
+        assert_eq!(version_in("1."), None);
+        assert_eq!(version_in(""), None);
+    }
+
+    #[test]
+    fn creates_directories_alongside_concurrent_creators() {
+        let root =
+            std::env::temp_dir().join(format!("rust-unicorn-create-dirs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        create_dirs(&root, Some(0o700)).unwrap();
+
+        let dir = root.join("share").join("applications");
+        let creators: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                std::thread::spawn(move || create_dirs(&dir, Some(0o750)))
+            })
+            .collect();
+        for creator in creators {
+            creator.join().unwrap().unwrap();
+        }
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode(&dir), 0o750);
+        assert_eq!(mode(dir.parent().unwrap()), 0o750);
+        // Directories that were already there keep their mode
+        assert_eq!(mode(&root), 0o700);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
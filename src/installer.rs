@@ -0,0 +1,236 @@
+// `Installer`: the entry point for programs embedding rust-unicorn, such as graphical
+// front ends. It sets up a `Context` and runs installs and updates, reporting their
+// progress to a callback or as a stream of `Event`s, so the program can draw its own
+// progress instead of the terminal's bars.
+//
+//     let installer = Installer::builder().on_progress(|event| println!("{:?}", event)).build()?;
+//     let report = installer.install("owner/repo").await?;
+//
+// Like the commands, every install takes the install directory's lock, waiting for
+// other rust-unicorn processes (and other installs of the same program) to finish.
+use futures_util::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinHandle;
+
+use crate::cli::InstallArgs;
+use crate::config::Config;
+use crate::http::HttpFetcher;
+use crate::install::{self, create_dirs, InstallReport};
+use crate::lock;
+use crate::progress::{self, Event, Observer};
+use crate::{Context, Error, OutputLevel};
+
+/// Settings for an `Installer`, from `Installer::builder`.
+pub struct InstallerBuilder {
+    config: Option<Config>,
+    level: OutputLevel,
+    github_token: Option<String>,
+    install_dir: Option<PathBuf>,
+    bin_dir: Option<PathBuf>,
+    state_path: Option<PathBuf>,
+    http: Option<Arc<dyn HttpFetcher>>,
+    observer: Option<Observer>,
+}
+
+impl InstallerBuilder {
+    /// Use `config` instead of the user's config file.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// How much to print; `Quiet` by default, which draws no progress bars.
+    pub fn level(mut self, level: OutputLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Token for the GitHub API, taking precedence over `$GITHUB_TOKEN` and the config.
+    pub fn github_token(mut self, token: impl Into<String>) -> Self {
+        self.github_token = Some(token.into());
+        self
+    }
+
+    /// Install AppImages into `dir` instead of the configured directory.
+    pub fn install_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.install_dir = Some(dir.into());
+        self
+    }
+
+    /// Put app symlinks into `dir` instead of the configured directory.
+    pub fn bin_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep the state file, which records installed apps, at `path` instead of the
+    /// default location. The history and last known releases are kept beside it.
+    pub fn state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    /// Send requests through `http` instead of a client built from the config's proxy,
+    /// TLS and timeout settings, e.g. a client with middleware of the program's own.
+    pub fn http_fetcher(mut self, http: impl HttpFetcher + 'static) -> Self {
+        self.http = Some(Arc::new(http));
+        self
+    }
+
+    /// Call `observer` with the progress of every install and update. It runs on the
+    /// installing task, so it should hand the event on rather than block.
+    pub fn on_progress(mut self, observer: impl Fn(Event) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Load the config (unless given) and set up the installer.
+    pub fn build(self) -> Result<Installer, Error> {
+        let config = match self.config {
+            Some(config) => config,
+            None => Config::load(None)?,
+        };
+        let mut ctx = match self.state_path {
+            Some(path) => Context::with_state_path(
+                config,
+                self.level,
+                self.github_token,
+                std::path::absolute(path)?,
+            )?,
+            None => Context::new(config, self.level, self.github_token)?,
+        };
+        if let Some(dir) = self.install_dir {
+            ctx.install_dir = std::path::absolute(dir)?;
+        }
+        if let Some(dir) = self.bin_dir {
+            ctx.bin_dir = std::path::absolute(dir)?;
+        }
+        if let Some(http) = self.http {
+            ctx.http = http;
+        }
+        Ok(Installer {
+            ctx: Arc::new(ctx),
+            observer: self.observer,
+        })
+    }
+}
+
+/// Installs and updates apps, reporting their progress. Clones share their settings.
+#[derive(Clone)]
+pub struct Installer {
+    ctx: Arc<Context>,
+    observer: Option<Observer>,
+}
+
+impl Installer {
+    /// Start setting up an installer.
+    pub fn builder() -> InstallerBuilder {
+        InstallerBuilder {
+            config: None,
+            level: OutputLevel::Quiet,
+            github_token: None,
+            install_dir: None,
+            bin_dir: None,
+            state_path: None,
+            http: None,
+            observer: None,
+        }
+    }
+
+    /// The settings installs run with, for the functions of `install` and the other
+    /// modules.
+    pub fn context(&self) -> &Arc<Context> {
+        &self.ctx
+    }
+
+    /// Install the latest release of `app`: a repository spec or the name of a
+    /// configured or catalog app.
+    pub async fn install(&self, app: &str) -> Result<InstallReport, Error> {
+        self.run(&args(app), false).await
+    }
+
+    /// Update the installed `app` to its latest release.
+    pub async fn update(&self, app: &str) -> Result<InstallReport, Error> {
+        self.run(&args(app), true).await
+    }
+
+    /// Install (or with `update`, update) as `args` say, as the `install` command does.
+    pub async fn run(&self, args: &InstallArgs, update: bool) -> Result<InstallReport, Error> {
+        let install = async {
+            create_dirs(&self.ctx.install_dir, self.ctx.dir_mode)?;
+            let _lock = lock::acquire(&self.ctx.install_dir, true).await?;
+            install::install(&self.ctx, args, update).await
+        };
+        match &self.observer {
+            Some(observer) => progress::observed(Arc::clone(observer), install).await,
+            None => install.await,
+        }
+    }
+
+    /// Install the latest release of `app` in the background, with its progress as a
+    /// stream of events. The callback given to `on_progress` is told as well.
+    pub fn install_events(&self, app: &str) -> Events {
+        self.events(args(app), false)
+    }
+
+    /// Update the installed `app` in the background, with its progress as a stream of
+    /// events.
+    pub fn update_events(&self, app: &str) -> Events {
+        self.events(args(app), true)
+    }
+
+    fn events(&self, args: InstallArgs, update: bool) -> Events {
+        let (sender, events) = mpsc::unbounded_channel();
+        let installer = Installer {
+            ctx: Arc::clone(&self.ctx),
+            observer: Some(match self.observer.clone() {
+                Some(observer) => Arc::new(move |event| {
+                    observer(event);
+                    let _ = sender.send(event);
+                }),
+                None => Arc::new(move |event| {
+                    let _ = sender.send(event);
+                }),
+            }),
+        };
+        let outcome = tokio::spawn(async move { installer.run(&args, update).await });
+        Events { events, outcome }
+    }
+}
+
+/// Arguments installing `app` without asking anything, as nobody may be there to answer.
+fn args(app: &str) -> InstallArgs {
+    InstallArgs {
+        app: Some(app.to_string()),
+        non_interactive: true,
+        ..InstallArgs::default()
+    }
+}
+
+/// An install running in the background: a stream of its progress, which ends when
+/// the install does, and then its outcome.
+pub struct Events {
+    events: UnboundedReceiver<Event>,
+    outcome: JoinHandle<Result<InstallReport, Error>>,
+}
+
+impl Events {
+    /// Wait for the install to finish, skipping any events not yet taken.
+    pub async fn outcome(self) -> Result<InstallReport, Error> {
+        self.outcome
+            .await
+            .map_err(|e| format!("Install task failed: {}", e))?
+    }
+}
+
+impl Stream for Events {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Event>> {
+        self.events.poll_recv(cx)
+    }
+}
@@ -0,0 +1,283 @@
+// The releases installed apps were last seen to have available, so `update
+// --use-last-known` can still install one while the forge's API is failing (its
+// downloads are often served by a CDN that keeps working). Kept next to the state
+// file as `last-known.json`, recorded by `check` and by update plans that find a
+// newer release.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+use crate::checksum::{self, Algorithm};
+use crate::install::create_dirs;
+use crate::release::{Asset, AssetFilter, Release};
+use crate::state::InstalledApp;
+use crate::{method, tr, Context, Error};
+
+/// A release found for an app, and when.
+#[derive(Serialize, Deserialize)]
+pub struct Resolution {
+    /// Tag of the version installed when the release was found
+    pub installed_tag: String,
+    pub release: Release,
+    /// The asset chosen from `release`
+    pub asset: Asset,
+    /// When the release was found, in seconds since the Unix epoch
+    pub resolved_at: u64,
+}
+
+/// `last-known.json` beside the state file at `state_path`.
+fn path(state_path: &Path) -> PathBuf {
+    state_path.with_file_name("last-known.json")
+}
+
+/// Counts this process's writes, which each go through a temporary file of their own.
+static WRITES: AtomicU64 = AtomicU64::new(0);
+
+fn load_all(state_path: &Path) -> BTreeMap<String, Resolution> {
+    fs::read_to_string(path(state_path))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Remember that `release`, with `asset`, is available for `app`, which has
+/// `installed` installed. Failing to is not an error: the record only helps when the
+/// API fails later.
+pub fn record(
+    ctx: &Context,
+    app: &str,
+    installed: &InstalledApp,
+    release: &Release,
+    asset: &Asset,
+) {
+    // Under the state lock, so concurrent updates don't drop each other's records
+    let _guard = ctx.lock_state();
+    let mut resolutions = load_all(&ctx.state_path);
+    resolutions.insert(
+        app.to_string(),
+        Resolution {
+            installed_tag: installed.tag.clone(),
+            release: release.clone(),
+            asset: asset.clone(),
+            resolved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        },
+    );
+    let path = path(&ctx.state_path);
+    // Other processes may be checking too, so each write has its own temporary file
+    let tmp_path = path.with_extension(format!(
+        "json.{}.{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = path
+        .parent()
+        .map_or(Ok(()), |dir| create_dirs(dir, ctx.dir_mode))
+        .and_then(|()| fs::write(&tmp_path, serde_json::to_string_pretty(&resolutions)?))
+        .and_then(|()| fs::rename(&tmp_path, &path));
+    if let Err(e) = written {
+        debug!("Cannot record the last known release of {}: {}", app, e);
+    }
+}
+
+/// The release and asset last found for `app`, to install instead of asking the API,
+/// which failed with `error`. There must be one found while `installed` was the
+/// installed version, for `tag` if given, whose asset still passes `filter` and
+/// comes with a SHA-256 digest the download will be verified against; otherwise
+/// `error` is returned, after a warning saying why the fallback wasn't used.
+pub fn fallback(
+    ctx: &Context,
+    app: &str,
+    installed: Option<&InstalledApp>,
+    tag: Option<&str>,
+    filter: &AssetFilter<'_>,
+    error: Error,
+) -> Result<(Release, Asset), Error> {
+    let resolution = load_all(&ctx.state_path).remove(app).filter(|resolution| {
+        installed.is_some_and(|installed| {
+            resolution.installed_tag == installed.tag
+                && resolution.release.tag_name != installed.tag
+        })
+    });
+    let Some(resolution) = resolution else {
+        warn!("{}", tr!("last-known-none", app = app));
+        return Err(error);
+    };
+    let (release, asset) = (resolution.release, resolution.asset);
+    if tag.is_some_and(|tag| tag != release.tag_name) {
+        warn!(
+            "{}",
+            tr!("last-known-other-tag", version = release.tag_name.as_str())
+        );
+        return Err(error);
+    }
+    let installable = match filter.prefer {
+        [] => method::for_type(filter.asset_type).matches(&asset.name),
+        prefer => {
+            prefer.iter().any(|p| p.matches(&asset.name)) && method::detect(&asset.name).is_some()
+        }
+    };
+    if !installable || filter.pattern.is_some_and(|p| !p.matches(&asset.name)) {
+        warn!(
+            "{}",
+            tr!("last-known-filtered", asset = asset.name.as_str())
+        );
+        return Err(error);
+    }
+    // Only a hash from the API itself vouches for the download without the API
+    let verified = asset
+        .digest
+        .as_deref()
+        .and_then(checksum::parse_digest)
+        .is_some_and(|digest| digest.algorithm == Algorithm::Sha256);
+    if !verified {
+        warn!(
+            "{}",
+            tr!("last-known-unverified", asset = asset.name.as_str())
+        );
+        return Err(error);
+    }
+
+    let age = SystemTime::now()
+        .duration_since(UNIX_EPOCH + Duration::from_secs(resolution.resolved_at))
+        .unwrap_or_default();
+    warn!(
+        "{}",
+        tr!(
+            "using-last-known",
+            app = app,
+            version = release.tag_name.as_str(),
+            error = error.to_string(),
+            age = describe_age(age)
+        )
+    );
+    Ok((release, asset))
+}
+
+/// How long ago something was, roughly: in minutes, hours or days.
+fn describe_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    match minutes {
+        0..=119 => tr!("age-minutes", count = minutes),
+        120..=2879 => tr!("age-hours", count = minutes / 60),
+        _ => tr!("age-days", count = minutes / (60 * 24)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AssetType, Config};
+    use crate::OutputLevel;
+
+    fn installed(tag: &str) -> InstalledApp {
+        serde_json::from_value(serde_json::json!({
+            "repo": "owner/tool",
+            "tag": tag,
+            "asset_name": format!("Tool-{}.AppImage", tag),
+            "sha256": "0".repeat(64),
+            "install_path": format!("/apps/Tool-{}.AppImage", tag),
+        }))
+        .unwrap()
+    }
+
+    fn release(digest: Option<String>) -> (Release, Asset) {
+        let asset = Asset {
+            name: "Tool-2.0.AppImage".to_string(),
+            browser_download_url: "https://example.com/Tool-2.0.AppImage".to_string(),
+            size: 1,
+            state: "uploaded".to_string(),
+            digest,
+        };
+        let release = Release {
+            tag_name: "2.0".to_string(),
+            prerelease: false,
+            draft: false,
+            body: None,
+            assets: Vec::new(),
+        };
+        (release, asset)
+    }
+
+    fn api_down() -> Error {
+        Error::RateLimited {
+            host: "api.github.com".to_string(),
+            reset: None,
+        }
+    }
+
+    #[test]
+    fn falls_back_only_to_a_verifiable_newer_release() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-unicorn-last-known-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut ctx = Context::new(Config::default(), OutputLevel::Silent, None).unwrap();
+        ctx.state_path = dir.join("state.json");
+        let filter = AssetFilter {
+            asset_type: AssetType::AppImage,
+            arch: "x86_64",
+            pattern: None,
+            prefer: &[],
+            interactive: false,
+        };
+        let fallback = |installed: &InstalledApp, tag: Option<&str>| {
+            fallback(&ctx, "tool", Some(installed), tag, &filter, api_down())
+        };
+        let old = installed("1.0");
+
+        // Nothing seen yet
+        assert!(matches!(
+            fallback(&old, None),
+            Err(Error::RateLimited { .. })
+        ));
+
+        // Seen, but without a hash to verify the download against
+        let (found, asset) = release(None);
+        record(&ctx, "tool", &old, &found, &asset);
+        assert!(fallback(&old, None).is_err());
+
+        let (found, asset) = release(Some(format!("sha256:{}", "a".repeat(64))));
+        record(&ctx, "tool", &old, &found, &asset);
+        let (release, asset) = fallback(&old, None).unwrap();
+        assert_eq!(release.tag_name, "2.0");
+        assert_eq!(asset.name, "Tool-2.0.AppImage");
+        assert!(fallback(&old, Some("2.0")).is_ok());
+        assert!(fallback(&old, Some("3.0")).is_err());
+        // Once something else is installed, the release may no longer be newer
+        assert!(fallback(&installed("2.0"), None).is_err());
+        assert!(fallback(&installed("3.0"), None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_records_of_concurrent_updates() {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-unicorn-last-known-concurrent-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut ctx = Context::new(Config::default(), OutputLevel::Silent, None).unwrap();
+        ctx.state_path = dir.join("state.json");
+        let (found, asset) = release(None);
+        let old = installed("1.0");
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (ctx, old, found, asset) = (&ctx, &old, &found, &asset);
+                scope.spawn(move || record(ctx, &format!("tool{}", i), old, found, asset));
+            }
+        });
+        assert_eq!(load_all(&ctx.state_path).len(), 8);
+        // Only the record itself is left
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,443 @@
+// The layout older versions left behind: Joplin only, its versioned AppImages in
+// ~/Documents/repository/rust-unicorn next to a relative `joplin` symlink, and no
+// state file. Installs and updates finding it offer to take it over (or do so with
+// --migrate-legacy): the current AppImage is adopted, which records it and links it
+// from the bin directory, Joplin gets a config entry, and the files can move to the
+// default install directory. Declining changes nothing; the files keep being
+// managed where they are.
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::cli::{self, AdoptArgs, LegacyMigration, OutputFormat};
+use crate::install::{create_dirs, version_in};
+use crate::state::State;
+use crate::verify::verify_appimage;
+use crate::{adopt, arch, defaults, lock, paths, running, style, tr, Context, Error};
+
+/// Name of the legacy symlink, and of the app it launched.
+const APP_NAME: &str = "joplin";
+
+/// Left next to the state file once the migration is declined, so it isn't offered
+/// again; --migrate-legacy still works.
+const DECLINED_FILE: &str = "legacy-declined";
+
+/// What is left to migrate of the legacy layout.
+pub struct Layout {
+    pub dir: PathBuf,
+    /// AppImages in `dir` that no state entry records
+    pub unmanaged: Vec<PathBuf>,
+    /// The AppImage to adopt as Joplin, unless Joplin is installed already
+    pub current: Option<PathBuf>,
+    /// The relative `joplin` symlink
+    pub symlink: Option<PathBuf>,
+}
+
+impl Layout {
+    /// The legacy layout in `dir`, if something of it is left to migrate given
+    /// `state`. Older AppImages alone are not; `cleanup` deals with those.
+    pub fn find(dir: &Path, state: &State) -> Result<Option<Layout>, Error> {
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let unmanaged: Vec<PathBuf> = adopt::appimages_in(dir)?
+            .into_iter()
+            .filter(|path| {
+                !state
+                    .apps
+                    .values()
+                    .any(|installed| &installed.install_path == path)
+            })
+            .collect();
+        let link = dir.join(APP_NAME);
+        let target = fs::read_link(&link)
+            .ok()
+            .filter(|target| target.is_relative());
+        let current = match state.apps.contains_key(APP_NAME) {
+            true => None,
+            // The symlink points at the version in use, or used to
+            false => target
+                .as_ref()
+                .map(|target| dir.join(target))
+                .filter(|path| unmanaged.contains(path))
+                .or_else(|| newest(&unmanaged)),
+        };
+        let symlink = target.map(|_| link);
+        if current.is_none() && symlink.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Layout {
+            dir: dir.to_path_buf(),
+            unmanaged,
+            current,
+            symlink,
+        }))
+    }
+}
+
+/// Offer to migrate the legacy layout, if there is one left: right away as `how`
+/// says, else by asking. Returns the install directory to use from now on when the
+/// files moved out of `ctx.install_dir`.
+pub async fn offer(
+    ctx: &Context,
+    config_path: Option<&Path>,
+    how: Option<LegacyMigration>,
+) -> Result<Option<PathBuf>, Error> {
+    let Some(dir) = paths::legacy_install_dir() else {
+        return Ok(None);
+    };
+    let declined = ctx.state_path.with_file_name(DECLINED_FILE);
+    if how.is_none() && declined.exists() {
+        return Ok(None);
+    }
+    let Some(layout) = Layout::find(&dir, &State::load(&ctx.state_path)?)? else {
+        return Ok(None);
+    };
+    let target = if ctx.install_dir == dir {
+        paths::standard_install_dir()
+            .ok_or("Cannot determine the install directory: HOME is not set")?
+    } else {
+        ctx.install_dir.clone()
+    };
+
+    let how = match how {
+        Some(how) => how,
+        None => {
+            let dir = dir.display().to_string();
+            let can_ask = ctx.level.shows_info()
+                && ctx.output == OutputFormat::Text
+                && io::stdin().is_terminal()
+                && io::stderr().is_terminal();
+            if !can_ask {
+                info!("{}", tr!("legacy-found-hint", dir = dir.as_str()));
+                return Ok(None);
+            }
+            info!("{}", tr!("legacy-found", dir = dir.as_str()));
+            if !running::confirm(ctx, &tr!("legacy-migrate-question"))? {
+                if let Some(parent) = declined.parent() {
+                    create_dirs(parent, ctx.dir_mode)?;
+                }
+                fs::write(&declined, "")?;
+                info!("{}", tr!("legacy-declined", dir = dir.as_str()));
+                return Ok(None);
+            }
+            let question = tr!(
+                "legacy-move-question",
+                target = target.display().to_string()
+            );
+            match running::confirm(ctx, &question)? {
+                true => LegacyMigration::Move,
+                false => LegacyMigration::InPlace,
+            }
+        }
+    };
+
+    let target = (how == LegacyMigration::Move).then_some(target);
+    if let Some(target) = &target {
+        create_dirs(target, ctx.dir_mode)?;
+    }
+    let _lock = lock::acquire(target.as_deref().unwrap_or(&dir), false).await?;
+    let moved = migrate(ctx, config_path, &layout, target.as_deref())?;
+    Ok(moved.filter(|_| ctx.install_dir == dir))
+}
+
+/// Migrate `layout`, moving its AppImages to `target` if given, and report each
+/// step. Returns the directory the files moved to.
+pub fn migrate(
+    ctx: &Context,
+    config_path: Option<&Path>,
+    layout: &Layout,
+    target: Option<&Path>,
+) -> Result<Option<PathBuf>, Error> {
+    let dir = &layout.dir;
+    info!(
+        "{}",
+        tr!("legacy-migrating", dir = dir.display().to_string())
+    );
+    // Check the AppImage can be adopted before anything changes
+    let version = match &layout.current {
+        Some(current) => {
+            let name = current
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            verify_appimage(current, &name, arch::host())
+                .map_err(|e| format!("Cannot migrate {}: {}", current.display(), e))?;
+            let version = version_in(&name).ok_or_else(|| {
+                format!(
+                    "Cannot tell the version of {} from its name; adopt it with `adopt --version`",
+                    current.display()
+                )
+            })?;
+            Some(version.to_string())
+        }
+        None => None,
+    };
+
+    let mut current = layout.current.clone();
+    if let Some(target) = target {
+        for file in &layout.unmanaged {
+            let moved = target.join(file.file_name().unwrap_or_default());
+            if moved.exists() {
+                warn!(
+                    "{}",
+                    tr!(
+                        "legacy-not-moved",
+                        file = file.display().to_string(),
+                        path = moved.display().to_string()
+                    )
+                );
+                continue;
+            }
+            move_file(file, &moved)?;
+            info!(
+                "{}",
+                tr!(
+                    "legacy-moved",
+                    file = file.display().to_string(),
+                    path = moved.display().to_string()
+                )
+            );
+            if current.as_ref() == Some(file) {
+                current = Some(moved);
+            }
+        }
+    }
+
+    if let (Some(current), Some(version)) = (&current, version) {
+        let args = AdoptArgs {
+            path: Some(current.clone()),
+            scan: None,
+            repo: Some(cli::DEFAULT_REPO.to_string()),
+            name: Some(APP_NAME.to_string()),
+            // Joplin's releases are tagged v<version>
+            version: Some(format!("v{}", version)),
+            dry_run: false,
+        };
+        adopt::adopt_file(ctx, current, &args)?;
+    }
+    if let Some(symlink) = &layout.symlink {
+        let replacement = State::load(&ctx.state_path)?
+            .apps
+            .remove(APP_NAME)
+            .and_then(|installed| installed.symlink);
+        // With the symlink in the install directory, adopting replaced it already
+        if replacement.as_ref() != Some(symlink) {
+            fs::remove_file(symlink)?;
+        }
+        let symlink = symlink.display().to_string();
+        match replacement {
+            Some(replacement) => info!(
+                "{}",
+                tr!(
+                    "legacy-symlink-replaced",
+                    symlink = symlink,
+                    path = replacement.display().to_string()
+                )
+            ),
+            None => info!("{}", tr!("legacy-symlink-removed", symlink = symlink)),
+        }
+    }
+
+    // Nothing else left, the directory goes, or it would stay the default one
+    let emptied = target.is_some() && remove_if_empty(dir);
+    if emptied {
+        info!(
+            "{}",
+            tr!("legacy-dir-removed", dir = dir.display().to_string())
+        );
+    }
+    // Where the files went becomes the install directory, unless it is the default now
+    let keep_install_dir = target.filter(|_| match &ctx.config.install_dir {
+        Some(configured) => configured == dir,
+        None => !emptied,
+    });
+    let mut added_entry = false;
+    let config_path = defaults::edit_config(ctx, config_path, |document| {
+        added_entry = document
+            .get("apps")
+            .and_then(|apps| apps.get(APP_NAME))
+            .is_none();
+        defaults::app_table(ctx, document, APP_NAME)?;
+        if let Some(target) = keep_install_dir {
+            document["install_dir"] = toml_edit::value(target.display().to_string());
+        }
+        Ok(())
+    })?;
+    let file = config_path.display().to_string();
+    if added_entry {
+        info!(
+            "{}",
+            tr!("legacy-config-entry", app = APP_NAME, file = file.as_str())
+        );
+    }
+    if let Some(target) = keep_install_dir {
+        info!(
+            "{}",
+            tr!(
+                "default-set",
+                key = "install_dir",
+                value = target.display().to_string(),
+                file = file.as_str()
+            )
+        );
+    }
+    info!("{}", style::success(tr!("legacy-migrated")));
+    Ok(target.map(Path::to_path_buf))
+}
+
+/// The AppImage among `files` with the highest version in its name.
+fn newest(files: &[PathBuf]) -> Option<PathBuf> {
+    let version = |path: &PathBuf| -> Vec<u64> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        version_in(&name)
+            .unwrap_or_default()
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse().ok())
+            .collect()
+    };
+    files.iter().max_by_key(|path| version(path)).cloned()
+}
+
+/// Move `file` to `path`, copying it when they are on different file systems.
+fn move_file(file: &Path, path: &Path) -> Result<(), Error> {
+    if fs::rename(file, path).is_err() {
+        debug!("Copying {} to {}", file.display(), path.display());
+        fs::copy(file, path)?;
+        fs::remove_file(file)?;
+    }
+    Ok(())
+}
+
+/// Remove `dir` when nothing but a lock file is left in it.
+fn remove_if_empty(dir: &Path) -> bool {
+    let lock_file = lock::path(dir);
+    let only_lock = fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .all(|entry| entry.path() == lock_file)
+    });
+    if !only_lock {
+        return false;
+    }
+    let _ = fs::remove_file(&lock_file);
+    fs::remove_dir(dir).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_harness::appimage;
+    use crate::OutputLevel;
+    use std::os::unix::fs::symlink;
+
+    /// Have the AppImage to be installed at `install_path` look extracted already, so
+    /// hosts without FUSE don't try to run the stand-in.
+    fn extracted(install_path: &Path) {
+        let app_dir = crate::extract::app_dir(install_path);
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(crate::extract::app_run(&app_dir), "").unwrap();
+    }
+
+    /// A scratch home holding the legacy layout, and a context installing into
+    /// `legacy` there.
+    fn scratch(name: &str) -> (Context, PathBuf, PathBuf) {
+        let home = std::env::temp_dir().join(format!(
+            "rust-unicorn-legacy-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&home);
+        let legacy = home.join("legacy");
+        fs::create_dir_all(&legacy).unwrap();
+        for version in ["2.13.1", "2.14.22"] {
+            fs::write(
+                legacy.join(format!("Joplin-{}.AppImage", version)),
+                appimage(),
+            )
+            .unwrap();
+        }
+        symlink("Joplin-2.13.1.AppImage", legacy.join(APP_NAME)).unwrap();
+
+        let config = Config {
+            install_dir: Some(legacy.clone()),
+            bin_dir: Some(home.join("bin")),
+            desktop_entry: Some(false),
+            ..Config::default()
+        };
+        let mut ctx = Context::new(config, OutputLevel::Silent, None).unwrap();
+        ctx.state_path = home.join("state").join("state.json");
+        (ctx, home, legacy)
+    }
+
+    fn find(ctx: &Context, dir: &Path) -> Option<Layout> {
+        Layout::find(dir, &State::load(&ctx.state_path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrating_in_place_adopts_the_linked_version_once() {
+        let (ctx, home, legacy) = scratch("in-place");
+        let config_path = home.join("config.toml");
+        let layout = find(&ctx, &legacy).unwrap();
+        assert_eq!(layout.unmanaged.len(), 2);
+        assert_eq!(layout.current, Some(legacy.join("Joplin-2.13.1.AppImage")));
+        extracted(&legacy.join("Joplin-2.13.1.AppImage"));
+
+        assert_eq!(
+            migrate(&ctx, Some(&config_path), &layout, None).unwrap(),
+            None
+        );
+        let joplin = &State::load(&ctx.state_path).unwrap().apps[APP_NAME];
+        assert_eq!(joplin.tag, "v2.13.1");
+        assert_eq!(joplin.install_path, legacy.join("Joplin-2.13.1.AppImage"));
+        assert!(home.join("bin").join(APP_NAME).is_symlink());
+        assert!(!legacy.join(APP_NAME).exists());
+        assert!(legacy.join("Joplin-2.14.22.AppImage").exists());
+        let config = fs::read_to_string(&config_path).unwrap();
+        assert!(config.contains("[apps.joplin]"), "{}", config);
+        assert!(config.contains("laurent22/joplin"), "{}", config);
+
+        // Nothing is left to migrate
+        assert!(find(&ctx, &legacy).is_none());
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn migrating_with_a_move_empties_the_legacy_directory() {
+        let (ctx, home, legacy) = scratch("move");
+        let config_path = home.join("config.toml");
+        fs::write(&config_path, "# mine\nkeep_versions = 2\n").unwrap();
+        fs::remove_file(legacy.join(APP_NAME)).unwrap();
+        let apps = home.join("apps");
+        fs::create_dir_all(&apps).unwrap();
+        // Without the symlink, the newest version is the one in use
+        let layout = find(&ctx, &legacy).unwrap();
+        assert_eq!(layout.current, Some(legacy.join("Joplin-2.14.22.AppImage")));
+        extracted(&apps.join("Joplin-2.14.22.AppImage"));
+
+        let moved = migrate(&ctx, Some(&config_path), &layout, Some(&apps)).unwrap();
+        assert_eq!(moved, Some(apps.clone()));
+        let joplin = &State::load(&ctx.state_path).unwrap().apps[APP_NAME];
+        assert_eq!(joplin.install_path, apps.join("Joplin-2.14.22.AppImage"));
+        assert!(apps.join("Joplin-2.13.1.AppImage").exists());
+        assert!(!legacy.exists());
+        let config = fs::read_to_string(&config_path).unwrap();
+        assert!(
+            config.starts_with("# mine\nkeep_versions = 2\n"),
+            "{}",
+            config
+        );
+        // The configured install directory follows the files
+        assert!(
+            config.contains(&format!("install_dir = \"{}\"", apps.display())),
+            "{}",
+            config
+        );
+
+        assert!(find(&ctx, &legacy).is_none());
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
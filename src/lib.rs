@@ -0,0 +1,283 @@
+// rust-unicorn: installs and updates AppImages from the releases of GitHub, GitLab,
+// Gitea/Forgejo or plain download URLs.
+//
+// The `rust-unicorn` binary is a thin command-line front end; other programs can embed
+// the same steps through `Installer`, which reports their progress, or drive them
+// one by one through `Context` and the functions in `install`.
+pub mod adopt;
+pub mod arch;
+pub mod archive;
+pub mod cache;
+pub mod catalog;
+pub mod checksum;
+pub mod cli;
+pub mod config;
+pub mod daemon;
+pub mod dbus;
+pub mod defaults;
+pub mod desktop;
+pub mod direct;
+pub mod doctor;
+pub mod download;
+pub mod error;
+pub mod export;
+pub mod extract;
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+pub mod history;
+pub mod hook;
+pub mod http;
+pub mod i18n;
+pub mod icon;
+pub mod install;
+pub mod installer;
+pub mod last_known;
+pub mod legacy;
+pub mod lock;
+pub mod logging;
+pub mod markdown;
+pub mod metainfo;
+pub mod method;
+pub mod notes;
+pub mod notify;
+pub mod paths;
+pub mod pattern;
+pub mod progress;
+pub mod provider;
+pub mod release;
+pub mod retry;
+pub mod running;
+pub mod sandbox;
+pub mod schedule;
+pub mod search;
+pub mod self_update;
+pub mod shell_path;
+pub mod signature;
+pub mod stage;
+pub mod state;
+pub mod style;
+#[cfg(any(test, feature = "test-harness"))]
+pub mod test_harness;
+pub mod throttle;
+pub mod ui;
+pub mod update_info;
+pub mod usage;
+pub mod verify;
+pub mod versions;
+pub mod zsync;
+
+use cli::OutputFormat;
+use config::Config;
+use state::State;
+use std::env; // For environment variables
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard}; // For sharing state between update tasks
+use std::time::Duration;
+use throttle::Throttle;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+pub use error::Error;
+pub use http::HttpFetcher;
+pub use installer::{Installer, InstallerBuilder};
+pub use progress::{Event, Phase};
+pub use release::{Asset, Release, ReleaseSelector};
+
+/// How much output the installer is allowed to produce.
+///
+/// - `Normal`: informational messages on stdout, warnings and errors on stderr.
+/// - `Quiet`: nothing on stdout, warnings and errors still on stderr.
+/// - `Silent`: nothing at all; callers rely on the exit code only.
+///
+/// Messages go through `tracing`, filtered by `logging::init` to match; the level
+/// itself only governs progress bars, prompts and JSON output.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputLevel {
+    Normal,
+    Quiet,
+    Silent,
+}
+
+impl OutputLevel {
+    /// Whether informational output (stdout, progress bars) should be shown.
+    pub fn shows_info(self) -> bool {
+        self == OutputLevel::Normal
+    }
+}
+
+/// Settings shared by every subcommand.
+pub struct Context {
+    pub config: Config,
+    pub state_path: PathBuf,
+    pub install_dir: PathBuf,
+    pub bin_dir: PathBuf,
+    pub dir_mode: Option<u32>,
+    /// How often a request is retried after a transient network failure
+    pub retries: u32,
+    /// Longest wait for an API rate limit to reset before giving up; `None` fails at once
+    pub rate_limit_wait: Option<Duration>,
+    /// Cap on the combined rate of all downloads, if any
+    pub throttle: Option<Throttle>,
+    /// Longest time a single download may take, if limited
+    pub download_timeout: Option<Duration>,
+    /// Cap on simultaneous downloads, if any
+    pub download_slots: Option<Semaphore>,
+    /// Cap on simultaneous API requests, if any
+    pub api_slots: Option<Semaphore>,
+    pub level: OutputLevel,
+    pub output: OutputFormat,
+    /// Sends every HTTP request; `http::client` unless a program embedding rust-unicorn
+    /// supplies its own
+    pub http: Arc<dyn HttpFetcher>,
+    /// Token for the GitHub API; never sent to other hosts
+    pub github_token: Option<String>,
+    /// Serializes read-modify-write cycles of the state file between concurrent installs
+    state_lock: Mutex<()>,
+}
+
+impl Context {
+    /// Settings from `config`, with the default directories where it sets none and the
+    /// recorded settings of installed apps it doesn't configure.
+    ///
+    /// The GitHub token is `github_token` if given, else `$GITHUB_TOKEN`, else the
+    /// config's `github_token`.
+    pub fn new(
+        config: Config,
+        level: OutputLevel,
+        github_token: Option<String>,
+    ) -> Result<Context, Error> {
+        let state_path = state::default_path()
+            .ok_or("Cannot determine the state file location: HOME is not set")?;
+        Context::with_state_path(config, level, github_token, state_path)
+    }
+
+    /// Like `new`, with the state file at `state_path`.
+    pub(crate) fn with_state_path(
+        mut config: Config,
+        level: OutputLevel,
+        github_token: Option<String>,
+        state_path: PathBuf,
+    ) -> Result<Context, Error> {
+        let install_dir = match config.install_dir.clone() {
+            Some(dir) => dir,
+            None => paths::default_install_dir()
+                .ok_or("Cannot determine the install directory: HOME is not set")?,
+        };
+        // Installed paths are recorded in the state file, so they must not depend on the working directory
+        let install_dir = std::path::absolute(install_dir)?;
+        let bin_dir = match config.bin_dir.clone() {
+            Some(dir) => std::path::absolute(dir)?,
+            None => paths::default_bin_dir()
+                .ok_or("Cannot determine the bin directory: HOME is not set")?,
+        };
+        // Installed apps missing from the config file keep the settings recorded at
+        // install time. A broken state file is reported by the commands that use it.
+        if let Ok(state) = State::load(&state_path) {
+            for (name, installed) in state.apps {
+                if let Some(settings) = installed.settings {
+                    config.apps.entry(name).or_insert(settings);
+                }
+            }
+        }
+
+        // Create an HTTP client with a User-Agent header to satisfy GitHub's requirements.
+        // GitHub API requests are authenticated when a token is available to avoid the low
+        // anonymous rate limit.
+        let github_token = github_token
+            .or_else(|| env::var("GITHUB_TOKEN").ok().filter(|t| !t.is_empty()))
+            .or_else(|| config.github_token.clone())
+            .map(|token| token.trim().to_string());
+        if github_token
+            .as_ref()
+            .is_some_and(|token| reqwest::header::HeaderValue::from_str(token).is_err())
+        {
+            return Err("GitHub token contains invalid characters".into());
+        }
+        let http = Arc::new(http::client(&config)?);
+
+        let retries = config.retries.unwrap_or(retry::DEFAULT_RETRIES);
+        let throttle = match &config.limit_rate {
+            Some(rate) => {
+                Some(Throttle::new(cli::parse_rate(rate).map_err(|e| {
+                    format!("{} (limit_rate in the config file)", e)
+                })?))
+            }
+            None => None,
+        };
+        let download_timeout = http::download_timeout(&config)?;
+        let download_slots = slots(config.max_parallel_downloads, "max_parallel_downloads")?;
+        let api_slots = slots(
+            config.max_parallel_api_requests,
+            "max_parallel_api_requests",
+        )?;
+
+        Ok(Context {
+            config,
+            state_path,
+            install_dir,
+            bin_dir,
+            dir_mode: None,
+            retries,
+            rate_limit_wait: None,
+            throttle,
+            download_timeout,
+            download_slots,
+            api_slots,
+            level,
+            output: OutputFormat::Text,
+            http,
+            github_token,
+            state_lock: Mutex::new(()),
+        })
+    }
+
+    /// Print `value` as the command's JSON result. JSON is asked for explicitly, so
+    /// only `--silent` suppresses it.
+    pub fn print_json(&self, value: &impl serde::Serialize) -> Result<(), Error> {
+        if self.level != OutputLevel::Silent {
+            println!("{}", serde_json::to_string_pretty(value)?);
+        }
+        Ok(())
+    }
+
+    /// Apply `change` to the state file under the state lock, so concurrent installs
+    /// never overwrite each other's entries.
+    pub fn update_state(&self, change: impl FnOnce(&mut State)) -> Result<(), Error> {
+        let _guard = self.lock_state();
+        let mut state = State::load(&self.state_path)?;
+        change(&mut state);
+        state.save(&self.state_path, self.dir_mode)
+    }
+
+    /// Hold the state lock until the returned guard is dropped, for the files kept
+    /// beside the state file that are rewritten the same way.
+    pub(crate) fn lock_state(&self) -> MutexGuard<'_, ()> {
+        self.state_lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait until a download may start. It counts against `max_parallel_downloads`
+    /// until the returned permit is dropped.
+    pub async fn download_permit(&self) -> Option<SemaphorePermit<'_>> {
+        acquire(&self.download_slots).await
+    }
+
+    /// Wait until an API request may be sent, as `download_permit` does for downloads.
+    pub async fn api_permit(&self) -> Option<SemaphorePermit<'_>> {
+        acquire(&self.api_slots).await
+    }
+}
+
+/// A semaphore admitting `limit` holders at once, named `key` in the config file.
+fn slots(limit: Option<u16>, key: &str) -> Result<Option<Semaphore>, Error> {
+    match limit {
+        Some(0) => Err(format!("{} in the config file must be at least 1", key).into()),
+        Some(limit) => Ok(Some(Semaphore::new(limit as usize))),
+        None => Ok(None),
+    }
+}
+
+/// A permit from `slots`, once one is free; `None` when there is no limit.
+async fn acquire(slots: &Option<Semaphore>) -> Option<SemaphorePermit<'_>> {
+    // The semaphores are never closed, so acquiring only fails if there is none
+    slots.as_ref()?.acquire().await.ok()
+}
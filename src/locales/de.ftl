@@ -0,0 +1,357 @@
+# Meldungen von rust-unicorn auf Deutsch. Fehlende Meldungen erscheinen auf Englisch.
+
+## Präfixe
+
+error-prefix = Fehler:
+warning-prefix = Warnung:
+
+## Installieren und Aktualisieren
+
+found-release = { $app } { $version } gefunden ({ $asset })
+found-prerelease = { $app } { $version } gefunden (Vorabversion) ({ $asset })
+installing-file = { $file } wird als { $app } { $version } installiert
+downloading-to = Herunterladen nach { $path }...
+downloaded-only = { $app } { $version } in den Cache heruntergeladen, ohne es zu installieren
+download-source = Quelle: { $url }
+download-served-by = Ausgeliefert von: { $host } ({ $url })
+downloaded = Heruntergeladen und ausführbar gemacht: { $path }
+copied = Kopiert und ausführbar gemacht: { $path }
+unpacked = { $program } aus { $archive } entpackt
+extracting-without-fuse = FUSE ist nicht verfügbar, daher wird { $app } entpackt, um ohne FUSE zu laufen
+delta-failed = Delta-Aktualisierung fehlgeschlagen ({ $error }); das ganze AppImage wird heruntergeladen
+icon-failed = Symbol von { $app } konnte nicht entpackt werden: { $error }
+metainfo-failed = AppStream-Metadaten von { $app } konnten nicht geschrieben werden: { $error }
+metainfo-summary = AppImage aus { $repo }, installiert von rust-unicorn
+install-success = { $action ->
+    [updated] { $app } { $version } wurde erfolgreich aktualisiert!
+   *[installed] { $app } { $version } wurde erfolgreich installiert!
+}
+run-by-name = Starte es im Terminal mit '{ $app }'.
+already-installed = { $app } { $version } ist bereits unter { $path } installiert
+use-force = Mit --force wird neu installiert oder aktualisiert.
+install-failed = { $app } konnte nicht installiert werden: { $error }
+update-failed = { $app } konnte nicht aktualisiert werden: { $error }
+skipping-pinned = { $app } wird übersprungen: auf { $version } festgehalten
+created-directory = Verzeichnis angelegt: { $path } (Modus { $mode })
+stage-leftover = Entferne, was eine abgebrochene Installation von { $app } hinterlassen hat
+app-started = { $app } gestartet (PID { $pid })
+app-notes = Notizen zu { $app }:
+app-start-failed = { $app } kann nicht gestartet werden: { $error }
+waiting-for-assets = Release { $version } hat noch { $method ->
+    [archive] kein hochgeladenes Archiv
+   *[other] kein hochgeladenes AppImage
+}, warte...
+assets-upload-timeout = Zeitüberschreitung beim Warten, bis { $kind ->
+    [asset] das Asset
+    [archive] das Archiv
+   *[other] das AppImage
+} von Release { $version } fertig hochgeladen ist
+assets-not-uploaded = Release { $version } hat noch { $kind ->
+    [asset] kein hochgeladenes Asset
+    [archive] kein hochgeladenes Archiv
+   *[other] kein hochgeladenes AppImage
+}; es wurde wohl gerade erst veröffentlicht. In ein paar Minuten erneut versuchen oder --wait-for-assets <Dauer> verwenden.
+choose-asset-title = In Release { $version } passen mehrere { $method ->
+    [archive] Archive
+   *[other] AppImages
+}:
+choose-asset-prompt = { $method ->
+    [archive] Archiv
+   *[other] AppImage
+} wählen [1-{ $count }]:
+choose-asset-invalid = Bitte eine Zahl zwischen 1 und { $count } eingeben
+choose-asset-ambiguous = In Release { $version } passen mehrere { $method ->
+    [archive] Archive
+   *[other] AppImages
+}: { $assets }. Mit --asset-filter eingrenzen oder mit --non-interactive das erste nehmen
+preference-changed = ein anderes prefer-Muster von { $app } wählt jetzt das Asset ({ $change })
+preference-changed-method = ein anderes prefer-Muster von { $app } wählt jetzt das Asset ({ $change }), daher wird es anders installiert: { $method }
+version-mismatch = { $asset } sieht nach Version { $version } aus, obwohl das Release { $tag } ist; vielleicht ist es ein veralteter Build
+version-mismatch-error = Asset { $asset } sieht nach Version { $version } aus, obwohl das Release { $tag } ist
+extracting = Entpacke { $appimage } nach { $path }...
+segmented-download-failed = segmentierter Download fehlgeschlagen ({ $error }); lade in einem Stück herunter
+delta-update = Delta-Update: { $reused } der installierten Version werden wiederverwendet, { $needed } von { $total } heruntergeladen
+download-status = { $label }: { $downloaded } von { $total } ({ $percent } %)
+retrying = { $error }; neuer Versuch in { $delay } s ({ $retry }/{ $retries })
+rate-limited-countdown = Von { $host } gedrosselt; neuer Versuch in { $time }
+waiting-for-lock = Warte, bis ein anderer rust-unicorn-Prozess{ $holder } fertig ist...
+verified-checksum = { $algorithm } gegen { $source } geprüft
+checksum-asset-digest = den Digest des Release-Assets
+checksum-release-notes = die Versionshinweise
+verified-signature = Signatur { $signature } von { $signer } geprüft
+no-checksum = Für { $asset } ist keine Prüfsummendatei veröffentlicht; Prüfung übersprungen
+hook-failed = post_install-Befehl von { $app } fehlgeschlagen ({ $status })
+hook-not-run = post_install-Befehl von { $app } kann nicht ausgeführt werden: { $error }
+history-write-failed = in die Verlaufsdatei { $path } kann nicht geschrieben werden: { $error }
+
+## Benachrichtigungen
+
+notify-updated = { $app } aktualisiert: { $old } → { $new }
+notify-failed = Aktualisierung von { $app } fehlgeschlagen: { $error }
+notify-apps-updated = { $count ->
+    [one] 1 App aktualisiert
+   *[other] { $count } Apps aktualisiert
+}
+notify-updates-failed = { $count ->
+    [one] 1 Aktualisierung fehlgeschlagen
+   *[other] { $count } Aktualisierungen fehlgeschlagen
+}
+notify-updated-and-failed = { $updated ->
+    [one] 1 App aktualisiert
+   *[other] { $updated } Apps aktualisiert
+}, { $failed } fehlgeschlagen
+notify-updates-available = { $count ->
+    [one] 1 Update verfügbar
+   *[other] { $count } Updates verfügbar
+}
+notify-available-hint = Mit `rust-unicorn update --all` installieren.
+notify-reminder = { $app } auf { $version } aktualisiert
+
+## Probeläufe
+
+already-installed-dry-run = { $app } { $version } ist bereits unter { $path } installiert; nichts herunterzuladen
+would-install = Würde { $app } { $version } installieren
+would-update = Würde { $app } { $version } aktualisieren
+would-install-prerelease = Würde { $app } { $version } (Vorabversion) installieren
+would-update-prerelease = Würde { $app } { $version } (Vorabversion) aktualisieren
+preview-asset = {"  "}Datei: { $asset } ({ $size })
+preview-source = {"  "}Quelle: { $url }
+preview-appimage = {"  "}AppImage: { $path }
+preview-extracted = {"  "}Entpackt nach: { $path }
+preview-sandbox = {"  "}Sandbox: { $tool } über { $wrapper }
+preview-symlink = {"  "}Symlink: { $symlink } -> { $target }
+preview-desktop-entry = {"  "}Desktop-Eintrag: { $path }
+
+## Laufende Instanzen
+
+app-running-question = { $app } läuft ({ $instances }). Vor der Aktualisierung beenden?
+app-running = { $app } läuft ({ $instances }); nach der Aktualisierung neu starten oder mit --stop-running vorher beenden
+stopping-app = { $app } wird beendet ({ $instances })...
+confirm-choices = [j/N]
+confirm-yes = j ja y yes
+
+## Weitere Befehle
+
+no-apps = Keine Apps installiert
+list-pinned = (festgehalten)
+rolled-back = { $app } zurückgesetzt: { $change } ({ $path })
+removed = { $app } { $version } entfernt ({ $path })
+removed-old-version = Alte Version entfernt: { $path }
+pinned = { $app } auf { $version } festgehalten
+unpinned = { $app } nicht mehr festgehalten (auf { $version })
+check-pinned = { $app } ({ $repo }): auf { $version } festgehalten
+check-up-to-date = aktuell ({ $version })
+check-update-available = Aktualisierung verfügbar: { $change } ({ $asset })
+check-not-installed = nicht installiert (neueste: { $version })
+notes-title = Versionshinweise zu { $app } { $version }:
+notes-none = { $app } { $version } hat keine Versionshinweise
+export-skipping = { $app } wird übersprungen: seine Einstellungen sind unbekannt; trage es in die Konfigurationsdatei ein oder installiere es neu
+
+## info
+
+info-version = Version: { $version }
+info-asset = Datei: { $asset }
+info-asset-version = Datei: { $asset } (scheint Version { $version } zu sein)
+info-path = Pfad: { $path }
+info-method = Installiert als: { $method ->
+    [archive] Programm aus einem Archiv
+   *[other] AppImage
+}
+info-source = Quelle: { $url }
+info-served-by = Ausgeliefert von: { $host }
+info-verification = Geprüft: { $method }
+info-installed-at = Installiert: { $time }
+info-pinned = Auf dieser Version festgehalten
+info-notes = Notizen:
+
+## PATH
+
+not-on-path = { $dir } ist nicht im PATH, daher lassen sich Apps nicht über ihren Namen starten; füge es hinzu oder starte erneut mit --fix-path
+added-to-path = { $dir } wurde in { $file } zum PATH hinzugefügt; das gilt für neue Terminals (oder `. { $file }` ausführen)
+already-on-path = { $file } fügt { $dir } bereits zum PATH hinzu; das gilt für neue Terminals
+add-to-path-failed = { $dir } konnte nicht zum PATH hinzugefügt werden: { $error }
+
+## cleanup
+
+nothing-to-clean = Nichts aufzuräumen
+cleanup-removed = Entfernt: { $path } ({ $app }, { $size })
+cleanup-would-remove = Würde entfernen: { $path } ({ $app }, { $size })
+cleanup-reclaimed = { $size } aus { $count ->
+    [one] einer Datei
+   *[other] { $count } Dateien
+} freigegeben
+cleanup-would-reclaim = Würde { $size } aus { $count ->
+    [one] einer Datei
+   *[other] { $count } Dateien
+} freigeben
+
+## disk-usage
+
+usage-app = APP
+usage-appimage = APPIMAGE
+usage-old-versions = ALTE VERSIONEN
+usage-extracted = ENTPACKT
+usage-icon = SYMBOL
+usage-total = GESAMT
+usage-cache = Cache: { $size }
+usage-grand-total = Gesamt: { $size }
+
+## adopt
+
+adopted = { $file } als { $app } { $version } übernommen ({ $repo })
+would-adopt = Würde { $file } als { $app } { $version } übernehmen ({ $repo })
+adopt-repo-question = Repository von { $file } (OWNER/REPO, leer zum Überspringen):
+adopt-skipping = { $file } wird übersprungen: { $error }
+adopted-count = { $adopted } von { $total } AppImages übernommen
+would-adopt-count = Würde { $adopted } von { $total } AppImages übernehmen
+
+## cache
+
+using-cached-download = Zwischengespeicherter Download von { $asset } wird verwendet
+served-from-cache-revalidated = { $asset } aus dem Cache geliefert (revalidiert): auf dem Server unverändert, nichts heruntergeladen
+cache-downloads = Downloads: { $count ->
+    [one] eine Datei
+   *[other] { $count } Dateien
+}, { $size }
+cache-api-responses = API-Antworten: { $count ->
+    [one] eine Datei
+   *[other] { $count } Dateien
+}, { $size }
+cache-location = Ort: { $path }
+cache-cleaned = Cache geleert, { $size } freigegeben
+
+## doctor
+
+appimage-missing = { $path } fehlt
+appimage-changed = { $path } stimmt nicht mit der bei der Installation erfassten SHA-256 überein
+appimage-unreadable = { $path } kann nicht gelesen werden: { $error }
+not-executable = { $path } ist nicht ausführbar
+symlink-missing = Symlink { $path } fehlt
+symlink-wrong = Symlink { $path } zeigt auf { $target } statt auf { $expected }
+symlink-not-link = { $path } ist kein Symlink
+desktop-entry-missing = Desktop-Eintrag { $path } fehlt
+icon-missing = Symbol { $path } fehlt
+metainfo-missing = AppStream-Metadaten { $path } fehlen
+wrapper-missing = Sandbox-Wrapper { $path } fehlt
+app-dir-missing = Entpackte Dateien in { $path } fehlen
+needs-fuse = benötigt FUSE, das nicht verfügbar ist
+fuse-missing = FUSE ist nicht verfügbar, daher können sich AppImages nicht selbst einhängen und laufen entpackt; die Installation von libfuse2 behebt das
+fix-make-executable = ausführbar machen
+fix-reinstall = { $app } { $version } neu installieren
+fix-reintegrate = Symlink, Desktop-Eintrag, Symbol, Metadaten und entpackte Dateien der App neu anlegen
+fix-extract = entpacken, um ohne FUSE zu laufen
+fix-move-away = { $path } beiseite schaffen und dann `doctor --fix` ausführen
+doctor-suggestion = {"  "}Behebung: { $fix }
+doctor-fixed = {"  "}Behoben: { $fix }
+doctor-fix-failed = { $app } konnte nicht repariert werden: { $error }
+doctor-healthy = Keine Probleme gefunden
+doctor-fixed-count = { $fixed } von { $total } Problemen behoben
+
+## verify
+
+verify-ok = { $app } { $version }: unverändert
+verify-ok-published = { $app } { $version }: unverändert, stimmt mit { $sources } überein
+verify-missing = { $app } { $version }: { $path } fehlt
+verify-unreadable = { $app } { $version }: { $path } kann nicht gelesen werden: { $error }
+verify-modified = { $app } { $version }: { $path } wurde seit der Installation verändert ({ $error })
+verify-mismatch = { $app } { $version }: { $path } stimmt nicht mit seiner Version überein: { $error }
+verify-release-failed = { $app } kann nicht mit seiner Version verglichen werden: { $error }
+
+## daemon
+
+daemon-started = Apps werden alle { $interval } aktualisiert
+daemon-started-checking = Aktualisierungen werden alle { $interval } gesucht
+daemon-next-run = Nächster Lauf um { $time }
+daemon-run-failed = Aktualisierungslauf fehlgeschlagen: { $error }
+daemon-update-requested = Aktualisierung über D-Bus angefordert
+daemon-stopped = Beendet
+dbus-unavailable = D-Bus-Schnittstelle kann nicht angeboten werden: { $error }
+
+## ui
+
+ui-installed = INSTALLIERT
+ui-latest = NEUESTE
+ui-status = STATUS
+ui-checking = wird geprüft...
+ui-check-failed = Prüfung fehlgeschlagen
+ui-update-available = Aktualisierung verfügbar
+ui-queued = wartet
+ui-verifying = wird geprüft...
+ui-integrating = wird installiert...
+ui-updated = auf { $version } aktualisiert
+ui-notes-title = { $app } { $version }
+ui-no-notes = Diese Version hat keine Versionshinweise.
+ui-help = ↑↓ bewegen · Leertaste auswählen · a alle · u aktualisieren · r entfernen · p festhalten/lösen · c prüfen · Bild↑/Bild↓ Hinweise · q beenden
+ui-busy = Aktualisierungen laufen; nach ihrem Ende beenden
+ui-remove-question = { $apps } entfernen?
+
+## self-update
+
+self-up-to-date = rust-unicorn { $version } ist die neueste Version
+self-updating = Aktualisiere rust-unicorn { $old_version } auf { $new_version } ({ $asset })
+self-updated = rust-unicorn wurde auf { $version } aktualisiert
+
+## schedule
+
+schedule-enabled = Geplante Updates ({ $schedule }) mit { $timer }
+schedule-disabled = Geplante Updates deaktiviert
+schedule-none = Updates sind nicht geplant
+schedule-none-hint = Updates sind nicht geplant; aktiviere sie mit `rust-unicorn schedule enable`
+schedule-status = Updates sind geplant: { $schedule } ({ $timer })
+schedule-timer = Timer: { $state }
+schedule-last-run = Letzter Lauf: { $time }
+schedule-next-run = Nächster Lauf: { $time }
+schedule-unknown = unbekannt
+schedule-never = nie
+
+## search
+
+search-github-failed = GitHub kann nicht durchsucht werden: { $error }
+search-none = Keine AppImage-Projekte für '{ $term }' gefunden
+search-from-catalog = (Katalog)
+search-install = rust-unicorn install { $spec }
+
+## config
+
+default-set = { $key } = { $value } in { $file } gesetzt
+default-set-app = { $key } = { $value } für { $app } in { $file } gesetzt
+default-unset-app = { $key } für { $app } aus { $file } entfernt
+default-source = { $source ->
+    [built-in] eingebaut
+    [config] Konfigurationsdatei
+    [installed] bei der Installation gespeichert
+   *[other] { $source }
+}
+default-unlimited = unbegrenzt
+default-none = keiner
+default-set-secret = (gesetzt)
+default-notify = wenn nicht im Terminal gestartet
+
+## last-known
+
+using-last-known = Die Release-Metadaten von { $app } konnten nicht abgerufen werden ({ $error }); { $version } wird nach Metadaten installiert, die vor { $age } zuletzt gesehen wurden und veraltet sein können
+last-known-none = seit dem letzten Update von { $app } wurde kein neueres Release gesehen, daher ist --use-last-known nicht möglich
+last-known-other-tag = das zuletzt bekannte Release ist { $version }, nicht das verlangte, daher ist --use-last-known nicht möglich
+last-known-filtered = das zuletzt bekannte Asset { $asset } passt nicht zum Asset-Filter, daher ist --use-last-known nicht möglich
+last-known-unverified = für das zuletzt bekannte Asset { $asset } ist keine SHA-256 zum Prüfen bekannt, daher ist --use-last-known nicht möglich
+age-minutes = { $count ->
+    [one] 1 Minute
+   *[other] { $count } Minuten
+}
+age-hours = { $count } Stunden
+age-days = { $count } Tagen
+
+## legacy
+legacy-found = In { $dir } liegt ein von einer älteren rust-unicorn-Version installiertes Joplin.
+legacy-found-hint = In { $dir } liegt ein von einer älteren rust-unicorn-Version installiertes Joplin; mit --migrate-legacy (oder --migrate-legacy=move) übernehmen
+legacy-migrate-question = In der Statusdatei erfassen, aus dem bin-Verzeichnis verlinken und einen Konfigurationseintrag anlegen?
+legacy-move-question = Die AppImages außerdem nach { $target } verschieben?
+legacy-declined = { $dir } bleibt unverändert und weiterhin das Installationsverzeichnis. Mit --migrate-legacy lässt es sich später migrieren
+legacy-migrating = Migriere { $dir }...
+legacy-moved = { $file } nach { $path } verschoben
+legacy-not-moved = { $file } wird nicht verschoben: { $path } existiert bereits
+legacy-symlink-replaced = Relativen Symlink { $symlink } durch { $path } ersetzt
+legacy-symlink-removed = Relativen Symlink { $symlink } entfernt
+legacy-dir-removed = Leeres Verzeichnis { $dir } entfernt
+legacy-config-entry = Konfigurationseintrag für { $app } in { $file } angelegt
+legacy-migrated = Migration abgeschlossen
@@ -0,0 +1,360 @@
+# Messages of rust-unicorn, in English. Every other locale falls back to these.
+# Arguments are written `{ $name }`; see https://projectfluent.org for the syntax.
+
+## Message prefixes
+
+error-prefix = Error:
+warning-prefix = Warning:
+
+## Installing and updating
+
+found-release = Found { $app } { $version } ({ $asset })
+found-prerelease = Found { $app } { $version } (prerelease) ({ $asset })
+installing-file = Installing { $file } as { $app } { $version }
+downloading-to = Downloading to { $path }...
+downloaded-only = Downloaded { $app } { $version } into the cache without installing it
+download-source = Source: { $url }
+download-served-by = Served by: { $host } ({ $url })
+downloaded = Downloaded and made executable: { $path }
+copied = Copied and made executable: { $path }
+unpacked = Unpacked { $program } from { $archive }
+extracting-without-fuse = FUSE is not available, so { $app } is extracted to run without it
+delta-failed = delta update failed ({ $error }); downloading the whole AppImage
+icon-failed = could not extract the icon of { $app }: { $error }
+metainfo-failed = could not write the AppStream metainfo of { $app }: { $error }
+# Summary in software centres of apps whose AppImage has no metainfo
+metainfo-summary = AppImage from { $repo }, installed by rust-unicorn
+install-success = { $action ->
+    [updated] { $app } { $version } has been successfully updated!
+   *[installed] { $app } { $version } has been successfully installed!
+}
+run-by-name = You can run it by typing '{ $app }' in your terminal.
+already-installed = { $app } { $version } is already installed at { $path }
+use-force = Use --force to reinstall or update.
+install-failed = failed to install { $app }: { $error }
+update-failed = failed to update { $app }: { $error }
+skipping-pinned = Skipping { $app }: pinned at { $version }
+created-directory = Created directory: { $path } (mode { $mode })
+stage-leftover = Removing what an interrupted install of { $app } left behind
+app-started = Started { $app } (PID { $pid })
+app-notes = Notes for { $app }:
+app-start-failed = cannot start { $app }: { $error }
+waiting-for-assets = Release { $version } has no uploaded { $method ->
+    [archive] archive
+   *[other] AppImage
+} yet, waiting...
+assets-upload-timeout = Timed out waiting for the { $kind ->
+    [asset] asset
+    [archive] archive
+   *[other] AppImage
+} of release { $version } to finish uploading
+assets-not-uploaded = No uploaded { $kind ->
+    [asset] asset
+    [archive] archive
+   *[other] AppImage
+} in release { $version } yet; it looks freshly published. Retry in a few minutes or use --wait-for-assets <duration>.
+choose-asset-title = Several { $method ->
+    [archive] archives
+   *[other] AppImages
+} match in release { $version }:
+choose-asset-prompt = Choose { $method ->
+    [archive] an archive
+   *[other] an AppImage
+} [1-{ $count }]:
+choose-asset-invalid = Please enter a number between 1 and { $count }
+choose-asset-ambiguous = Several { $method ->
+    [archive] archives
+   *[other] AppImages
+} match in release { $version }: { $assets }. Narrow it down with --asset-filter, or pass --non-interactive to take the first
+preference-changed = a different prefer pattern of { $app } now picks its asset ({ $change })
+preference-changed-method = a different prefer pattern of { $app } now picks its asset ({ $change }), so it will be installed another way: { $method }
+version-mismatch = { $asset } looks like version { $version } although the release is { $tag }; it may be a stale build
+version-mismatch-error = Asset { $asset } looks like version { $version } although the release is { $tag }
+extracting = Extracting { $appimage } to { $path }...
+segmented-download-failed = segmented download failed ({ $error }); downloading in one stream
+delta-update = Delta update: reusing { $reused } from the installed version, downloading { $needed } of { $total }
+download-status = { $label }: { $downloaded } of { $total } ({ $percent }%)
+retrying = { $error }; retrying in { $delay }s ({ $retry }/{ $retries })
+rate-limited-countdown = Rate limited by { $host }; retrying in { $time }
+waiting-for-lock = Waiting for another rust-unicorn process{ $holder } to finish...
+verified-checksum = Verified { $algorithm } against { $source }
+checksum-asset-digest = the release's asset digest
+checksum-release-notes = the release notes
+verified-signature = Verified signature { $signature } by { $signer }
+no-checksum = No checksum file published for { $asset }; skipping verification
+hook-failed = post_install command of { $app } failed ({ $status })
+hook-not-run = cannot run post_install command of { $app }: { $error }
+history-write-failed = could not write to the history file { $path }: { $error }
+
+## Notifications
+
+notify-updated = { $app } updated { $old } → { $new }
+notify-failed = { $app } failed to update: { $error }
+notify-apps-updated = { $count ->
+    [one] 1 app updated
+   *[other] { $count } apps updated
+}
+notify-updates-failed = { $count ->
+    [one] 1 update failed
+   *[other] { $count } updates failed
+}
+notify-updated-and-failed = { $updated ->
+    [one] 1 app updated
+   *[other] { $updated } apps updated
+}, { $failed } failed
+notify-updates-available = { $count ->
+    [one] 1 update available
+   *[other] { $count } updates available
+}
+notify-available-hint = Run `rust-unicorn update --all` to install them.
+notify-reminder = { $app } updated to { $version }
+
+## Dry runs
+
+already-installed-dry-run = { $app } { $version } is already installed at { $path }; nothing to download
+would-install = Would install { $app } { $version }
+would-update = Would update { $app } { $version }
+would-install-prerelease = Would install { $app } { $version } (prerelease)
+would-update-prerelease = Would update { $app } { $version } (prerelease)
+preview-asset = {"  "}Asset: { $asset } ({ $size })
+preview-source = {"  "}Source: { $url }
+preview-appimage = {"  "}AppImage: { $path }
+preview-extracted = {"  "}Extracted to: { $path }
+preview-sandbox = {"  "}Sandbox: { $tool } through { $wrapper }
+preview-symlink = {"  "}Symlink: { $symlink } -> { $target }
+preview-desktop-entry = {"  "}Desktop entry: { $path }
+
+## Running instances
+
+app-running-question = { $app } is running ({ $instances }). Stop it before updating?
+app-running = { $app } is running ({ $instances }); restart it after the update, or pass --stop-running to stop it first
+stopping-app = Stopping { $app } ({ $instances })...
+# Shown after a yes/no question; the first accepted answers are listed in confirm-yes
+confirm-choices = [y/N]
+confirm-yes = y yes
+
+## Other commands
+
+no-apps = No apps installed
+list-pinned = (pinned)
+rolled-back = Rolled { $app } back: { $change } ({ $path })
+removed = Removed { $app } { $version } ({ $path })
+removed-old-version = Removed old version { $path }
+pinned = Pinned { $app } at { $version }
+unpinned = Unpinned { $app } (at { $version })
+check-pinned = { $app } ({ $repo }): pinned at { $version }
+check-up-to-date = up to date ({ $version })
+check-update-available = update available: { $change } ({ $asset })
+check-not-installed = not installed (latest: { $version })
+notes-title = Release notes for { $app } { $version }:
+notes-none = { $app } { $version } has no release notes
+export-skipping = skipping { $app }: its settings are unknown; add it to the config file or reinstall it
+
+## info
+
+info-version = Version: { $version }
+info-asset = Asset: { $asset }
+info-asset-version = Asset: { $asset } (looks like version { $version })
+info-path = Path: { $path }
+info-method = Installed as: { $method ->
+    [archive] program from an archive
+   *[other] AppImage
+}
+info-source = Source: { $url }
+info-served-by = Served by: { $host }
+info-verification = Verified: { $method }
+info-installed-at = Installed: { $time }
+info-pinned = Pinned at this version
+info-notes = Notes:
+
+## PATH
+
+not-on-path = { $dir } is not on your PATH, so apps can't be started by name; add it or run again with --fix-path
+added-to-path = Added { $dir } to PATH in { $file }; it applies to new terminals (or run `. { $file }`)
+already-on-path = { $file } already adds { $dir } to PATH; it applies to new terminals
+add-to-path-failed = could not add { $dir } to PATH: { $error }
+
+## cleanup
+
+nothing-to-clean = Nothing to clean up
+cleanup-removed = Removed { $path } ({ $app }, { $size })
+cleanup-would-remove = Would remove { $path } ({ $app }, { $size })
+cleanup-reclaimed = Reclaimed { $size } from { $count ->
+    [one] one file
+   *[other] { $count } files
+}
+cleanup-would-reclaim = Would reclaim { $size } from { $count ->
+    [one] one file
+   *[other] { $count } files
+}
+
+## disk-usage
+
+usage-app = APP
+usage-appimage = APPIMAGE
+usage-old-versions = OLD VERSIONS
+usage-extracted = EXTRACTED
+usage-icon = ICON
+usage-total = TOTAL
+usage-cache = Cache: { $size }
+usage-grand-total = Total: { $size }
+
+## adopt
+
+adopted = Adopted { $file } as { $app } { $version } ({ $repo })
+would-adopt = Would adopt { $file } as { $app } { $version } ({ $repo })
+adopt-repo-question = Repository of { $file } (OWNER/REPO, empty to skip):
+adopt-skipping = skipping { $file }: { $error }
+adopted-count = Adopted { $adopted } of { $total } AppImages
+would-adopt-count = Would adopt { $adopted } of { $total } AppImages
+
+## cache
+
+using-cached-download = Using the cached download of { $asset }
+served-from-cache-revalidated = { $asset } served from cache (revalidated): unchanged on the server, nothing downloaded
+cache-downloads = Downloads: { $count ->
+    [one] one file
+   *[other] { $count } files
+}, { $size }
+cache-api-responses = API responses: { $count ->
+    [one] one file
+   *[other] { $count } files
+}, { $size }
+cache-location = Location: { $path }
+cache-cleaned = Cleaned the cache, reclaiming { $size }
+
+## doctor
+
+appimage-missing = { $path } is missing
+appimage-changed = { $path } doesn't match the SHA-256 recorded when it was installed
+appimage-unreadable = cannot read { $path }: { $error }
+not-executable = { $path } is not executable
+symlink-missing = symlink { $path } is missing
+symlink-wrong = symlink { $path } points at { $target } instead of { $expected }
+symlink-not-link = { $path } is not a symlink
+desktop-entry-missing = desktop entry { $path } is missing
+icon-missing = icon { $path } is missing
+metainfo-missing = AppStream metainfo { $path } is missing
+wrapper-missing = sandbox wrapper { $path } is missing
+app-dir-missing = extracted files in { $path } are missing
+needs-fuse = needs FUSE to run, which isn't available
+fuse-missing = FUSE is not available, so AppImages can't mount themselves and run extracted instead; installing libfuse2 fixes this
+fix-make-executable = make it executable
+fix-reinstall = reinstall { $app } { $version }
+fix-reintegrate = recreate the app's symlink, desktop entry, icon, metainfo and extracted files
+fix-extract = extract it to run without FUSE
+fix-move-away = move { $path } out of the way, then run `doctor --fix`
+doctor-suggestion = {"  "}Fix: { $fix }
+doctor-fixed = {"  "}Fixed: { $fix }
+doctor-fix-failed = could not fix { $app }: { $error }
+doctor-healthy = No problems found
+doctor-fixed-count = Fixed { $fixed } of { $total } problems
+
+## verify
+
+verify-ok = { $app } { $version }: intact
+verify-ok-published = { $app } { $version }: intact, matching { $sources }
+verify-missing = { $app } { $version }: { $path } is missing
+verify-unreadable = { $app } { $version }: cannot read { $path }: { $error }
+verify-modified = { $app } { $version }: { $path } has changed since it was installed ({ $error })
+verify-mismatch = { $app } { $version }: { $path } doesn't match its release: { $error }
+verify-release-failed = cannot check { $app } against its release: { $error }
+
+## daemon
+
+daemon-started = Updating apps every { $interval }
+daemon-started-checking = Checking for updates every { $interval }
+daemon-next-run = Next run at { $time }
+daemon-run-failed = update run failed: { $error }
+daemon-update-requested = Update requested over D-Bus
+daemon-stopped = Stopped
+dbus-unavailable = cannot offer the D-Bus interface: { $error }
+
+## ui
+
+ui-installed = INSTALLED
+ui-latest = LATEST
+ui-status = STATUS
+ui-checking = checking...
+ui-check-failed = check failed
+ui-update-available = update available
+ui-queued = queued
+ui-verifying = verifying...
+ui-integrating = installing...
+ui-updated = updated to { $version }
+ui-notes-title = { $app } { $version }
+ui-no-notes = This release has no notes.
+ui-help = ↑↓ move · space select · a all · u update · r remove · p pin/unpin · c check · PgUp/PgDn notes · q quit
+ui-busy = Updates are running; quit when they are done
+ui-remove-question = Remove { $apps }?
+
+## self-update
+
+self-up-to-date = rust-unicorn { $version } is the latest version
+self-updating = Updating rust-unicorn { $old_version } to { $new_version } ({ $asset })
+self-updated = rust-unicorn has been updated to { $version }
+
+## schedule
+
+schedule-enabled = Scheduled updates ({ $schedule }) with { $timer }
+schedule-disabled = Scheduled updates disabled
+schedule-none = Updates are not scheduled
+schedule-none-hint = Updates are not scheduled; enable them with `rust-unicorn schedule enable`
+schedule-status = Updates are scheduled: { $schedule } ({ $timer })
+schedule-timer = Timer: { $state }
+schedule-last-run = Last run: { $time }
+schedule-next-run = Next run: { $time }
+schedule-unknown = unknown
+schedule-never = never
+
+## search
+
+search-github-failed = cannot search GitHub: { $error }
+search-none = No AppImage projects found for '{ $term }'
+search-from-catalog = (catalog)
+search-install = rust-unicorn install { $spec }
+
+## config
+
+default-set = Set { $key } = { $value } in { $file }
+default-set-app = Set { $key } = { $value } for { $app } in { $file }
+default-unset-app = Removed { $key } for { $app } from { $file }
+default-source = { $source ->
+    [built-in] built in
+    [config] config file
+    [installed] recorded at install
+   *[other] { $source }
+}
+default-unlimited = unlimited
+default-none = none
+default-set-secret = (set)
+default-notify = when not run from a terminal
+
+## last-known
+
+using-last-known = The release metadata of { $app } could not be fetched ({ $error }); installing { $version } from metadata last seen { $age } ago, which may be out of date
+last-known-none = no newer release of { $app } has been seen since its last update, so --use-last-known can't be used
+last-known-other-tag = the last known release is { $version }, not the one asked for, so --use-last-known can't be used
+last-known-filtered = the last known asset { $asset } doesn't match the asset filter, so --use-last-known can't be used
+last-known-unverified = no SHA-256 of the last known asset { $asset } is known to verify it against, so --use-last-known can't be used
+age-minutes = { $count ->
+    [one] 1 minute
+   *[other] { $count } minutes
+}
+age-hours = { $count } hours
+age-days = { $count } days
+
+## legacy
+legacy-found = Found Joplin installed by an older rust-unicorn in { $dir }.
+legacy-found-hint = Found Joplin installed by an older rust-unicorn in { $dir }; run with --migrate-legacy (or --migrate-legacy=move) to take it over
+legacy-migrate-question = Record it in the state file, link it from the bin directory and add a config entry for it?
+legacy-move-question = Also move its AppImages to { $target }?
+legacy-declined = Left { $dir } as it is; it stays the install directory. Run with --migrate-legacy to migrate it later
+legacy-migrating = Migrating { $dir }...
+legacy-moved = Moved { $file } to { $path }
+legacy-not-moved = not moving { $file }: { $path } already exists
+legacy-symlink-replaced = Replaced the relative symlink { $symlink } with { $path }
+legacy-symlink-removed = Removed the relative symlink { $symlink }
+legacy-dir-removed = Removed the empty directory { $dir }
+legacy-config-entry = Added a config entry for { $app } to { $file }
+legacy-migrated = Migration complete
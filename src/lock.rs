@@ -0,0 +1,87 @@
+// Advisory lock keeping two rust-unicorn processes (say, the update timer and a
+// manual install) from changing the same install directory at once.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::tr;
+use crate::Error;
+
+/// How often a waiting process tries the lock again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Exclusive lock on an install directory, held until dropped.
+pub struct InstallLock {
+    // Closing the file releases the lock
+    _file: File,
+}
+
+/// Lock file guarding `install_dir`.
+pub fn path(install_dir: &Path) -> PathBuf {
+    install_dir.join(".rust-unicorn.lock")
+}
+
+/// Lock `install_dir`, which must exist. When another process holds the lock, wait
+/// for it with `wait`, otherwise fail right away.
+pub async fn acquire(install_dir: &Path, wait: bool) -> Result<InstallLock, Error> {
+    let path = path(install_dir);
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| format!("Cannot open the lock file {}: {}", path.display(), e))?;
+
+    let mut announced = false;
+    while !try_lock(&file)? {
+        let holder = holder(&mut file);
+        if !wait {
+            return Err(format!(
+                "Another rust-unicorn process{} is changing {}; try again when it is done, or pass --wait-for-lock",
+                holder,
+                install_dir.display()
+            )
+            .into());
+        }
+        if !announced {
+            info!("{}", tr!("waiting-for-lock", holder = holder.as_str()));
+            announced = true;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    // Leave our pid for the error message of whoever comes next
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    debug!("Locked {}", path.display());
+    Ok(InstallLock { _file: file })
+}
+
+/// Try to take the lock without blocking; false when someone else has it.
+fn try_lock(file: &File) -> io::Result<bool> {
+    // SAFETY: flock only operates on the descriptor, which `file` keeps open
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let error = io::Error::last_os_error();
+    if error.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(error)
+    }
+}
+
+/// ` (pid N)` for the process holding the lock, when it left its pid.
+fn holder(file: &mut File) -> String {
+    let mut pid = String::new();
+    let _ = file.rewind().and_then(|_| file.read_to_string(&mut pid));
+    match pid.trim().parse::<u32>() {
+        Ok(pid) => format!(" (pid {})", pid),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,196 @@
+// Logging through `tracing`: informational messages go to stdout as plain text,
+// warnings and errors to stderr with a (colored) `Warning:`/`Error:` prefix, and the debug and
+// trace events enabled by -v/-vv (or RUST_LOG) to stderr tagged with their level.
+// While a full-screen interface owns the terminal, messages are diverted to it.
+use regex::Regex;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::cli::OutputFormat;
+use crate::progress::AboveBars;
+use crate::style;
+use crate::OutputLevel;
+
+/// Install the global logger. `RUST_LOG`, when set, takes precedence over `level`
+/// and `verbose` (the number of -v flags). With JSON output, informational messages
+/// go to stderr so stdout holds only the JSON document.
+pub fn init(level: OutputLevel, verbose: u8, output: OutputFormat) {
+    let default = match (level, verbose) {
+        (OutputLevel::Silent, _) => "off",
+        (_, 2..) => "rust_unicorn=trace,info",
+        (_, 1) => "rust_unicorn=debug,info",
+        (OutputLevel::Normal, _) => "info",
+        (OutputLevel::Quiet, _) => "warn",
+    };
+    let filter = EnvFilter::try_from_env("RUST_LOG").unwrap_or_else(|_| EnvFilter::new(default));
+
+    // -v only adds debug messages; quiet output keeps dropping the informational ones
+    let info = match (level, output) {
+        (OutputLevel::Quiet, _) => BoxMakeWriter::new(io::sink),
+        (_, OutputFormat::Text) => BoxMakeWriter::new(stdout),
+        (_, OutputFormat::Json) => BoxMakeWriter::new(stderr),
+    };
+    // Warnings and errors, then info, then debug/trace: each goes to the first writer
+    // whose level range includes it
+    let writer = stderr
+        .with_max_level(Level::WARN)
+        .or_else(info.with_max_level(Level::INFO).or_else(stderr));
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .event_format(HumanFormat)
+        .fmt_fields(MessageFields)
+        // A closed pipe (e.g. `| head`) shouldn't produce complaints about it
+        .log_internal_errors(false);
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(layer)
+        .try_init();
+}
+
+/// Where log messages go instead of the terminal, if anywhere.
+static DIVERTED: Mutex<Option<UnboundedSender<String>>> = Mutex::new(None);
+
+/// Send log messages to `sender`, one per message, instead of writing them to the
+/// terminal; with `None`, write them to the terminal again.
+pub fn divert(sender: Option<UnboundedSender<String>>) {
+    *DIVERTED.lock().unwrap() = sender;
+}
+
+/// Standard output, keeping clear of progress bars.
+fn stdout() -> Console<io::Stdout> {
+    Console(AboveBars(io::stdout()))
+}
+
+/// Standard error, keeping clear of progress bars.
+fn stderr() -> Console<io::Stderr> {
+    Console(AboveBars(io::stderr()))
+}
+
+/// A terminal stream, passed over while messages are diverted.
+struct Console<W>(AboveBars<W>);
+
+impl<W: Write> Write for Console<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match DIVERTED.lock().unwrap().as_ref() {
+            Some(sender) => {
+                // A closed receiver only means nobody shows the messages anymore
+                let _ = sender.send(String::from_utf8_lossy(buf).trim_end().to_string());
+                Ok(())
+            }
+            None => self.0.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Plain messages, prefixed by their level except for info.
+struct HumanFormat;
+
+impl<S, N> FormatEvent<S, N> for HumanFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "{} ", style::error_prefix())?,
+            Level::WARN => write!(writer, "{} ", style::warning_prefix())?,
+            Level::INFO => {}
+            Level::DEBUG => write!(writer, "[debug] ")?,
+            Level::TRACE => write!(writer, "[trace] ")?,
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Event fields, the message first as it is apart from control characters. The
+/// default formatter escapes every escape sequence, colors included.
+struct MessageFields;
+
+impl<'writer> FormatFields<'writer> for MessageFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = MessageVisitor {
+            writer: &mut writer,
+            result: Ok(()),
+        };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct MessageVisitor<'a, 'writer> {
+    writer: &'a mut Writer<'writer>,
+    result: fmt::Result,
+}
+
+impl Visit for MessageVisitor<'_, '_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if self.result.is_err() {
+            return;
+        }
+        self.result = if field.name() == "message" {
+            write!(self.writer, "{}", escape_controls(&format!("{:?}", value)))
+        } else {
+            write!(
+                self.writer,
+                " {}={}",
+                field.name(),
+                escape_controls(&format!("{:?}", value))
+            )
+        };
+    }
+}
+
+/// `text` with its control characters escaped, so text from elsewhere (release
+/// notes, server errors) can't drive the terminal. Line breaks, tabs and the
+/// sequences setting colors and styles stay.
+fn escape_controls(text: &str) -> String {
+    static STYLE: OnceLock<Regex> = OnceLock::new();
+    let style = STYLE.get_or_init(|| Regex::new(r"^\x1b\[[0-9;]*m").unwrap());
+    let mut escaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(sequence) = style.find(rest) {
+            escaped.push_str(sequence.as_str());
+            rest = &rest[sequence.end()..];
+            continue;
+        }
+        if c.is_control() && c != '\n' && c != '\t' {
+            escaped.extend(c.escape_default());
+        } else {
+            escaped.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    escaped
+}
@@ -1,207 +1,241 @@
-// 1. Bring in necessary crates and traits.
-use reqwest::Client;                                  // Async HTTP client
-use serde::Deserialize;                               // Derive Deserialize for JSON mapping
-use std::env;                                         // For command-line args
-use std::fs::{self, File};
-use std::io::Write;                                   // For writing to files
-use std::os::unix::fs::PermissionsExt;                // For setting Unix permission bits
-use std::path::{Path, PathBuf};
-use std::process::Command;                            // For running shell commands
-use indicatif::{ProgressBar, ProgressStyle};          // For progress bar
-use futures_util::StreamExt;                          // For stream handling
-
-// 2. Define structs matching the JSON structure from GitHub API.
-#[derive(Deserialize)]
-struct Release {
-    tag_name: String,
-    assets: Vec<Asset>,
-}
-
-#[derive(Deserialize)]
-struct Asset {
-    name: String,
-    browser_download_url: String,
-}
+// rust-unicorn command-line front end: parses arguments, loads the config and runs
+// the chosen command from the library.
+use clap::{ArgMatches, CommandFactory, FromArgMatches}; // Derive-based argument parsing
+use clap_complete::CompleteEnv; // Dynamic shell completion
+use rust_unicorn::cli::{
+    self, CacheCommand, Cli, Command, ConfigCommand, InstallArgs, InstallCommandArgs, OutputFormat,
+    ScheduleCommand,
+};
+use rust_unicorn::config::{Config, Manifest};
+use rust_unicorn::export::export;
+use rust_unicorn::install::{
+    check, create_dirs, info, install, install_all, install_file, list, pin, remove, rollback,
+    update, update_all,
+};
+use rust_unicorn::self_update::self_update;
+use rust_unicorn::throttle::Throttle;
+use rust_unicorn::{
+    adopt, cache, daemon, defaults, doctor, legacy, lock, logging, notes, schedule, search, style,
+    ui, usage, verify, versions, Context, Error, OutputLevel,
+};
+use std::process::ExitCode; // For exit status reporting
+use std::sync::Arc; // For sharing state between update tasks
+use tracing::error; // Error reporting through the logger
+
+/// Exit status of `check` when at least one update is available. Failures exit with
+/// the status given by `Error::exit_code`.
+const UPDATES_AVAILABLE_EXIT: u8 = 100;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Parse command-line arguments
-    let args: Vec<String> = env::args().collect();
-    // TODO: Temporary default install directory
-    let mut install_dir = PathBuf::from(env::var("HOME")?).join("./Documents/repository/rust-unicorn");
-    let mut create_symlink = true;
-    let mut force_update = false;
-    let mut quiet = false;
-
-    // Simple command-line argument parsing
-    for i in 1..args.len() {
-        match args[i].as_str() {
-            "--install-dir" | "-d" if i + 1 < args.len() => {
-                install_dir = PathBuf::from(&args[i + 1]);
-            }
-            "--no-symlink" => {
-                create_symlink = false;
-            }
-            "--force" | "-f" => {
-                force_update = true;
-            }
-            "--quiet" | "-q" => {
-                quiet = true;
-            }
-            "--help" | "-h" => {
-                print_help();
-                return Ok(());
-            }
-            _ => {}
-        }
+async fn main() -> ExitCode {
+    // Answer shell completion requests (COMPLETE=<shell>) before anything else
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    // Parse command-line arguments; `config set-default` also needs to know which were given
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let mut level = cli.output_level();
+
+    // Load the config file; its `quiet` setting applies unless --quiet/--silent was given.
+    let config = Config::load(cli.config.as_deref());
+    if level == OutputLevel::Normal
+        && config
+            .as_ref()
+            .is_ok_and(|config| config.quiet == Some(true))
+    {
+        level = OutputLevel::Quiet;
     }
-
-    // Create installation directory if it doesn't exist
-    if !install_dir.exists() {
-        fs::create_dir_all(&install_dir)?;
-        if !quiet {
-            println!("Created directory: {}", install_dir.display());
-        }
+    // Keep stdout for the JSON document alone
+    let output = match &cli.command {
+        Command::Check { json: true, .. } => OutputFormat::Json,
+        _ => cli.output,
+    };
+    if level == OutputLevel::Normal && output == OutputFormat::Json {
+        level = OutputLevel::Quiet;
     }
+    style::init(cli.color);
+    logging::init(level, cli.verbose, output);
 
-    // 3. Configure owner/repo and construct the "latest release" API URL.
-    let owner = "laurent22";
-    let repo = "joplin";
-    let api_url = format!(
-        "https://api.github.com/repos/{}/{}/releases/latest",
-        owner, repo
-    );
-
-    // 4. Create an HTTP client with a User-Agent header to satisfy GitHub's requirements.
-    let client = Client::builder()
-        .user_agent("rust-joplin-installer")
-        .build()?;
-
-    // 5. Fetch and deserialize the release information.
-    let release: Release = client
-        .get(&api_url)
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    // 6. Find the first asset whose name ends with ".AppImage".
-    let asset = release
-        .assets
-        .into_iter()
-        .find(|a| a.name.ends_with(".AppImage"))
-        .ok_or("No AppImage asset found in latest release")?;
-
-    let install_path = install_dir.join(&asset.name);
-    
-    // Check if we already have the latest version
-    if install_path.exists() && !force_update {
-        if !quiet {
-            println!("Joplin {} is already installed at {}", release.tag_name, install_path.display());
-            println!("Use --force to reinstall or update.");
-        }
-        
-        // Make sure it's executable anyway
-        let mut perms = fs::metadata(&install_path)?.permissions();
-        perms.set_mode(perms.mode() | 0o755);
-        fs::set_permissions(&install_path, perms)?;
-        
-        // Set up symlink if needed
-        if create_symlink {
-            create_joplin_symlink(&install_dir, &asset.name)?;
+    let result = match config {
+        Ok(config) => run(cli, &matches, config, level, output).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::from(e.exit_code())
         }
-        
-        return Ok(());
-    }
-
-    if !quiet {
-        println!("Found Joplin {} ({})", release.tag_name, asset.name);
-        println!("Downloading to {}...", install_path.display());
     }
+}
 
-    // 7. Download the binary asset with progress bar.
-    let resp = client
-        .get(&asset.browser_download_url)
-        .send()
-        .await?
-        .error_for_status()?;
-    
-    // Get the content length for the progress bar
-    let total_size = resp.content_length().unwrap_or(0);
-    
-    // Create and configure the progress bar
-    let pb = if !quiet && total_size > 0 {
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"));
-        pb
-    } else {
-        ProgressBar::hidden()
+async fn run(
+    cli: Cli,
+    matches: &ArgMatches,
+    mut config: Config,
+    level: OutputLevel,
+    output: OutputFormat,
+) -> Result<ExitCode, Error> {
+    // Apps from `install --from` are installed like configured ones, with their settings
+    let manifest = match &cli.command {
+        Command::Install(InstallCommandArgs {
+            from: Some(path), ..
+        })
+        | Command::Import { file: path, .. } => {
+            let manifest = Manifest::load(path)?;
+            config.apps.extend(manifest.apps.clone());
+            Some(manifest)
+        }
+        _ => None,
     };
 
-    // Stream the download with progress updates
-    let mut file = File::create(&install_path)?;
-    let mut downloaded: u64 = 0;
-    let mut stream = resp.bytes_stream();
-    
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        file.write_all(&chunk)?;
-        
-        downloaded += chunk.len() as u64;
-        pb.set_position(downloaded);
+    // The HTTP client is built from the config, so its flags go there
+    if cli.proxy.is_some() {
+        config.proxy = cli.proxy.clone();
     }
-    
-    pb.finish_with_message("Download complete!");
-
-    // 9. Update file permissions to add the executable bit (chmod +x).
-    let mut perms = file.metadata()?.permissions();
-    perms.set_mode(perms.mode() | 0o755);
-    fs::set_permissions(&install_path, perms)?;
-
-    if !quiet {
-        println!("Downloaded and made executable: {}", install_path.display());
+    if cli.cacert.is_some() {
+        config.cacert = cli.cacert.clone();
     }
-    
-    // 10. Create a symlink for easier access
-    if create_symlink {
-        create_joplin_symlink(&install_dir, &asset.name)?;
+    if cli.tls.is_some() {
+        config.tls = cli.tls;
     }
-    
-    if !quiet {
-        println!("Joplin {} has been successfully installed!", release.tag_name);
-        println!("You can run it by typing 'joplin' in your terminal.");
+    if cli.connect_timeout.is_some() {
+        config.connect_timeout = cli.connect_timeout.clone();
     }
-    
-    Ok(())
-}
+    if cli.read_timeout.is_some() {
+        config.read_timeout = cli.read_timeout.clone();
+    }
+    if cli.download_timeout.is_some() {
+        config.download_timeout = cli.download_timeout.clone();
+    }
+    let mut ctx = Context::new(config, level, cli.token)?;
+    ctx.output = output;
+    if let Some(dir) = cli.install_dir {
+        ctx.install_dir = std::path::absolute(dir)?;
+    }
+    if cli.symlink_in_install_dir {
+        ctx.bin_dir = ctx.install_dir.clone();
+    } else if let Some(dir) = cli.bin_dir {
+        ctx.bin_dir = std::path::absolute(dir)?;
+    }
+    ctx.dir_mode = cli.dir_mode;
+    if let Some(retries) = cli.retries {
+        ctx.retries = retries;
+    }
+    ctx.rate_limit_wait = cli.wait_for_rate_limit;
+    if let Some(rate) = cli.limit_rate {
+        ctx.throttle = Some(Throttle::new(rate));
+    }
+    // Offered before the first change, so it doesn't land in the legacy directory
+    if cli.command.modifies_installs() || cli.migrate_legacy.is_some() {
+        if let Some(dir) = legacy::offer(&ctx, cli.config.as_deref(), cli.migrate_legacy).await? {
+            ctx.install_dir = dir;
+        }
+    }
+    let ctx = Arc::new(ctx);
 
-fn create_joplin_symlink(install_dir: &Path, app_image_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let symlink_path = install_dir.join("joplin");
-    
-    // Remove existing symlink if it exists
-    if symlink_path.exists() {
-        fs::remove_file(&symlink_path)?;
+    // Held until the command is done
+    let _lock = if cli.command.modifies_installs() {
+        create_dirs(&ctx.install_dir, ctx.dir_mode)?;
+        Some(lock::acquire(&ctx.install_dir, cli.wait_for_lock).await?)
+    } else {
+        None
+    };
+
+    match cli.command {
+        Command::Install(args) => match (&manifest, &args.file) {
+            (Some(manifest), _) => install_all(&ctx, &args.install, manifest, false).await?,
+            (None, file) => {
+                let report = match file {
+                    Some(file) => {
+                        install_file(
+                            &ctx,
+                            &args.install,
+                            file,
+                            args.name.as_deref(),
+                            args.file_version.as_deref(),
+                        )
+                        .await?
+                    }
+                    None => install(&ctx, &args.install, false).await?,
+                };
+                if ctx.output == OutputFormat::Json {
+                    ctx.print_json(&report)?;
+                }
+            }
+        },
+        Command::Update(args) if args.all => update_all(&ctx, &args).await?,
+        Command::Update(args) => update(&ctx, &args).await?,
+        Command::Adopt(args) => adopt::adopt(&ctx, &args)?,
+        Command::Remove { app, purge } => remove(&ctx, &app, purge)?,
+        Command::Rollback { app } => rollback(&ctx, &app)?,
+        Command::Cleanup { dry_run } => versions::cleanup(&ctx, dry_run)?,
+        Command::Pin { app } => pin(&ctx, &app, true)?,
+        Command::Unpin { app } => pin(&ctx, &app, false)?,
+        Command::List => list(&ctx)?,
+        Command::Info { app } => info(&ctx, &app)?,
+        Command::DiskUsage { sort } => usage::disk_usage(&ctx, sort)?,
+        Command::SelfUpdate { force } => self_update(&ctx, force).await?,
+        Command::Export => export(&ctx, &mut std::io::stdout())?,
+        Command::Import { exact, dry_run, .. } => {
+            let args = InstallArgs {
+                dry_run,
+                ..InstallArgs::default()
+            };
+            install_all(
+                &ctx,
+                &args,
+                manifest.as_ref().ok_or("No manifest loaded")?,
+                exact,
+            )
+            .await?
+        }
+        Command::Changelog { app, tag, raw } => {
+            notes::changelog(&ctx, &app, tag.as_deref(), raw).await?
+        }
+        Command::Search { term, limit } => search::search(&ctx, &term, limit).await?,
+        Command::Schedule { action } => match action {
+            ScheduleCommand::Enable {
+                weekly,
+                on_calendar,
+                ..
+            } => {
+                let calendar =
+                    on_calendar
+                        .as_deref()
+                        .unwrap_or(if weekly { "weekly" } else { "daily" });
+                schedule::enable(&ctx, calendar, cli.config.as_deref())?
+            }
+            ScheduleCommand::Disable => schedule::disable(&ctx)?,
+            ScheduleCommand::Status => schedule::status(&ctx)?,
+        },
+        Command::Daemon(args) => daemon::run(&ctx, &args).await?,
+        Command::Verify { apps, offline } => verify::verify_installed(&ctx, &apps, offline).await?,
+        Command::Doctor { fix } => doctor::doctor(&ctx, fix).await?,
+        Command::Ui => ui::run(&ctx).await?,
+        Command::Cache { action } => match action {
+            CacheCommand::Clean => cache::clean(&ctx)?,
+            CacheCommand::Stats => cache::stats(&ctx)?,
+        },
+        Command::Config { action } => match action {
+            ConfigCommand::SetDefault(args) => {
+                let given = matches
+                    .subcommand_matches("config")
+                    .and_then(|matches| matches.subcommand_matches("set-default"))
+                    .ok_or("Cannot tell which flags were given")?;
+                defaults::set_default(&ctx, cli.config.as_deref(), args.app.as_deref(), given)?
+            }
+            ConfigCommand::ShowDefaults { app } => {
+                defaults::show_defaults(&ctx, cli.config.as_deref(), app.as_deref())?
+            }
+        },
+        Command::Completions { shell } => cli::write_completions(shell, &mut std::io::stdout())?,
+        Command::Check { apps, .. } => {
+            if !check(&ctx, &apps).await?.is_empty() {
+                return Ok(ExitCode::from(UPDATES_AVAILABLE_EXIT));
+            }
+        }
     }
-    
-    // Create the symlink
-    std::os::unix::fs::symlink(app_image_name, &symlink_path)?;
-    
-    Ok(())
-}
 
-fn print_help() {
-    println!("Joplin AppImage Installer");
-    println!();
-    println!("USAGE:");
-    println!("    rust-unicorn [OPTIONS]");
-    println!();
-    println!("OPTIONS:");
-    println!("    -d, --install-dir <PATH>    Installation directory (default: ~/Documents/repository/rust-unicorn)");
-    println!("    --no-symlink                Don't create a 'joplin' symlink");
-    println!("    -f, --force                 Force download even if already installed");
-    println!("    -q, --quiet                 Suppress output messages");
-    println!("    -h, --help                  Print this help message");
+    Ok(ExitCode::SUCCESS)
 }
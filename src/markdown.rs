@@ -0,0 +1,259 @@
+// Renders markdown (release notes) as plain terminal text: headings, lists, quotes
+// and code laid out as on the web, links followed by their URL, HTML tags dropped.
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::sync::OnceLock;
+
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Render `markdown` for a terminal, with bold, italic and colored text when `styled`.
+pub fn render(markdown: &str, styled: bool) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer {
+        styled,
+        at_line_start: true,
+        ..Renderer::default()
+    };
+    for event in Parser::new_ext(markdown, options) {
+        renderer.event(event);
+    }
+    renderer.out.trim_end().to_string()
+}
+
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    styled: bool,
+    /// Prefix of each line, one part per enclosing quote or list item
+    indent: Vec<String>,
+    /// Enclosing lists: the next number of ordered ones
+    lists: Vec<Option<u64>>,
+    /// URL and output position of each enclosing link
+    links: Vec<(String, usize)>,
+    /// Active styles, innermost last
+    styles: Vec<&'static str>,
+    /// Start of the heading being written, for underlining it
+    heading: Option<(HeadingLevel, usize)>,
+    at_line_start: bool,
+    /// A list marker was just written, so the item's first paragraph continues its line
+    after_marker: bool,
+    /// Inside an image, whose alt text is dropped
+    in_image: usize,
+    first_cell: bool,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.in_image == 0 => self.write(&text),
+            Event::Code(code) => {
+                self.push_style(CYAN);
+                self.write(&code);
+                self.pop_style();
+            }
+            Event::Html(html) => {
+                for line in strip_tags(&html)
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                {
+                    self.write(line);
+                    self.newline();
+                }
+            }
+            Event::InlineHtml(html)
+                if html
+                    .trim_start_matches('<')
+                    .to_lowercase()
+                    .starts_with("br") =>
+            {
+                self.newline()
+            }
+            Event::InlineHtml(html) => self.write(&strip_tags(&html)),
+            Event::SoftBreak | Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.blank_line();
+                self.write(&"─".repeat(40));
+                self.newline();
+            }
+            Event::TaskListMarker(done) => self.write(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.blank_line(),
+            Tag::Heading { level, .. } => {
+                self.blank_line();
+                self.push_style(BOLD);
+                if level == HeadingLevel::H1 {
+                    self.push_style(UNDERLINE);
+                }
+                self.heading = Some((level, self.out.len()));
+            }
+            Tag::BlockQuote(_) => {
+                self.blank_line();
+                self.indent
+                    .push(if self.styled { "│ " } else { "> " }.to_string());
+            }
+            Tag::CodeBlock(_) => {
+                self.blank_line();
+                self.indent.push("    ".to_string());
+            }
+            Tag::List(start) => {
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                if !self.at_line_start {
+                    self.newline();
+                }
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.write(&marker);
+                self.indent.push(" ".repeat(marker.chars().count()));
+                self.after_marker = true;
+            }
+            Tag::HtmlBlock | Tag::Table(_) => self.blank_line(),
+            Tag::TableHead | Tag::TableRow => self.first_cell = true,
+            Tag::TableCell => {
+                if !self.first_cell {
+                    self.write(" | ");
+                }
+                self.first_cell = false;
+            }
+            Tag::Emphasis => self.push_style(ITALIC),
+            Tag::Strong => self.push_style(BOLD),
+            Tag::Link { dest_url, .. } => self.links.push((dest_url.to_string(), self.out.len())),
+            Tag::Image { .. } => self.in_image += 1,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.newline(),
+            TagEnd::Heading(level) => {
+                self.pop_style();
+                if level == HeadingLevel::H1 {
+                    self.pop_style();
+                }
+                // Without styles, the top headings are set apart by underlining them
+                if let (false, Some((level @ (HeadingLevel::H1 | HeadingLevel::H2), start))) =
+                    (self.styled, self.heading)
+                {
+                    let width = self.out[start..].chars().count();
+                    self.newline();
+                    self.write(&if level == HeadingLevel::H1 { "=" } else { "-" }.repeat(width));
+                }
+                self.heading = None;
+                self.newline();
+            }
+            TagEnd::BlockQuote(_) | TagEnd::CodeBlock | TagEnd::Item => {
+                self.indent.pop();
+                self.after_marker = false;
+                if !self.at_line_start {
+                    self.newline();
+                }
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::TableHead | TagEnd::TableRow => self.newline(),
+            TagEnd::Emphasis | TagEnd::Strong => self.pop_style(),
+            TagEnd::Link => {
+                let Some((url, start)) = self.links.pop() else {
+                    return;
+                };
+                // Autolinks and in-page anchors would only repeat themselves, and image
+                // links (badges) have no text to explain
+                let text = &self.out[start..];
+                if !url.starts_with('#') && !text.is_empty() && !text.contains(url.as_str()) {
+                    self.write(&format!(" ({})", url));
+                }
+            }
+            TagEnd::Image => self.in_image -= 1,
+            _ => {}
+        }
+    }
+
+    /// Append `text`, indenting each line it starts.
+    fn write(&mut self, text: &str) {
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.newline();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if self.at_line_start {
+                let indent = self.indent.concat();
+                self.out.push_str(&indent);
+                self.at_line_start = false;
+            }
+            self.out.push_str(line);
+            self.after_marker = false;
+        }
+    }
+
+    fn newline(&mut self) {
+        // Empty blocks (a paragraph of badges, say) would pile up blank lines
+        if self.out.ends_with("\n\n") {
+            return;
+        }
+        self.out.push('\n');
+        self.at_line_start = true;
+    }
+
+    /// End the current line and leave an empty one before the next block.
+    fn blank_line(&mut self) {
+        if self.out.is_empty() || self.after_marker {
+            return;
+        }
+        if !self.at_line_start {
+            self.newline();
+        }
+        if !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn push_style(&mut self, style: &'static str) {
+        if self.styled {
+            self.styles.push(style);
+            self.out.push_str(style);
+        }
+    }
+
+    fn pop_style(&mut self) {
+        if self.styled {
+            self.styles.pop();
+            // Terminals can only reset everything, so restore the outer styles
+            self.out.push_str(RESET);
+            self.out.push_str(&self.styles.concat());
+        }
+    }
+}
+
+/// `html` without its tags.
+fn strip_tags(html: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| Regex::new(r"<[^>]*>").unwrap())
+        .replace_all(html, "")
+        .into_owned()
+}
@@ -0,0 +1,235 @@
+// AppStream metainfo (~/.local/share/metainfo/<app>.metainfo.xml) so software
+// centres such as GNOME Software and KDE Discover list installed AppImages. The
+// AppImage's own metainfo is used when it ships one; otherwise a minimal one is made
+// up from what rust-unicorn knows about the app. Either way its launchable is the
+// app's desktop entry.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+use crate::cli::Provider;
+use crate::config::App;
+use crate::install::create_dirs;
+use crate::{catalog, paths, tr, Error};
+
+/// Where AppImages keep their metainfo, current location first.
+const METAINFO_DIRS: &[&str] = &["usr/share/metainfo", "usr/share/appdata"];
+
+/// Directory for user metainfo: `$XDG_DATA_HOME/metainfo`.
+pub fn metainfo_dir() -> Option<PathBuf> {
+    Some(paths::data_home()?.join("metainfo"))
+}
+
+/// Write `<dir>/<app>.metainfo.xml` for `app`, installed at `version` from `appimage`
+/// and launched through the desktop entry `<app>.desktop`. Missing directories are
+/// created with `dir_mode`.
+pub fn write_metainfo(
+    dir: &Path,
+    appimage: &Path,
+    app: &App,
+    version: &str,
+    dir_mode: Option<u32>,
+) -> Result<PathBuf, Error> {
+    create_dirs(dir, dir_mode)?;
+    let desktop_id = format!("{}.desktop", app.name);
+    let contents = match extract_metainfo(appimage, dir, &app.name, dir_mode) {
+        Ok(Some(xml)) => with_launchable(&xml, &desktop_id),
+        Ok(None) => synthesize(app, version, &desktop_id),
+        Err(e) => {
+            debug!("Cannot extract the metainfo of {}: {}", app.name, e);
+            synthesize(app, version, &desktop_id)
+        }
+    };
+    let path = dir.join(format!("{}.metainfo.xml", app.name));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// The metainfo shipped inside `appimage`, if any, extracted with the AppImage
+/// runtime's `--appimage-extract <pattern>` into a scratch directory under `dir`.
+fn extract_metainfo(
+    appimage: &Path,
+    dir: &Path,
+    app_name: &str,
+    dir_mode: Option<u32>,
+) -> Result<Option<String>, Error> {
+    let work_dir = dir.join(format!(".{}-extract", app_name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    create_dirs(&work_dir, dir_mode)?;
+
+    let result = extract_component(appimage, &work_dir);
+    fs::remove_dir_all(&work_dir)?;
+
+    result
+}
+
+/// Extract the metainfo directories of `appimage` into `work_dir`, one after the
+/// other, until one describes a component.
+fn extract_component(appimage: &Path, work_dir: &Path) -> Result<Option<String>, Error> {
+    for metainfo_dir in METAINFO_DIRS {
+        let status = Command::new(appimage)
+            .arg("--appimage-extract")
+            .arg(format!("{}/*.xml", metainfo_dir))
+            .current_dir(work_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| {
+                format!(
+                    "Cannot run {} to extract its metainfo: {}",
+                    appimage.display(),
+                    e
+                )
+            })?;
+        if !status.success() {
+            return Err(format!(
+                "{} --appimage-extract failed ({})",
+                appimage.display(),
+                status
+            )
+            .into());
+        }
+        if let Some(xml) = find_component(&work_dir.join("squashfs-root").join(metainfo_dir))? {
+            return Ok(Some(xml));
+        }
+    }
+    Ok(None)
+}
+
+/// The first regular XML file in `dir` describing an AppStream component.
+fn find_component(dir: &Path) -> Result<Option<String>, Error> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(None);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    paths.sort();
+    for path in paths {
+        // Links would point outside the extracted files
+        if !fs::symlink_metadata(&path)?.is_file() {
+            continue;
+        }
+        let xml = fs::read_to_string(&path).unwrap_or_default();
+        if xml.contains("<component") {
+            return Ok(Some(xml));
+        }
+    }
+    Ok(None)
+}
+
+/// `xml` with its desktop-id launchable replaced by `desktop_id`, since the desktop
+/// file it names inside the AppImage isn't installed.
+fn with_launchable(xml: &str, desktop_id: &str) -> String {
+    let launchable = format!(
+        "<launchable type=\"desktop-id\">{}</launchable>",
+        escape(desktop_id)
+    );
+    if let Some(start) = xml.find("<launchable type=\"desktop-id\">") {
+        if let Some(end) = xml[start..].find("</launchable>") {
+            let end = start + end + "</launchable>".len();
+            return format!("{}{}{}", &xml[..start], launchable, &xml[end..]);
+        }
+    }
+    match xml.rfind("</component>") {
+        Some(end) => format!("{}  {}\n{}", &xml[..end], launchable, &xml[end..]),
+        None => xml.to_string(),
+    }
+}
+
+/// Minimal metainfo for an AppImage that ships none.
+fn synthesize(app: &App, version: &str, desktop_id: &str) -> String {
+    let summary = match catalog::get(&app.name) {
+        Some(entry) => entry.description.clone(),
+        None => tr!(
+            "metainfo-summary",
+            repo = format!("{}/{}", app.repo.owner, app.repo.name)
+        ),
+    };
+    let homepage = match app.repo.provider {
+        Provider::Url => String::new(),
+        _ => format!(
+            "  <url type=\"homepage\">https://{}/{}/{}</url>\n",
+            escape(app.repo.host()),
+            escape(&app.repo.owner),
+            escape(&app.repo.name)
+        ),
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <component type=\"desktop-application\">\n\
+         \x20 <id>{}</id>\n\
+         \x20 <metadata_license>CC0-1.0</metadata_license>\n\
+         \x20 <name>{}</name>\n\
+         \x20 <summary>{}</summary>\n\
+         {}\
+         \x20 <launchable type=\"desktop-id\">{}</launchable>\n\
+         \x20 <releases>\n\
+         \x20   <release version=\"{}\"/>\n\
+         \x20 </releases>\n\
+         </component>\n",
+        escape(&component_id(app)),
+        escape(&app.repo.name),
+        escape(&summary),
+        homepage,
+        escape(desktop_id),
+        escape(version),
+    )
+}
+
+/// A reverse-DNS component id from where the app is published, such as
+/// `com.github.owner.name`; direct-URL apps go under rust-unicorn's own prefix.
+fn component_id(app: &App) -> String {
+    let parts: Vec<String> = match app.repo.provider {
+        Provider::Url => vec![
+            "org".to_string(),
+            "rustunicorn".to_string(),
+            app.name.clone(),
+        ],
+        _ => app
+            .repo
+            .host()
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .rev()
+            .chain(app.repo.owner.split('/'))
+            .chain([app.repo.name.as_str()])
+            .map(str::to_string)
+            .collect(),
+    };
+    parts
+        .iter()
+        .map(|part| {
+            let part: String = part
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            // Segments may not start with a digit
+            if part.starts_with(|c: char| c.is_ascii_digit()) {
+                format!("_{}", part)
+            } else {
+                part
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Escape text for XML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
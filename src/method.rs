@@ -0,0 +1,576 @@
+// Install methods: how a downloaded (and verified) release asset becomes the installed
+// program, what is created around it and what removing the app deletes again. Each
+// kind of asset an app can be installed from (its `asset_type`, or `--method`) has
+// one; `for_type` is the only place they are told apart.
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::config::{App, AssetType};
+use crate::install::{create_app_symlink, create_dirs, integrate, Integration};
+use crate::sandbox::{self, Sandbox};
+use crate::state::InstalledApp;
+use crate::verify::{sha256_file, verify_appimage, verify_executable};
+use crate::{archive, desktop, extract, tr, Context, Error};
+
+/// A release asset downloaded (or copied from disk) and verified, not yet installed.
+pub struct Artifact<'a> {
+    pub path: &'a Path,
+    /// The asset's file name
+    pub name: &'a str,
+    /// SHA-256 of the file at `path`, lowercase hex
+    pub sha256: &'a str,
+}
+
+/// The program made from an artifact, ready to be moved into place.
+pub struct StagedInstall {
+    pub path: PathBuf,
+    /// SHA-256 of the program, lowercase hex
+    pub sha256: String,
+}
+
+/// What is created around the installed program, as the flags and config ask.
+pub struct Finalize {
+    pub symlink: bool,
+    pub desktop_entry: bool,
+    pub sandbox: Option<Sandbox>,
+    pub extract: bool,
+}
+
+/// One way of installing apps.
+pub trait InstallMethod: Sync {
+    /// The asset type installed this way.
+    fn asset_type(&self) -> AssetType;
+
+    /// Whether the release asset `name` can be installed this way.
+    fn matches(&self, name: &str) -> bool;
+
+    /// The versioned file name the program from the asset `name` is installed under.
+    fn file_name<'a>(&self, name: &'a str) -> &'a str;
+
+    /// The file name `app_name` is installed under with `stable_filename`.
+    fn stable_file_name(&self, app_name: &str) -> String;
+
+    /// The name of an asset of `app_name` that nothing else names, such as a direct
+    /// download from a URL without a file name.
+    fn fallback_asset_name(&self, app_name: &str) -> String;
+
+    /// Whether the program is a desktop app, which can get a desktop entry and be
+    /// sandboxed or extracted; command-line tools can't.
+    fn desktop_app(&self) -> bool;
+
+    /// Where the asset is downloaded to when its program is staged at `path`.
+    fn artifact_path(&self, path: &Path, name: &str) -> PathBuf;
+
+    /// Whether the installed program can seed a delta download of the asset.
+    fn delta_updates(&self) -> bool;
+
+    /// Check the downloaded asset `name` at `path` is what this method installs, for
+    /// `arch`, before it is verified against its release.
+    fn check_artifact(&self, path: &Path, name: &str, arch: &str) -> Result<(), Error>;
+
+    /// Make the program for `app_name` from the verified `artifact`, at `path`. The
+    /// artifact is gone afterwards unless it is the program itself.
+    fn stage(
+        &self,
+        artifact: &Artifact<'_>,
+        app_name: &str,
+        path: &Path,
+        arch: &str,
+        dir_mode: Option<u32>,
+    ) -> Result<StagedInstall, Error>;
+
+    /// Create the files around `app`'s program at `install_path`, installed at
+    /// `version`, that `options` ask for.
+    fn finalize(
+        &self,
+        ctx: &Context,
+        app: &App,
+        install_path: &Path,
+        version: &str,
+        options: &Finalize,
+    ) -> Result<Integration, Error>;
+
+    /// Delete the files the install of `app_name`, recorded as `installed`, created,
+    /// returning the ones that were there. Versions kept for `rollback` stay.
+    fn uninstall_artifacts(
+        &self,
+        app_name: &str,
+        installed: &InstalledApp,
+    ) -> Result<Vec<PathBuf>, Error>;
+}
+
+/// The method installing apps from assets of type `asset_type`.
+pub fn for_type(asset_type: AssetType) -> &'static dyn InstallMethod {
+    match asset_type {
+        AssetType::AppImage => &AppImage,
+        AssetType::Archive => &Archive,
+    }
+}
+
+/// The kind of asset `name` is, when some method can install it.
+pub fn detect(name: &str) -> Option<AssetType> {
+    [AssetType::AppImage, AssetType::Archive]
+        .into_iter()
+        .find(|&asset_type| for_type(asset_type).matches(name))
+}
+
+/// The method `app` is installed with: `method` when given on the command line, else
+/// its configured `asset_type`.
+pub fn select(method: Option<AssetType>, app: &App) -> AssetType {
+    method.unwrap_or(app.settings.asset_type)
+}
+
+/// AppImages, installed as they are downloaded and integrated with the desktop.
+pub struct AppImage;
+
+impl InstallMethod for AppImage {
+    fn asset_type(&self) -> AssetType {
+        AssetType::AppImage
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        name.ends_with(".AppImage")
+    }
+
+    fn file_name<'a>(&self, name: &'a str) -> &'a str {
+        name
+    }
+
+    fn stable_file_name(&self, app_name: &str) -> String {
+        format!("{}.AppImage", app_name)
+    }
+
+    fn fallback_asset_name(&self, app_name: &str) -> String {
+        format!("{}.AppImage", app_name)
+    }
+
+    fn desktop_app(&self) -> bool {
+        true
+    }
+
+    fn artifact_path(&self, path: &Path, _name: &str) -> PathBuf {
+        path.to_path_buf()
+    }
+
+    fn delta_updates(&self) -> bool {
+        true
+    }
+
+    fn check_artifact(&self, path: &Path, name: &str, arch: &str) -> Result<(), Error> {
+        verify_appimage(path, name, arch)
+    }
+
+    fn stage(
+        &self,
+        artifact: &Artifact<'_>,
+        _app_name: &str,
+        path: &Path,
+        _arch: &str,
+        _dir_mode: Option<u32>,
+    ) -> Result<StagedInstall, Error> {
+        if artifact.path != path {
+            fs::rename(artifact.path, path)?;
+        }
+        Ok(StagedInstall {
+            path: path.to_path_buf(),
+            sha256: artifact.sha256.to_string(),
+        })
+    }
+
+    fn finalize(
+        &self,
+        ctx: &Context,
+        app: &App,
+        install_path: &Path,
+        version: &str,
+        options: &Finalize,
+    ) -> Result<Integration, Error> {
+        integrate(
+            ctx,
+            app,
+            install_path,
+            version,
+            options.symlink,
+            options.desktop_entry,
+            options.sandbox,
+            options.extract,
+        )
+    }
+
+    fn uninstall_artifacts(
+        &self,
+        app_name: &str,
+        installed: &InstalledApp,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut removed = remove_program(installed)?;
+        for file in [
+            &installed.desktop_entry,
+            &installed.icon,
+            &installed.metainfo,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if file.exists() {
+                debug!("Removing {}", file.display());
+                fs::remove_file(file)?;
+                removed.push(file.clone());
+            }
+        }
+        // So nothing is still opened with the app
+        if let Some(dir) = installed.desktop_entry.as_deref().and_then(Path::parent) {
+            desktop::update_database(dir);
+        }
+        let wrapper = sandbox::wrapper_path(app_name)
+            .ok()
+            .filter(|path| path.exists());
+        sandbox::remove_wrapper(app_name)?;
+        removed.extend(wrapper);
+        if let Some(app_dir) = installed.app_dir.as_ref().filter(|dir| dir.exists()) {
+            extract::remove(app_dir)?;
+            removed.push(app_dir.clone());
+        }
+        Ok(removed)
+    }
+}
+
+/// Programs unpacked from `.tar.gz`, `.tgz` and `.zip` archives: command-line tools,
+/// which only get a symlink.
+pub struct Archive;
+
+impl InstallMethod for Archive {
+    fn asset_type(&self) -> AssetType {
+        AssetType::Archive
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        archive::is_archive(name)
+    }
+
+    fn file_name<'a>(&self, name: &'a str) -> &'a str {
+        archive::program_name(name)
+    }
+
+    fn stable_file_name(&self, app_name: &str) -> String {
+        app_name.to_string()
+    }
+
+    // Tarballs are the likelier, and a name is all that picks the unpacker
+    fn fallback_asset_name(&self, app_name: &str) -> String {
+        format!("{}.tar.gz", app_name)
+    }
+
+    fn desktop_app(&self) -> bool {
+        false
+    }
+
+    // Checksums and signatures are published for the archive, not the program in it
+    fn artifact_path(&self, path: &Path, name: &str) -> PathBuf {
+        path.with_file_name(format!("{}.part", name))
+    }
+
+    fn delta_updates(&self) -> bool {
+        false
+    }
+
+    // The program is checked once unpacked
+    fn check_artifact(&self, _path: &Path, name: &str, _arch: &str) -> Result<(), Error> {
+        if archive::is_archive(name) {
+            Ok(())
+        } else {
+            Err(format!("{} is not a .tar.gz, .tgz or .zip archive", name).into())
+        }
+    }
+
+    fn stage(
+        &self,
+        artifact: &Artifact<'_>,
+        app_name: &str,
+        path: &Path,
+        arch: &str,
+        dir_mode: Option<u32>,
+    ) -> Result<StagedInstall, Error> {
+        let unpacked =
+            archive::unpack_program(artifact.path, artifact.name, app_name, path, dir_mode);
+        let _ = fs::remove_file(artifact.path);
+        let program = unpacked?;
+        verify_executable(path, &program, arch)?;
+        info!(
+            "{}",
+            tr!("unpacked", program = program, archive = artifact.name)
+        );
+        Ok(StagedInstall {
+            path: path.to_path_buf(),
+            sha256: sha256_file(path)?,
+        })
+    }
+
+    fn finalize(
+        &self,
+        ctx: &Context,
+        app: &App,
+        install_path: &Path,
+        _version: &str,
+        options: &Finalize,
+    ) -> Result<Integration, Error> {
+        let symlink = if options.symlink {
+            create_dirs(&ctx.bin_dir, ctx.dir_mode)?;
+            Some(create_app_symlink(&ctx.bin_dir, &app.name, install_path)?)
+        } else {
+            None
+        };
+        Ok(Integration {
+            symlink,
+            desktop_entry: None,
+            icon: None,
+            metainfo: None,
+            sandbox: None,
+            wrapper: None,
+            app_dir: None,
+        })
+    }
+
+    fn uninstall_artifacts(
+        &self,
+        _app_name: &str,
+        installed: &InstalledApp,
+    ) -> Result<Vec<PathBuf>, Error> {
+        remove_program(installed)
+    }
+}
+
+/// Delete the installed program and its symlink, returning the ones that were there.
+fn remove_program(installed: &InstalledApp) -> Result<Vec<PathBuf>, Error> {
+    let mut removed = Vec::new();
+    if installed.install_path.exists() {
+        debug!("Removing {}", installed.install_path.display());
+        fs::remove_file(&installed.install_path)?;
+        removed.push(installed.install_path.clone());
+    }
+    if let Some(symlink) = installed.symlink.as_ref().filter(|path| path.is_symlink()) {
+        debug!("Removing symlink {}", symlink.display());
+        fs::remove_file(symlink)?;
+        removed.push(symlink.clone());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch;
+    use crate::config::Config;
+    use crate::install::record_install;
+    use crate::release::{Asset, Release};
+    use crate::state::{Provenance, State};
+    use crate::test_harness::{elf, tarball};
+    use crate::OutputLevel;
+    use std::collections::BTreeSet;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Every path under `dir`.
+    fn tree(dir: &Path) -> BTreeSet<PathBuf> {
+        let mut paths = BTreeSet::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() && !path.is_symlink() {
+                paths.extend(tree(&path));
+            }
+            paths.insert(path);
+        }
+        paths
+    }
+
+    /// Stands in for an AppImage's runtime once the synthetic AppImage has passed its
+    /// checks, since that can't run: `--appimage-extract` unpacks an AppRun, an icon,
+    /// a desktop file and metainfo, whatever was asked for.
+    const EXTRACTING_APPIMAGE: &str = "#!/bin/sh
+mkdir -p squashfs-root/usr/share/metainfo
+printf '#!/bin/sh\\n' > squashfs-root/AppRun
+printf '<svg xmlns=\"http://www.w3.org/2000/svg\"/>\\n' > squashfs-root/.DirIcon
+printf '[Desktop Entry]\\nMimeType=x-scheme-handler/unicorn;\\n' > squashfs-root/unicorn.desktop
+printf '<component type=\"desktop-application\"><id>unicorn</id></component>\\n' \\
+    > squashfs-root/usr/share/metainfo/unicorn.appdata.xml
+";
+
+    /// Install `asset_name`, written by `write`, with `method` into a scratch
+    /// directory holding a file of someone else's, then uninstall it, checking the
+    /// uninstall removes exactly what the install created. Desktop apps get the lot:
+    /// a desktop entry, icon and metainfo under a scratch `$XDG_DATA_HOME`, and an
+    /// extracted app run through a bubblewrap wrapper.
+    fn install_and_uninstall(
+        method: &dyn InstallMethod,
+        asset_name: &str,
+        write: impl FnOnce(&Path),
+    ) {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-unicorn-method-{}-{}",
+            method.asset_type().name(),
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let (install_dir, bin_dir) = (dir.join("apps"), dir.join("bin"));
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(bin_dir.join("other-tool"), "not ours").unwrap();
+        let desktop = method.desktop_app();
+        let data_home = dir.join("data");
+        if desktop {
+            // Only the wrapper needs bubblewrap, and only to be installed
+            let tools = dir.join("tools");
+            fs::create_dir_all(&tools).unwrap();
+            fs::write(tools.join("bwrap"), "#!/bin/sh\n").unwrap();
+            fs::set_permissions(tools.join("bwrap"), fs::Permissions::from_mode(0o755)).unwrap();
+            let path = std::env::var_os("PATH").unwrap_or_default();
+            let path =
+                std::env::join_paths([tools].into_iter().chain(std::env::split_paths(&path)));
+            std::env::set_var("PATH", path.unwrap());
+            std::env::set_var("XDG_DATA_HOME", &data_home);
+        }
+        let before = tree(&dir);
+
+        let ctx = Context::new(
+            Config {
+                install_dir: Some(install_dir.clone()),
+                bin_dir: Some(bin_dir),
+                ..Config::default()
+            },
+            OutputLevel::Silent,
+            None,
+        )
+        .unwrap();
+        let mut app = ctx
+            .config
+            .resolve("owner/unicorn-method-test", None)
+            .unwrap();
+        app.settings.asset_type = method.asset_type();
+        create_dirs(&install_dir, None).unwrap();
+        let install_path = install_dir.join(method.file_name(asset_name));
+        let staged_path = install_dir.join(".staged");
+        let artifact_path = method.artifact_path(&staged_path, asset_name);
+        write(&artifact_path);
+        let sha256 = sha256_file(&artifact_path).unwrap();
+        let artifact = Artifact {
+            path: &artifact_path,
+            name: asset_name,
+            sha256: &sha256,
+        };
+        let staged = method
+            .stage(&artifact, &app.name, &staged_path, arch::host(), None)
+            .unwrap();
+        fs::rename(&staged.path, &install_path).unwrap();
+        if desktop {
+            fs::write(&install_path, EXTRACTING_APPIMAGE).unwrap();
+            fs::set_permissions(&install_path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let options = Finalize {
+            symlink: true,
+            desktop_entry: desktop,
+            sandbox: desktop.then_some(Sandbox::Bwrap),
+            extract: desktop,
+        };
+        let integration = method
+            .finalize(&ctx, &app, &install_path, "1.0", &options)
+            .unwrap();
+        // The program and its symlink, and for desktop apps the rest
+        let integrated: BTreeSet<PathBuf> = [
+            Some(install_path.clone()),
+            integration.symlink.clone(),
+            integration.desktop_entry.clone(),
+            integration.icon.clone(),
+            integration.metainfo.clone(),
+            integration.wrapper.clone(),
+            integration.app_dir.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        assert_eq!(integrated.len(), if desktop { 7 } else { 2 });
+        for path in &integrated {
+            assert!(path.starts_with(&dir), "{}", path.display());
+            assert!(fs::symlink_metadata(path).is_ok(), "{}", path.display());
+        }
+        let mut state = State::default();
+        let asset = Asset {
+            name: asset_name.to_string(),
+            browser_download_url: String::new(),
+            size: 0,
+            state: "uploaded".to_string(),
+            digest: None,
+        };
+        let release = Release {
+            tag_name: "1.0".to_string(),
+            prerelease: false,
+            draft: false,
+            body: None,
+            assets: Vec::new(),
+        };
+        record_install(
+            &mut state,
+            &app,
+            &release,
+            &asset,
+            staged.sha256,
+            install_path,
+            integration,
+            Provenance::default(),
+        );
+        let installed = &state.apps[&app.name];
+        assert!(installed.method() == method.asset_type());
+        // The install directory itself is shared by every app
+        let before: BTreeSet<PathBuf> = before.into_iter().chain([install_dir]).collect();
+        let created: BTreeSet<PathBuf> = tree(&dir).difference(&before).cloned().collect();
+
+        let removed: BTreeSet<PathBuf> = method
+            .uninstall_artifacts(&app.name, installed)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(removed, integrated);
+        // What is left are the directories shared with other apps, and the sandbox's
+        // profile and home, kept like an unconfined app's settings
+        let left: BTreeSet<PathBuf> = tree(&dir).difference(&before).cloned().collect();
+        let sandbox_dir = data_home.join("rust-unicorn/sandbox").join(&app.name);
+        let kept: BTreeSet<PathBuf> = if desktop {
+            [
+                data_home.clone(),
+                data_home.join("applications"),
+                data_home.join("icons"),
+                data_home.join("metainfo"),
+                data_home.join("rust-unicorn"),
+                data_home.join("rust-unicorn/sandbox"),
+                sandbox_dir.join("bwrap.args"),
+                sandbox_dir.join("home"),
+                sandbox_dir,
+            ]
+            .into()
+        } else {
+            BTreeSet::new()
+        };
+        assert_eq!(left, kept);
+        // And nothing but what was integrated went, along with what the app was
+        // extracted to
+        for path in created.difference(&left) {
+            assert!(
+                removed.iter().any(|removed| path.starts_with(removed)),
+                "{} is gone",
+                path.display()
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn appimage_uninstall_removes_what_its_install_created() {
+        install_and_uninstall(&AppImage, "Unicorn-1.0-x86_64.AppImage", |path| {
+            fs::write(path, elf()).unwrap()
+        });
+    }
+
+    #[test]
+    fn archive_uninstall_removes_what_its_install_created() {
+        install_and_uninstall(&Archive, "unicorn-1.0-linux.tar.gz", |path| {
+            fs::write(path, tarball("unicorn", &elf())).unwrap()
+        });
+    }
+}
@@ -0,0 +1,84 @@
+// Release notes: the `changelog` command and the notes `update --show-notes` prints
+// before installing a new version.
+use tracing::info;
+
+use crate::cli::OutputFormat;
+use crate::config::App;
+use crate::markdown;
+use crate::release::{fetch_release, Release, ReleaseSelector};
+use crate::state::State;
+use crate::{style, tr, Context, Error};
+
+/// Print the notes of the release of `spec` tagged `tag`, or else of the release an
+/// update would install. With `raw`, the markdown is printed as published.
+pub async fn changelog(
+    ctx: &Context,
+    spec: &str,
+    tag: Option<&str>,
+    raw: bool,
+) -> Result<(), Error> {
+    let app = match ctx.config.resolve(spec, None) {
+        Ok(app) => app,
+        // Installed apps that aren't configured come from their recorded repository
+        Err(e) => match State::load(&ctx.state_path)?.apps.remove(spec) {
+            Some(installed) => App {
+                name: spec.to_string(),
+                ..ctx.config.resolve(&installed.repo, None)?
+            },
+            None => return Err(e.into()),
+        },
+    };
+    let release = fetch_release(ctx, &app, ReleaseSelector::new(tag, app.settings.channel)).await?;
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "app": app.name,
+            "repo": app.repo.to_string(),
+            "version": release.tag_name,
+            "notes": notes(&release),
+        }))
+    } else {
+        show(ctx, &app.name, &release, raw);
+        Ok(())
+    }
+}
+
+/// Print the notes of `release` of `app_name` (in text output), rendered for the
+/// terminal unless `raw`, returning them as published.
+pub fn show(ctx: &Context, app_name: &str, release: &Release, raw: bool) -> Option<String> {
+    let notes = notes(release);
+    if ctx.output == OutputFormat::Text {
+        match &notes {
+            Some(notes) if raw => info!("{}", notes),
+            Some(notes) => {
+                // Styles would only garble the notes when they are piped or unwanted
+                let styled = style::enabled();
+                info!(
+                    "{}\n",
+                    tr!(
+                        "notes-title",
+                        app = app_name,
+                        version = release.tag_name.as_str()
+                    )
+                );
+                info!("{}\n", markdown::render(notes, styled));
+            }
+            None => info!(
+                "{}",
+                tr!(
+                    "notes-none",
+                    app = app_name,
+                    version = release.tag_name.as_str()
+                )
+            ),
+        }
+    }
+    notes
+}
+
+/// The notes of `release` with plain line endings, unless it has none.
+pub fn notes(release: &Release) -> Option<String> {
+    let body = release.body.as_deref()?.trim();
+    // GitHub keeps the CRLF line endings of notes written in its web editor
+    (!body.is_empty()).then(|| body.replace("\r\n", "\n"))
+}
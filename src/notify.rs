@@ -0,0 +1,117 @@
+// Desktop notifications summarizing what an update run changed, for updates run
+// from a timer or daemon where nobody reads the output.
+use notify_rust::{Notification, Timeout, Urgency};
+use std::io::{self, IsTerminal};
+use tracing::debug;
+
+use crate::install::InstallReport;
+use crate::tr;
+
+/// What an update run did, collected for its notification.
+#[derive(Default)]
+pub struct UpdateSummary {
+    updated: Vec<String>,
+    failed: Vec<String>,
+}
+
+impl UpdateSummary {
+    /// Note the outcome of one app's update; only actual updates are mentioned.
+    pub fn add(&mut self, report: &InstallReport) {
+        if report.action == "updated" {
+            let old_version = report.old_version.as_deref().unwrap_or("?");
+            self.updated.push(tr!(
+                "notify-updated",
+                app = report.app.as_str(),
+                old = old_version,
+                new = report.new_version.as_str()
+            ));
+        }
+    }
+
+    /// Note that updating `app` failed.
+    pub fn add_failure(&mut self, app: &str, error: &str) {
+        self.failed
+            .push(tr!("notify-failed", app = app, error = error));
+    }
+
+    fn title(&self) -> String {
+        match (self.updated.len(), self.failed.len()) {
+            (updated, 0) => tr!("notify-apps-updated", count = updated),
+            (0, failed) => tr!("notify-updates-failed", count = failed),
+            (updated, failed) => tr!(
+                "notify-updated-and-failed",
+                updated = updated,
+                failed = failed
+            ),
+        }
+    }
+}
+
+/// Whether to notify: as configured (`notify` in the config file, or `--notify`),
+/// otherwise only when not run from a terminal.
+pub fn wanted(flag: bool, configured: Option<bool>) -> bool {
+    flag || configured.unwrap_or_else(|| !io::stdout().is_terminal())
+}
+
+/// Show a notification summarizing `summary`, unless nothing changed. Failing to
+/// notify (no notification daemon, no session bus) is not an error.
+pub async fn send(summary: UpdateSummary) {
+    if summary.updated.is_empty() && summary.failed.is_empty() {
+        return;
+    }
+    let urgency = if summary.failed.is_empty() {
+        Urgency::Normal
+    } else {
+        Urgency::Critical
+    };
+    let body = summary
+        .updated
+        .iter()
+        .chain(&summary.failed)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    show(format!("rust-unicorn: {}", summary.title()), body, urgency).await;
+}
+
+/// Show a notification that `apps` have updates available, unless there are none.
+pub async fn send_available(apps: &[String]) {
+    if apps.is_empty() {
+        return;
+    }
+    let title = format!(
+        "rust-unicorn: {}",
+        tr!("notify-updates-available", count = apps.len())
+    );
+    let body = format!("{}\n{}", apps.join(", "), tr!("notify-available-hint"));
+    show(title, body, Urgency::Normal).await;
+}
+
+/// Show a notification reminding of `notes` after `app` was updated to `version`.
+pub async fn send_reminder(app: &str, version: &str, notes: &str) {
+    let title = format!(
+        "rust-unicorn: {}",
+        tr!("notify-reminder", app = app, version = version)
+    );
+    show(title, notes.to_string(), Urgency::Normal).await;
+}
+
+async fn show(title: String, body: String, urgency: Urgency) {
+    let shown = tokio::task::spawn_blocking(move || {
+        Notification::new()
+            .appname("rust-unicorn")
+            .summary(&title)
+            .body(&body)
+            .icon("system-software-update")
+            .urgency(urgency)
+            .timeout(Timeout::Default)
+            .show()
+            .map(|_| ())
+    })
+    .await;
+    match shown {
+        Ok(Ok(())) => debug!("Sent the update notification"),
+        Ok(Err(e)) => debug!("Could not send a desktop notification: {}", e),
+        Err(e) => debug!("Could not send a desktop notification: {}", e),
+    }
+}
@@ -0,0 +1,79 @@
+// Standard locations, following the XDG Base Directory specification: each base
+// directory honours its XDG_*_HOME variable and falls back to the usual default
+// under $HOME.
+use std::env;
+use std::path::PathBuf;
+
+/// The user's home directory.
+pub fn home() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `$<var>`, or `~/<fallback>` when it is unset. Relative values are invalid per the
+/// specification and ignored.
+fn base_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Some(dir),
+        _ => Some(home()?.join(fallback)),
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn state_home() -> Option<PathBuf> {
+    base_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CACHE_HOME`, falling back to `~/.cache`.
+pub fn cache_home() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// Default directory for installed AppImages: `~/Applications` when it exists (where
+/// other AppImage tools put them), otherwise `$XDG_DATA_HOME/rust-unicorn/apps`.
+///
+/// The directory older versions used, `~/Documents/repository/rust-unicorn`, stays
+/// the default while it exists so existing installs aren't split across two places.
+pub fn default_install_dir() -> Option<PathBuf> {
+    match legacy_install_dir() {
+        Some(legacy) if legacy.is_dir() => Some(legacy),
+        _ => standard_install_dir(),
+    }
+}
+
+/// Where older versions installed Joplin: `~/Documents/repository/rust-unicorn`.
+pub fn legacy_install_dir() -> Option<PathBuf> {
+    Some(
+        home()?
+            .join("Documents")
+            .join("repository")
+            .join("rust-unicorn"),
+    )
+}
+
+/// The default install directory without the legacy one.
+pub fn standard_install_dir() -> Option<PathBuf> {
+    let applications = home()?.join("Applications");
+    if applications.is_dir() {
+        Some(applications)
+    } else {
+        Some(data_home()?.join("rust-unicorn").join("apps"))
+    }
+}
+
+/// Default directory for app symlinks: `~/.local/bin`, which the specification
+/// expects to be on PATH.
+pub fn default_bin_dir() -> Option<PathBuf> {
+    Some(home()?.join(".local").join("bin"))
+}
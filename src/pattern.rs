@@ -0,0 +1,55 @@
+// User-supplied asset name patterns: shell-style globs, or regexes with a `re:` prefix.
+use regex::Regex;
+
+#[derive(Clone)]
+pub enum AssetPattern {
+    /// `*` matches any run of characters, `?` any single character
+    Glob(String),
+    /// Regular expression, searched anywhere in the name unless anchored
+    Regex(Regex),
+}
+
+impl AssetPattern {
+    /// Parse `re:<regex>` as a regex and anything else as a glob.
+    pub fn parse(value: &str) -> Result<AssetPattern, String> {
+        match value.strip_prefix("re:") {
+            Some(expr) => Regex::new(expr)
+                .map(AssetPattern::Regex)
+                .map_err(|e| format!("Invalid asset regex '{}': {}", expr, e)),
+            None if value.is_empty() => Err("Asset pattern must not be empty".to_string()),
+            None => Ok(AssetPattern::Glob(value.to_string())),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            AssetPattern::Glob(glob) => glob_matches(glob.as_bytes(), name.as_bytes()),
+            AssetPattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Iterative glob matching with backtracking to the most recent `*`.
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
@@ -0,0 +1,198 @@
+// Progress display. Every bar is drawn through one `MultiProgress`, so parallel
+// downloads each keep a line of their own and log messages print above the bars
+// instead of through them. When stdout is not a terminal, bars give way to a status
+// line logged every few seconds. Front ends drawing progress themselves run their
+// installs `observed`, getting each step and the bytes downloaded as `Event`s.
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::{tr, Context};
+
+/// How often a transfer reports its progress when bars can't be drawn.
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The display every bar is added to.
+pub fn bars() -> &'static MultiProgress {
+    static BARS: OnceLock<MultiProgress> = OnceLock::new();
+    BARS.get_or_init(|| {
+        let target = if interactive() {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        MultiProgress::with_draw_target(target)
+    })
+}
+
+/// Whether bars can be drawn: both output streams are terminals.
+fn interactive() -> bool {
+    io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Hide the bars while something else draws on the terminal, or show them again.
+pub fn hide(hidden: bool) {
+    let target = if interactive() && !hidden {
+        ProgressDrawTarget::stderr()
+    } else {
+        ProgressDrawTarget::hidden()
+    };
+    bars().set_draw_target(target);
+}
+
+/// A step of installing an app.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Looking up the release and the asset to install
+    Resolving,
+    /// Fetching the asset (or the blocks of it that changed)
+    Downloading,
+    /// Checking the download against its checksum and signature
+    Verifying,
+    /// Creating the symlink, desktop entry and other files around the AppImage
+    Integrating,
+}
+
+/// Progress of an install, as told to an `Observer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The install moves on to `Phase`
+    Phase(Phase),
+    /// `downloaded` bytes of a download of `total` (0 when unknown) are in
+    Download { downloaded: u64, total: u64 },
+}
+
+/// Told about the progress of the installs run `observed`.
+pub type Observer = Arc<dyn Fn(Event) + Send + Sync>;
+
+tokio::task_local! {
+    static OBSERVER: Observer;
+}
+
+/// Run `future`, telling `observer` about the progress of the installs it makes.
+pub async fn observed<F: Future>(observer: Observer, future: F) -> F::Output {
+    OBSERVER.scope(observer, future).await
+}
+
+/// Tell the observer of the current install, if any, that it moves on to `phase`.
+pub fn phase(phase: Phase) {
+    let _ = OBSERVER.try_with(|observer| observer(Event::Phase(phase)));
+}
+
+/// Progress of one download of `total` bytes: a bar of its own, or periodic status
+/// lines when bars can't be drawn.
+pub struct Transfer {
+    bar: ProgressBar,
+    label: String,
+    total: u64,
+    /// When to log the next status line; `None` while bars are drawn
+    next_status: Option<Instant>,
+    /// Set when the download runs `observed`
+    observer: Option<Observer>,
+}
+
+impl Transfer {
+    /// Start showing the progress of downloading `total` bytes of `label` (an asset
+    /// name). Nothing is shown when informational output is off or the size is unknown.
+    pub fn new(ctx: &Context, label: &str, total: u64) -> Transfer {
+        let shown = ctx.level.shows_info() && total > 0;
+        let bar = if shown && interactive() {
+            let bar = bars().add(ProgressBar::new(total));
+            bar.set_style(ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {prefix}")
+                .unwrap()
+                .progress_chars("#>-"));
+            bar.set_prefix(label.to_string());
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+        Transfer {
+            bar,
+            label: label.to_string(),
+            total,
+            next_status: (shown && !interactive()).then(|| Instant::now() + STATUS_INTERVAL),
+            observer: OBSERVER.try_with(Arc::clone).ok(),
+        }
+    }
+
+    /// Note that `position` bytes have been transferred.
+    pub fn set_position(&mut self, position: u64) {
+        self.bar.set_position(position);
+        if let Some(observer) = &self.observer {
+            observer(Event::Download {
+                downloaded: position,
+                total: self.total,
+            });
+        }
+        if let Some(next_status) = self
+            .next_status
+            .filter(|&next_status| Instant::now() >= next_status)
+        {
+            info!(
+                "{}",
+                tr!(
+                    "download-status",
+                    label = self.label.as_str(),
+                    downloaded = HumanBytes(position).to_string(),
+                    total = HumanBytes(self.total).to_string(),
+                    percent = position * 100 / self.total.max(1)
+                )
+            );
+            self.next_status = Some(next_status + STATUS_INTERVAL);
+        }
+    }
+
+    /// Note that `bytes` more have been transferred.
+    pub fn inc(&mut self, bytes: u64) {
+        self.set_position(self.bar.position() + bytes);
+    }
+
+    /// Leave the completed bar in place.
+    pub fn finish(self) {
+        self.bar.finish();
+    }
+
+    /// Remove the bar of an abandoned transfer.
+    pub fn clear(self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Overall line for a batch of `count` apps, advanced as each one finishes; hidden
+/// when there's a single app or bars can't be drawn.
+pub fn summary(ctx: &Context, count: usize) -> ProgressBar {
+    if !ctx.level.shows_info() || count < 2 || !interactive() {
+        return ProgressBar::hidden();
+    }
+    let bar = bars().add(ProgressBar::new(count as u64));
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green} [{elapsed_precise}] {pos}/{len} apps done {msg}",
+        )
+        .unwrap(),
+    );
+    bar.enable_steady_tick(Duration::from_millis(200));
+    bar
+}
+
+/// A writer for log messages that clears the bars while writing, so messages end up
+/// above them.
+pub struct AboveBars<W>(pub W);
+
+impl<W: Write> Write for AboveBars<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        bars().suspend(|| self.0.write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        bars().suspend(|| self.0.write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,196 @@
+// Release providers: where an app's releases come from. Each forge implements
+// `ReleaseProvider`, mapping its API onto the GitHub-shaped `Release` the install
+// logic works with.
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, trace};
+
+use crate::cache;
+use crate::cli::Provider;
+use crate::config::App;
+use crate::direct::Direct;
+use crate::gitea::Gitea;
+use crate::github::GitHub;
+use crate::gitlab::GitLab;
+use crate::http::HttpFetcher;
+use crate::Error;
+use crate::Release;
+
+/// A source of releases for one app. Implementations may simply use `async fn`; the
+/// futures must be `Send` so installs can run on spawned tasks.
+pub trait ReleaseProvider {
+    /// The latest stable release.
+    fn latest_release(&self) -> impl Future<Output = Result<Release, Error>> + Send;
+
+    /// The release tagged `tag`.
+    fn release_by_tag(&self, tag: &str) -> impl Future<Output = Result<Release, Error>> + Send;
+
+    /// Recent published releases, prereleases included, newest first.
+    fn list_releases(&self) -> impl Future<Output = Result<Vec<Release>, Error>> + Send;
+}
+
+/// The provider an app is configured for.
+pub enum AppProvider<'a> {
+    GitHub(GitHub<'a>),
+    GitLab(GitLab<'a>),
+    Gitea(Gitea<'a>),
+    Direct(Direct<'a>),
+}
+
+impl<'a> AppProvider<'a> {
+    /// Pick the provider for `app`. `github_token` is only ever sent to GitHub; with
+    /// `bypass_cache`, API responses are requested fresh (for polling). The response
+    /// cache's directories are created with `dir_mode`.
+    pub fn for_app(
+        http: &'a dyn HttpFetcher,
+        app: &'a App,
+        github_token: Option<&'a str>,
+        bypass_cache: bool,
+        dir_mode: Option<u32>,
+    ) -> Self {
+        let repo = &app.repo;
+        match repo.provider {
+            Provider::GitHub => AppProvider::GitHub(GitHub::new(
+                http,
+                repo,
+                github_token,
+                bypass_cache,
+                dir_mode,
+            )),
+            Provider::GitLab => {
+                AppProvider::GitLab(GitLab::new(http, repo, bypass_cache, dir_mode))
+            }
+            Provider::Gitea => AppProvider::Gitea(Gitea::new(http, repo, bypass_cache, dir_mode)),
+            Provider::Url => AppProvider::Direct(Direct::new(http, app)),
+        }
+    }
+}
+
+impl ReleaseProvider for AppProvider<'_> {
+    async fn latest_release(&self) -> Result<Release, Error> {
+        match self {
+            AppProvider::GitHub(provider) => provider.latest_release().await,
+            AppProvider::GitLab(provider) => provider.latest_release().await,
+            AppProvider::Gitea(provider) => provider.latest_release().await,
+            AppProvider::Direct(provider) => provider.latest_release().await,
+        }
+    }
+
+    async fn release_by_tag(&self, tag: &str) -> Result<Release, Error> {
+        match self {
+            AppProvider::GitHub(provider) => provider.release_by_tag(tag).await,
+            AppProvider::GitLab(provider) => provider.release_by_tag(tag).await,
+            AppProvider::Gitea(provider) => provider.release_by_tag(tag).await,
+            AppProvider::Direct(provider) => provider.release_by_tag(tag).await,
+        }
+    }
+
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        match self {
+            AppProvider::GitHub(provider) => provider.list_releases().await,
+            AppProvider::GitLab(provider) => provider.list_releases().await,
+            AppProvider::Gitea(provider) => provider.list_releases().await,
+            AppProvider::Direct(provider) => provider.list_releases().await,
+        }
+    }
+}
+
+/// Fetch and deserialize a release (or list of releases), authenticating with `token`
+/// if given. Responses are cached with their ETag and revalidated with
+/// `If-None-Match`, so unchanged releases come from the cache. When `bypass_cache` is
+/// set, the cache is skipped and intermediaries are asked for a fresh copy so polling
+/// actually observes newly uploaded assets. Cache directories are created with
+/// `dir_mode`.
+pub async fn fetch_json<T: DeserializeOwned>(
+    http: &dyn HttpFetcher,
+    api_url: &str,
+    token: Option<&str>,
+    bypass_cache: bool,
+    dir_mode: Option<u32>,
+) -> Result<T, Error> {
+    let mut request = http.get(api_url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let cached = if bypass_cache {
+        None
+    } else {
+        cache::load(api_url)
+    };
+    if bypass_cache {
+        request = request.header(header::CACHE_CONTROL, "no-cache");
+    } else if let Some(cached) = &cached {
+        request = request.header(header::IF_NONE_MATCH, &cached.etag);
+    }
+
+    debug!(
+        "GET {}{}",
+        api_url,
+        if cached.is_some() {
+            " (cached copy available)"
+        } else {
+            ""
+        }
+    );
+    let response = request.send().await?;
+    debug!("{} {}", response.status(), api_url);
+    if let (reqwest::StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), &cached) {
+        debug!("Using the cached response for {}", api_url);
+        return Ok(serde_json::from_str(&cached.body)?);
+    }
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!("Release not found: {}", api_url).into());
+    }
+    if let Some(reset) = rate_limit_reset(&response) {
+        return Err(Error::RateLimited {
+            host: response.url().host_str().unwrap_or("").to_string(),
+            reset,
+        });
+    }
+
+    let etag = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.error_for_status()?.text().await?;
+    trace!("Response body of {}: {}", api_url, body);
+    let release = serde_json::from_str(&body)?;
+    // A failed cache write only costs a full request next time
+    if let Some(etag) = etag {
+        let _ = cache::store(api_url, &etag, &body, dir_mode);
+    }
+
+    Ok(release)
+}
+
+/// When `response` is a rate-limit refusal, the time the limit resets (if announced).
+///
+/// GitHub answers 403 or 429 with `x-ratelimit-remaining: 0`, or with `retry-after`
+/// for its secondary limits; GitLab and Gitea use 429 with `ratelimit-reset`/
+/// `x-ratelimit-reset` headers.
+fn rate_limit_reset(response: &reqwest::Response) -> Option<Option<SystemTime>> {
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let exhausted = header("x-ratelimit-remaining") == Some("0");
+    let retry_after = header("retry-after").and_then(|value| value.parse::<u64>().ok());
+    if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        && !(status == reqwest::StatusCode::FORBIDDEN && (exhausted || retry_after.is_some()))
+    {
+        return None;
+    }
+
+    let reset = header("x-ratelimit-reset")
+        .or_else(|| header("ratelimit-reset"))
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(|epoch| UNIX_EPOCH + Duration::from_secs(epoch))
+        .or_else(|| retry_after.map(|seconds| SystemTime::now() + Duration::from_secs(seconds)));
+    Some(reset)
+}
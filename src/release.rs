@@ -0,0 +1,576 @@
+// Release lookup: finds the release an app should be installed from and picks its
+// AppImage asset, whichever provider the app's releases come from.
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle}; // Rate-limit countdown
+use serde::{Deserialize, Serialize}; // Derive (de)serialization for JSON mapping
+use std::future::Future;
+use std::io::{self, IsTerminal, Write}; // For prompting
+use std::time::{Duration, Instant, SystemTime}; // For polling deadlines
+use tracing::{debug, info};
+
+use crate::arch;
+use crate::config::{App, AssetType, Channel};
+use crate::method;
+use crate::pattern::AssetPattern;
+use crate::progress;
+use crate::provider::{AppProvider, ReleaseProvider};
+use crate::retry;
+use crate::tr;
+use crate::Context;
+use crate::Error;
+
+// Structs matching the JSON structure of the GitHub API; other providers map onto them.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    /// Release notes, usually markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: u64,
+    // "uploaded" once the asset is complete; "starting"/"open" while still uploading
+    #[serde(default = "default_asset_state")]
+    pub state: String,
+    /// Digest of the uploaded file, as `sha256:<hex>`; recent GitHub responses have it
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+fn default_asset_state() -> String {
+    "uploaded".to_string()
+}
+
+/// Whether the asset name `name` can be installed under as it is: not empty, `.` or
+/// `..`, and without path separators or control characters that would put the file
+/// elsewhere or garble listings.
+pub fn is_safe_file_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..")
+        && !name.contains(['/', '\\'])
+        && !name.contains(char::is_control)
+}
+
+/// The file name a server suggested in `name` (say, in a Content-Disposition header)
+/// made safe to install under: its last path component without control characters,
+/// or `None` when nothing usable is left.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    is_safe_file_name(name).then(|| name.to_string())
+}
+
+/// How often to re-query a release while waiting for its assets to finish uploading.
+const ASSET_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Extra time to wait past an announced rate-limit reset, for clock skew.
+const RATE_LIMIT_MARGIN: Duration = Duration::from_secs(2);
+
+/// Which release of a repository to install.
+#[derive(Clone, Copy)]
+pub enum ReleaseSelector<'a> {
+    /// The latest stable release
+    Latest,
+    /// The newest release, including prereleases
+    Newest,
+    /// The release with this tag
+    Tag(&'a str),
+}
+
+impl<'a> ReleaseSelector<'a> {
+    /// Pick the selector from an explicit tag, else from the app's channel.
+    pub fn new(tag: Option<&'a str>, channel: Channel) -> Self {
+        match (tag, channel) {
+            (Some(tag), _) => ReleaseSelector::Tag(tag),
+            (None, Channel::Prerelease) => ReleaseSelector::Newest,
+            (None, Channel::Stable) => ReleaseSelector::Latest,
+        }
+    }
+}
+
+/// Which AppImage (or archive) asset of a release to pick.
+pub struct AssetFilter<'a> {
+    /// Kind of asset the app is installed from
+    pub asset_type: AssetType,
+    /// Target CPU architecture
+    pub arch: &'a str,
+    /// Pattern the asset name must match, if any
+    pub pattern: Option<&'a AssetPattern>,
+    /// Patterns in order of preference; when given, they choose the asset, and with it
+    /// the asset type, instead of `asset_type`
+    pub prefer: &'a [AssetPattern],
+    /// Ask the user when several assets match equally well, instead of taking the first
+    pub interactive: bool,
+}
+
+/// Look up the release of `app` chosen by `selector` and its fully uploaded AppImage
+/// (or, for archive apps, archive) asset chosen by `filter`, polling for up to `wait_for_assets` while the release's
+/// assets are still uploading.
+pub async fn fetch_release_appimage(
+    ctx: &Context,
+    app: &App,
+    selector: ReleaseSelector<'_>,
+    filter: &AssetFilter<'_>,
+    wait_for_assets: Option<Duration>,
+) -> Result<(Release, Asset), Error> {
+    let repo = &app.repo;
+    let (arch, pattern) = (filter.arch, filter.pattern);
+    let kind = match filter.asset_type {
+        _ if !filter.prefer.is_empty() => "asset",
+        AssetType::AppImage => "AppImage",
+        AssetType::Archive => "archive",
+    };
+    let provider = AppProvider::for_app(
+        &*ctx.http,
+        app,
+        ctx.github_token.as_deref(),
+        wait_for_assets.is_some(),
+        ctx.dir_mode,
+    );
+
+    // 5. Fetch the release information, polling while its assets are still uploading
+    //    if --wait-for-assets was given.
+    let deadline = wait_for_assets.map(|timeout| Instant::now() + timeout);
+    loop {
+        let mut release = select_release(ctx, &provider, app, selector).await?;
+
+        // 6. Find the fully uploaded ".AppImage" assets (or archives) for our
+        //    architecture: one naming it explicitly wins over one naming no
+        //    architecture at all.
+        let method = method::for_type(filter.asset_type);
+        let rank = |a: &Asset| filter.prefer.iter().position(|p| p.matches(&a.name));
+        let installable = |a: &Asset| match filter.prefer {
+            [] => method.matches(&a.name),
+            _ => rank(a).is_some() && method::detect(&a.name).is_some(),
+        };
+        let is_candidate = |a: &Asset| {
+            installable(a)
+                && is_safe_file_name(&a.name)
+                && a.state == "uploaded"
+                && pattern.is_none_or(|p| p.matches(&a.name))
+        };
+        let scored: Vec<(u8, usize)> = release
+            .assets
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| is_candidate(a))
+            .filter_map(|(index, a)| arch::score(&a.name, arch).map(|score| (score, index)))
+            .collect();
+        debug!(
+            "Release {} has {} assets",
+            release.tag_name,
+            release.assets.len()
+        );
+        for (index, a) in release.assets.iter().enumerate() {
+            match scored
+                .iter()
+                .find(|&&(_, scored_index)| scored_index == index)
+            {
+                Some((score, _)) => {
+                    debug!("  {}: candidate for {} (score {})", a.name, arch, score)
+                }
+                None if !is_candidate(a) => debug!(
+                    "  {}: skipped (not an uploaded {} matching the filter)",
+                    a.name, kind
+                ),
+                None => debug!("  {}: skipped (built for another architecture)", a.name),
+            }
+        }
+        // The first preference any candidate matches rules out the others
+        let preferred = scored
+            .iter()
+            .filter_map(|&(_, index)| rank(&release.assets[index]))
+            .min();
+        let scored: Vec<(u8, usize)> = scored
+            .into_iter()
+            .filter(|&(_, index)| rank(&release.assets[index]) == preferred)
+            .collect();
+        if let Some(best_score) = scored.iter().map(|&(score, _)| score).max() {
+            let best: Vec<usize> = scored
+                .iter()
+                .filter(|&&(score, _)| score == best_score)
+                .map(|&(_, index)| index)
+                .collect();
+            let index = if best.len() > 1 && filter.interactive {
+                let asset_type =
+                    method::detect(&release.assets[best[0]].name).unwrap_or(filter.asset_type);
+                choose_asset(ctx, &release, &best, asset_type)?
+            } else {
+                best[0]
+            };
+            let asset = release.assets.swap_remove(index);
+            debug!(
+                "Chose {} from {} equally good candidates",
+                asset.name,
+                best.len()
+            );
+            return Ok((release, asset));
+        }
+
+        let other_arch: Vec<&str> = release
+            .assets
+            .iter()
+            .filter(|a| is_candidate(a))
+            .map(|a| a.name.as_str())
+            .collect();
+        if !other_arch.is_empty() {
+            return Err(Error::NoMatchingAsset {
+                repo: repo.to_string(),
+                tag: release.tag_name,
+                reason: format!(
+                    "none for {} (available: {}); use --arch to pick another architecture",
+                    arch,
+                    other_arch.join(", ")
+                ),
+            });
+        }
+
+        let still_uploading =
+            release.assets.is_empty() || release.assets.iter().any(|a| a.state != "uploaded");
+        match deadline {
+            Some(deadline) if still_uploading && Instant::now() < deadline => {
+                info!(
+                    "{}",
+                    tr!(
+                        "waiting-for-assets",
+                        version = release.tag_name.as_str(),
+                        method = filter.asset_type.name()
+                    )
+                );
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                tokio::time::sleep(remaining.min(ASSET_POLL_INTERVAL)).await;
+            }
+            Some(_) if still_uploading => {
+                return Err(tr!(
+                    "assets-upload-timeout",
+                    kind = kind,
+                    version = release.tag_name.as_str()
+                )
+                .into());
+            }
+            None if still_uploading => {
+                return Err(tr!(
+                    "assets-not-uploaded",
+                    kind = kind,
+                    version = release.tag_name.as_str()
+                )
+                .into());
+            }
+            _ => {
+                let reason = if !filter.prefer.is_empty() {
+                    "no installable asset matches the app's prefer patterns".to_string()
+                } else if pattern.is_some() {
+                    format!("no {} matches the asset filter", kind)
+                } else {
+                    format!("it has no {} assets", kind)
+                };
+                return Err(Error::NoMatchingAsset {
+                    repo: repo.to_string(),
+                    tag: release.tag_name,
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+/// Look up the release of `app` chosen by `selector`, whatever its assets.
+pub async fn fetch_release(
+    ctx: &Context,
+    app: &App,
+    selector: ReleaseSelector<'_>,
+) -> Result<Release, Error> {
+    let provider = AppProvider::for_app(
+        &*ctx.http,
+        app,
+        ctx.github_token.as_deref(),
+        false,
+        ctx.dir_mode,
+    );
+    select_release(ctx, &provider, app, selector).await
+}
+
+async fn select_release(
+    ctx: &Context,
+    provider: &AppProvider<'_>,
+    app: &App,
+    selector: ReleaseSelector<'_>,
+) -> Result<Release, Error> {
+    Ok(match selector {
+        ReleaseSelector::Latest => wait_out_rate_limit(ctx, || provider.latest_release()).await?,
+        ReleaseSelector::Tag(tag) => {
+            wait_out_rate_limit(ctx, || provider.release_by_tag(tag)).await?
+        }
+        ReleaseSelector::Newest => wait_out_rate_limit(ctx, || provider.list_releases())
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No releases found for {}", app.repo))?,
+    })
+}
+
+/// Run the API request made by `request`, retrying transient failures and, after a
+/// countdown, rate-limited requests whose limit resets within `ctx.rate_limit_wait`.
+pub(crate) async fn wait_out_rate_limit<T, F, Fut>(
+    ctx: &Context,
+    mut request: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let deadline = ctx.rate_limit_wait.map(|limit| Instant::now() + limit);
+    // Each attempt holds a slot of max_parallel_api_requests, but not the backoff between them
+    let mut request = || {
+        let attempt = request();
+        async move {
+            let _permit = ctx.api_permit().await;
+            attempt.await
+        }
+    };
+    loop {
+        let (host, reset) = match retry::retry_transient(ctx, &mut request).await {
+            Err(Error::RateLimited {
+                host,
+                reset: Some(reset),
+            }) => (host, reset),
+            result => return result,
+        };
+        let wait = reset.duration_since(SystemTime::now()).unwrap_or_default() + RATE_LIMIT_MARGIN;
+        if deadline.is_none_or(|deadline| Instant::now() + wait > deadline) {
+            return Err(Error::RateLimited {
+                host,
+                reset: Some(reset),
+            });
+        }
+
+        let countdown = if ctx.level.shows_info() {
+            progress::bars().add(ProgressBar::new_spinner())
+        } else {
+            ProgressBar::hidden()
+        };
+        countdown.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+        let resume = Instant::now() + wait;
+        loop {
+            let remaining = resume.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let seconds = remaining.as_secs_f64().ceil() as u64;
+            countdown.set_message(tr!(
+                "rate-limited-countdown",
+                host = host.as_str(),
+                time = format!("{}:{:02}", seconds / 60, seconds % 60)
+            ));
+            countdown.tick();
+            tokio::time::sleep(remaining.min(Duration::from_secs(1))).await;
+        }
+        countdown.finish_and_clear();
+    }
+}
+
+/// Ask the user which of several equally suitable assets to install.
+///
+/// Prompts never appear when they can't be answered (stdin or stderr not a terminal)
+/// or when output is quieted; the selection then fails with a hint instead.
+fn choose_asset(
+    ctx: &Context,
+    release: &Release,
+    candidates: &[usize],
+    asset_type: AssetType,
+) -> Result<usize, Error> {
+    let names: Vec<&str> = candidates
+        .iter()
+        .map(|&i| release.assets[i].name.as_str())
+        .collect();
+    if !ctx.level.shows_info() || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Err(tr!(
+            "choose-asset-ambiguous",
+            method = asset_type.name(),
+            version = release.tag_name.as_str(),
+            assets = names.join(", ")
+        )
+        .into());
+    }
+
+    eprintln!(
+        "{}",
+        tr!(
+            "choose-asset-title",
+            method = asset_type.name(),
+            version = release.tag_name.as_str()
+        )
+    );
+    for (number, &index) in candidates.iter().enumerate() {
+        let asset = &release.assets[index];
+        eprintln!(
+            "  {}) {} ({})",
+            number + 1,
+            asset.name,
+            HumanBytes(asset.size)
+        );
+    }
+
+    loop {
+        eprint!(
+            "{} ",
+            tr!(
+                "choose-asset-prompt",
+                method = asset_type.name(),
+                count = candidates.len()
+            )
+        );
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Err("No asset chosen".into());
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=candidates.len()).contains(&number) => {
+                return Ok(candidates[number - 1])
+            }
+            _ => eprintln!("{}", tr!("choose-asset-invalid", count = candidates.len())),
+        }
+    }
+}
+
+/// The app's configured `prefer` patterns, parsed, most preferred first.
+pub fn configured_preferences(app: &App) -> Result<Vec<AssetPattern>, Error> {
+    app.settings
+        .prefer
+        .iter()
+        .map(|pattern| {
+            AssetPattern::parse(pattern)
+                .map_err(|e| format!("Config entry for app '{}': prefer: {}", app.name, e).into())
+        })
+        .collect()
+}
+
+/// The app's configured `asset_pattern`, parsed.
+pub fn configured_pattern(app: &App) -> Result<Option<AssetPattern>, Error> {
+    match &app.settings.asset_pattern {
+        Some(pattern) => {
+            Ok(Some(AssetPattern::parse(pattern).map_err(|e| {
+                format!("Config entry for app '{}': {}", app.name, e)
+            })?))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::http::HttpFetcher;
+    use crate::OutputLevel;
+    use reqwest::{Request, Response};
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    /// Answers every request with a release offering `assets`.
+    struct Releases {
+        assets: &'static [&'static str],
+    }
+
+    impl HttpFetcher for Releases {
+        fn execute(
+            &self,
+            _request: Request,
+        ) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send + '_>> {
+            let assets: Vec<_> = self
+                .assets
+                .iter()
+                .map(|name| {
+                    serde_json::json!({
+                        "name": name,
+                        "browser_download_url": format!("https://example.com/{}", name),
+                    })
+                })
+                .collect();
+            let body = serde_json::json!({ "tag_name": "1.0", "assets": assets }).to_string();
+            Box::pin(async move { Ok(Response::from(http::Response::new(body))) })
+        }
+    }
+
+    /// The asset picked for `owner/tool` from `assets`, preferring `prefer`.
+    async fn pick(assets: &'static [&'static str], prefer: &[&str]) -> Result<String, Error> {
+        let mut ctx = Context::new(Config::default(), OutputLevel::Silent, None).unwrap();
+        ctx.http = Arc::new(Releases { assets });
+        let app = ctx.config.resolve("owner/tool", None).unwrap();
+        let prefer: Vec<AssetPattern> = prefer
+            .iter()
+            .map(|pattern| AssetPattern::parse(pattern).unwrap())
+            .collect();
+        let filter = AssetFilter {
+            asset_type: AssetType::AppImage,
+            arch: "x86_64",
+            pattern: None,
+            prefer: &prefer,
+            interactive: false,
+        };
+        let (_, asset) =
+            fetch_release_appimage(&ctx, &app, ReleaseSelector::Latest, &filter, None).await?;
+        Ok(asset.name)
+    }
+
+    #[test]
+    fn suggested_file_names_are_made_safe() {
+        let sanitized = |name| sanitize_file_name(name);
+        assert_eq!(sanitized("Tool.AppImage").as_deref(), Some("Tool.AppImage"));
+        assert_eq!(
+            sanitized("../../.local/bin/Tool.AppImage").as_deref(),
+            Some("Tool.AppImage")
+        );
+        assert_eq!(
+            sanitized("C:\\Temp\\Tool\n.AppImage").as_deref(),
+            Some("Tool.AppImage")
+        );
+        for name in ["", "..", "dir/", " . "] {
+            assert_eq!(sanitized(name), None, "{:?} was accepted", name);
+        }
+        assert!(!is_safe_file_name("../Tool.AppImage"));
+        assert!(!is_safe_file_name("Tool\u{1b}[2J.AppImage"));
+    }
+
+    #[tokio::test]
+    async fn prefer_patterns_pick_the_asset_in_order() {
+        let assets = &[
+            "tool-1.0-x86_64.AppImage",
+            "tool-1.0-aarch64.tar.gz",
+            "tool-1.0-x86_64.tar.gz",
+            "tool-1.0-x86_64.deb",
+        ];
+        assert_eq!(
+            pick(assets, &["*.tar.gz", "*.AppImage"]).await.unwrap(),
+            "tool-1.0-x86_64.tar.gz"
+        );
+        assert_eq!(
+            pick(assets, &["*.AppImage", "*.tar.gz"]).await.unwrap(),
+            "tool-1.0-x86_64.AppImage"
+        );
+        // A preference nothing can install from, or built for another architecture,
+        // gives way to the next
+        assert_eq!(
+            pick(assets, &["*.deb", "*aarch64*", "*.tar.gz"])
+                .await
+                .unwrap(),
+            "tool-1.0-x86_64.tar.gz"
+        );
+        assert!(matches!(
+            pick(assets, &["*.deb", "*.rpm"]).await,
+            Err(Error::NoMatchingAsset { .. })
+        ));
+        // Without preferences, the asset type decides
+        assert_eq!(pick(assets, &[]).await.unwrap(), "tool-1.0-x86_64.AppImage");
+    }
+}
@@ -0,0 +1,84 @@
+// Retry policy for transient network failures: connection resets, timeouts and 5xx
+// responses are retried with jittered exponential backoff instead of aborting.
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::tr;
+use crate::Context;
+use crate::Error;
+
+/// Retries after a transient failure when the config sets no `retries`.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Backoff before the first retry; it doubles for each further one.
+const BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest backoff between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Whether `error` is a failure that may well succeed when tried again.
+pub fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::Network(e) => {
+            e.is_connect()
+                || e.is_timeout()
+                || e.is_request()
+                || e.is_body()
+                // A connection dropped mid-body surfaces as a decoding error
+                || e.is_decode()
+                || e.status().is_some_and(|status| status.is_server_error())
+        }
+        _ => false,
+    }
+}
+
+/// Delay before retry number `retry` (starting at 1): exponential, capped, and
+/// jittered between half and the full delay so parallel updates don't retry in lockstep.
+pub fn backoff(retry: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(1 << (retry - 1).min(16))
+        .min(MAX_DELAY);
+    // The clock's sub-second noise is random enough for spreading retries
+    let noise = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos()
+        % 1000;
+    delay / 2 + delay.mul_f64(f64::from(noise) / 2000.0)
+}
+
+/// Wait before retry number `retry` after `error`, warning about it.
+pub async fn pause(ctx: &Context, retry: u32, error: &Error) {
+    let delay = backoff(retry);
+    warn!(
+        "{}",
+        tr!(
+            "retrying",
+            error = error.to_string(),
+            delay = format!("{:.1}", delay.as_secs_f64()),
+            retry = retry,
+            retries = ctx.retries
+        )
+    );
+    tokio::time::sleep(delay).await;
+}
+
+/// Run the request made by `request`, retrying it up to `ctx.retries` times while it
+/// fails transiently.
+pub async fn retry_transient<T, F, Fut>(ctx: &Context, mut request: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut retry = 0;
+    loop {
+        match request().await {
+            Err(e) if retry < ctx.retries && is_transient(&e) => {
+                retry += 1;
+                pause(ctx, retry, &e).await;
+            }
+            result => return result,
+        }
+    }
+}
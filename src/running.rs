@@ -0,0 +1,194 @@
+// Running instances of installed AppImages. Replacing an AppImage under a running
+// instance can upset its mounted squashfs, so updates look for them first and, when
+// asked, stop them gracefully. `install --run` starts them here too.
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::{progress, tr, Context, Error};
+
+/// How long stopped instances get to exit.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A process running an AppImage.
+pub struct Instance {
+    pub pid: u32,
+    /// The process's command name
+    pub name: String,
+}
+
+/// Processes running the AppImage at `path`: its runtime, whose executable is the
+/// file itself, and the app's processes, which the runtime gives `APPIMAGE=<path>`.
+/// Processes of other users can't be inspected and are not found.
+pub fn find(path: &Path) -> Vec<Instance> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut environment_entry = b"APPIMAGE=".to_vec();
+    environment_entry.extend_from_slice(path.as_os_str().as_bytes());
+
+    let mut instances: Vec<Instance> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            if pid == std::process::id() {
+                return None;
+            }
+            let dir = entry.path();
+            let runs_file = fs::read_link(dir.join("exe")).is_ok_and(|exe| exe == path);
+            let runs_app = || {
+                fs::read(dir.join("environ")).is_ok_and(|environ| {
+                    environ
+                        .split(|&byte| byte == 0)
+                        .any(|entry| entry == environment_entry)
+                })
+            };
+            if !runs_file && !runs_app() {
+                return None;
+            }
+            let name = fs::read_to_string(dir.join("comm"))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            Some(Instance { pid, name })
+        })
+        .collect();
+    instances.sort_by_key(|instance| instance.pid);
+    instances
+}
+
+/// Deal with `instances` of `app_name` before its AppImage is replaced: stop them when
+/// `stop` is set or the user agrees when asked (only when `interactive`), otherwise
+/// warn that they keep running the old version.
+pub async fn handle(
+    ctx: &Context,
+    app_name: &str,
+    instances: &[Instance],
+    stop: bool,
+    interactive: bool,
+) -> Result<(), Error> {
+    if instances.is_empty() {
+        return Ok(());
+    }
+    let list = describe(instances);
+    let stop = stop
+        || (interactive
+            && confirm(
+                ctx,
+                &tr!(
+                    "app-running-question",
+                    app = app_name,
+                    instances = list.as_str()
+                ),
+            )?);
+    if !stop {
+        warn!(
+            "{}",
+            tr!("app-running", app = app_name, instances = list.as_str())
+        );
+        return Ok(());
+    }
+
+    info!(
+        "{}",
+        tr!("stopping-app", app = app_name, instances = list.as_str())
+    );
+    terminate(instances)
+        .await
+        .map_err(|e| format!("Cannot stop {}: {}", app_name, e).into())
+}
+
+/// `PID 12 (name), PID 34 (name)`.
+fn describe(instances: &[Instance]) -> String {
+    instances
+        .iter()
+        .map(|instance| format!("PID {} ({})", instance.pid, instance.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ask a yes/no `question`, defaulting to no. Prompts only appear when they can be
+/// answered (stdin and stderr are terminals) and output isn't quieted.
+pub fn confirm(ctx: &Context, question: &str) -> Result<bool, Error> {
+    if !ctx.level.shows_info() || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(false);
+    }
+    progress::bars().suspend(|| {
+        eprint!("{} {} ", question, tr!("confirm-choices"));
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim().to_lowercase();
+        Ok(!answer.is_empty()
+            && tr!("confirm-yes")
+                .split_whitespace()
+                .any(|yes| yes == answer))
+    })
+}
+
+/// Send SIGTERM to `instances` and wait for them to exit.
+async fn terminate(instances: &[Instance]) -> Result<(), String> {
+    for instance in instances {
+        debug!("Sending SIGTERM to {}", instance.pid);
+        // SAFETY: kill only takes plain integers
+        if unsafe { libc::kill(instance.pid as libc::pid_t, libc::SIGTERM) } != 0 {
+            let error = io::Error::last_os_error();
+            // Exited in the meantime
+            if error.raw_os_error() != Some(libc::ESRCH) {
+                return Err(format!("PID {}: {}", instance.pid, error));
+            }
+        }
+    }
+
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    loop {
+        let remaining: Vec<&Instance> = instances
+            .iter()
+            .filter(|instance| alive(instance.pid))
+            .collect();
+        if remaining.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            let pids: Vec<String> = remaining
+                .iter()
+                .map(|instance| instance.pid.to_string())
+                .collect();
+            return Err(format!(
+                "still running after {}s (PID {})",
+                STOP_TIMEOUT.as_secs(),
+                pids.join(", ")
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Whether process `pid` still exists (and isn't a zombie waiting to be reaped).
+fn alive(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        // The state follows the parenthesized command name, which may contain spaces
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .is_some_and(|(_, rest)| !rest.trim_start().starts_with('Z')),
+        Err(_) => false,
+    }
+}
+
+/// Start the AppImage at `path` in the background, returning its PID. It gets a
+/// process group of its own, so it outlives the terminal's Ctrl-C.
+pub fn launch(path: &Path) -> Result<u32, Error> {
+    debug!("Starting {}", path.display());
+    let child = Command::new(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    Ok(child.id())
+}
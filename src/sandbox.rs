@@ -0,0 +1,219 @@
+// Sandboxed launch wrappers: a script starting the AppImage inside firejail or
+// bubblewrap, which the app's symlink and desktop entry point at instead of the
+// AppImage. Each app gets `$XDG_DATA_HOME/rust-unicorn/sandbox/<app>/` holding the
+// wrapper, its profile and the home directory the app sees instead of the user's.
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::install::create_dirs;
+use crate::{paths, Error};
+
+/// Tool confining a sandboxed app.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Sandbox {
+    /// firejail, with a firejail profile
+    Firejail,
+    /// bubblewrap, with a list of bwrap arguments
+    Bwrap,
+}
+
+impl Sandbox {
+    /// The tool's command.
+    pub fn program(self) -> &'static str {
+        match self {
+            Sandbox::Firejail => "firejail",
+            Sandbox::Bwrap => "bwrap",
+        }
+    }
+
+    /// Make sure the tool is installed, so `app_name` can be sandboxed.
+    pub fn check(self, app_name: &str) -> Result<(), Error> {
+        if on_path(self.program()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Cannot sandbox {}: {} is not installed",
+                app_name,
+                self.program()
+            )
+            .into())
+        }
+    }
+
+    /// Name of the per-app profile file.
+    fn profile_name(self) -> &'static str {
+        match self {
+            Sandbox::Firejail => "firejail.profile",
+            Sandbox::Bwrap => "bwrap.args",
+        }
+    }
+}
+
+/// Directory of `app_name`'s wrapper, profiles and sandboxed home.
+fn sandbox_dir(app_name: &str) -> Result<PathBuf, Error> {
+    Ok(paths::data_home()
+        .ok_or("Cannot determine the sandbox directory: HOME is not set")?
+        .join("rust-unicorn")
+        .join("sandbox")
+        .join(app_name))
+}
+
+/// Path of `app_name`'s launch wrapper.
+pub fn wrapper_path(app_name: &str) -> Result<PathBuf, Error> {
+    Ok(sandbox_dir(app_name)?.join("launch"))
+}
+
+/// Write the wrapper starting `appimage` inside `sandbox`, returning its path; with
+/// `app_dir`, the AppImage extracted there is started instead. The profile is only
+/// written when missing, so the user's changes to it are kept; the sandbox's
+/// directories are created with `dir_mode`.
+pub fn write_wrapper(
+    sandbox: Sandbox,
+    app_name: &str,
+    appimage: &Path,
+    app_dir: Option<&Path>,
+    dir_mode: Option<u32>,
+) -> Result<PathBuf, Error> {
+    sandbox.check(app_name)?;
+    let dir = sandbox_dir(app_name)?;
+    let home = dir.join("home");
+    create_dirs(&home, dir_mode)?;
+
+    let profile = dir.join(sandbox.profile_name());
+    if !profile.exists() {
+        debug!("Writing {}", profile.display());
+        fs::write(&profile, default_profile(sandbox, app_name, &home)?)?;
+    }
+
+    let profile = quote(&profile.to_string_lossy());
+    let command = match (sandbox, app_dir) {
+        (Sandbox::Firejail, None) => {
+            format!(
+                "exec firejail --profile={} --appimage {} \"$@\"",
+                profile,
+                quote(&appimage.to_string_lossy())
+            )
+        }
+        (Sandbox::Firejail, Some(_)) => {
+            return Err("firejail can only run AppImages, not extracted apps".into())
+        }
+        // The profile's arguments are split on whitespace, without globbing. FUSE is
+        // out of reach inside the sandbox, so the AppImage extracts itself instead
+        (Sandbox::Bwrap, None) => {
+            let appimage = quote(&appimage.to_string_lossy());
+            format!(
+                "set -f\n\
+                 exec bwrap $(grep -v '^[[:space:]]*#' {profile}) --ro-bind {appimage} {appimage} \
+                 --setenv APPIMAGE_EXTRACT_AND_RUN 1 -- {appimage} \"$@\"",
+            )
+        }
+        (Sandbox::Bwrap, Some(app_dir)) => {
+            let (app_dir, app_run) = (
+                quote(&app_dir.to_string_lossy()),
+                quote(&app_dir.join("AppRun").to_string_lossy()),
+            );
+            format!(
+                "set -f\n\
+                 exec bwrap $(grep -v '^[[:space:]]*#' {profile}) --ro-bind {app_dir} {app_dir} -- {app_run} \"$@\"",
+            )
+        }
+    };
+    let path = wrapper_path(app_name)?;
+    debug!("Writing {}", path.display());
+    fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\n\
+             # Starts {} in {} with the profile {}.\n\
+             # rust-unicorn rewrites this script on every install; edit the profile instead.\n\
+             {}\n",
+            app_name,
+            sandbox.program(),
+            profile,
+            command
+        ),
+    )?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
+
+/// Delete `app_name`'s wrapper. The profile and the app's sandboxed home stay, like
+/// the settings unconfined apps leave in the real home.
+pub fn remove_wrapper(app_name: &str) -> Result<(), Error> {
+    let Ok(path) = wrapper_path(app_name) else {
+        return Ok(());
+    };
+    if path.exists() {
+        debug!("Removing {}", path.display());
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The profile a new sandboxed app starts with: the system read-only, the app's own
+/// `home` in place of the user's, and the display, sound and network reachable.
+fn default_profile(sandbox: Sandbox, app_name: &str, home: &Path) -> Result<String, Error> {
+    let profile = match sandbox {
+        Sandbox::Firejail => format!(
+            "# firejail profile for {}. rust-unicorn writes it once and keeps your changes.\n\
+             # The app gets a home directory of its own instead of yours\n\
+             private {}\n\
+             include default.profile\n",
+            app_name,
+            home.display()
+        ),
+        Sandbox::Bwrap => {
+            let user_home =
+                paths::home().ok_or("Cannot determine the home directory: HOME is not set")?;
+            let runtime_dir = env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                // SAFETY: getuid has no preconditions
+                .unwrap_or_else(|| {
+                    PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() }))
+                });
+            format!(
+                "# bubblewrap arguments for {}, separated by whitespace; lines starting with #\n\
+                 # are ignored. rust-unicorn writes this file once and keeps your changes.\n\
+                 # The system, read-only, with a home directory of the app's own instead of yours\n\
+                 --ro-bind / /\n\
+                 --dev /dev\n\
+                 --proc /proc\n\
+                 --tmpfs /tmp\n\
+                 --bind {} {}\n\
+                 # Display, sound and the session bus\n\
+                 --ro-bind-try /tmp/.X11-unix /tmp/.X11-unix\n\
+                 --bind-try {} {}\n\
+                 --unshare-all\n\
+                 --share-net\n\
+                 --die-with-parent\n",
+                app_name,
+                home.display(),
+                user_home.display(),
+                runtime_dir.display(),
+                runtime_dir.display()
+            )
+        }
+    };
+    Ok(profile)
+}
+
+/// Whether `program` is an executable on PATH.
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| {
+            fs::metadata(dir.join(program)).is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+    })
+}
+
+/// Quote `arg` for the shell.
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
@@ -0,0 +1,250 @@
+// `schedule`: unattended updates through a systemd user timer running
+// `rust-unicorn update --all --quiet`.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+
+use crate::cli::OutputFormat;
+use crate::install::create_dirs;
+use crate::{paths, tr, Context, Error};
+
+/// Name shared by the service and timer units.
+const UNIT: &str = "rust-unicorn-update";
+
+/// Directory for the user's own systemd units: `$XDG_CONFIG_HOME/systemd/user`.
+fn unit_dir() -> Result<PathBuf, Error> {
+    Ok(paths::config_home()
+        .ok_or("Cannot determine the systemd unit directory: HOME is not set")?
+        .join("systemd")
+        .join("user"))
+}
+
+/// Write the service and timer units running `update --all` at `calendar` (a systemd
+/// `OnCalendar` expression such as `daily`), then enable and start the timer.
+/// `config_path` is passed on to the scheduled runs when given.
+pub fn enable(ctx: &Context, calendar: &str, config_path: Option<&Path>) -> Result<(), Error> {
+    let dir = unit_dir()?;
+    create_dirs(&dir, None)?;
+
+    let mut command = vec![env::current_exe()?.canonicalize()?];
+    if let Some(config_path) = config_path {
+        command.extend([PathBuf::from("--config"), std::path::absolute(config_path)?]);
+    }
+    // A manual install in progress delays the scheduled run rather than failing it
+    command.extend(["update", "--all", "--quiet", "--wait-for-lock"].map(PathBuf::from));
+    let exec_start: Vec<String> = command
+        .iter()
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect();
+
+    let service = format!(
+        "[Unit]\n\
+         Description=Update AppImages installed by rust-unicorn\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={}\n",
+        exec_start.join(" ")
+    );
+    // Persistent catches up on runs missed while the machine was off; the random
+    // delay keeps every machine from hitting the API at midnight
+    let timer = format!(
+        "[Unit]\n\
+         Description=Scheduled AppImage updates by rust-unicorn\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={}\n\
+         Persistent=true\n\
+         RandomizedDelaySec=1h\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        calendar
+    );
+    let (service_path, timer_path) = unit_paths(&dir);
+    debug!(
+        "Writing {} and {}",
+        service_path.display(),
+        timer_path.display()
+    );
+    fs::write(&service_path, service)?;
+    fs::write(&timer_path, timer)?;
+
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &format!("{}.timer", UNIT)])?;
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "action": "enabled",
+            "schedule": calendar,
+            "timer": timer_path,
+            "service": service_path,
+        }))?;
+    } else {
+        info!(
+            "{}",
+            tr!(
+                "schedule-enabled",
+                schedule = calendar,
+                timer = timer_path.display().to_string()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Stop and disable the timer and delete both units.
+pub fn disable(ctx: &Context) -> Result<(), Error> {
+    let (service_path, timer_path) = unit_paths(&unit_dir()?);
+    let enabled = timer_path.exists();
+    if enabled {
+        systemctl(&["disable", "--now", &format!("{}.timer", UNIT)])?;
+        for path in [&timer_path, &service_path] {
+            if path.exists() {
+                debug!("Removing {}", path.display());
+                fs::remove_file(path)?;
+            }
+        }
+        systemctl(&["daemon-reload"])?;
+    }
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "action": if enabled { "disabled" } else { "not-scheduled" },
+        }))?;
+    } else if enabled {
+        info!("{}", tr!("schedule-disabled"));
+    } else {
+        info!("{}", tr!("schedule-none"));
+    }
+    Ok(())
+}
+
+/// Report whether updates are scheduled, how often, and when the timer last ran and
+/// runs next.
+pub fn status(ctx: &Context) -> Result<(), Error> {
+    let (_, timer_path) = unit_paths(&unit_dir()?);
+    let calendar = fs::read_to_string(&timer_path).ok().and_then(|timer| {
+        timer
+            .lines()
+            .find_map(|line| line.strip_prefix("OnCalendar="))
+            .map(str::to_string)
+    });
+    // A missing user session only loses the run times, not the rest of the report
+    let properties = match &calendar {
+        Some(_) => show_timer().unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty() && *value != "n/a")
+    };
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "scheduled": calendar.is_some(),
+            "schedule": calendar,
+            "active": property("ActiveState") == Some("active"),
+            "last_run": property("LastTriggerUSec"),
+            "next_run": property("NextElapseUSecRealtime"),
+            "timer": calendar.as_ref().map(|_| &timer_path),
+        }))?;
+    } else if let Some(calendar) = &calendar {
+        info!(
+            "{}",
+            tr!(
+                "schedule-status",
+                schedule = calendar,
+                timer = timer_path.display().to_string()
+            )
+        );
+        let (unknown, never) = (tr!("schedule-unknown"), tr!("schedule-never"));
+        info!(
+            "  {}",
+            tr!(
+                "schedule-timer",
+                state = property("ActiveState").unwrap_or(&unknown)
+            )
+        );
+        info!(
+            "  {}",
+            tr!(
+                "schedule-last-run",
+                time = property("LastTriggerUSec").unwrap_or(&never)
+            )
+        );
+        info!(
+            "  {}",
+            tr!(
+                "schedule-next-run",
+                time = property("NextElapseUSecRealtime").unwrap_or(&unknown)
+            )
+        );
+    } else {
+        info!("{}", tr!("schedule-none-hint"));
+    }
+    Ok(())
+}
+
+/// Paths of the service and timer units in `dir`.
+fn unit_paths(dir: &Path) -> (PathBuf, PathBuf) {
+    (
+        dir.join(format!("{}.service", UNIT)),
+        dir.join(format!("{}.timer", UNIT)),
+    )
+}
+
+/// The timer's state from `systemctl --user show`, as key-value pairs.
+fn show_timer() -> Result<Vec<(String, String)>, Error> {
+    let output = systemctl(&[
+        "show",
+        &format!("{}.timer", UNIT),
+        "--property=ActiveState,LastTriggerUSec,NextElapseUSecRealtime",
+    ])?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect())
+}
+
+/// Run `systemctl --user` with `args`, returning its output.
+fn systemctl(args: &[&str]) -> Result<String, Error> {
+    debug!("Running systemctl --user {}", args.join(" "));
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Cannot run systemctl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "systemctl --user {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Quote `arg` for a unit file command line when it needs it.
+fn quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "\"'\\;$%".contains(c)) {
+        // `$` and `%` have their own escapes; the rest is C-style inside double quotes
+        let escaped = arg
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "$$")
+            .replace('%', "%%");
+        format!("\"{}\"", escaped)
+    } else {
+        arg.to_string()
+    }
+}
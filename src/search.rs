@@ -0,0 +1,164 @@
+// `search`: finds AppImage-shipping projects in the built-in catalog and on GitHub,
+// so discovering an app doesn't take a browser.
+use futures_util::stream::{self, StreamExt};
+use tracing::{debug, info, warn};
+
+use crate::cli::{self, OutputFormat};
+use crate::github::{self, GitHub};
+use crate::provider::ReleaseProvider;
+use crate::release::wait_out_rate_limit;
+use crate::{catalog, tr, Context, Error};
+
+/// Latest releases checked for AppImages at once.
+const CONCURRENT_CHECKS: usize = 8;
+
+/// A project that can be installed.
+struct Found {
+    /// Name or repository to pass to `install`
+    install: String,
+    repo: String,
+    description: Option<String>,
+    /// Only known for projects GitHub returned
+    stars: Option<u64>,
+    from_catalog: bool,
+}
+
+/// Print up to `limit` GitHub projects matching `term` whose latest release has an
+/// AppImage, after the matching catalog apps.
+pub async fn search(ctx: &Context, term: &str, limit: usize) -> Result<(), Error> {
+    let matches: Vec<_> = catalog::search(term).collect();
+    // Many matches ship no AppImage at all, so ask for more than will be shown
+    let query = format!(
+        "{} appimage in:name,description,readme archived:false",
+        term
+    );
+    let per_page = (limit * 3).clamp(10, 100);
+    let repositories = match github::search_repositories(
+        &*ctx.http,
+        ctx.github_token.as_deref(),
+        &query,
+        per_page,
+        ctx.dir_mode,
+    )
+    .await
+    {
+        Ok(repositories) => repositories,
+        // The catalog alone is still worth showing, say when offline
+        Err(e) if !matches.is_empty() => {
+            warn!("{}", tr!("search-github-failed", error = e.to_string()));
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut found: Vec<Found> = matches
+        .into_iter()
+        .map(|(name, entry)| Found {
+            install: name.clone(),
+            repo: entry.repo.clone(),
+            description: Some(entry.description.clone()),
+            stars: repositories
+                .iter()
+                .find(|repository| repository.full_name.eq_ignore_ascii_case(&entry.repo))
+                .map(|repository| repository.stargazers_count),
+            from_catalog: true,
+        })
+        .collect();
+
+    // Catalog apps are listed already; check the rest in order of stars
+    let candidates = repositories.into_iter().filter(|repository| {
+        !catalog::entries()
+            .values()
+            .any(|entry| entry.repo.eq_ignore_ascii_case(&repository.full_name))
+    });
+    let mut checks = stream::iter(candidates)
+        .map(|repository| async move {
+            let ships = ships_appimage(ctx, &repository.full_name).await;
+            (repository, ships)
+        })
+        .buffered(CONCURRENT_CHECKS);
+    let mut shown = 0;
+    while shown < limit {
+        let Some((repository, ships)) = checks.next().await else {
+            break;
+        };
+        if ships? {
+            found.push(Found {
+                install: repository.full_name.clone(),
+                repo: repository.full_name,
+                description: repository.description,
+                stars: Some(repository.stargazers_count),
+                from_catalog: false,
+            });
+            shown += 1;
+        }
+    }
+
+    if ctx.output == OutputFormat::Json {
+        let results: Vec<_> = found
+            .iter()
+            .map(|found| {
+                serde_json::json!({
+                    "repo": found.repo,
+                    "description": found.description,
+                    "stars": found.stars,
+                    "catalog": found.from_catalog,
+                    "install": format!("rust-unicorn install {}", found.install),
+                })
+            })
+            .collect();
+        return ctx.print_json(&results);
+    }
+    if found.is_empty() {
+        info!("{}", tr!("search-none", term = term));
+        return Ok(());
+    }
+    for found in &found {
+        let stars = found
+            .stars
+            .map(|stars| format!("  ★ {}", stars))
+            .unwrap_or_default();
+        let catalog = if found.from_catalog {
+            format!("  {}", tr!("search-from-catalog"))
+        } else {
+            String::new()
+        };
+        info!("{}{}{}", found.repo, stars, catalog);
+        if let Some(description) = found
+            .description
+            .as_deref()
+            .filter(|description| !description.is_empty())
+        {
+            info!("    {}", description);
+        }
+        info!(
+            "    {}",
+            tr!("search-install", spec = found.install.as_str())
+        );
+    }
+    Ok(())
+}
+
+/// Whether the latest release of the GitHub repository `full_name` has an AppImage.
+/// Only a rate limit is an error; a repository without releases just doesn't qualify.
+async fn ships_appimage(ctx: &Context, full_name: &str) -> Result<bool, Error> {
+    let repo = cli::parse_repo(full_name, None)?;
+    let provider = GitHub::new(
+        &*ctx.http,
+        &repo,
+        ctx.github_token.as_deref(),
+        false,
+        ctx.dir_mode,
+    );
+    match wait_out_rate_limit(ctx, || provider.latest_release()).await {
+        Ok(release) => Ok(release
+            .assets
+            .iter()
+            .any(|asset| asset.name.ends_with(".AppImage"))),
+        Err(e @ Error::RateLimited { .. }) => Err(e),
+        Err(e) => {
+            debug!("Skipping {}: {}", full_name, e);
+            Ok(false)
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// `self-update`: replaces the running rust-unicorn binary with the latest release,
+// verified and swapped in atomically like a managed app.
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt; // For setting Unix permission bits
+use std::path::Path;
+use tracing::info;
+
+use crate::cli::{self, OutputFormat};
+use crate::config::{App, AppConfig};
+use crate::history::{self, Entry};
+use crate::provider::{AppProvider, ReleaseProvider};
+use crate::verify::{verify_checksum, verify_executable};
+use crate::{arch, download, retry, style, tr, Context};
+use crate::{Asset, Error, Release};
+
+/// Repository rust-unicorn itself is released from.
+pub const SELF_REPO: &str = "Cyber-Syntax/rust-unicorn";
+
+/// Update the running binary to the latest release. Unless `force` is set, nothing
+/// happens when it already is that version.
+pub async fn self_update(ctx: &Context, force: bool) -> Result<(), Error> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    let exe = env::current_exe()?.canonicalize()?;
+
+    let repo = cli::parse_repo(SELF_REPO, None)?;
+    let app = App {
+        name: "rust-unicorn".to_string(),
+        repo,
+        settings: AppConfig::default(),
+    };
+    let provider = AppProvider::for_app(
+        &*ctx.http,
+        &app,
+        ctx.github_token.as_deref(),
+        false,
+        ctx.dir_mode,
+    );
+    let mut release = retry::retry_transient(ctx, || provider.latest_release()).await?;
+    let latest_version = release.tag_name.trim_start_matches('v').to_string();
+
+    if latest_version == current_version && !force {
+        if ctx.output == OutputFormat::Json {
+            ctx.print_json(&serde_json::json!({
+                "action": "up-to-date",
+                "old_version": current_version,
+                "new_version": latest_version,
+                "path": exe,
+            }))?;
+        } else {
+            info!("{}", tr!("self-up-to-date", version = current_version));
+        }
+        return Ok(());
+    }
+
+    let index = choose_binary(&release.assets).ok_or_else(|| Error::NoMatchingAsset {
+        repo: SELF_REPO.to_string(),
+        tag: release.tag_name.clone(),
+        reason: format!("no rust-unicorn binary for {}", arch::host()),
+    })?;
+    let asset = release.assets.swap_remove(index);
+    info!(
+        "{}",
+        tr!(
+            "self-updating",
+            old_version = current_version,
+            new_version = release.tag_name.as_str(),
+            asset = asset.name.as_str()
+        )
+    );
+
+    // Download next to the running binary so the final rename is atomic, and only
+    // replace it once the download is verified.
+    let file_name = exe.file_name().unwrap_or_default().to_string_lossy();
+    let partial_path = exe.with_file_name(format!(".{}.part", file_name));
+    let sha256 = match download_verified(ctx, &release, &asset, &partial_path).await {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            let _ = fs::remove_file(&partial_path);
+            return Err(e);
+        }
+    };
+    // Linux keeps the running program's inode alive, so renaming over it is safe
+    if let Err(e) = fs::rename(&partial_path, &exe) {
+        let _ = fs::remove_file(&partial_path);
+        return Err(format!("Cannot replace {}: {}", exe.display(), e).into());
+    }
+    history::record(
+        ctx,
+        &Entry {
+            repo: Some(SELF_REPO),
+            old_version: Some(current_version),
+            new_version: Some(&release.tag_name),
+            sha256: Some(&sha256),
+            path: Some(&exe),
+            ..Entry::new("self-updated", "rust-unicorn")
+        },
+    );
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "action": "updated",
+            "old_version": current_version,
+            "new_version": latest_version,
+            "path": exe,
+        }))?;
+    } else {
+        info!(
+            "{}",
+            style::success(tr!("self-updated", version = release.tag_name.as_str()))
+        );
+    }
+    Ok(())
+}
+
+/// Download `asset` to `path`, verify it and make it executable. Returns its SHA-256.
+async fn download_verified(
+    ctx: &Context,
+    release: &Release,
+    asset: &Asset,
+    path: &Path,
+) -> Result<String, Error> {
+    let sha256 = download::download(ctx, &asset.browser_download_url, path, &[])
+        .await?
+        .sha256;
+    verify_executable(path, &asset.name, arch::host())?;
+    verify_checksum(ctx, release, asset, path, &sha256, None).await?;
+
+    let mut perms = fs::metadata(path)?.permissions();
+    perms.set_mode(perms.mode() | 0o755);
+    fs::set_permissions(path, perms)?;
+    Ok(sha256)
+}
+
+/// Index of the release asset that is the rust-unicorn binary for this machine: named
+/// after the program and its architecture (or no architecture), and not a checksum,
+/// signature or archive.
+fn choose_binary(assets: &[Asset]) -> Option<usize> {
+    const NOT_BINARIES: &[&str] = &[
+        ".sha256",
+        ".sha512",
+        ".asc",
+        ".sig",
+        ".txt",
+        ".zsync",
+        ".AppImage",
+        ".tar.gz",
+        ".tgz",
+        ".zip",
+        ".deb",
+        ".rpm",
+    ];
+    assets
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| a.name.starts_with("rust-unicorn") && a.state == "uploaded")
+        .filter(|(_, a)| !NOT_BINARIES.iter().any(|suffix| a.name.ends_with(suffix)))
+        .filter_map(|(index, a)| arch::score(&a.name, arch::host()).map(|score| (score, index)))
+        .max_by_key(|&(score, index)| (score, std::cmp::Reverse(index)))
+        .map(|(_, index)| index)
+}
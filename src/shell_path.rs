@@ -0,0 +1,119 @@
+// Whether the symlink directory is on PATH, so apps start by name, and adding it to
+// the user's shell startup file when it isn't.
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+use crate::install::create_dirs;
+use crate::{paths, tr, Error};
+
+/// Comment marking the line `--fix-path` adds, so it is added only once.
+const MARKER: &str = "# Added by rust-unicorn";
+
+/// Whether `dir` is one of the directories on PATH.
+pub fn on_path(dir: &Path) -> bool {
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|entry| fs::canonicalize(&entry).unwrap_or(entry) == dir)
+    })
+}
+
+/// Make sure the app symlinks in `bin_dir` can be run by name: warn when it isn't on
+/// PATH or, with `fix`, add it in the shell's startup file (creating its directory
+/// with `dir_mode`). Only the first call in a run does anything, so updating many
+/// apps says it once.
+pub fn check(bin_dir: &Path, fix: bool, dir_mode: Option<u32>) {
+    static CHECKED: AtomicBool = AtomicBool::new(false);
+    if CHECKED.swap(true, Ordering::Relaxed) || on_path(bin_dir) {
+        return;
+    }
+    if !fix {
+        warn!(
+            "{}",
+            tr!("not-on-path", dir = bin_dir.display().to_string())
+        );
+        return;
+    }
+    let dir = bin_dir.display().to_string();
+    match add_to_path(bin_dir, dir_mode) {
+        Ok((rc_file, true)) => info!(
+            "{}",
+            tr!(
+                "added-to-path",
+                dir = dir,
+                file = rc_file.display().to_string()
+            )
+        ),
+        Ok((rc_file, false)) => info!(
+            "{}",
+            tr!(
+                "already-on-path",
+                dir = dir,
+                file = rc_file.display().to_string()
+            )
+        ),
+        Err(e) => warn!(
+            "{}",
+            tr!("add-to-path-failed", dir = dir, error = e.to_string())
+        ),
+    }
+}
+
+/// Append a line putting `dir` on PATH to the user's shell startup file, unless an
+/// earlier run did, creating the file's directory with `dir_mode`. Returns the file
+/// and whether it was changed.
+pub fn add_to_path(dir: &Path, dir_mode: Option<u32>) -> Result<(PathBuf, bool), Error> {
+    let home = paths::home().ok_or("HOME is not set")?;
+    let shell = env::var("SHELL").unwrap_or_default();
+    let shell = shell.rsplit('/').next().unwrap_or_default();
+
+    // Spelled relative to $HOME when it can be, like the lines people write themselves
+    let dir_text = match dir.strip_prefix(&home) {
+        Ok(relative) => format!("$HOME/{}", relative.display()),
+        Err(_) => dir.display().to_string(),
+    };
+    let (rc_file, line) = match shell {
+        "fish" => (
+            paths::config_home()
+                .ok_or("HOME is not set")?
+                .join("fish")
+                .join("config.fish"),
+            format!("fish_add_path \"{}\"", dir_text),
+        ),
+        "zsh" => (
+            home.join(".zshrc"),
+            format!("export PATH=\"{}:$PATH\"", dir_text),
+        ),
+        "bash" => (
+            home.join(".bashrc"),
+            format!("export PATH=\"{}:$PATH\"", dir_text),
+        ),
+        _ => (
+            home.join(".profile"),
+            format!("export PATH=\"{}:$PATH\"", dir_text),
+        ),
+    };
+
+    let existing = fs::read_to_string(&rc_file).unwrap_or_default();
+    if existing.lines().any(|existing| existing.trim() == line) {
+        return Ok((rc_file, false));
+    }
+    if let Some(parent) = rc_file.parent() {
+        create_dirs(parent, dir_mode)?;
+    }
+    // Set apart from what comes before
+    let separator = match existing.as_str() {
+        "" => "",
+        text if text.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&rc_file)?;
+    write!(file, "{}{}\n{}\n", separator, MARKER, line)?;
+    Ok((rc_file, true))
+}
@@ -0,0 +1,61 @@
+// Detached OpenPGP signature verification via the `gpg` command.
+use std::path::Path;
+use std::process::Command;
+
+use crate::Asset;
+use crate::Error;
+
+/// Find the detached signature for `asset_name` (`<name>.asc` or `<name>.sig`).
+pub fn find_signature_asset<'a>(assets: &'a [Asset], asset_name: &str) -> Option<&'a Asset> {
+    let candidates = [format!("{}.asc", asset_name), format!("{}.sig", asset_name)];
+    assets.iter().find(|a| candidates.contains(&a.name))
+}
+
+/// Verify `signature` over `file` with gpg and require that it was made by one of
+/// `trusted_keys` (full fingerprints, or long key IDs matching the fingerprint's tail).
+///
+/// The signer's public key must already be in the user's keyring; gpg's own trust
+/// database is not consulted, only the configured fingerprints.
+pub fn verify(file: &Path, signature: &Path, trusted_keys: &[String]) -> Result<String, Error> {
+    let output = Command::new("gpg")
+        .args(["--batch", "--no-tty", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg(file)
+        .output()
+        .map_err(|e| format!("Cannot run gpg to verify the signature: {}", e))?;
+    let status = String::from_utf8_lossy(&output.stdout);
+
+    // [GNUPG:] VALIDSIG <subkey-fpr> <date> <timestamp> ... <primary-key-fpr>
+    let valid = status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .map(|rest| rest.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| !fields.is_empty());
+
+    let fields = match valid {
+        Some(fields) => fields,
+        None if status.contains("NO_PUBKEY") => {
+            return Err("Signature was made by a key that is not in your gpg keyring; import the signer's public key first".into());
+        }
+        None => {
+            let detail = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Signature verification failed: {}", detail.trim()).into());
+        }
+    };
+
+    let signer = fields[0];
+    let primary = fields.last().copied().unwrap_or(signer);
+    let trusted = trusted_keys.iter().any(|key| {
+        let key = key.replace(' ', "").to_uppercase();
+        key.len() >= 16 && (signer.ends_with(&key) || primary.ends_with(&key))
+    });
+    if !trusted {
+        return Err(format!(
+            "Valid signature, but by untrusted key {}; it is not among the configured signing keys",
+            primary
+        )
+        .into());
+    }
+
+    Ok(primary.to_string())
+}
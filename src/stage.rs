@@ -0,0 +1,188 @@
+// Staging area of installs: each app's downloads, and the program made from them,
+// go to `<install dir>/.rust-unicorn/staging/<app>/` until they are moved into place,
+// so concurrent updates never share a temporary file even when their assets have the
+// same name. A `stage.json` beside them says what is being downloaded, and by whom,
+// so the leftovers of an install that was killed are recognized and cleared the next
+// time the app is installed.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::download::Validators;
+use crate::install::create_dirs;
+use crate::{tr, Error};
+
+/// Directory of the staging areas, in each install directory.
+const STAGING_DIR: &str = ".rust-unicorn/staging";
+
+/// What a staging area holds, kept in its `stage.json`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Sidecar {
+    /// Process staging the install
+    pub pid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Size the release gives for the download
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// SHA-256 the release publishes for the download, or once it is complete, the
+    /// download's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, flatten)]
+    pub validators: Validators,
+}
+
+/// The staging area of one app's install, removed with everything in it when dropped.
+pub struct Stage {
+    dir: PathBuf,
+    sidecar: Sidecar,
+}
+
+impl Stage {
+    /// Set up the staging area of `app_name` in `install_dir`, creating its directories
+    /// with `dir_mode`. Whatever an interrupted install left there is removed first.
+    pub fn new(install_dir: &Path, app_name: &str, dir_mode: Option<u32>) -> Result<Stage, Error> {
+        let dir = install_dir.join(STAGING_DIR).join(app_name);
+        if dir.exists() {
+            info!("{}", tr!("stage-leftover", app = app_name));
+            if let Some(url) = read_sidecar(&dir).and_then(|sidecar| sidecar.url) {
+                debug!("It was downloading {}", url);
+            }
+            fs::remove_dir_all(&dir)?;
+        }
+        // Another app's install may remove the shared directories as it finishes
+        if let Err(e) = create_dirs(&dir, dir_mode) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+            create_dirs(&dir, dir_mode)?;
+        }
+        let stage = Stage {
+            dir,
+            sidecar: Sidecar {
+                pid: std::process::id(),
+                ..Sidecar::default()
+            },
+        };
+        stage.save()?;
+        Ok(stage)
+    }
+
+    /// Where the file `name` is staged.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", name))
+    }
+
+    /// Record that `url`, expected to be `size` bytes with the SHA-256 `sha256` when
+    /// the release says, is being downloaded.
+    pub fn describe(
+        &mut self,
+        url: &str,
+        size: Option<u64>,
+        sha256: Option<&str>,
+    ) -> io::Result<()> {
+        self.sidecar.url = Some(url.to_string());
+        self.sidecar.size = size;
+        self.sidecar.sha256 = sha256.map(str::to_string);
+        self.save()
+    }
+
+    /// Record the finished download: its SHA-256 and what identifies it to the server.
+    pub fn downloaded(&mut self, sha256: &str, validators: Option<&Validators>) -> io::Result<()> {
+        self.sidecar.sha256 = Some(sha256.to_string());
+        if let Some(validators) = validators {
+            self.sidecar.validators = validators.clone();
+        }
+        self.save()
+    }
+
+    /// Move the staged file at `staged` to `target`, replacing what is there. The
+    /// staging area is in the install directory, so this is a single atomic rename,
+    /// made durable before returning.
+    pub fn publish(&self, staged: &Path, target: &Path) -> io::Result<()> {
+        debug!("Moving {} to {}", staged.display(), target.display());
+        fs::rename(staged, target)?;
+        if let Some(dir) = target.parent() {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::write(
+            self.dir.join("stage.json"),
+            serde_json::to_string_pretty(&self.sidecar)?,
+        )
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            debug!("Cannot remove {}: {}", self.dir.display(), e);
+        }
+        // The staging directories go too once no other install uses them
+        for dir in self.dir.ancestors().skip(1).take(2) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// The sidecar of the staging area `dir`, if it has a readable one.
+fn read_sidecar(dir: &Path) -> Option<Sidecar> {
+    let text = fs::read_to_string(dir.join("stage.json")).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_apps_apart_and_clears_up_after_them() {
+        let install_dir =
+            std::env::temp_dir().join(format!("rust-unicorn-stage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&install_dir);
+        fs::create_dir_all(&install_dir).unwrap();
+
+        let mut alpha = Stage::new(&install_dir, "alpha", None).unwrap();
+        let beta = Stage::new(&install_dir, "beta", None).unwrap();
+        // The same asset name doesn't make the two share a file
+        assert_ne!(alpha.path("tool.tar.gz"), beta.path("tool.tar.gz"));
+        alpha
+            .describe("https://example.com/tool.tar.gz", Some(3), None)
+            .unwrap();
+        fs::write(alpha.path("tool.tar.gz"), "abc").unwrap();
+        fs::write(beta.path("tool.tar.gz"), "xyz").unwrap();
+        assert_eq!(fs::read(alpha.path("tool.tar.gz")).unwrap(), b"abc");
+
+        let target = install_dir.join("tool");
+        beta.publish(&beta.path("tool.tar.gz"), &target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"xyz");
+        let staging = install_dir.join(STAGING_DIR);
+        drop(beta);
+        assert!(!staging.join("beta").exists());
+
+        // An install killed before it finished leaves its staging area behind
+        std::mem::forget(alpha);
+        let sidecar = read_sidecar(&staging.join("alpha")).unwrap();
+        assert_eq!(sidecar.pid, std::process::id());
+        assert_eq!(
+            sidecar.url.as_deref(),
+            Some("https://example.com/tool.tar.gz")
+        );
+        let alpha = Stage::new(&install_dir, "alpha", None).unwrap();
+        assert!(!alpha.path("tool.tar.gz").exists());
+        drop(alpha);
+
+        // Nothing is left but the published file
+        assert!(!install_dir.join(".rust-unicorn").exists());
+        assert_eq!(fs::read_dir(&install_dir).unwrap().count(), 1);
+        fs::remove_dir_all(&install_dir).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+// Persistent record of installed apps (~/.local/state/rust-unicorn/state.json).
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{AppConfig, AssetType};
+use crate::install::create_dirs;
+use crate::paths;
+use crate::sandbox::Sandbox;
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    /// Installed apps keyed by app name
+    #[serde(default)]
+    pub apps: BTreeMap<String, InstalledApp>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstalledApp {
+    /// Repository spec, as accepted on the command line
+    pub repo: String,
+    /// Release tag of the installed version
+    pub tag: String,
+    /// File name of the installed release asset
+    pub asset_name: String,
+    /// Version found in the asset's name, which may disagree with the tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_version: Option<String>,
+    /// SHA-256 of the installed file, lowercase hex
+    pub sha256: String,
+    /// Absolute path of the installed AppImage
+    pub install_path: PathBuf,
+    /// Symlink created for the app, if any
+    #[serde(default)]
+    pub symlink: Option<PathBuf>,
+    /// Desktop entry created for the app, if any
+    #[serde(default)]
+    pub desktop_entry: Option<PathBuf>,
+    /// Icon extracted from the AppImage, if any
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
+    /// AppStream metainfo written for the app, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metainfo: Option<PathBuf>,
+    /// Sandbox the app is launched in, through a wrapper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<Sandbox>,
+    /// Directory the AppImage is extracted to, for systems without FUSE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_dir: Option<PathBuf>,
+    /// Unix timestamp of the install
+    #[serde(default)]
+    pub installed_at: u64,
+    /// The version the last update replaced, kept for `rollback`
+    #[serde(default)]
+    pub previous: Option<PreviousVersion>,
+    /// Versions replaced before `previous` and still kept, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive: Vec<PreviousVersion>,
+    /// Held at its current version: skipped by `update --all` and `check`
+    #[serde(default)]
+    pub pinned: bool,
+    /// The app's settings at install time, so apps installed from a manifest (or
+    /// dropped from the config file) keep them; the config file takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<AppConfig>,
+    /// Where the installed file was downloaded from and how it was verified
+    #[serde(flatten)]
+    pub provenance: Provenance,
+    /// How the app was installed; entries written before it was recorded have it in
+    /// their settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<AssetType>,
+    /// The app's `prefer` pattern that chose the installed asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred: Option<String>,
+}
+
+/// Where an installed file came from and how it was checked. Files installed from
+/// disk, and by versions that didn't record it, have none of it.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Provenance {
+    /// URL the file was requested from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Host that served it after redirects; none when it came from the download
+    /// cache or as a delta update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    /// How it was verified, e.g. `SHA-256 (SHA256SUMS)`; `none` when the release
+    /// published nothing to check it against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<String>,
+}
+
+impl InstalledApp {
+    /// The kind of asset the app was installed from, which says how.
+    pub fn method(&self) -> AssetType {
+        self.method
+            .or(self.settings.as_ref().map(|settings| settings.asset_type))
+            .unwrap_or_default()
+    }
+
+    /// Whether the app is a program unpacked from an archive rather than an AppImage.
+    pub fn from_archive(&self) -> bool {
+        self.method() == AssetType::Archive
+    }
+
+    /// The replaced versions kept, newest first.
+    pub fn kept_versions(&self) -> Vec<PreviousVersion> {
+        self.previous.iter().chain(&self.archive).cloned().collect()
+    }
+
+    /// Keep `versions`, newest first: the first for `rollback`, the rest archived.
+    pub fn set_kept_versions(&mut self, versions: Vec<PreviousVersion>) {
+        let mut versions = versions.into_iter();
+        self.previous = versions.next();
+        self.archive = versions.collect();
+    }
+}
+
+/// A replaced version of an app, kept in the archive next to the current one.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PreviousVersion {
+    pub tag: String,
+    pub asset_name: String,
+    pub sha256: String,
+    /// Where the AppImage is kept until it is restored
+    pub backup_path: PathBuf,
+}
+
+/// Location of the state file: `$XDG_STATE_HOME/rust-unicorn/state.json`,
+/// falling back to `~/.local/state/rust-unicorn/state.json`.
+pub fn default_path() -> Option<PathBuf> {
+    Some(paths::state_home()?.join("rust-unicorn").join("state.json"))
+}
+
+impl State {
+    /// Load the state file, treating a missing file as "nothing installed".
+    pub fn load(path: &Path) -> Result<State, Error> {
+        if !path.exists() {
+            return Ok(State::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read state file {}: {}", path.display(), e))?;
+        let state = serde_json::from_str(&text)
+            .map_err(|e| format!("Corrupt state file {}: {}", path.display(), e))?;
+
+        Ok(state)
+    }
+
+    /// Write the state file, replacing the previous one atomically so an
+    /// interrupted write never leaves a truncated file behind. Missing directories
+    /// are created with `dir_mode`.
+    pub fn save(&self, path: &Path, dir_mode: Option<u32>) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            create_dirs(parent, dir_mode)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
+
+/// Current time as a Unix timestamp.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,53 @@
+// Colored terminal output: successes in green, warnings in yellow, errors in red and
+// version changes highlighted. `--color` decides; by default each stream is colored
+// only when it is a terminal and NO_COLOR isn't set.
+use console::{style, StyledObject};
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+use crate::cli::ColorChoice;
+use crate::tr;
+
+/// Turn colors on or off for stdout and stderr, as `choice` says.
+pub fn init(choice: ColorChoice) {
+    let enabled = |terminal: bool| match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        }
+    };
+    console::set_colors_enabled(enabled(io::stdout().is_terminal()));
+    console::set_colors_enabled_stderr(enabled(io::stderr().is_terminal()));
+}
+
+/// Whether messages on stdout are colored.
+pub fn enabled() -> bool {
+    console::colors_enabled()
+}
+
+/// A message about something that worked.
+pub fn success<D: Display>(message: D) -> StyledObject<D> {
+    style(message).green()
+}
+
+/// Text the user asked to be reminded of, such as an app's notes.
+pub fn reminder<D: Display>(message: D) -> StyledObject<D> {
+    style(message).cyan().bold()
+}
+
+/// The `Error:` prefix of error messages, for stderr.
+pub fn error_prefix() -> StyledObject<String> {
+    style(tr!("error-prefix")).red().bold().for_stderr()
+}
+
+/// The `Warning:` prefix of warnings, for stderr.
+pub fn warning_prefix() -> StyledObject<String> {
+    style(tr!("warning-prefix")).yellow().bold().for_stderr()
+}
+
+/// A version moving from `old` to `new`, e.g. `1.2.0 -> 1.3.0`.
+pub fn change(old: &str, new: &str) -> String {
+    format!("{} -> {}", style(old).yellow(), style(new).green().bold())
+}
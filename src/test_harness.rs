@@ -0,0 +1,382 @@
+// Test fixtures for programs embedding rust-unicorn (and for its own tests), behind
+// the `test-harness` feature: `FixtureServer`, an `HttpFetcher` that answers
+// GitHub's releases API and serves release assets from memory, so installs and
+// updates can be tested offline. Releases are given as recorded API responses
+// or built with `FixtureRelease`; the assets are synthetic programs from `elf`,
+// `appimage` and `tarball`.
+//
+//     let server = FixtureServer::new();
+//     server.publish("owner/tool", FixtureRelease::new("v1.0").asset("tool.tar.gz", tarball("tool", &elf())));
+//     let installer = Installer::builder().http_fetcher(server.clone()).build()?;
+//
+// Assets carry an ETag, and requests sending it back get a 304. Requests for
+// anything else get a 404. `rate_limit` and `truncate` make the server fail the way
+// GitHub and dropped connections do.
+use reqwest::header;
+use reqwest::{Request, Response, ResponseBuilderExt, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http::HttpFetcher;
+use crate::Error;
+
+/// A release to publish on a `FixtureServer`, shaped like GitHub's API response.
+pub struct FixtureRelease {
+    tag: String,
+    prerelease: bool,
+    body: String,
+    /// Name, contents and published `sha256:` digest of each asset
+    assets: Vec<(String, Vec<u8>, Option<String>)>,
+}
+
+impl FixtureRelease {
+    /// A release tagged `tag`, without assets.
+    pub fn new(tag: &str) -> Self {
+        FixtureRelease {
+            tag: tag.to_string(),
+            prerelease: false,
+            body: String::new(),
+            assets: Vec::new(),
+        }
+    }
+
+    /// Mark the release as a prerelease, which `releases/latest` skips.
+    pub fn prerelease(mut self) -> Self {
+        self.prerelease = true;
+        self
+    }
+
+    /// Set the release notes.
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Attach the asset `name` holding `contents`, with its SHA-256 as its digest as
+    /// GitHub publishes it.
+    pub fn asset(self, name: &str, contents: &[u8]) -> Self {
+        let digest = format!("sha256:{}", sha256(contents));
+        self.asset_with_digest(name, contents, Some(&digest))
+    }
+
+    /// Attach the asset `name` holding `contents`, with `digest` (or none) as its
+    /// published digest, e.g. a wrong one to test checksum failures.
+    pub fn asset_with_digest(mut self, name: &str, contents: &[u8], digest: Option<&str>) -> Self {
+        self.assets.push((
+            name.to_string(),
+            contents.to_vec(),
+            digest.map(str::to_string),
+        ));
+        self
+    }
+}
+
+#[derive(Default)]
+struct Fixtures {
+    /// Releases of each `owner/name`, newest last
+    releases: HashMap<String, Vec<Value>>,
+    /// Contents served for each download URL
+    files: HashMap<String, Vec<u8>>,
+    /// Download URLs whose responses break off, with the bytes sent before they do
+    truncated: HashMap<String, usize>,
+    rate_limited: bool,
+    /// Every request, as `METHOD url`
+    requests: Vec<String>,
+    /// Download URLs answered with 304 Not Modified
+    not_modified: Vec<String>,
+}
+
+/// An `HttpFetcher` serving published fixture releases and their assets. Clones share
+/// the same fixtures, so a test can keep one to change them while installs run.
+#[derive(Clone, Default)]
+pub struct FixtureServer {
+    fixtures: Arc<Mutex<Fixtures>>,
+}
+
+impl FixtureServer {
+    pub fn new() -> Self {
+        FixtureServer::default()
+    }
+
+    fn fixtures(&self) -> std::sync::MutexGuard<'_, Fixtures> {
+        self.fixtures.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Publish `release` in the GitHub repository `repo` (`owner/name`), as its newest
+    /// release, and serve its assets from their `browser_download_url`s.
+    pub fn publish(&self, repo: &str, release: FixtureRelease) {
+        let mut fixtures = self.fixtures();
+        let id = fixtures.releases.values().map(Vec::len).sum::<usize>() + 1;
+        let assets: Vec<Value> = release
+            .assets
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, contents, digest))| {
+                let url = format!(
+                    "https://github.com/{}/releases/download/{}/{}",
+                    repo, release.tag, name
+                );
+                let asset = json!({
+                    "id": id * 100 + index,
+                    "name": name,
+                    "content_type": "application/octet-stream",
+                    "state": "uploaded",
+                    "size": contents.len(),
+                    "digest": digest,
+                    "browser_download_url": url,
+                });
+                fixtures.files.insert(url, contents);
+                asset
+            })
+            .collect();
+        let value = json!({
+            "id": id,
+            "html_url": format!("https://github.com/{}/releases/tag/{}", repo, release.tag),
+            "tag_name": release.tag,
+            "name": release.tag,
+            "draft": false,
+            "prerelease": release.prerelease,
+            "created_at": "2024-01-01T10:00:00Z",
+            "published_at": "2024-01-01T10:00:00Z",
+            "body": release.body,
+            "assets": assets,
+        });
+        fixtures
+            .releases
+            .entry(repo.to_string())
+            .or_default()
+            .push(value);
+    }
+
+    /// Publish a release response recorded from GitHub's API in `repo`, as its newest
+    /// release. Its assets are only served once given to `serve`.
+    pub fn publish_recorded(&self, repo: &str, response: &str) -> Result<(), Error> {
+        let value: Value = serde_json::from_str(response)?;
+        self.fixtures()
+            .releases
+            .entry(repo.to_string())
+            .or_default()
+            .push(value);
+        Ok(())
+    }
+
+    /// Serve `contents` at `url`.
+    pub fn serve(&self, url: &str, contents: &[u8]) {
+        self.fixtures()
+            .files
+            .insert(url.to_string(), contents.to_vec());
+    }
+
+    /// Answer API requests (or with `limited` unset, stop answering them) with GitHub's
+    /// 403 for an exhausted rate limit, which resets in an hour.
+    pub fn rate_limit(&self, limited: bool) {
+        self.fixtures().rate_limited = limited;
+    }
+
+    /// Break off downloads of `url` after `after` bytes, as a dropped connection does,
+    /// though they announce their full length.
+    pub fn truncate(&self, url: &str, after: usize) {
+        self.fixtures().truncated.insert(url.to_string(), after);
+    }
+
+    /// Every request received so far, as `METHOD url`.
+    pub fn requests(&self) -> Vec<String> {
+        self.fixtures().requests.clone()
+    }
+
+    /// The downloads answered with 304 Not Modified so far, by URL, as the request
+    /// sent back their ETag.
+    pub fn not_modified(&self) -> Vec<String> {
+        self.fixtures().not_modified.clone()
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        let url = request.url().as_str();
+        let mut fixtures = self.fixtures();
+        fixtures
+            .requests
+            .push(format!("{} {}", request.method(), url));
+        let builder = ::http::Response::builder().url(request.url().clone());
+
+        if let Some(path) = url.strip_prefix("https://api.github.com/repos/") {
+            if fixtures.rate_limited {
+                let reset = SystemTime::now() + Duration::from_secs(3600);
+                let reset = reset.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let body = r#"{"message":"API rate limit exceeded"}"#;
+                return response(
+                    builder
+                        .status(StatusCode::FORBIDDEN)
+                        .header("x-ratelimit-limit", "60")
+                        .header("x-ratelimit-remaining", "0")
+                        .header("x-ratelimit-reset", reset.as_secs().to_string()),
+                    body.into(),
+                );
+            }
+            return match api_response(&fixtures.releases, path) {
+                Some(value) => response(
+                    builder.header(header::CONTENT_TYPE, "application/json"),
+                    value.to_string().into(),
+                ),
+                None => not_found(builder),
+            };
+        }
+
+        let Some(contents) = fixtures.files.get(url) else {
+            return not_found(builder);
+        };
+        // Files are tagged by their content, so a changed file gets a new ETag
+        let etag = format!("\"{}\"", sha256(contents));
+        let builder = builder.header(header::ETAG, &etag);
+        if request
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|tag| tag == etag.as_str())
+        {
+            fixtures.not_modified.push(url.to_string());
+            return response(
+                builder.status(StatusCode::NOT_MODIFIED),
+                reqwest::Body::from(""),
+            );
+        }
+        let builder = builder
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(header::CONTENT_LENGTH, contents.len());
+        match fixtures.truncated.get(url) {
+            Some(&after) => {
+                let sent = contents[..after.min(contents.len())].to_vec();
+                let chunks: Vec<io::Result<Vec<u8>>> = vec![
+                    Ok(sent),
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed before the end of the body",
+                    )),
+                ];
+                let body = reqwest::Body::wrap_stream(futures_util::stream::iter(chunks));
+                response(builder, body)
+            }
+            None => response(builder, contents.clone().into()),
+        }
+    }
+}
+
+impl HttpFetcher for FixtureServer {
+    fn execute(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send + '_>> {
+        let response = self.respond(&request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// The API's answer for `path` (after `/repos/`): the latest release, a release by
+/// tag, or the list of releases, newest first.
+fn api_response(releases: &HashMap<String, Vec<Value>>, path: &str) -> Option<Value> {
+    let path = path.split('?').next().unwrap_or(path);
+    let mut segments = path.split('/');
+    let repo = format!("{}/{}", segments.next()?, segments.next()?);
+    let releases = releases.get(&repo)?;
+    let rest: Vec<String> = segments.map(decode).collect();
+    match rest.as_slice() {
+        [releases_segment] if releases_segment == "releases" => {
+            Some(Value::Array(releases.iter().rev().cloned().collect()))
+        }
+        [_, latest] if latest == "latest" => releases
+            .iter()
+            .rev()
+            .find(|release| release["prerelease"] != true && release["draft"] != true)
+            .cloned(),
+        [_, tags, tag] if tags == "tags" => releases
+            .iter()
+            .find(|release| release["tag_name"] == tag.as_str())
+            .cloned(),
+        _ => None,
+    }
+}
+
+/// `segment` with its percent-escapes decoded.
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| segment.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn response(builder: ::http::response::Builder, body: reqwest::Body) -> Response {
+    Response::from(builder.body(body).expect("valid fixture response"))
+}
+
+fn not_found(builder: ::http::response::Builder) -> Response {
+    let body = r#"{"message":"Not Found"}"#;
+    response(builder.status(StatusCode::NOT_FOUND), body.into())
+}
+
+/// The lowercase hex SHA-256 of `contents`, as SHA256SUMS files list it.
+pub fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// A synthetic program: the ELF header of the running executable, so it passes the
+/// architecture check, followed by filler. It can't be run.
+pub fn elf() -> Vec<u8> {
+    let exe = std::env::current_exe()
+        .and_then(std::fs::read)
+        .unwrap_or_default();
+    let mut program = exe.get(..64).unwrap_or_default().to_vec();
+    // Without section headers nothing tries to read past the filler
+    if program.len() == 64 {
+        program[0x28..0x30].fill(0);
+    }
+    program.resize(4096, 0);
+    program
+}
+
+/// A synthetic AppImage: `elf` carrying the type 2 AppImage magic. It can't be run,
+/// so it can't be extracted either, which installs do on hosts without FUSE.
+pub fn appimage() -> Vec<u8> {
+    let mut appimage = elf();
+    appimage[8..11].copy_from_slice(b"AI\x02");
+    appimage
+}
+
+/// A `.tar.gz` holding `program` as `<name>-fixture/<name>`, beside a README.
+pub fn tarball(name: &str, program: &[u8]) -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    let mut archive = tar::Builder::new(encoder);
+    let mut add = |path: String, contents: &[u8], mode: u32| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        archive
+            .append_data(&mut header, path, contents)
+            .expect("tarball in memory");
+    };
+    add(format!("{}-fixture/README.md", name), b"# Fixture\n", 0o644);
+    add(format!("{}-fixture/{}", name, name), program, 0o755);
+    let mut encoder = archive.into_inner().expect("tarball in memory");
+    encoder.flush().expect("tarball in memory");
+    encoder.finish().expect("tarball in memory")
+}
@@ -0,0 +1,39 @@
+// Bandwidth limiting (--limit-rate): one budget shared by every download, so
+// parallel updates together stay under the cap.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Delays shorter than this are carried over rather than slept, to spare the timer.
+const MIN_SLEEP: Duration = Duration::from_millis(20);
+
+/// Caps the combined rate of the transfers that report to it.
+pub struct Throttle {
+    bytes_per_second: u64,
+    /// When the bytes let through so far are paid for at the capped rate
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Throttle {
+            bytes_per_second: bytes_per_second.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Account for `bytes` just received, waiting as long as it takes to stay under
+    /// the cap.
+    pub async fn consume(&self, bytes: usize) {
+        let delay = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            // Idle time doesn't build up an allowance for a burst later
+            *next = (*next).max(now)
+                + Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+            next.saturating_duration_since(now)
+        };
+        if delay >= MIN_SLEEP {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
@@ -0,0 +1,622 @@
+// `ui`: a full-screen manager of the installed apps. A table shows each app's
+// installed and latest version; apps picked with space are updated, removed or
+// (un)pinned together, updates drawing their download progress in their row. Below
+// the table are the notes of the highlighted app's latest release.
+//
+// Log messages go to the line at the bottom while the interface is up, and the
+// install directory is locked only while something changes it.
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::StreamExt;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use regex::Regex;
+use std::io::{self, IsTerminal};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Semaphore;
+
+use crate::cli::InstallArgs;
+use crate::install::{self, create_dirs, DEFAULT_PARALLEL_UPDATES};
+use crate::lock::{self, InstallLock};
+use crate::progress::{self, Observer, Phase};
+use crate::release::Release;
+use crate::state::{InstalledApp, State};
+use crate::{logging, markdown, notes, tr, Context, Error};
+
+/// Width of the progress bars in the status column.
+const BAR_WIDTH: usize = 20;
+
+/// Lines the release notes scroll by per key press.
+const SCROLL_STEP: u16 = 5;
+
+/// What is known about an app's latest release.
+enum Latest {
+    Checking,
+    Found(Release),
+    Failed(String),
+}
+
+/// What is being done to an app.
+enum Activity {
+    Idle,
+    /// Waiting for a free update slot
+    Queued,
+    /// Past the download
+    Working(Phase),
+    Downloading {
+        done: u64,
+        total: u64,
+    },
+    Done(String),
+    Failed(String),
+}
+
+struct AppRow {
+    name: String,
+    installed: InstalledApp,
+    latest: Latest,
+    activity: Activity,
+    selected: bool,
+}
+
+impl AppRow {
+    fn busy(&self) -> bool {
+        matches!(
+            self.activity,
+            Activity::Queued | Activity::Working(_) | Activity::Downloading { .. }
+        )
+    }
+
+    fn update_available(&self) -> bool {
+        matches!(&self.latest, Latest::Found(release) if release.tag_name != self.installed.tag)
+    }
+}
+
+/// Results coming back from background tasks.
+enum Message {
+    Checked {
+        app: String,
+        result: Result<Release, String>,
+    },
+    Progress {
+        app: String,
+        event: progress::Event,
+    },
+    /// With what to show for it: the new version or the error
+    Updated {
+        app: String,
+        result: Result<String, String>,
+    },
+}
+
+/// Run the interface until the user quits.
+pub async fn run(ctx: &Arc<Context>) -> Result<(), Error> {
+    if !io::stdout().is_terminal() {
+        return Err("`ui` needs a terminal".into());
+    }
+    let state = State::load(&ctx.state_path)?;
+    let rows = state
+        .apps
+        .into_iter()
+        .map(|(name, installed)| AppRow {
+            name,
+            installed,
+            latest: Latest::Checking,
+            activity: Activity::Idle,
+            selected: false,
+        })
+        .collect();
+    let jobs = ctx
+        .config
+        .parallel_updates
+        .unwrap_or(DEFAULT_PARALLEL_UPDATES)
+        .max(1);
+    let (sender, messages) = mpsc::unbounded_channel();
+    let (log_sender, log) = mpsc::unbounded_channel();
+    let mut ui = Ui {
+        ctx: Arc::clone(ctx),
+        rows,
+        table: TableState::default().with_selected(0),
+        notes_scroll: 0,
+        removal: None,
+        status: String::new(),
+        running: 0,
+        lock: None,
+        permits: Arc::new(Semaphore::new(jobs as usize)),
+        sender,
+    };
+    ui.check();
+
+    logging::divert(Some(log_sender));
+    progress::hide(true);
+    let mut terminal = ratatui::init();
+    let result = ui.run(&mut terminal, messages, log).await;
+    ratatui::restore();
+    progress::hide(false);
+    logging::divert(None);
+    result
+}
+
+struct Ui {
+    ctx: Arc<Context>,
+    rows: Vec<AppRow>,
+    table: TableState,
+    notes_scroll: u16,
+    /// Apps to remove once the user confirms
+    removal: Option<Vec<String>>,
+    /// Last log message or answer to a key
+    status: String,
+    /// Updates queued or in progress
+    running: usize,
+    /// Held while updates run
+    lock: Option<InstallLock>,
+    /// Slots for updates, as in `update --all`
+    permits: Arc<Semaphore>,
+    sender: UnboundedSender<Message>,
+}
+
+impl Ui {
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        mut messages: UnboundedReceiver<Message>,
+        mut log: UnboundedReceiver<String>,
+    ) -> Result<(), Error> {
+        let mut events = EventStream::new();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                        if self.key(key).await {
+                            return Ok(());
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(()),
+                },
+                Some(message) = messages.recv() => self.apply(message),
+                Some(line) = log.recv() => self.status = plain(&line),
+            }
+            // Downloads report progress faster than it is worth drawing
+            while let Ok(message) = messages.try_recv() {
+                self.apply(message);
+            }
+        }
+    }
+
+    /// Act on a key press; true to quit.
+    async fn key(&mut self, key: KeyEvent) -> bool {
+        if let Some(apps) = self.removal.take() {
+            if matches!(key.code, KeyCode::Char(c) if tr!("confirm-yes").split_whitespace().any(|yes| yes.starts_with(c)))
+            {
+                self.remove(apps).await;
+            } else {
+                self.status.clear();
+            }
+            return false;
+        }
+
+        let ctrl_c =
+            key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+        match key.code {
+            _ if ctrl_c => return self.quit(),
+            KeyCode::Char('q') | KeyCode::Esc => return self.quit(),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.notes_scroll = self.notes_scroll.saturating_sub(SCROLL_STEP),
+            KeyCode::PageDown => self.notes_scroll = self.notes_scroll.saturating_add(SCROLL_STEP),
+            KeyCode::Char(' ') => {
+                if let Some(row) = self
+                    .table
+                    .selected()
+                    .and_then(|index| self.rows.get_mut(index))
+                {
+                    row.selected = !row.selected;
+                }
+                self.move_by(1);
+            }
+            KeyCode::Char('a') => {
+                let select = !self.rows.iter().all(|row| row.selected);
+                self.rows.iter_mut().for_each(|row| row.selected = select);
+            }
+            KeyCode::Char('u') => self.update().await,
+            KeyCode::Char('p') => self.pin().await,
+            KeyCode::Char('c') => self.check(),
+            KeyCode::Char('r') => {
+                let apps: Vec<String> = self
+                    .targets()
+                    .filter(|row| !row.busy())
+                    .map(|row| row.name.clone())
+                    .collect();
+                if !apps.is_empty() {
+                    self.status = format!(
+                        "{} {}",
+                        tr!("ui-remove-question", apps = apps.join(", ")),
+                        tr!("confirm-choices")
+                    );
+                    self.removal = Some(apps);
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Whether to quit; not while updates are running, which would leave them half done.
+    fn quit(&mut self) -> bool {
+        if self.running > 0 {
+            self.status = tr!("ui-busy");
+            return false;
+        }
+        true
+    }
+
+    fn move_by(&mut self, step: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let index = self
+            .table
+            .selected()
+            .unwrap_or(0)
+            .saturating_add_signed(step)
+            .min(self.rows.len() - 1);
+        if Some(index) != self.table.selected() {
+            self.table.select(Some(index));
+            self.notes_scroll = 0;
+        }
+    }
+
+    /// The apps an action applies to: those picked with space, else the highlighted one.
+    fn targets(&self) -> impl Iterator<Item = &AppRow> {
+        let picked = self.rows.iter().any(|row| row.selected);
+        let highlighted = self.table.selected();
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(move |(index, row)| {
+                if picked {
+                    row.selected
+                } else {
+                    Some(*index) == highlighted
+                }
+            })
+            .map(|(_, row)| row)
+    }
+
+    /// How rust-unicorn commands refer to the installed app `name`.
+    fn spec(&self, name: &str, installed: &InstalledApp) -> String {
+        // Configured apps are addressed by name so their per-app settings apply
+        if self.ctx.config.apps.contains_key(name) {
+            name.to_string()
+        } else {
+            installed.repo.clone()
+        }
+    }
+
+    /// Look for the latest release of every app in the background.
+    fn check(&mut self) {
+        for row in &mut self.rows {
+            row.latest = Latest::Checking;
+        }
+        for row in &self.rows {
+            let (ctx, sender, app) = (Arc::clone(&self.ctx), self.sender.clone(), row.name.clone());
+            let spec = self.spec(&row.name, &row.installed);
+            tokio::spawn(async move {
+                let result = match ctx.config.resolve(&spec, None) {
+                    Ok(resolved) => install::latest_release(&ctx, &resolved)
+                        .await
+                        .map(|(release, _)| release)
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                let _ = sender.send(Message::Checked { app, result });
+            });
+        }
+    }
+
+    /// Take the install directory's lock unless it is already held; false (with the
+    /// reason shown) when another process has it.
+    async fn lock(&mut self) -> bool {
+        if self.lock.is_some() {
+            return true;
+        }
+        let locked = match create_dirs(&self.ctx.install_dir, self.ctx.dir_mode) {
+            Ok(()) => lock::acquire(&self.ctx.install_dir, false).await,
+            Err(e) => Err(e.into()),
+        };
+        match locked {
+            Ok(lock) => {
+                self.lock = Some(lock);
+                true
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                false
+            }
+        }
+    }
+
+    /// Let go of the lock once nothing is changing the install directory.
+    fn unlock_when_idle(&mut self) {
+        if self.running == 0 {
+            self.lock = None;
+        }
+    }
+
+    /// Update the targeted apps that aren't pinned or already being updated.
+    async fn update(&mut self) {
+        let names: Vec<String> = self
+            .targets()
+            .filter(|row| !row.busy() && !row.installed.pinned)
+            .map(|row| row.name.clone())
+            .collect();
+        if names.is_empty() || !self.lock().await {
+            return;
+        }
+        for name in names {
+            let Some(index) = self.rows.iter().position(|row| row.name == name) else {
+                continue;
+            };
+            let spec = self.spec(&name, &self.rows[index].installed);
+            self.rows[index].activity = Activity::Queued;
+            self.rows[index].selected = false;
+            let args = InstallArgs {
+                app: Some(spec),
+                // Nobody can answer a prompt behind the interface
+                non_interactive: true,
+                ..InstallArgs::default()
+            };
+            let (ctx, sender, permits) = (
+                Arc::clone(&self.ctx),
+                self.sender.clone(),
+                Arc::clone(&self.permits),
+            );
+            self.running += 1;
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let observer: Observer = {
+                    let (sender, app) = (sender.clone(), name.clone());
+                    Arc::new(move |event| {
+                        let _ = sender.send(Message::Progress {
+                            app: app.clone(),
+                            event,
+                        });
+                    })
+                };
+                let result =
+                    match progress::observed(observer, install::install(&ctx, &args, true)).await {
+                        Ok(report) if report.action == "up-to-date" => Ok(tr!(
+                            "check-up-to-date",
+                            version = report.new_version.as_str()
+                        )),
+                        Ok(report) => Ok(tr!("ui-updated", version = report.new_version.as_str())),
+                        Err(e) => Err(e.to_string()),
+                    };
+                let _ = sender.send(Message::Updated { app: name, result });
+            });
+        }
+    }
+
+    /// Remove `apps`, after the user confirmed it.
+    async fn remove(&mut self, apps: Vec<String>) {
+        if !self.lock().await {
+            return;
+        }
+        for name in apps {
+            match install::remove(&self.ctx, &name, false) {
+                Ok(()) => self.rows.retain(|row| row.name != name),
+                Err(e) => {
+                    if let Some(row) = self.rows.iter_mut().find(|row| row.name == name) {
+                        row.activity = Activity::Failed(e.to_string());
+                    }
+                }
+            }
+        }
+        self.unlock_when_idle();
+        if let Some(index) = self.table.selected() {
+            self.table
+                .select((!self.rows.is_empty()).then(|| index.min(self.rows.len() - 1)));
+        }
+    }
+
+    /// Pin the targeted apps that aren't pinned, and unpin those that are.
+    async fn pin(&mut self) {
+        let names: Vec<String> = self
+            .targets()
+            .filter(|row| !row.busy())
+            .map(|row| row.name.clone())
+            .collect();
+        if names.is_empty() || !self.lock().await {
+            return;
+        }
+        for row in self.rows.iter_mut().filter(|row| names.contains(&row.name)) {
+            match install::pin(&self.ctx, &row.name, !row.installed.pinned) {
+                Ok(()) => row.installed.pinned = !row.installed.pinned,
+                Err(e) => row.activity = Activity::Failed(e.to_string()),
+            }
+            row.selected = false;
+        }
+        self.unlock_when_idle();
+    }
+
+    fn apply(&mut self, message: Message) {
+        match message {
+            Message::Checked { app, result } => {
+                if let Some(row) = self.rows.iter_mut().find(|row| row.name == app) {
+                    row.latest = match result {
+                        Ok(release) => Latest::Found(release),
+                        Err(e) => Latest::Failed(e),
+                    };
+                }
+            }
+            Message::Progress { app, event } => {
+                if let Some(row) = self.rows.iter_mut().find(|row| row.name == app) {
+                    match event {
+                        progress::Event::Download { downloaded, total } => {
+                            row.activity = Activity::Downloading {
+                                done: downloaded,
+                                total,
+                            }
+                        }
+                        // The download's bar says enough until it is over
+                        progress::Event::Phase(Phase::Resolving | Phase::Downloading) => {}
+                        progress::Event::Phase(phase) => row.activity = Activity::Working(phase),
+                    }
+                }
+            }
+            Message::Updated { app, result } => {
+                self.running -= 1;
+                // The state file has the new version, and its files
+                let installed = State::load(&self.ctx.state_path)
+                    .ok()
+                    .and_then(|mut state| state.apps.remove(&app));
+                if let Some(row) = self.rows.iter_mut().find(|row| row.name == app) {
+                    row.activity = match result {
+                        Ok(done) => Activity::Done(done),
+                        Err(e) => Activity::Failed(e),
+                    };
+                    if let Some(installed) = installed {
+                        row.installed = installed;
+                    }
+                }
+                self.unlock_when_idle();
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, notes_area, help_area, status_area] = Layout::vertical([
+            Constraint::Percentage(50),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new([
+            String::new(),
+            tr!("usage-app"),
+            tr!("ui-installed"),
+            tr!("ui-latest"),
+            tr!("ui-status"),
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.rows.iter().map(|row| {
+            let mark = if row.selected { "[x]" } else { "[ ]" };
+            let installed = if row.installed.pinned {
+                format!("{} {}", row.installed.tag, tr!("list-pinned"))
+            } else {
+                row.installed.tag.clone()
+            };
+            let latest = match &row.latest {
+                Latest::Checking => Cell::from(tr!("ui-checking")),
+                Latest::Found(release) if row.update_available() => {
+                    Cell::from(release.tag_name.clone()).style(Style::new().fg(Color::Yellow))
+                }
+                Latest::Found(release) => {
+                    Cell::from(release.tag_name.clone()).style(Style::new().fg(Color::Green))
+                }
+                Latest::Failed(_) => {
+                    Cell::from(tr!("ui-check-failed")).style(Style::new().fg(Color::Red))
+                }
+            };
+            Row::new([
+                Cell::from(mark),
+                Cell::from(row.name.clone()),
+                Cell::from(installed),
+                latest,
+                status(row),
+            ])
+        });
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Fill(1),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" rust-unicorn "))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let highlighted = self.table.selected().and_then(|index| self.rows.get(index));
+        let (title, notes) = match highlighted.map(|row| (row, &row.latest)) {
+            Some((row, Latest::Found(release))) => (
+                tr!(
+                    "ui-notes-title",
+                    app = row.name.as_str(),
+                    version = release.tag_name.as_str()
+                ),
+                match notes::notes(release) {
+                    Some(notes) => markdown::render(&notes, false),
+                    None => tr!("ui-no-notes"),
+                },
+            ),
+            Some((row, Latest::Failed(e))) => (row.name.clone(), e.clone()),
+            Some((row, Latest::Checking)) => (row.name.clone(), tr!("ui-checking")),
+            None => (String::new(), tr!("no-apps")),
+        };
+        let notes = Paragraph::new(Text::from(notes))
+            .wrap(Wrap { trim: false })
+            .scroll((self.notes_scroll, 0))
+            .block(Block::bordered().title(format!(" {} ", title)));
+        frame.render_widget(notes, notes_area);
+
+        frame.render_widget(
+            Line::from(tr!("ui-help")).style(Style::new().add_modifier(Modifier::DIM)),
+            help_area,
+        );
+        frame.render_widget(Line::from(self.status.as_str()), status_area);
+    }
+}
+
+/// The status column of `row`: what is being done to it, or else whether it has an update.
+fn status(row: &AppRow) -> Cell<'static> {
+    match &row.activity {
+        Activity::Queued => Cell::from(tr!("ui-queued")),
+        Activity::Working(Phase::Verifying) => Cell::from(tr!("ui-verifying")),
+        Activity::Working(_) => Cell::from(tr!("ui-integrating")),
+        Activity::Downloading { done, total } => {
+            let filled = if *total > 0 {
+                (*done * BAR_WIDTH as u64 / total) as usize
+            } else {
+                0
+            };
+            let percent = if *total > 0 { done * 100 / total } else { 0 };
+            Cell::from(format!(
+                "{}{} {:>3}%",
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled.min(BAR_WIDTH)),
+                percent
+            ))
+            .style(Style::new().fg(Color::Cyan))
+        }
+        Activity::Done(text) => Cell::from(text.clone()).style(Style::new().fg(Color::Green)),
+        Activity::Failed(e) => Cell::from(e.clone()).style(Style::new().fg(Color::Red)),
+        Activity::Idle if row.installed.pinned => Cell::from(String::new()),
+        Activity::Idle if row.update_available() => {
+            Cell::from(tr!("ui-update-available")).style(Style::new().fg(Color::Yellow))
+        }
+        Activity::Idle => Cell::from(String::new()),
+    }
+}
+
+/// The last line of a log message, without the escape sequences styling it.
+fn plain(message: &str) -> String {
+    static STYLE: OnceLock<Regex> = OnceLock::new();
+    let style = STYLE.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+    let line = message
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    style.replace_all(line, "").into_owned()
+}
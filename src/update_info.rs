@@ -0,0 +1,158 @@
+// Update information embedded in AppImages: the `.upd_info` ELF section, e.g.
+// `gh-releases-zsync|laurent22|joplin|latest|Joplin-*.AppImage.zsync`, as described
+// by the AppImage specification.
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use crate::pattern::AssetPattern;
+
+/// Where an AppImage says its updates come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateInfo {
+    /// `gh-releases-zsync|owner|repo|tag|pattern`: GitHub releases
+    GitHubReleases {
+        owner: String,
+        repo: String,
+        /// `latest`, `latest-pre`, `latest-all` or a specific tag
+        tag: String,
+        /// Glob matching the release's `.zsync` asset
+        pattern: String,
+    },
+    /// `zsync|url`: a fixed `.zsync` URL
+    Zsync { url: String },
+}
+
+impl UpdateInfo {
+    /// Parse the update information string; unknown transports yield `None`.
+    pub fn parse(text: &str) -> Option<UpdateInfo> {
+        let fields: Vec<&str> = text.trim().split('|').collect();
+        match fields.as_slice() {
+            ["gh-releases-zsync", owner, repo, tag, pattern]
+                if !owner.is_empty() && !repo.is_empty() =>
+            {
+                Some(UpdateInfo::GitHubReleases {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    tag: tag.to_string(),
+                    pattern: pattern.to_string(),
+                })
+            }
+            ["zsync", url] if !url.is_empty() => Some(UpdateInfo::Zsync {
+                url: url.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The repository as `owner/name`, for update information naming one.
+    pub fn repo(&self) -> Option<String> {
+        match self {
+            UpdateInfo::GitHubReleases { owner, repo, .. } => Some(format!("{}/{}", owner, repo)),
+            UpdateInfo::Zsync { .. } => None,
+        }
+    }
+
+    /// Pattern for the AppImage asset, derived from the `.zsync` asset pattern.
+    pub fn asset_pattern(&self) -> Option<AssetPattern> {
+        match self {
+            UpdateInfo::GitHubReleases { pattern, .. } => {
+                AssetPattern::parse(pattern.strip_suffix(".zsync").unwrap_or(pattern)).ok()
+            }
+            UpdateInfo::Zsync { .. } => None,
+        }
+    }
+}
+
+/// Read the update information embedded in the AppImage at `path`, if it has any.
+pub fn read(path: &Path) -> io::Result<Option<UpdateInfo>> {
+    Ok(read_section(path, ".upd_info")?
+        .and_then(|data| {
+            // The section is a fixed-size, NUL-padded buffer
+            let end = data
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(data.len());
+            String::from_utf8(data[..end].to_vec()).ok()
+        })
+        .and_then(|text| UpdateInfo::parse(&text)))
+}
+
+/// The contents of the ELF section called `name` in the file at `path`.
+fn read_section(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    let file = File::open(path)?;
+    let read_at = |offset: u64, len: usize| -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0; len];
+        file.read_exact_at(&mut buffer, offset)?;
+        Ok(buffer)
+    };
+
+    let ident = read_at(0, 16)?;
+    if !ident.starts_with(b"\x7fELF") {
+        return Ok(None);
+    }
+    let is_64 = ident[4] == 2;
+    let little_endian = ident[5] == 1;
+    let uint = |bytes: &[u8]| -> u64 {
+        let fold = |value: u64, &byte: &u8| (value << 8) | u64::from(byte);
+        if little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        }
+    };
+
+    // Section header table location and the index of the section name table
+    let header = read_at(0, if is_64 { 64 } else { 52 })?;
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (
+            uint(&header[0x28..0x30]),
+            uint(&header[0x3a..0x3c]),
+            uint(&header[0x3c..0x3e]),
+            uint(&header[0x3e..0x40]),
+        )
+    } else {
+        (
+            uint(&header[0x20..0x24]),
+            uint(&header[0x2e..0x30]),
+            uint(&header[0x30..0x32]),
+            uint(&header[0x32..0x34]),
+        )
+    };
+    if shoff == 0 || shnum == 0 || shstrndx >= shnum || shentsize < if is_64 { 0x28 } else { 0x18 }
+    {
+        return Ok(None);
+    }
+    let sections = read_at(shoff, (shentsize * shnum) as usize)?;
+    // (name offset, file offset, size) of section `index`
+    let section = |index: u64| {
+        let entry = &sections[(index * shentsize) as usize..];
+        if is_64 {
+            (
+                uint(&entry[0..4]),
+                uint(&entry[0x18..0x20]),
+                uint(&entry[0x20..0x28]),
+            )
+        } else {
+            (
+                uint(&entry[0..4]),
+                uint(&entry[0x10..0x14]),
+                uint(&entry[0x14..0x18]),
+            )
+        }
+    };
+
+    let (_, names_offset, names_size) = section(shstrndx);
+    let names = read_at(names_offset, names_size as usize)?;
+    for index in 0..shnum {
+        let (name_offset, offset, size) = section(index);
+        let section_name = names
+            .get(name_offset as usize..)
+            .and_then(|rest| rest.split(|&byte| byte == 0).next());
+        if section_name == Some(name.as_bytes()) {
+            return read_at(offset, size as usize).map(Some);
+        }
+    }
+    Ok(None)
+}
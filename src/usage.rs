@@ -0,0 +1,128 @@
+// Disk usage of installed apps: each app's AppImage, the older versions kept or left
+// next to it, its extracted AppDir and icon, plus rust-unicorn's own cache.
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use crate::cli::{OutputFormat, UsageSort};
+use crate::state::{InstalledApp, State};
+use crate::{paths, tr, versions, Context, Error};
+
+/// Space one app takes, in bytes.
+#[derive(Serialize)]
+pub struct AppUsage {
+    pub app: String,
+    pub appimage: u64,
+    /// Replaced versions kept for `rollback` and older AppImages left behind
+    pub old_versions: u64,
+    /// The AppDir of an extracted install
+    pub extracted: u64,
+    pub icon: u64,
+    pub total: u64,
+}
+
+impl AppUsage {
+    fn measure(app: &str, installed: &InstalledApp) -> Result<AppUsage, Error> {
+        let mut old_versions: u64 = installed
+            .kept_versions()
+            .iter()
+            .map(|version| size(&version.backup_path))
+            .sum();
+        old_versions += versions::old_versions(installed)?
+            .iter()
+            .map(|path| size(path))
+            .sum::<u64>();
+        let appimage = size(&installed.install_path);
+        let extracted = installed.app_dir.as_deref().map_or(0, size);
+        let icon = installed.icon.as_deref().map_or(0, size);
+        Ok(AppUsage {
+            app: app.to_string(),
+            appimage,
+            old_versions,
+            extracted,
+            icon,
+            total: appimage + old_versions + extracted + icon,
+        })
+    }
+}
+
+/// Report how much space each installed app takes, ordered by `sort`, and the size of
+/// the cache.
+pub fn disk_usage(ctx: &Context, sort: UsageSort) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+    let mut apps = state
+        .apps
+        .iter()
+        .map(|(name, installed)| AppUsage::measure(name, installed))
+        .collect::<Result<Vec<_>, Error>>()?;
+    if sort == UsageSort::Size {
+        apps.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.app.cmp(&b.app)));
+    }
+    let cache = paths::cache_home().map_or(0, |dir| size(&dir.join("rust-unicorn")));
+    let total = apps.iter().map(|app| app.total).sum::<u64>() + cache;
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "apps": apps,
+            "cache": cache,
+            "total": total,
+        }))?;
+        return Ok(());
+    }
+
+    if apps.is_empty() {
+        info!("{}", tr!("no-apps"));
+    } else {
+        info!(
+            "{:<20} {:>11} {:>14} {:>11} {:>11} {:>11}",
+            tr!("usage-app"),
+            tr!("usage-appimage"),
+            tr!("usage-old-versions"),
+            tr!("usage-extracted"),
+            tr!("usage-icon"),
+            tr!("usage-total")
+        );
+    }
+    for app in &apps {
+        info!(
+            "{:<20} {:>11} {:>14} {:>11} {:>11} {:>11}",
+            app.app,
+            HumanBytes(app.appimage).to_string(),
+            HumanBytes(app.old_versions).to_string(),
+            HumanBytes(app.extracted).to_string(),
+            HumanBytes(app.icon).to_string(),
+            HumanBytes(app.total).to_string()
+        );
+    }
+    info!(
+        "{}",
+        tr!("usage-cache", size = HumanBytes(cache).to_string())
+    );
+    info!(
+        "{}",
+        tr!("usage-grand-total", size = HumanBytes(total).to_string())
+    );
+
+    Ok(())
+}
+
+/// Size of the file or directory tree at `path`; 0 if it is missing. Symlinks aren't
+/// followed.
+pub fn size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,554 @@
+// Verification of downloaded assets against the checksums and signatures published
+// alongside them, and the `verify` command, which re-checks installed apps against
+// their recorded and published checksums.
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha512}; // For hashing installed files
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::checksum::{self, Algorithm, Checksum};
+use crate::cli::OutputFormat;
+use crate::release::{fetch_release, Asset, Release, ReleaseSelector};
+use crate::state::{InstalledApp, State};
+use crate::Error;
+use crate::{arch, signature, tr, Context};
+
+/// Sources of checksums that aren't a checksum file, as recorded with the checksums
+/// matched.
+const ASSET_DIGEST: &str = "the release's asset digest";
+const RELEASE_NOTES: &str = "the release notes";
+
+/// Compare the downloaded `file`, whose SHA-256 is `sha256`, with the digest the API
+/// reports for `asset` and the checksum published for it in the same release, in the
+/// app's `checksum_asset` if it has one. The checksum files that may cover the asset
+/// are tried in turn; without any, the release notes may list the checksum. Releases
+/// with none of these pass with a notice; a mismatch is an error. Returns the
+/// algorithm and source of each checksum matched.
+pub async fn verify_checksum(
+    ctx: &Context,
+    release: &Release,
+    asset: &Asset,
+    file: &Path,
+    sha256: &str,
+    checksum_asset: Option<&str>,
+) -> Result<Vec<(Algorithm, String)>, Error> {
+    let matched = match_published(ctx, release, asset, file, sha256, checksum_asset).await?;
+    for (algorithm, source_name) in &matched {
+        let source = match source_name.as_str() {
+            ASSET_DIGEST => tr!("checksum-asset-digest"),
+            RELEASE_NOTES => tr!("checksum-release-notes"),
+            file_name => file_name.to_string(),
+        };
+        info!(
+            "{}",
+            tr!(
+                "verified-checksum",
+                algorithm = algorithm.name(),
+                source = source
+            )
+        );
+    }
+    if matched.is_empty() {
+        info!("{}", tr!("no-checksum", asset = asset.name.as_str()));
+    }
+    Ok(matched)
+}
+
+/// How a file was verified, for the records: the checksums it `matched` (as
+/// returned by `verify_checksum`) and whether its signature was checked.
+pub fn verification_method(matched: &[(Algorithm, String)], signed: bool) -> String {
+    let mut methods: Vec<String> = matched
+        .iter()
+        .map(|(algorithm, source_name)| format!("{} ({})", algorithm.name(), source_name))
+        .collect();
+    if signed {
+        methods.push("signature".to_string());
+    }
+    if methods.is_empty() {
+        "none".to_string()
+    } else {
+        methods.join(", ")
+    }
+}
+
+/// Compare `file` with the checksums published for `asset` as `verify_checksum`
+/// does, quietly, returning the algorithm and source of each checksum it matched;
+/// none when the release publishes no checksum for it.
+pub async fn match_published(
+    ctx: &Context,
+    release: &Release,
+    asset: &Asset,
+    file: &Path,
+    sha256: &str,
+    checksum_asset: Option<&str>,
+) -> Result<Vec<(Algorithm, String)>, Error> {
+    let mut matched = Vec::new();
+    if let Some(expected) = asset.digest.as_deref().and_then(checksum::parse_digest) {
+        compare(asset, file, sha256, &expected, ASSET_DIGEST)?;
+        matched.push((expected.algorithm, ASSET_DIGEST.to_string()));
+    }
+
+    let candidates = checksum::find_checksum_assets(&release.assets, &asset.name, checksum_asset);
+    if candidates.is_empty() {
+        // Some projects paste their checksums into the release notes instead
+        let noted = release
+            .body
+            .as_deref()
+            .and_then(|body| checksum::parse_release_notes(body, &asset.name));
+        if let Some(expected) = &noted {
+            compare(asset, file, sha256, expected, RELEASE_NOTES)?;
+            matched.push((expected.algorithm, RELEASE_NOTES.to_string()));
+        }
+        return Ok(matched);
+    }
+
+    for checksum_asset in &candidates {
+        debug!(
+            "Fetching checksums from {}",
+            checksum_asset.browser_download_url
+        );
+        let text = ctx
+            .http
+            .get(&checksum_asset.browser_download_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let Some(expected) = checksum::parse_checksum(&text, &asset.name) else {
+            debug!("{} has no entry for {}", checksum_asset.name, asset.name);
+            continue;
+        };
+
+        compare(asset, file, sha256, &expected, &checksum_asset.name)?;
+        matched.push((expected.algorithm, checksum_asset.name.clone()));
+        return Ok(matched);
+    }
+
+    let names: Vec<&str> = candidates
+        .iter()
+        .map(|candidate| candidate.name.as_str())
+        .collect();
+    Err(match names.as_slice() {
+        [name] => format!("Checksum file {} has no entry for {}", name, asset.name),
+        _ => format!(
+            "None of the checksum files {} has an entry for {}",
+            names.join(", "),
+            asset.name
+        ),
+    }
+    .into())
+}
+
+/// Check `file`, whose SHA-256 is `sha256`, against the `expected` checksum of
+/// `asset` read from `source_name`.
+fn compare(
+    asset: &Asset,
+    file: &Path,
+    sha256: &str,
+    expected: &Checksum,
+    source_name: &str,
+) -> Result<(), Error> {
+    let actual = match expected.algorithm {
+        Algorithm::Sha256 => sha256.to_string(),
+        Algorithm::Sha512 => sha512_file(file)?,
+    };
+    if expected.digest != actual {
+        return Err(Error::ChecksumMismatch {
+            asset: asset.name.clone(),
+            expected: expected.digest.clone(),
+            actual,
+            source_name: source_name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Download the release's detached signature for `asset` and check it with gpg
+/// against the app's configured signing keys.
+pub async fn verify_signature(
+    ctx: &Context,
+    release: &Release,
+    asset: &Asset,
+    file: &Path,
+    signing_keys: &[String],
+) -> Result<(), Error> {
+    let signature_asset = signature::find_signature_asset(&release.assets, &asset.name)
+        .ok_or_else(|| {
+            format!(
+                "Signing keys are configured, but release {} has no .asc/.sig signature for {}",
+                release.tag_name, asset.name
+            )
+        })?;
+
+    let bytes = ctx
+        .http
+        .get(&signature_asset.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let signature_path = file.with_file_name(format!(".{}", signature_asset.name));
+    fs::write(&signature_path, &bytes)?;
+    let result = signature::verify(file, &signature_path, signing_keys);
+    fs::remove_file(&signature_path)?;
+    let signer = result?;
+
+    info!(
+        "{}",
+        tr!(
+            "verified-signature",
+            signature = signature_asset.name.as_str(),
+            signer = signer.as_str()
+        )
+    );
+
+    Ok(())
+}
+
+/// Check that the downloaded `file` really is an AppImage: an ELF executable carrying
+/// the AppImage magic (`AI` and the type byte) at offset 8, built for `arch`. This
+/// catches HTML error pages and wrongly matched assets before they are made executable.
+pub fn verify_appimage(file: &Path, asset_name: &str, arch: &str) -> Result<(), Error> {
+    verify_elf(file, asset_name, arch, true)
+}
+
+/// Check that the downloaded `file` is an ELF executable built for `arch`.
+pub fn verify_executable(file: &Path, asset_name: &str, arch: &str) -> Result<(), Error> {
+    verify_elf(file, asset_name, arch, false)
+}
+
+fn verify_elf(file: &Path, asset_name: &str, arch: &str, appimage: bool) -> Result<(), Error> {
+    let mut header = Vec::with_capacity(20);
+    File::open(file)?.take(20).read_to_end(&mut header)?;
+    let kind = if appimage {
+        "an AppImage"
+    } else {
+        "an executable"
+    };
+
+    let reason = if !header.starts_with(b"\x7fELF") {
+        if header.trim_ascii_start().starts_with(b"<") {
+            "it looks like an HTML page"
+        } else {
+            "it is not an ELF executable"
+        }
+    } else if appimage
+        && header.get(8..11) != Some(b"AI\x02")
+        && header.get(8..11) != Some(b"AI\x01")
+    {
+        "it is an ELF executable without the AppImage magic bytes"
+    } else {
+        // e_machine, in the byte order given by EI_DATA (1 = little endian)
+        let machine = match (header[5], header.get(18..20)) {
+            (1, Some(&[low, high])) => u16::from_le_bytes([low, high]),
+            (_, Some(&[high, low])) => u16::from_be_bytes([high, low]),
+            _ => {
+                return Err(format!(
+                    "Downloaded {} is not {}: its ELF header is truncated",
+                    asset_name, kind
+                )
+                .into())
+            }
+        };
+        return match arch::from_elf_machine(machine) {
+            Some(built_for) if built_for != arch => Err(format!(
+                "Downloaded {} is built for {}, not {}; use --asset-filter to pick another asset",
+                asset_name, built_for, arch
+            )
+            .into()),
+            _ => Ok(()),
+        };
+    };
+    Err(format!("Downloaded {} is not {}: {}", asset_name, kind, reason).into())
+}
+
+/// SHA-256 of a file on disk, lowercase hex.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// SHA-512 of a file on disk, lowercase hex.
+pub fn sha512_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha512::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The outcome of re-checking an installed app, as reported by `verify --output json`.
+#[derive(Serialize)]
+pub struct Verification {
+    pub app: String,
+    pub version: String,
+    pub path: PathBuf,
+    /// `ok`; `missing` or `unreadable`; `modified` when the file no longer matches the
+    /// SHA-256 recorded at install, or `mismatch` when it doesn't match its release's
+    /// published checksum
+    pub status: &'static str,
+    /// SHA-256 of the file as it is now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Published checksums the file matched, by where they were found
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matched: Vec<String>,
+    /// What went wrong, for a failed check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Verification {
+    fn failed(&self) -> bool {
+        self.status != "ok"
+    }
+}
+
+/// Recompute the SHA-256 of the installed `apps` (all of them when empty) and compare
+/// it with the one recorded at install and, unless `offline`, with the checksums
+/// their releases publish. Fails when a file is missing or has changed.
+pub async fn verify_installed(ctx: &Context, apps: &[String], offline: bool) -> Result<(), Error> {
+    let state = State::load(&ctx.state_path)?;
+    let mut names: Vec<String> = if apps.is_empty() {
+        state.apps.keys().cloned().collect()
+    } else {
+        apps.iter()
+            .map(|app| match ctx.config.resolve(app, None) {
+                Ok(resolved) if state.apps.contains_key(&resolved.name) => Ok(resolved.name),
+                _ if state.apps.contains_key(app) => Ok(app.clone()),
+                _ => Err(format!("{} is not installed", app)),
+            })
+            .collect::<Result<_, _>>()?
+    };
+    names.sort();
+    names.dedup();
+    if names.is_empty() {
+        info!("{}", tr!("no-apps"));
+    }
+
+    let mut verifications = Vec::new();
+    for name in names {
+        let verification = verify_app(ctx, &name, &state.apps[&name], offline).await;
+        if ctx.output != OutputFormat::Json {
+            report(&verification);
+        }
+        verifications.push(verification);
+    }
+
+    let failed = verifications
+        .iter()
+        .filter(|verification| verification.failed())
+        .count();
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&verifications)?;
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} app(s) failed verification",
+            failed,
+            verifications.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Check the installed app `name`: first against its state entry, then against its
+/// release, which for a program unpacked from an archive publishes checksums of the
+/// archive only.
+async fn verify_app(
+    ctx: &Context,
+    name: &str,
+    installed: &InstalledApp,
+    offline: bool,
+) -> Verification {
+    let mut verification = Verification {
+        app: name.to_string(),
+        version: installed.tag.clone(),
+        path: installed.install_path.clone(),
+        status: "ok",
+        sha256: None,
+        matched: Vec::new(),
+        error: None,
+    };
+    if !installed.install_path.exists() {
+        verification.status = "missing";
+        return verification;
+    }
+    let sha256 = match sha256_file(&installed.install_path) {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            verification.status = "unreadable";
+            verification.error = Some(e.to_string());
+            return verification;
+        }
+    };
+    verification.sha256 = Some(sha256.clone());
+    if !installed.sha256.is_empty() && sha256 != installed.sha256 {
+        verification.status = "modified";
+        verification.error = Some(format!("expected {}, got {}", installed.sha256, sha256));
+        return verification;
+    }
+    if offline || installed.from_archive() {
+        return verification;
+    }
+
+    match match_release(ctx, name, installed, &sha256).await {
+        Ok(matched) => {
+            verification.matched = matched
+                .into_iter()
+                .map(|(algorithm, source)| format!("{} ({})", source, algorithm.name()))
+                .collect();
+        }
+        Err(e @ Error::ChecksumMismatch { .. }) => {
+            verification.status = "mismatch";
+            verification.error = Some(e.to_string());
+        }
+        // Not being able to ask says nothing about the file
+        Err(e) => warn!(
+            "{}",
+            tr!("verify-release-failed", app = name, error = e.to_string())
+        ),
+    }
+    verification
+}
+
+/// Compare the installed file, whose SHA-256 is `sha256`, with the checksums published
+/// in the release it was installed from.
+async fn match_release(
+    ctx: &Context,
+    name: &str,
+    installed: &InstalledApp,
+    sha256: &str,
+) -> Result<Vec<(Algorithm, String)>, Error> {
+    // Configured apps are resolved by name so their per-app settings apply
+    let spec = if ctx.config.apps.contains_key(name) {
+        name
+    } else {
+        installed.repo.as_str()
+    };
+    let app = ctx.config.resolve(spec, None)?;
+    let release = fetch_release(ctx, &app, ReleaseSelector::Tag(&installed.tag)).await?;
+    let Some(asset) = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == installed.asset_name)
+    else {
+        debug!(
+            "Release {} of {} has no asset {}",
+            installed.tag, name, installed.asset_name
+        );
+        return Ok(Vec::new());
+    };
+    match_published(
+        ctx,
+        &release,
+        asset,
+        &installed.install_path,
+        sha256,
+        app.settings.checksum_asset.as_deref(),
+    )
+    .await
+}
+
+fn report(verification: &Verification) {
+    let (app, version) = (verification.app.as_str(), verification.version.as_str());
+    let path = verification.path.display().to_string();
+    let error = verification.error.clone().unwrap_or_default();
+    match verification.status {
+        "ok" if verification.matched.is_empty() => {
+            info!("{}", tr!("verify-ok", app = app, version = version))
+        }
+        "ok" => info!(
+            "{}",
+            tr!(
+                "verify-ok-published",
+                app = app,
+                version = version,
+                sources = verification.matched.join(", ")
+            )
+        ),
+        "missing" => warn!(
+            "{}",
+            tr!("verify-missing", app = app, version = version, path = path)
+        ),
+        "unreadable" => warn!(
+            "{}",
+            tr!(
+                "verify-unreadable",
+                app = app,
+                version = version,
+                path = path,
+                error = error
+            )
+        ),
+        "modified" => warn!(
+            "{}",
+            tr!(
+                "verify-modified",
+                app = app,
+                version = version,
+                path = path,
+                error = error
+            )
+        ),
+        _ => warn!(
+            "{}",
+            tr!(
+                "verify-mismatch",
+                app = app,
+                version = version,
+                path = path,
+                error = error
+            )
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check `header`, written to a scratch file, as an AppImage (or with `appimage`
+    /// unset, an executable) for x86_64.
+    fn check(name: &str, header: &[u8], appimage: bool) -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!(
+            "rust-unicorn-verify-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, header).unwrap();
+        let result = verify_elf(&path, name, "x86_64", appimage);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    /// The start of an ELF header (little endian) for `machine`, with `magic` at offset 8.
+    fn elf(machine: u16, magic: &[u8; 3]) -> Vec<u8> {
+        let mut header = b"\x7fELF\x02\x01\x01\0".to_vec();
+        header.extend_from_slice(magic);
+        header.resize(18, 0);
+        header.extend_from_slice(&machine.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn checks_elf_header_magic_and_architecture() {
+        assert!(check("appimage", &elf(0x3e, b"AI\x02"), true).is_ok());
+        assert!(check("type-1", &elf(0x3e, b"AI\x01"), true).is_ok());
+        assert!(check("plain", &elf(0x3e, b"\0\0\0"), false).is_ok());
+        // An unknown machine isn't held against the file
+        assert!(check("unknown", &elf(0x1234, b"AI\x02"), true).is_ok());
+
+        let error =
+            |name, header: &[u8], appimage| check(name, header, appimage).unwrap_err().to_string();
+        assert!(error("no-magic", &elf(0x3e, b"\0\0\0"), true).contains("AppImage magic"));
+        assert!(error("arm64", &elf(0xb7, b"AI\x02"), true).contains("built for aarch64"));
+        assert!(error("html", b"  <!DOCTYPE html>", true).contains("HTML page"));
+        assert!(error("text", b"Not Found", false).contains("not an ELF executable"));
+        assert!(error("short", b"\x7fELF\x02\x01\x01\0AI\x02", true).contains("truncated"));
+    }
+}
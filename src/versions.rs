@@ -0,0 +1,287 @@
+// Replaced versions of installed apps. An update moves the outgoing AppImage into the
+// archive next to the install, `<install dir>/.versions/<app>/<tag>/<asset>`, where
+// `rollback` finds it. The config's `keep_versions` says how many are kept per app;
+// updates delete the rest, and `cleanup` also clears out older AppImages left in the
+// install directory and the archives of apps no longer installed.
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::cli::OutputFormat;
+use crate::install::{create_dirs, version_in};
+use crate::state::{InstalledApp, PreviousVersion, State};
+use crate::{style, tr, Context, Error};
+
+/// Replaced versions kept per app when the config sets no `keep_versions`.
+pub const DEFAULT_KEEP_VERSIONS: u32 = 1;
+
+/// Directory of the archive, in each install directory.
+const ARCHIVE_DIR: &str = ".versions";
+
+/// A file `cleanup` deleted (or would delete).
+#[derive(Serialize)]
+pub struct Removed {
+    pub app: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// How many replaced versions of each app are kept.
+pub fn keep_versions(ctx: &Context) -> usize {
+    ctx.config.keep_versions.unwrap_or(DEFAULT_KEEP_VERSIONS) as usize
+}
+
+/// Move `installed`'s AppImage into `app_name`'s archive, creating its directories
+/// with `dir_mode`, and describe it.
+pub fn archive(
+    app_name: &str,
+    installed: &InstalledApp,
+    dir_mode: Option<u32>,
+) -> Result<PreviousVersion, Error> {
+    let backup_path = installed
+        .install_path
+        .with_file_name(ARCHIVE_DIR)
+        .join(app_name)
+        .join(installed.tag.replace('/', "-"))
+        .join(&installed.asset_name);
+    if let Some(dir) = backup_path.parent() {
+        create_dirs(dir, dir_mode)?;
+    }
+    debug!(
+        "Archiving {} to {}",
+        installed.install_path.display(),
+        backup_path.display()
+    );
+    fs::rename(&installed.install_path, &backup_path)?;
+
+    Ok(PreviousVersion {
+        tag: installed.tag.clone(),
+        asset_name: installed.asset_name.clone(),
+        sha256: installed.sha256.clone(),
+        backup_path,
+    })
+}
+
+/// Split an app's replaced versions, newest first, into the `keep` newest still on
+/// disk and the rest, whose files are to be deleted. A version tagged `current_tag`
+/// is a copy of the installed one and isn't kept either.
+pub fn retain(
+    versions: Vec<PreviousVersion>,
+    keep: usize,
+    current_tag: &str,
+) -> (Vec<PreviousVersion>, Vec<PreviousVersion>) {
+    let (mut paths, mut tags) = (HashSet::new(), HashSet::new());
+    let (mut kept, mut dropped) = (Vec::new(), Vec::new());
+    for version in versions {
+        if !version.backup_path.exists() || !paths.insert(version.backup_path.clone()) {
+            continue;
+        }
+        if version.tag == current_tag || !tags.insert(version.tag.clone()) || kept.len() >= keep {
+            dropped.push(version);
+        } else {
+            kept.push(version);
+        }
+    }
+    (kept, dropped)
+}
+
+/// Delete the file at `path` and the archive directories it leaves empty, returning
+/// its size.
+pub fn remove_file(path: &Path) -> Result<u64, Error> {
+    let bytes = fs::symlink_metadata(path)?.len();
+    debug!("Removing {}", path.display());
+    fs::remove_file(path)?;
+    remove_empty_dirs(path);
+    Ok(bytes)
+}
+
+/// Remove the tag, app and archive directories above the archived file `path` while
+/// they are empty. Files outside an archive leave their directory alone.
+pub fn remove_empty_dirs(path: &Path) {
+    let archived = path
+        .ancestors()
+        .nth(3)
+        .and_then(Path::file_name)
+        .is_some_and(|name| name == ARCHIVE_DIR);
+    if archived {
+        for dir in path.ancestors().skip(1).take(3) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Other versions of an installed app's AppImage in its directory: files named like
+/// the installed asset with a different version in place of its own
+/// (`Joplin-2.13.1.AppImage` next to `Joplin-2.14.22.AppImage`).
+pub fn old_versions(installed: &InstalledApp) -> Result<Vec<PathBuf>, Error> {
+    let name = &installed.asset_name;
+    let (Some(version), Some(dir)) = (version_in(name), installed.install_path.parent()) else {
+        return Ok(Vec::new());
+    };
+    let start = name.find(version).unwrap_or(0);
+    let (prefix, suffix) = (&name[..start], &name[start + version.len()..]);
+
+    let mut old = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let middle = file_name
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+            .filter(|_| file_name != name);
+        if middle.is_some_and(|v| version_in(v) == Some(v)) && path.is_file() {
+            old.push(path);
+        }
+    }
+    old.sort();
+
+    Ok(old)
+}
+
+/// Delete what no app needs any more: replaced versions beyond `keep_versions`, older
+/// AppImages left next to installed ones and archived versions of removed apps. With
+/// `dry_run`, only report what would go.
+pub fn cleanup(ctx: &Context, dry_run: bool) -> Result<(), Error> {
+    let keep = keep_versions(ctx);
+    let mut state = State::load(&ctx.state_path)?;
+    let installed_paths: HashSet<PathBuf> = state
+        .apps
+        .values()
+        .map(|installed| installed.install_path.clone())
+        .collect();
+
+    let mut doomed: Vec<(String, PathBuf)> = Vec::new();
+    for (app_name, installed) in state.apps.iter_mut() {
+        let (kept, dropped) = retain(installed.kept_versions(), keep, &installed.tag);
+        doomed.extend(
+            dropped
+                .into_iter()
+                .map(|version| (app_name.clone(), version.backup_path)),
+        );
+        for path in old_versions(installed)? {
+            if !installed_paths.contains(&path) {
+                doomed.push((app_name.clone(), path));
+            }
+        }
+        installed.set_kept_versions(kept);
+    }
+
+    // Archived files no installed app refers to
+    let referenced: HashSet<PathBuf> = state
+        .apps
+        .values()
+        .flat_map(InstalledApp::kept_versions)
+        .map(|version| version.backup_path)
+        .chain(doomed.iter().map(|(_, path)| path.clone()))
+        .collect();
+    let install_dirs: BTreeSet<PathBuf> = state
+        .apps
+        .values()
+        .filter_map(|installed| installed.install_path.parent().map(Path::to_path_buf))
+        .chain([ctx.install_dir.clone()])
+        .collect();
+    for dir in install_dirs {
+        for (app_name, path) in archived_files(&dir.join(ARCHIVE_DIR))? {
+            if !referenced.contains(&path) {
+                doomed.push((app_name, path));
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    let mut seen = HashSet::new();
+    for (app, path) in doomed {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let bytes = if dry_run {
+            fs::symlink_metadata(&path)?.len()
+        } else {
+            remove_file(&path)?
+        };
+        removed.push(Removed { app, path, bytes });
+    }
+    if !dry_run {
+        state.save(&ctx.state_path, ctx.dir_mode)?;
+    }
+    let reclaimed: u64 = removed.iter().map(|removed| removed.bytes).sum();
+
+    if ctx.output == OutputFormat::Json {
+        ctx.print_json(&serde_json::json!({
+            "dry_run": dry_run,
+            "removed": removed,
+            "reclaimed_bytes": reclaimed,
+        }))?;
+    } else if removed.is_empty() {
+        info!("{}", tr!("nothing-to-clean"));
+    } else {
+        for removed in &removed {
+            let (path, app, size) = (
+                removed.path.display().to_string(),
+                removed.app.as_str(),
+                HumanBytes(removed.bytes).to_string(),
+            );
+            if dry_run {
+                info!(
+                    "{}",
+                    tr!("cleanup-would-remove", path = path, app = app, size = size)
+                );
+            } else {
+                info!(
+                    "{}",
+                    tr!("cleanup-removed", path = path, app = app, size = size)
+                );
+            }
+        }
+        let (size, count) = (HumanBytes(reclaimed).to_string(), removed.len());
+        let summary = if dry_run {
+            tr!("cleanup-would-reclaim", size = size, count = count)
+        } else {
+            tr!("cleanup-reclaimed", size = size, count = count)
+        };
+        info!("{}", style::success(summary));
+    }
+
+    Ok(())
+}
+
+/// The files in the archive at `archive`, with the app each belongs to.
+fn archived_files(archive: &Path) -> Result<Vec<(String, PathBuf)>, Error> {
+    let mut files = Vec::new();
+    if !archive.is_dir() {
+        return Ok(files);
+    }
+    for app_dir in fs::read_dir(archive)? {
+        let app_dir = app_dir?.path();
+        let Some(app_name) = app_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        if !app_dir.is_dir() {
+            continue;
+        }
+        for tag_dir in fs::read_dir(&app_dir)? {
+            let tag_dir = tag_dir?.path();
+            if !tag_dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&tag_dir)? {
+                let file = file?.path();
+                if file.is_file() {
+                    files.push((app_name.clone(), file));
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,395 @@
+// zsync delta updates (http://zsync.moria.org.uk/): builds the new AppImage from the
+// blocks it shares with the installed one, using the block checksums in the release's
+// `.zsync` file, and only downloads the blocks that changed.
+use futures_util::StreamExt; // For stream handling
+use indicatif::HumanBytes;
+use md4::{Digest, Md4};
+use reqwest::{header, StatusCode};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use tracing::info;
+
+use crate::download::{self, check_host};
+use crate::progress::Transfer;
+use crate::release::Asset;
+use crate::retry;
+use crate::tr;
+use crate::Context;
+use crate::Error;
+
+/// Missing blocks closer together than this are fetched in one range request.
+const MERGE_GAP: u64 = 64 * 1024;
+
+/// A parsed `.zsync` control file.
+struct ControlFile {
+    block_size: usize,
+    length: u64,
+    /// Consecutive blocks that must match before a match is trusted (1 or 2)
+    seq_matches: usize,
+    rsum_bytes: usize,
+    checksum_bytes: usize,
+    sha1: String,
+    blocks: Vec<BlockSum>,
+}
+
+/// Checksums of one block of the target file.
+struct BlockSum {
+    /// Weak rolling checksum, truncated to `rsum_bytes`
+    rsum: u32,
+    /// Strong MD4 checksum, truncated to `checksum_bytes`
+    checksum: Vec<u8>,
+}
+
+/// Build `asset` at `path` from the blocks it shares with the installed AppImage at
+/// `seed`, downloading the rest as described by the `.zsync` file `zsync_asset`.
+/// Returns the SHA-256 of the result and the number of bytes downloaded.
+pub async fn download_delta(
+    ctx: &Context,
+    asset: &Asset,
+    zsync_asset: &Asset,
+    seed: &Path,
+    path: &Path,
+    pinned_hosts: &[String],
+) -> Result<(String, u64), Error> {
+    let _permit = ctx.download_permit().await;
+    let download = build(ctx, asset, zsync_asset, seed, path, pinned_hosts);
+    download::within_deadline(ctx, &asset.browser_download_url, download).await
+}
+
+async fn build(
+    ctx: &Context,
+    asset: &Asset,
+    zsync_asset: &Asset,
+    seed: &Path,
+    path: &Path,
+    pinned_hosts: &[String],
+) -> Result<(String, u64), Error> {
+    let url = &zsync_asset.browser_download_url;
+    let control = retry::retry_transient(ctx, || async move {
+        Ok(ctx
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?)
+    })
+    .await?;
+    let control = parse(&control)?;
+
+    // Find the target's blocks in the installed version
+    let seed_data = fs::read(seed)?;
+    let found = match_blocks(&control, &seed_data);
+    let reused =
+        found.iter().filter(|offset| offset.is_some()).count() as u64 * control.block_size as u64;
+    let reused = reused.min(control.length);
+
+    let file = File::create(path)?;
+    file.set_len(control.length)?;
+    for (index, offset) in found.iter().enumerate() {
+        if let Some(offset) = *offset {
+            let start = index as u64 * control.block_size as u64;
+            let end = (start + control.block_size as u64).min(control.length);
+            file.write_all_at(&seed_data[offset..offset + (end - start) as usize], start)?;
+        }
+    }
+    drop(seed_data);
+
+    let ranges = missing_ranges(&control, &found);
+    let needed: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+    info!(
+        "{}",
+        tr!(
+            "delta-update",
+            reused = HumanBytes(reused).to_string(),
+            needed = HumanBytes(needed).to_string(),
+            total = HumanBytes(control.length).to_string()
+        )
+    );
+
+    let mut progress = Transfer::new(ctx, &asset.name, needed);
+    let mut transferred = 0;
+    for &(start, end) in &ranges {
+        let mut offset = start;
+        let mut retry = 0;
+        while offset < end {
+            let resp = retry::retry_transient(ctx, || async move {
+                let range = format!("bytes={}-{}", offset, end - 1);
+                let request = ctx
+                    .http
+                    .get(&asset.browser_download_url)
+                    .header(header::RANGE, range);
+                Ok(request.send().await?.error_for_status()?)
+            })
+            .await?;
+            check_host(resp.url(), pinned_hosts)?;
+            if resp.status() != StatusCode::PARTIAL_CONTENT {
+                return Err("the server doesn't support range requests".into());
+            }
+
+            let resumed_at = offset;
+            let mut stream = resp.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        // Resume the range where it broke off
+                        let e = Error::from(e);
+                        if retry >= ctx.retries || !retry::is_transient(&e) {
+                            return Err(e);
+                        }
+                        retry += 1;
+                        retry::pause(ctx, retry, &e).await;
+                        break;
+                    }
+                };
+                let chunk = &chunk[..chunk.len().min((end - offset) as usize)];
+                file.write_all_at(chunk, offset)?;
+                if let Some(throttle) = &ctx.throttle {
+                    throttle.consume(chunk.len()).await;
+                }
+                offset += chunk.len() as u64;
+                transferred += chunk.len() as u64;
+                progress.set_position(transferred);
+            }
+            if offset == resumed_at {
+                return Err("range request returned no data".into());
+            }
+        }
+    }
+    file.sync_all()?;
+    progress.finish();
+
+    // The assembled file must be exactly the published one
+    let (sha1, sha256) = hash_file(path)?;
+    if sha1 != control.sha1 {
+        return Err(format!(
+            "assembled file has SHA-1 {}, expected {}",
+            sha1, control.sha1
+        )
+        .into());
+    }
+
+    Ok((sha256, needed))
+}
+
+/// Parse a `.zsync` file: `Key: value` header lines, a blank line, then the checksums
+/// of every block.
+fn parse(data: &[u8]) -> Result<ControlFile, Error> {
+    let invalid = |what: &str| Error::from(format!("invalid .zsync file: {}", what));
+    let header_end = data
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .ok_or_else(|| invalid("no end of header"))?;
+    let header = String::from_utf8_lossy(&data[..header_end]);
+
+    let mut fields = HashMap::new();
+    for line in header.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    if fields.contains_key("Recompress") || fields.contains_key("Z-URL") {
+        return Err("compressed .zsync targets are not supported".into());
+    }
+    let number = |key: &str| -> Result<u64, Error> {
+        fields
+            .get(key)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| invalid(&format!("missing or bad {}", key)))
+    };
+    let block_size = number("Blocksize")? as usize;
+    let length = number("Length")?;
+    let hash_lengths: Vec<usize> = fields
+        .get("Hash-Lengths")
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let &[seq_matches, rsum_bytes, checksum_bytes] = hash_lengths.as_slice() else {
+        return Err(invalid("missing or bad Hash-Lengths"));
+    };
+    if block_size == 0
+        || !block_size.is_power_of_two()
+        || !(1..=2).contains(&seq_matches)
+        || !(1..=4).contains(&rsum_bytes)
+        || !(1..=16).contains(&checksum_bytes)
+    {
+        return Err(invalid("unsupported block size or hash lengths"));
+    }
+    let sha1 = fields
+        .get("SHA-1")
+        .ok_or_else(|| invalid("missing SHA-1"))?
+        .to_lowercase();
+
+    let block_count = length.div_ceil(block_size as u64) as usize;
+    let sums = &data[header_end + 2..];
+    let entry_len = rsum_bytes + checksum_bytes;
+    if sums.len() < block_count * entry_len {
+        return Err(invalid("truncated block checksums"));
+    }
+    let blocks = sums
+        .chunks_exact(entry_len)
+        .take(block_count)
+        .map(|entry| BlockSum {
+            // Stored as the last `rsum_bytes` bytes of the big-endian (a, b) pair
+            rsum: entry[..rsum_bytes]
+                .iter()
+                .fold(0, |sum, &byte| (sum << 8) | u32::from(byte)),
+            checksum: entry[rsum_bytes..].to_vec(),
+        })
+        .collect();
+
+    Ok(ControlFile {
+        block_size,
+        length,
+        seq_matches,
+        rsum_bytes,
+        checksum_bytes,
+        sha1,
+        blocks,
+    })
+}
+
+/// The zsync rolling checksum of `block`: `a` sums the bytes, `b` weights them by
+/// their distance from the end.
+fn rsum(block: &[u8]) -> (u16, u16) {
+    let mut a: u16 = 0;
+    let mut b: u16 = 0;
+    for (index, &byte) in block.iter().enumerate() {
+        a = a.wrapping_add(u16::from(byte));
+        b = b.wrapping_add(((block.len() - index) as u16).wrapping_mul(u16::from(byte)));
+    }
+    (a, b)
+}
+
+/// For every block of the target, an offset in `seed` where it occurs, if any.
+fn match_blocks(control: &ControlFile, seed: &[u8]) -> Vec<Option<usize>> {
+    let block_size = control.block_size;
+    let shift = block_size.trailing_zeros();
+    let mask = if control.rsum_bytes == 4 {
+        u32::MAX
+    } else {
+        (1 << (8 * control.rsum_bytes)) - 1
+    };
+    let key = |(a, b): (u16, u16)| ((u32::from(a) << 16) | u32::from(b)) & mask;
+
+    let mut by_rsum: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, block) in control.blocks.iter().enumerate() {
+        by_rsum.entry(block.rsum).or_default().push(index);
+    }
+    let checksum_of = |start: usize| {
+        let block = &seed[start..start + block_size];
+        Md4::digest(block)[..control.checksum_bytes].to_vec()
+    };
+    // Whether the seed data at `start` is target block `index`
+    let matches = |start: usize, index: usize, checksum: &[u8]| {
+        let block = &control.blocks[index];
+        if block.checksum != checksum {
+            return false;
+        }
+        // With seq_matches = 2 the hashes are too short to trust on their own; the
+        // next block has to follow as well
+        let next = index + 1;
+        control.seq_matches == 1
+            || next >= control.blocks.len()
+            || start + 2 * block_size > seed.len()
+            || (key(rsum(&seed[start + block_size..start + 2 * block_size]))
+                == control.blocks[next].rsum
+                && checksum_of(start + block_size) == control.blocks[next].checksum)
+    };
+
+    let mut found = vec![None; control.blocks.len()];
+    if seed.len() < block_size {
+        return found;
+    }
+    let mut start = 0;
+    let (mut a, mut b) = rsum(&seed[..block_size]);
+    loop {
+        let mut matched = false;
+        if let Some(candidates) = by_rsum.get(&key((a, b))) {
+            let checksum = checksum_of(start);
+            for &index in candidates {
+                if found[index].is_none() && matches(start, index, &checksum) {
+                    found[index] = Some(start);
+                    matched = true;
+                }
+                if matched
+                    && control.seq_matches == 2
+                    && index + 1 < found.len()
+                    && start + 2 * block_size <= seed.len()
+                {
+                    // `matches` checked the following block too
+                    found[index + 1].get_or_insert(start + block_size);
+                }
+            }
+        }
+
+        if matched {
+            // Continue right after the matched block
+            start += block_size;
+            if start + block_size > seed.len() {
+                break;
+            }
+            (a, b) = rsum(&seed[start..start + block_size]);
+        } else {
+            if start + block_size >= seed.len() {
+                break;
+            }
+            let (old, new) = (u16::from(seed[start]), u16::from(seed[start + block_size]));
+            a = a.wrapping_sub(old).wrapping_add(new);
+            b = b
+                .wrapping_sub((u32::from(old) << shift) as u16)
+                .wrapping_add(a);
+            start += 1;
+        }
+    }
+    found
+}
+
+/// Byte ranges (start, end exclusive) of the target that weren't found in the seed.
+fn missing_ranges(control: &ControlFile, found: &[Option<usize>]) -> Vec<(u64, u64)> {
+    let block_size = control.block_size as u64;
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for (index, _) in found
+        .iter()
+        .enumerate()
+        .filter(|(_, offset)| offset.is_none())
+    {
+        let start = index as u64 * block_size;
+        let end = (start + block_size).min(control.length);
+        match ranges.last_mut() {
+            Some(last) if start - last.1 <= MERGE_GAP => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// SHA-1 and SHA-256 of a file, lowercase hex, in one pass.
+fn hash_file(path: &Path) -> io::Result<(String, String)> {
+    let mut file = File::open(path)?;
+    let (mut sha1, mut sha256) = (Sha1::new(), Sha256::new());
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&buffer[..read]);
+        sha256.update(&buffer[..read]);
+    }
+    Ok((
+        format!("{:x}", sha1.finalize()),
+        format!("{:x}", sha256.finalize()),
+    ))
+}
@@ -0,0 +1,397 @@
+// Installs and updates through `Installer`, against the fixture server of the test
+// harness instead of GitHub.
+use rust_unicorn::cli::InstallArgs;
+use rust_unicorn::config::{AppConfig, AssetType, Config};
+use rust_unicorn::state::State;
+use rust_unicorn::test_harness::{elf, sha256, tarball, FixtureRelease, FixtureServer};
+use rust_unicorn::{arch, install, Error, Installer};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const REPO: &str = "fixture/tool";
+
+/// A scratch directory holding the apps, symlinks and state of one test.
+struct Sandbox {
+    dir: PathBuf,
+    server: FixtureServer,
+    installer: Installer,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        Sandbox::with_apps(name, &[("tool", REPO)], Config::default())
+    }
+
+    /// A sandbox with the archive `apps`, given as names and repositories, and
+    /// settings from `config`.
+    fn with_apps(name: &str, apps: &[(&str, &str)], config: Config) -> Sandbox {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-unicorn-installer-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut config = Config {
+            desktop_entry: config.desktop_entry.or(Some(false)),
+            cache_downloads: config.cache_downloads.or(Some(false)),
+            notify: Some(false),
+            retries: Some(0),
+            ..config
+        };
+        for (app, repo) in apps {
+            config.apps.insert(
+                app.to_string(),
+                AppConfig {
+                    repo: repo.to_string(),
+                    asset_type: AssetType::Archive,
+                    ..AppConfig::default()
+                },
+            );
+        }
+        let server = FixtureServer::new();
+        let installer = Installer::builder()
+            .config(config)
+            .install_dir(dir.join("apps"))
+            .bin_dir(dir.join("bin"))
+            .state_path(dir.join("state").join("state.json"))
+            .http_fetcher(server.clone())
+            .build()
+            .unwrap();
+        Sandbox {
+            dir,
+            server,
+            installer,
+        }
+    }
+
+    fn state(&self) -> State {
+        State::load(&self.dir.join("state").join("state.json")).unwrap()
+    }
+
+    /// The files in the install directory, apart from its lock.
+    fn installed_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = fs::read_dir(self.dir.join("apps"))
+            .map(|entries| {
+                entries
+                    .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                    .filter(|name| !name.starts_with(".rust-unicorn"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Name of the tool's tarball of `version` for this machine.
+fn asset(version: &str) -> String {
+    format!("tool-{}-{}-linux.tar.gz", version, arch::host())
+}
+
+/// Release `tag` of the tool, with `program` packed in a tarball named for `version`.
+fn release(tag: &str, version: &str, program: &[u8]) -> (FixtureRelease, String) {
+    let name = asset(version);
+    let release = FixtureRelease::new(tag).asset(&name, &tarball("tool", program));
+    (release, name)
+}
+
+fn download_url(tag: &str, name: &str) -> String {
+    format!(
+        "https://github.com/{}/releases/download/{}/{}",
+        REPO, tag, name
+    )
+}
+
+#[tokio::test]
+async fn installs_the_latest_release() {
+    let sandbox = Sandbox::new("install");
+    let program = elf();
+    sandbox.server.publish(
+        REPO,
+        FixtureRelease::new("v0.9").asset(&asset("1.0"), b"old"),
+    );
+    let (latest, _) = release("v1.0", "1.0", &program);
+    sandbox.server.publish(REPO, latest);
+    sandbox.server.publish(
+        REPO,
+        FixtureRelease::new("v1.1-rc1")
+            .prerelease()
+            .asset(&asset("1.0"), b"unreleased"),
+    );
+
+    let report = sandbox.installer.install("tool").await.unwrap();
+    assert_eq!(report.action, "installed");
+    assert_eq!(report.new_version, "v1.0");
+    assert_eq!(report.asset, asset("1.0"));
+    assert_eq!(fs::read(&report.path).unwrap(), program);
+    assert_eq!(
+        sandbox.installed_files(),
+        [format!("tool-1.0-{}-linux", arch::host())]
+    );
+
+    let state = sandbox.state();
+    let installed = &state.apps["tool"];
+    assert_eq!(installed.tag, "v1.0");
+    assert_eq!(installed.sha256, sha256(&program));
+}
+
+#[tokio::test]
+async fn leaves_an_installed_release_alone() {
+    let sandbox = Sandbox::new("already-installed");
+    let (latest, name) = release("v1.0", "1.0", &elf());
+    sandbox.server.publish(REPO, latest);
+    sandbox.installer.install("tool").await.unwrap();
+
+    let report = sandbox.installer.install("tool").await.unwrap();
+    assert_eq!(report.action, "up-to-date");
+    assert_eq!(report.bytes_downloaded, 0);
+    let url = download_url("v1.0", &name);
+    let downloads = sandbox
+        .server
+        .requests()
+        .iter()
+        .filter(|request| request.ends_with(&url))
+        .count();
+    assert_eq!(downloads, 1);
+}
+
+#[tokio::test]
+async fn updates_to_a_newer_release() {
+    let sandbox = Sandbox::new("update");
+    let (first, _) = release("v1.0", "1.0", &elf());
+    sandbox.server.publish(REPO, first);
+    let installed = sandbox.installer.install("tool").await.unwrap();
+
+    let mut program = elf();
+    program.extend_from_slice(b"2.0");
+    let (second, _) = release("v2.0", "2.0", &program);
+    sandbox.server.publish(REPO, second);
+    let report = sandbox.installer.update("tool").await.unwrap();
+    assert_eq!(report.action, "updated");
+    assert_eq!(report.old_version.as_deref(), Some("v1.0"));
+    assert_eq!(report.new_version, "v2.0");
+    assert_eq!(fs::read(&report.path).unwrap(), program);
+    assert!(!installed.path.exists());
+    assert_eq!(sandbox.state().apps["tool"].tag, "v2.0");
+}
+
+#[tokio::test]
+async fn refuses_a_download_that_fails_its_checksum() {
+    let sandbox = Sandbox::new("checksum-mismatch");
+    let digest = format!("sha256:{}", "0".repeat(64));
+    sandbox.server.publish(
+        REPO,
+        FixtureRelease::new("v1.0").asset_with_digest(
+            &asset("1.0"),
+            &tarball("tool", &elf()),
+            Some(&digest),
+        ),
+    );
+
+    let error = sandbox
+        .installer
+        .install("tool")
+        .await
+        .err()
+        .expect("install fails");
+    assert!(matches!(error, Error::ChecksumMismatch { .. }), "{}", error);
+    assert!(sandbox.installed_files().is_empty());
+    assert!(!sandbox.state().apps.contains_key("tool"));
+}
+
+#[tokio::test]
+async fn fails_on_a_truncated_download() {
+    let sandbox = Sandbox::new("truncated");
+    let (latest, name) = release("v1.0", "1.0", &elf());
+    sandbox.server.publish(REPO, latest);
+    sandbox.server.truncate(&download_url("v1.0", &name), 100);
+
+    let error = sandbox
+        .installer
+        .install("tool")
+        .await
+        .err()
+        .expect("install fails");
+    assert!(matches!(error, Error::Network(_)), "{}", error);
+    assert!(sandbox.installed_files().is_empty());
+    assert!(!sandbox.state().apps.contains_key("tool"));
+}
+
+#[tokio::test]
+async fn reports_an_exhausted_rate_limit() {
+    let sandbox = Sandbox::new("rate-limit");
+    let (latest, _) = release("v1.0", "1.0", &elf());
+    sandbox.server.publish(REPO, latest);
+    sandbox.server.rate_limit(true);
+
+    let error = sandbox
+        .installer
+        .install("tool")
+        .await
+        .err()
+        .expect("install fails");
+    match error {
+        Error::RateLimited { host, reset } => {
+            assert_eq!(host, "api.github.com");
+            assert!(reset.is_some());
+        }
+        error => panic!("expected a rate limit, got {}", error),
+    }
+    assert!(sandbox.installed_files().is_empty());
+
+    sandbox.server.rate_limit(false);
+    sandbox.installer.install("tool").await.unwrap();
+}
+
+#[tokio::test]
+async fn reports_a_release_without_an_installable_asset() {
+    let sandbox = Sandbox::new("no-asset");
+    sandbox
+        .server
+        .publish_recorded(REPO, include_str!("fixtures/release-without-archives.json"))
+        .unwrap();
+
+    let error = sandbox
+        .installer
+        .install("tool")
+        .await
+        .err()
+        .expect("install fails");
+    match error {
+        Error::NoMatchingAsset { repo, tag, .. } => {
+            assert_eq!(repo, REPO);
+            assert_eq!(tag, "v3.0");
+        }
+        error => panic!("expected no matching asset, got {}", error),
+    }
+    // Nothing was downloaded
+    assert!(sandbox
+        .server
+        .requests()
+        .iter()
+        .all(|request| request.contains("api.github.com")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn installs_apps_with_the_same_asset_name_at_once() {
+    let config = Config {
+        stable_filename: Some(true),
+        ..Config::default()
+    };
+    let sandbox = Sandbox::with_apps(
+        "concurrent",
+        &[("alpha", "fixture/alpha"), ("beta", "fixture/beta")],
+        config,
+    );
+    let mut alpha = elf();
+    alpha.extend_from_slice(b"alpha");
+    let mut beta = elf();
+    beta.extend_from_slice(b"beta");
+    let (release_alpha, name) = release("v1.0", "1.0", &alpha);
+    let (release_beta, _) = release("v1.0", "1.0", &beta);
+    sandbox.server.publish("fixture/alpha", release_alpha);
+    sandbox.server.publish("fixture/beta", release_beta);
+
+    // As `update --all` does, with both under the one lock of the install directory
+    let ctx = sandbox.installer.context();
+    fs::create_dir_all(&ctx.install_dir).unwrap();
+    let installs: Vec<_> = ["alpha", "beta"]
+        .into_iter()
+        .map(|app| {
+            let ctx = Arc::clone(ctx);
+            let args = InstallArgs {
+                app: Some(app.to_string()),
+                non_interactive: true,
+                ..InstallArgs::default()
+            };
+            tokio::spawn(async move { install::install(&ctx, &args, false).await })
+        })
+        .collect();
+    let mut reports = Vec::new();
+    for install in installs {
+        reports.push(install.await.unwrap().unwrap());
+    }
+    let (installed_alpha, installed_beta) = (&reports[0], &reports[1]);
+    assert_eq!(installed_alpha.asset, name);
+    assert_eq!(installed_beta.asset, name);
+    assert_eq!(fs::read(&installed_alpha.path).unwrap(), alpha);
+    assert_eq!(fs::read(&installed_beta.path).unwrap(), beta);
+    // Neither staging area is left behind
+    assert!(!ctx.install_dir.join(".rust-unicorn").exists());
+    assert_eq!(sandbox.installed_files(), ["alpha", "beta"]);
+}
+
+#[tokio::test]
+async fn refuses_a_stale_asset_when_asked_to() {
+    let sandbox = Sandbox::new("stale-asset");
+    // The tag was bumped, but the asset is the old build
+    let (stale, name) = release("v2.0", "1.0", &elf());
+    sandbox.server.publish(REPO, stale);
+
+    let strict = InstallArgs {
+        app: Some("tool".to_string()),
+        non_interactive: true,
+        strict_version_match: true,
+        ..InstallArgs::default()
+    };
+    let error = sandbox
+        .installer
+        .run(&strict, false)
+        .await
+        .err()
+        .expect("install fails");
+    assert!(error.to_string().contains(&name), "{}", error);
+    assert!(sandbox.installed_files().is_empty());
+
+    // Otherwise it is only warned about
+    let report = sandbox.installer.install("tool").await.unwrap();
+    assert_eq!(report.new_version, "v2.0");
+}
+
+#[tokio::test]
+async fn downloads_only_into_the_cache_and_revalidates_it() {
+    let config = Config {
+        cache_downloads: Some(true),
+        ..Config::default()
+    };
+    let sandbox = Sandbox::with_apps("download-only", &[("tool", REPO)], config);
+    // Only this test caches downloads
+    std::env::set_var("XDG_CACHE_HOME", sandbox.dir.join("cache"));
+    let name = asset("1.0");
+    // Without a published digest, the cached copy is found by its URL
+    let contents = tarball("tool", &elf());
+    sandbox.server.publish(
+        REPO,
+        FixtureRelease::new("v1.0").asset_with_digest(&name, &contents, None),
+    );
+    let download_only = InstallArgs {
+        app: Some("tool".to_string()),
+        non_interactive: true,
+        download_only: true,
+        ..InstallArgs::default()
+    };
+
+    let first = sandbox.installer.run(&download_only, false).await.unwrap();
+    assert_eq!(first.action, "downloaded");
+    assert_eq!(first.bytes_downloaded, contents.len() as u64);
+    assert!(sandbox.installed_files().is_empty());
+    assert!(!sandbox.state().apps.contains_key("tool"));
+
+    let second = sandbox.installer.run(&download_only, false).await.unwrap();
+    assert_eq!(second.action, "downloaded");
+    assert_eq!(second.bytes_downloaded, 0);
+    let url = download_url("v1.0", &name);
+    assert_eq!(sandbox.server.not_modified(), [url]);
+
+    // The install takes the cached copy
+    let installed = sandbox.installer.install("tool").await.unwrap();
+    assert_eq!(installed.action, "installed");
+    assert_eq!(installed.bytes_downloaded, 0);
+}
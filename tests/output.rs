@@ -0,0 +1,139 @@
+// What the binary prints at each output level: by default information on stdout and
+// warnings and errors on stderr, with --quiet only the warnings and errors, and with
+// --silent nothing, leaving the exit code to tell what happened.
+use rust_unicorn::test_harness::{elf, tarball};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Output of one run of the binary.
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+/// A home directory of its own for the runs at one output level.
+struct Home {
+    dir: PathBuf,
+    /// `--quiet`, `--silent` or nothing
+    level: Option<&'static str>,
+}
+
+impl Home {
+    fn new(name: &str, level: Option<&'static str>) -> Home {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-unicorn-output-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("config.toml"), "").unwrap();
+        fs::write(dir.join("tool-1.0.tar.gz"), tarball("tool", &elf())).unwrap();
+        Home { dir, level }
+    }
+
+    /// Install `file` (relative to the home directory) as owner/tool.
+    fn install(&self, file: &str) -> Run {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rust-unicorn"));
+        command
+            .env_clear()
+            .env("PATH", "/usr/bin:/bin")
+            .env("HOME", &self.dir)
+            .current_dir(&self.dir)
+            .args(["--config", "config.toml"]);
+        if let Some(level) = self.level {
+            command.arg(level);
+        }
+        let output = command
+            .args([
+                "install",
+                "--method",
+                "archive",
+                "--file",
+                file,
+                "owner/tool",
+            ])
+            .output()
+            .unwrap();
+        Run {
+            code: output.status.code(),
+            stdout: String::from_utf8(output.stdout).unwrap(),
+            stderr: String::from_utf8(output.stderr).unwrap(),
+        }
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn normal_output_informs_on_stdout_and_warns_on_stderr() {
+    let home = Home::new("normal", None);
+
+    let installed = home.install("tool-1.0.tar.gz");
+    assert_eq!(installed.code, Some(0));
+    assert!(installed
+        .stdout
+        .contains("tool 1.0 has been successfully installed!"));
+    // ~/.local/bin isn't on the PATH given
+    assert!(installed.stderr.starts_with("Warning: "));
+    assert!(installed.stderr.contains("is not on your PATH"));
+
+    let again = home.install("tool-1.0.tar.gz");
+    assert_eq!(again.code, Some(0));
+    assert!(again.stdout.contains("tool 1.0 is already installed"));
+    assert_eq!(again.stderr, "");
+
+    let failed = home.install("missing-1.0.tar.gz");
+    assert_eq!(failed.code, Some(1));
+    assert_eq!(failed.stdout, "");
+    assert!(failed
+        .stderr
+        .starts_with("Error: Cannot read missing-1.0.tar.gz"));
+}
+
+#[test]
+fn quiet_output_only_warns_and_reports_errors() {
+    let home = Home::new("quiet", Some("--quiet"));
+
+    let installed = home.install("tool-1.0.tar.gz");
+    assert_eq!(installed.code, Some(0));
+    assert_eq!(installed.stdout, "");
+    assert!(installed.stderr.starts_with("Warning: "));
+    assert!(installed.stderr.contains("is not on your PATH"));
+
+    let again = home.install("tool-1.0.tar.gz");
+    assert_eq!(again.code, Some(0));
+    assert_eq!((again.stdout.as_str(), again.stderr.as_str()), ("", ""));
+
+    let failed = home.install("missing-1.0.tar.gz");
+    assert_eq!(failed.code, Some(1));
+    assert_eq!(failed.stdout, "");
+    assert!(failed
+        .stderr
+        .starts_with("Error: Cannot read missing-1.0.tar.gz"));
+}
+
+#[test]
+fn silent_output_leaves_only_the_exit_code() {
+    let home = Home::new("silent", Some("--silent"));
+
+    for (file, code) in [
+        ("tool-1.0.tar.gz", 0),
+        ("tool-1.0.tar.gz", 0),
+        ("missing-1.0.tar.gz", 1),
+    ] {
+        let run = home.install(file);
+        assert_eq!(run.code, Some(code), "{}", file);
+        assert_eq!((run.stdout.as_str(), run.stderr.as_str()), ("", ""));
+    }
+    assert!(home
+        .dir
+        .join(".local/share/rust-unicorn/apps/tool-1.0")
+        .exists());
+}